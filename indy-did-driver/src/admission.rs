@@ -0,0 +1,254 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use rouille::Response;
+
+/// Limits of the admission queue in front of the resolution handling
+#[derive(Clone, Copy, Debug)]
+pub struct AdmissionLimits {
+    /// Requests resolved at the same time
+    pub max_concurrent: usize,
+    /// Requests waiting for a resolution slot, further requests are shed
+    pub queue_size: usize,
+    /// Time a request may wait in the queue before it is dropped
+    pub queue_timeout: Duration,
+    /// Time of continuous shedding after which the driver reports not ready
+    pub degrade_after: Duration,
+}
+
+impl Default for AdmissionLimits {
+    fn default() -> Self {
+        AdmissionLimits {
+            max_concurrent: 16,
+            queue_size: 16,
+            queue_timeout: Duration::from_secs(10),
+            degrade_after: Duration::from_secs(30),
+        }
+    }
+}
+
+struct State {
+    active: usize,
+    queued: usize,
+    // Start of the current overload, reset when the queue drains
+    shedding_since: Option<Instant>,
+}
+
+impl State {
+    fn dequeue(&mut self) {
+        self.queued -= 1;
+        if self.queued == 0 {
+            self.shedding_since = None;
+        }
+    }
+}
+
+/// Bounded admission queue. Requests beyond the concurrency limit wait in the
+/// queue; when it is full they are shed with 503, and requests waiting longer
+/// than the queue timeout are dropped with 504 before touching the ledger.
+pub struct Admission {
+    limits: AdmissionLimits,
+    state: Mutex<State>,
+    released: Condvar,
+    shed: AtomicU64,
+    expired: AtomicU64,
+}
+
+/// Resolution slot of an admitted request, released when dropped
+pub struct Permit<'a>(&'a Admission);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().active -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+impl Admission {
+    pub fn new(limits: AdmissionLimits) -> Self {
+        Admission {
+            limits,
+            state: Mutex::new(State {
+                active: 0,
+                queued: 0,
+                shedding_since: None,
+            }),
+            released: Condvar::new(),
+            shed: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits for a resolution slot. Fails with the response to send instead of
+    /// resolving: 503 with Retry-After if the queue is full, 504 if the request
+    /// waited longer than the queue timeout.
+    pub fn admit(&self) -> Result<Permit<'_>, Response> {
+        let mut state = self.state.lock().unwrap();
+        if state.active < self.limits.max_concurrent && state.queued == 0 {
+            state.active += 1;
+            state.shedding_since = None;
+            return Ok(Permit(self));
+        }
+        if state.queued >= self.limits.queue_size {
+            self.shed.fetch_add(1, Ordering::SeqCst);
+            state.shedding_since.get_or_insert_with(Instant::now);
+            let retry_after = self.limits.queue_timeout.as_secs_f64().ceil().max(1.0);
+            return Err(Response::text("503")
+                .with_status_code(503)
+                .with_unique_header("Retry-After", retry_after.to_string()));
+        }
+
+        state.queued += 1;
+        let deadline = Instant::now() + self.limits.queue_timeout;
+        while state.active >= self.limits.max_concurrent {
+            let now = Instant::now();
+            if now >= deadline {
+                state.dequeue();
+                self.expired.fetch_add(1, Ordering::SeqCst);
+                // Pass on a wakeup this request may have consumed
+                if state.active < self.limits.max_concurrent {
+                    self.released.notify_one();
+                }
+                return Err(Response::text("504").with_status_code(504));
+            }
+            state = self.released.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        state.dequeue();
+        state.active += 1;
+        Ok(Permit(self))
+    }
+
+    /// Whether the driver takes requests, false once shedding lasted longer
+    /// than `AdmissionLimits::degrade_after`
+    pub fn is_ready(&self) -> bool {
+        match self.state.lock().unwrap().shedding_since {
+            Some(since) => since.elapsed() < self.limits.degrade_after,
+            None => true,
+        }
+    }
+
+    /// Queue depth, requests in progress, shed and expired requests in the
+    /// Prometheus text format
+    pub fn metrics(&self) -> String {
+        let (active, queued) = {
+            let state = self.state.lock().unwrap();
+            (state.active, state.queued)
+        };
+        let mut metrics = String::new();
+        for (name, kind, value) in [
+            ("queue_depth", "gauge", queued as u64),
+            ("requests_in_progress", "gauge", active as u64),
+            ("shed_total", "counter", self.shed.load(Ordering::SeqCst)),
+            (
+                "queue_expired_total",
+                "counter",
+                self.expired.load(Ordering::SeqCst),
+            ),
+        ]
+        .iter()
+        {
+            writeln!(metrics, "# TYPE indy_did_driver_{} {}", name, kind).unwrap();
+            writeln!(metrics, "indy_did_driver_{} {}", name, value).unwrap();
+        }
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    fn limits(max_concurrent: usize, queue_size: usize, queue_timeout: u64) -> AdmissionLimits {
+        AdmissionLimits {
+            max_concurrent,
+            queue_size,
+            queue_timeout: Duration::from_millis(queue_timeout),
+            degrade_after: Duration::from_secs(30),
+        }
+    }
+
+    // Sends the requests at once, each resolved by a pool taking the given time.
+    // Returns the status codes sorted.
+    fn load(admission: &Arc<Admission>, requests: usize, pool_delay: u64) -> Vec<u16> {
+        let barrier = Arc::new(Barrier::new(requests));
+        let handles: Vec<_> = (0..requests)
+            .map(|_| {
+                let admission = admission.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    match admission.admit() {
+                        Ok(_permit) => {
+                            thread::sleep(Duration::from_millis(pool_delay));
+                            200
+                        }
+                        Err(response) => response.status_code,
+                    }
+                })
+            })
+            .collect();
+        let mut codes: Vec<u16> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        codes.sort_unstable();
+        codes
+    }
+
+    #[test]
+    fn sheds_requests_beyond_queue() {
+        let admission = Arc::new(Admission::new(limits(2, 2, 5000)));
+
+        let codes = load(&admission, 8, 300);
+
+        assert_eq!(vec![200, 200, 200, 200, 503, 503, 503, 503], codes);
+        let metrics = admission.metrics();
+        assert!(metrics.contains("indy_did_driver_shed_total 4\n"));
+        assert!(metrics.contains("indy_did_driver_queue_depth 0\n"));
+        assert!(metrics.contains("indy_did_driver_requests_in_progress 0\n"));
+    }
+
+    #[test]
+    fn drops_requests_waiting_past_timeout() {
+        let admission = Arc::new(Admission::new(limits(1, 4, 100)));
+
+        let codes = load(&admission, 3, 500);
+
+        assert_eq!(vec![200, 504, 504], codes);
+        assert!(admission
+            .metrics()
+            .contains("indy_did_driver_queue_expired_total 2\n"));
+    }
+
+    #[test]
+    fn shed_response_has_retry_after() {
+        let admission = Admission::new(limits(1, 0, 2500));
+        let _permit = admission.admit().unwrap();
+
+        let response = admission.admit().err().unwrap();
+
+        assert_eq!(503, response.status_code);
+        assert!(response
+            .headers
+            .iter()
+            .any(|(name, value)| name == "Retry-After" && value == "3"));
+    }
+
+    #[test]
+    fn readiness_degrades_while_shedding_persists() {
+        let admission = Admission::new(AdmissionLimits {
+            degrade_after: Duration::ZERO,
+            ..limits(1, 0, 100)
+        });
+        let permit = admission.admit().unwrap();
+        assert!(admission.is_ready());
+
+        assert!(admission.admit().is_err());
+        assert!(!admission.is_ready());
+
+        drop(permit);
+        let _permit = admission.admit().unwrap();
+        assert!(admission.is_ready());
+    }
+}
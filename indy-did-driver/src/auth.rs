@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rouille::{Request, Response};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+static RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// API key of a partner as configured in the keys file. Only the SHA-256 digest
+/// of the key is stored.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ApiKey {
+    /// Name the usage of the key is reported under
+    pub name: String,
+    /// Hex encoded SHA-256 digest of the key
+    pub sha256: String,
+    /// Requests per minute allowed with the key, overriding the default limit
+    #[serde(default)]
+    pub rate_limit: Option<u32>,
+    /// Whether the key grants access to the admin endpoints
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Default)]
+struct Usage {
+    requests: u64,
+    rate_limited: u64,
+    window_start: Option<Instant>,
+    in_window: u32,
+}
+
+/// API keys accepted by the driver, with the usage of each key
+pub struct ApiKeys {
+    keys: Vec<ApiKey>,
+    default_rate_limit: Option<u32>,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl ApiKeys {
+    /// Reads the keys from a JSON file holding a list of `ApiKey`s
+    pub fn load(path: impl AsRef<Path>, default_rate_limit: Option<u32>) -> Result<Self, String> {
+        let keys = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let keys: Vec<ApiKey> = serde_json::from_str(&keys).map_err(|err| err.to_string())?;
+        ApiKeys::new(keys, default_rate_limit)
+    }
+
+    pub fn new(keys: Vec<ApiKey>, default_rate_limit: Option<u32>) -> Result<Self, String> {
+        let mut keys = keys;
+        for key in keys.iter_mut() {
+            key.sha256 = key.sha256.to_lowercase();
+            if key.sha256.len() != 64 || !key.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Invalid SHA-256 digest for API key {}", key.name));
+            }
+        }
+        Ok(ApiKeys {
+            keys,
+            default_rate_limit,
+            usage: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Checks the key of the request, given as `Authorization: Bearer <key>` or
+    /// `X-API-Key: <key>`, and counts the request towards its usage. Returns the
+    /// name of the key, or the response rejecting the request: 401 without a
+    /// key, 403 for an unknown key and 429 if the rate limit of the key is
+    /// exceeded.
+    pub fn authenticate(&self, request: &Request) -> Result<String, Response> {
+        let presented = match presented_key(request) {
+            Some(key) => key,
+            None => {
+                return Err(Response::text("401")
+                    .with_status_code(401)
+                    .with_unique_header("WWW-Authenticate", "Bearer"))
+            }
+        };
+        let digest = format!("{:x}", Sha256::digest(presented.as_bytes()));
+
+        // All keys are compared so the time taken does not depend on the match
+        let mut matched = None;
+        for key in &self.keys {
+            if constant_time_eq(key.sha256.as_bytes(), digest.as_bytes()) {
+                matched = Some(key);
+            }
+        }
+        let key = match matched {
+            Some(key) => key,
+            None => return Err(Response::text("403").with_status_code(403)),
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(key.name.clone()).or_default();
+        let now = Instant::now();
+        match usage.window_start {
+            Some(start) if now.duration_since(start) < RATE_LIMIT_WINDOW => {}
+            _ => {
+                usage.window_start = Some(now);
+                usage.in_window = 0;
+            }
+        }
+        if let Some(limit) = key.rate_limit.or(self.default_rate_limit) {
+            if usage.in_window >= limit {
+                usage.rate_limited += 1;
+                return Err(Response::text("429").with_status_code(429));
+            }
+        }
+        usage.in_window += 1;
+        usage.requests += 1;
+        Ok(key.name.clone())
+    }
+
+    /// Whether the key of the given name grants access to the admin endpoints
+    pub fn is_admin(&self, name: &str) -> bool {
+        self.keys.iter().any(|key| key.name == name && key.admin)
+    }
+
+    /// Usage counters of the keys in the Prometheus text format
+    pub fn metrics(&self) -> String {
+        let usage = self.usage.lock().unwrap();
+        let mut names: Vec<&String> = usage.keys().collect();
+        names.sort();
+
+        let mut metrics = String::new();
+        writeln!(
+            metrics,
+            "# TYPE indy_did_driver_api_key_requests_total counter"
+        )
+        .unwrap();
+        for name in &names {
+            writeln!(
+                metrics,
+                "indy_did_driver_api_key_requests_total{{key=\"{}\"}} {}",
+                name, usage[*name].requests
+            )
+            .unwrap();
+        }
+        writeln!(
+            metrics,
+            "# TYPE indy_did_driver_api_key_rate_limited_total counter"
+        )
+        .unwrap();
+        for name in &names {
+            writeln!(
+                metrics,
+                "indy_did_driver_api_key_rate_limited_total{{key=\"{}\"}} {}",
+                name, usage[*name].rate_limited
+            )
+            .unwrap();
+        }
+        metrics
+    }
+}
+
+fn presented_key(request: &Request) -> Option<&str> {
+    if let Some(key) = request.header("X-API-Key") {
+        return Some(key.trim());
+    }
+    request
+        .header("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|key| key.trim())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(key: &str) -> String {
+        format!("{:x}", Sha256::digest(key.as_bytes()))
+    }
+
+    fn api_keys() -> ApiKeys {
+        ApiKeys::new(
+            vec![ApiKey {
+                name: String::from("partner"),
+                sha256: digest("partner-secret"),
+                rate_limit: None,
+                admin: false,
+            }],
+            None,
+        )
+        .unwrap()
+    }
+
+    fn request(headers: Vec<(&str, &str)>) -> Request {
+        Request::fake_http(
+            "GET",
+            "/1.0/identifiers/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            headers
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn valid_key_is_accepted() {
+        let keys = api_keys();
+        assert_eq!(
+            Ok(String::from("partner")),
+            keys.authenticate(&request(vec![("Authorization", "Bearer partner-secret")]))
+                .map_err(|response| response.status_code)
+        );
+        assert_eq!(
+            Ok(String::from("partner")),
+            keys.authenticate(&request(vec![("X-API-Key", "partner-secret")]))
+                .map_err(|response| response.status_code)
+        );
+        assert!(keys
+            .metrics()
+            .contains("indy_did_driver_api_key_requests_total{key=\"partner\"} 2"));
+    }
+
+    #[test]
+    fn missing_key_is_unauthorized() {
+        let response = api_keys().authenticate(&request(vec![])).unwrap_err();
+        assert_eq!(401, response.status_code);
+    }
+
+    #[test]
+    fn wrong_key_is_forbidden() {
+        let keys = api_keys();
+        let response = keys
+            .authenticate(&request(vec![("X-API-Key", "other-secret")]))
+            .unwrap_err();
+        assert_eq!(403, response.status_code);
+        let response = keys
+            .authenticate(&request(vec![("Authorization", "Basic partner-secret")]))
+            .unwrap_err();
+        assert_eq!(401, response.status_code);
+    }
+
+    #[test]
+    fn rate_limit_of_key_overrides_default() {
+        let keys = ApiKeys::new(
+            vec![ApiKey {
+                name: String::from("partner"),
+                sha256: digest("partner-secret"),
+                rate_limit: Some(2),
+                admin: false,
+            }],
+            Some(100),
+        )
+        .unwrap();
+        let request = request(vec![("X-API-Key", "partner-secret")]);
+
+        assert!(keys.authenticate(&request).is_ok());
+        assert!(keys.authenticate(&request).is_ok());
+        assert_eq!(429, keys.authenticate(&request).unwrap_err().status_code);
+        assert!(keys
+            .metrics()
+            .contains("indy_did_driver_api_key_rate_limited_total{key=\"partner\"} 1"));
+    }
+
+    #[test]
+    fn only_admin_keys_grant_admin_access() {
+        let keys = ApiKeys::new(
+            vec![
+                ApiKey {
+                    name: String::from("partner"),
+                    sha256: digest("partner-secret"),
+                    rate_limit: None,
+                    admin: false,
+                },
+                ApiKey {
+                    name: String::from("operator"),
+                    sha256: digest("operator-secret"),
+                    rate_limit: None,
+                    admin: true,
+                },
+            ],
+            None,
+        )
+        .unwrap();
+        assert!(!keys.is_admin("partner"));
+        assert!(keys.is_admin("operator"));
+        assert!(!keys.is_admin("unknown"));
+    }
+
+    #[test]
+    fn invalid_digest_is_rejected() {
+        let keys = ApiKeys::new(
+            vec![ApiKey {
+                name: String::from("partner"),
+                sha256: String::from("partner-secret"),
+                rate_limit: None,
+                admin: false,
+            }],
+            None,
+        );
+        assert!(keys.is_err());
+    }
+}
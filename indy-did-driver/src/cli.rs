@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use indy_didresolver::did::DidUrl;
+use indy_didresolver::error::DidIndyError;
+use indy_didresolver::networks::NetworkProvider;
+use indy_didresolver::resolver::Resolver;
+use indy_vdr::pool::SharedPool;
+use serde_json::{json, Value};
+
+use crate::http::ErrorMetadata;
+
+// Stable exit codes of the resolve, dereference and validate commands
+pub const SUCCESS: i32 = 0;
+pub const FAILURE: i32 = 1;
+pub const INVALID_DID_URL: i32 = 2;
+pub const NOT_FOUND: i32 = 3;
+pub const LEDGER_FAILURE: i32 = 4;
+pub const CONFIGURATION_ERROR: i32 = 5;
+
+/// Format of the errors printed to stderr
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    /// The error object of the resolution metadata, `error` and `message`
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Unknown error format {}, use text or json", format)),
+        }
+    }
+}
+
+/// Exit code of a failed resolution. Every error names its code, so that
+/// scripts can rely on the codes of new errors too.
+pub fn exit_code(error: &DidIndyError) -> i32 {
+    match error {
+        DidIndyError::InvalidDidUrl
+        | DidIndyError::MisorderedDidUrl { .. }
+        | DidIndyError::NamespaceRequired(_)
+        | DidIndyError::AmbiguousIdentifier { .. }
+        | DidIndyError::ConflictingQueryParameters { .. }
+        | DidIndyError::QueryParameterNotSupported
+        | DidIndyError::DateTimeError(_)
+        | DidIndyError::InvalidTimestamp(_)
+        | DidIndyError::ObjectFamilyNotSupported
+        | DidIndyError::UnsupportedAnoncredsVersion { .. }
+        | DidIndyError::ObjectTypeNotSuported
+        | DidIndyError::UnsupportedLedgerObject { .. }
+        | DidIndyError::UnsupportedTransform
+        | DidIndyError::RepresentationNotSupported(_) => INVALID_DID_URL,
+        DidIndyError::EmptyData | DidIndyError::NotFound | DidIndyError::NotFoundBefore { .. } => {
+            NOT_FOUND
+        }
+        DidIndyError::PoolUnavailable(_)
+        | DidIndyError::RequestTimeout
+        | DidIndyError::ResolutionTimeout
+        | DidIndyError::LedgerUnreachable(_)
+        | DidIndyError::VersionedRequestRejected { .. }
+        | DidIndyError::PolicyNotSatisfied { .. }
+        | DidIndyError::RevRegListingNotSupported
+        | DidIndyError::VdrError(_) => LEDGER_FAILURE,
+        DidIndyError::NamespaceNotSupported
+        | DidIndyError::UnknownNamespace(_)
+        | DidIndyError::UnknownNetwork { .. }
+        | DidIndyError::GenesisHashNotFound { .. }
+        | DidIndyError::InvalidSigningKey
+        | DidIndyError::RequestSigningFailed(_) => CONFIGURATION_ERROR,
+        DidIndyError::ParsingError(_)
+        | DidIndyError::ParseContent { .. }
+        | DidIndyError::RevocationIndexOutOfRange { .. }
+        | DidIndyError::InvalidDidDoc
+        | DidIndyError::InvalidServiceEndpoint(_)
+        | DidIndyError::ServiceIdConflict(_)
+        | DidIndyError::VerificationMethodIdConflict(_)
+        | DidIndyError::DidDocumentValidation(_)
+        | DidIndyError::NotImplemented => FAILURE,
+    }
+}
+
+/// Failure of a command with the exit code and the error to print
+pub struct CliError {
+    pub exit_code: i32,
+    metadata: ErrorMetadata,
+}
+
+impl CliError {
+    pub fn of(error: &DidIndyError) -> Self {
+        CliError {
+            exit_code: exit_code(error),
+            metadata: ErrorMetadata::of(error),
+        }
+    }
+
+    /// Invalid or conflicting arguments naming the DID URL
+    pub fn invalid_arguments(message: String) -> Self {
+        CliError {
+            exit_code: INVALID_DID_URL,
+            metadata: ErrorMetadata::new("invalidDidUrl", message),
+        }
+    }
+
+    /// Network directory, genesis or batch file that cannot be used
+    pub fn configuration(message: String) -> Self {
+        CliError {
+            exit_code: CONFIGURATION_ERROR,
+            metadata: ErrorMetadata::new("internalError", message),
+        }
+    }
+
+    /// The error as a line for stderr
+    pub fn render(&self, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Text => format!("{}\n", self.metadata.message()),
+            ErrorFormat::Json => format!("{}\n", json!(self.metadata)),
+        }
+    }
+}
+
+/// What a command prints and its exit code
+#[derive(Debug, Default)]
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+}
+
+impl Output {
+    pub fn failed(error: CliError, format: ErrorFormat) -> Self {
+        Output {
+            stdout: String::new(),
+            stderr: error.render(format),
+            exit_code: error.exit_code,
+        }
+    }
+}
+
+/// Resolves or dereferences the DIDs and DID URLs with the pools of their
+/// namespaces. A single input is printed as it is and fails with its exit code.
+/// A batch prints one JSON object per line with the result or error of each
+/// input, and a summary of the failures to stderr. It exits with the code of
+/// its first failure.
+pub fn run(
+    provider: &dyn NetworkProvider<SharedPool>,
+    inputs: &[String],
+    batch: bool,
+    format: ErrorFormat,
+) -> Output {
+    let mut resolvers = HashMap::new();
+    let results: Vec<Result<String, CliError>> = inputs
+        .iter()
+        .map(|input| resolve(provider, &mut resolvers, input))
+        .collect();
+
+    if !batch {
+        return match results.into_iter().next() {
+            Some(Ok(result)) => Output {
+                stdout: format!("{}\n", result),
+                ..Output::default()
+            },
+            Some(Err(error)) => Output::failed(error, format),
+            None => Output::default(),
+        };
+    }
+
+    let mut output = Output::default();
+    let mut failures = Vec::new();
+    for (input, result) in inputs.iter().zip(results) {
+        let line = match result {
+            Ok(result) => json!({
+                "didUrl": input,
+                "result": serde_json::from_str::<Value>(&result).unwrap_or(Value::String(result)),
+            }),
+            Err(error) => {
+                let line = json!({ "didUrl": input, "error": error.metadata });
+                if output.exit_code == SUCCESS {
+                    output.exit_code = error.exit_code;
+                }
+                failures.push((input, error));
+                line
+            }
+        };
+        output.stdout.push_str(&format!("{}\n", line));
+    }
+    if !failures.is_empty() {
+        output.stderr = match format {
+            ErrorFormat::Text => {
+                let mut summary = format!("{} of {} failed\n", failures.len(), inputs.len());
+                for (input, error) in &failures {
+                    summary.push_str(&format!("{}: {}", input, error.render(format)));
+                }
+                summary
+            }
+            ErrorFormat::Json => format!(
+                "{}\n",
+                json!({
+                    "total": inputs.len(),
+                    "failed": failures.len(),
+                    "exitCode": output.exit_code,
+                    "errors": failures
+                        .iter()
+                        .map(|(input, error)| json!({ "didUrl": input, "error": error.metadata }))
+                        .collect::<Vec<Value>>(),
+                })
+            ),
+        };
+    }
+    output
+}
+
+// Resolves a DID or dereferences a DID URL, building the pool of a namespace
+// once per run
+fn resolve(
+    provider: &dyn NetworkProvider<SharedPool>,
+    resolvers: &mut HashMap<String, Resolver<SharedPool>>,
+    input: &str,
+) -> Result<String, CliError> {
+    let did_url = DidUrl::from_str(input).map_err(|err| CliError::of(&err))?;
+    if !resolvers.contains_key(&did_url.namespace) {
+        let pool = provider.pool_for(&did_url.namespace).ok_or_else(|| {
+            CliError::configuration(format!(
+                "Could not initialize pool for {}",
+                did_url.namespace
+            ))
+        })?;
+        resolvers.insert(did_url.namespace.clone(), Resolver::new(pool));
+    }
+    let resolver = &resolvers[&did_url.namespace];
+    let result = if did_url.path.is_some() {
+        resolver.dereference_string(input)
+    } else {
+        resolver.resolve_string(input)
+    };
+    result.map_err(|err| CliError::of(&err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoNetworks;
+
+    impl NetworkProvider<SharedPool> for NoNetworks {
+        fn pool_for(&self, _namespace: &str) -> Option<SharedPool> {
+            None
+        }
+    }
+
+    #[test]
+    fn exit_codes_of_errors() {
+        assert_eq!(INVALID_DID_URL, exit_code(&DidIndyError::InvalidDidUrl));
+        assert_eq!(NOT_FOUND, exit_code(&DidIndyError::NotFound));
+        assert_eq!(LEDGER_FAILURE, exit_code(&DidIndyError::RequestTimeout));
+        assert_eq!(
+            CONFIGURATION_ERROR,
+            exit_code(&DidIndyError::NamespaceNotSupported)
+        );
+        assert_eq!(FAILURE, exit_code(&DidIndyError::InvalidDidDoc));
+    }
+
+    #[test]
+    fn invalid_did_url_as_json_error() {
+        let output = run(
+            &NoNetworks,
+            &[String::from("did:indy:idunion:invalid")],
+            false,
+            ErrorFormat::Json,
+        );
+
+        assert_eq!(INVALID_DID_URL, output.exit_code);
+        assert!(output.stdout.is_empty());
+        let error: Value = serde_json::from_str(&output.stderr).unwrap();
+        assert_eq!(
+            json!({ "error": "invalidDid", "message": "Invalid DID URL" }),
+            error
+        );
+    }
+
+    #[test]
+    fn unknown_network_as_text_error() {
+        let output = run(
+            &NoNetworks,
+            &[String::from("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")],
+            false,
+            ErrorFormat::Text,
+        );
+
+        assert_eq!(CONFIGURATION_ERROR, output.exit_code);
+        assert_eq!("Could not initialize pool for idunion\n", output.stderr);
+    }
+
+    #[test]
+    fn batch_failures_summarized() {
+        let inputs = vec![
+            String::from("did:indy:idunion:invalid"),
+            String::from("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+        ];
+
+        let output = run(&NoNetworks, &inputs, true, ErrorFormat::Json);
+
+        assert_eq!(INVALID_DID_URL, output.exit_code);
+        let lines: Vec<Value> = output
+            .stdout
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(2, lines.len());
+        assert_eq!("did:indy:idunion:invalid", lines[0]["didUrl"]);
+        assert_eq!("invalidDid", lines[0]["error"]["error"]);
+        assert_eq!("internalError", lines[1]["error"]["error"]);
+        let summary: Value = serde_json::from_str(&output.stderr).unwrap();
+        assert_eq!(2, summary["total"]);
+        assert_eq!(2, summary["failed"]);
+        assert_eq!(INVALID_DID_URL, summary["exitCode"]);
+    }
+}
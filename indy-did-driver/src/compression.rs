@@ -0,0 +1,284 @@
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use rouille::{Request, Response, ResponseBody};
+
+type Headers = Vec<(Cow<'static, str>, Cow<'static, str>)>;
+
+/// Compression of response bodies negotiated with `Accept-Encoding`
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionSettings {
+    /// Bodies smaller than this many bytes are sent as they are, compressing
+    /// them saves less than it costs
+    pub min_size: usize,
+    /// zlib compression level from 0 (none) to 9 (best)
+    pub level: u32,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        CompressionSettings {
+            min_size: 1024,
+            level: 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Compresses the body of the response with gzip or deflate if the request
+/// accepts one of them. Responses to HEAD requests, small bodies and bodies that
+/// are already encoded are left as they are. Responses that could be compressed
+/// carry `Vary: Accept-Encoding`, also if the request accepts no compression,
+/// and a weak `ETag` once compressed.
+pub fn compress(request: &Request, response: Response, settings: &CompressionSettings) -> Response {
+    if request.method() == "HEAD"
+        || response
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Content-Encoding"))
+    {
+        return response;
+    }
+    let Response {
+        status_code,
+        headers,
+        data,
+        upgrade,
+    } = response;
+    let (mut reader, size) = data.into_reader_and_size();
+    let mut body = Vec::with_capacity(size.unwrap_or_default());
+    if let Err(err) = reader.read_to_end(&mut body) {
+        error!("Could not read response body for compression, {}", err);
+        return Response::text("500").with_status_code(500);
+    }
+    let eligible = body.len() >= settings.min_size;
+    let encoding = match request.header("Accept-Encoding").and_then(negotiate) {
+        Some(encoding) if eligible => encoding,
+        _ => {
+            let response = Response {
+                status_code,
+                headers,
+                data: ResponseBody::from_data(body),
+                upgrade,
+            };
+            return if eligible {
+                response.with_additional_header("Vary", "Accept-Encoding")
+            } else {
+                response
+            };
+        }
+    };
+
+    let level = flate2::Compression::new(settings.level);
+    let compressed = match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), level);
+            encoder.write_all(&body).and_then(|_| encoder.finish())
+        }
+        Encoding::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(&body).and_then(|_| encoder.finish())
+        }
+    };
+    let (data, headers) = match compressed {
+        Ok(compressed) => {
+            debug!(
+                "Compressed response with {} from {} to {} bytes",
+                encoding.name(),
+                body.len(),
+                compressed.len()
+            );
+            (ResponseBody::from_data(compressed), weaken_etag(headers))
+        }
+        Err(err) => {
+            warn!("Could not compress response, sending it as it is, {}", err);
+            let response = Response {
+                status_code,
+                headers,
+                data: ResponseBody::from_data(body),
+                upgrade,
+            };
+            return response.with_additional_header("Vary", "Accept-Encoding");
+        }
+    };
+    Response {
+        status_code,
+        headers,
+        data,
+        upgrade,
+    }
+    .with_additional_header("Vary", "Accept-Encoding")
+    .with_unique_header("Content-Encoding", encoding.name())
+}
+
+// The compressed body differs from the plain one the ETag was computed for, it
+// is only equivalent to it. `If-None-Match` also matches weak ETags.
+fn weaken_etag(mut headers: Headers) -> Headers {
+    for (name, value) in headers.iter_mut() {
+        if name.eq_ignore_ascii_case("ETag") && !value.starts_with("W/") {
+            *value = Cow::Owned(format!("W/{}", value));
+        }
+    }
+    headers
+}
+
+// Encoding with the highest quality value the Accept-Encoding header allows,
+// gzip if both are equally acceptable
+fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut wildcard = None;
+    let (mut gzip, mut deflate) = (None, None);
+    for coding in accept_encoding.split(',') {
+        let mut params = coding.split(';');
+        let name = params.next().unwrap().trim().to_ascii_lowercase();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match name.as_str() {
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "deflate" => deflate = Some(quality),
+            "*" => wildcard = Some(quality),
+            _ => {}
+        }
+    }
+    let gzip = gzip.or(wildcard).unwrap_or(0.0);
+    let deflate = deflate.or(wildcard).unwrap_or(0.0);
+    if gzip <= 0.0 && deflate <= 0.0 {
+        None
+    } else if gzip >= deflate {
+        Some(Encoding::Gzip)
+    } else {
+        Some(Encoding::Deflate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    fn request(method: &str, accept_encoding: Option<&str>) -> Request {
+        Request::fake_http(
+            method,
+            "/1.0/identifiers/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            accept_encoding
+                .map(|value| (String::from("Accept-Encoding"), value.to_string()))
+                .into_iter()
+                .collect(),
+            vec![],
+        )
+    }
+
+    fn result() -> String {
+        serde_json::json!({
+            "didDocument": {"id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"},
+            "didDocumentMetadata": {
+                "methodMetadata": {"nodeResponse": {"result": {"data": "x".repeat(4096)}}}
+            }
+        })
+        .to_string()
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &**v)
+    }
+
+    fn body(response: Response) -> Vec<u8> {
+        let (mut reader, _) = response.data.into_reader_and_size();
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        body
+    }
+
+    #[test]
+    fn gzip_body_decompresses_to_plain_body() {
+        let response = compress(
+            &request("GET", Some("gzip, deflate, br")),
+            Response::text(result()).with_unique_header("ETag", "\"abc\""),
+            &CompressionSettings::default(),
+        );
+
+        assert_eq!(Some("gzip"), header(&response, "Content-Encoding"));
+        assert_eq!(Some("Accept-Encoding"), header(&response, "Vary"));
+        assert_eq!(Some("W/\"abc\""), header(&response, "ETag"));
+        let compressed = body(response);
+        assert!(compressed.len() < result().len());
+        let mut decompressed = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(result(), decompressed);
+    }
+
+    #[test]
+    fn deflate_body_decompresses_to_plain_body() {
+        let response = compress(
+            &request("GET", Some("gzip;q=0.5, deflate")),
+            Response::text(result()),
+            &CompressionSettings {
+                level: 9,
+                ..CompressionSettings::default()
+            },
+        );
+
+        assert_eq!(Some("deflate"), header(&response, "Content-Encoding"));
+        let mut decompressed = String::new();
+        ZlibDecoder::new(body(response).as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(result(), decompressed);
+    }
+
+    #[test]
+    fn plain_body_without_accepted_encoding() {
+        for accept_encoding in [None, Some("br"), Some("gzip;q=0, identity")] {
+            let response = compress(
+                &request("GET", accept_encoding),
+                Response::text(result()),
+                &CompressionSettings::default(),
+            );
+
+            assert_eq!(None, header(&response, "Content-Encoding"));
+            assert_eq!(Some("Accept-Encoding"), header(&response, "Vary"));
+            assert_eq!(result().into_bytes(), body(response));
+        }
+    }
+
+    #[test]
+    fn small_bodies_and_head_requests_not_compressed() {
+        let response = compress(
+            &request("GET", Some("gzip")),
+            Response::text("OK"),
+            &CompressionSettings::default(),
+        );
+        assert_eq!(None, header(&response, "Content-Encoding"));
+        assert_eq!(None, header(&response, "Vary"));
+        assert_eq!(b"OK".to_vec(), body(response));
+
+        let response = compress(
+            &request("HEAD", Some("gzip")),
+            Response::text(result()),
+            &CompressionSettings::default(),
+        );
+        assert_eq!(None, header(&response, "Content-Encoding"));
+    }
+}
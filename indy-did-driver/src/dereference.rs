@@ -0,0 +1,313 @@
+use std::path::PathBuf;
+
+use indy_didresolver::did::{DidUrlBuilder, QueryParameter};
+
+use crate::cli::CliError;
+use crate::validate::read_batch;
+
+/// DID URL to dereference, given as a whole or built from its parts
+#[derive(clap::Args, Debug, Default)]
+pub struct DereferenceArgs {
+    /// DID URL to dereference, instead of building it from --did and an object flag
+    pub did_url: Option<String>,
+    /// DID of the ledger object, e.g. did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp
+    #[clap(long = "did")]
+    pub did: Option<String>,
+    /// Name of a schema, requires --version
+    #[clap(long = "schema")]
+    pub schema: Option<String>,
+    /// Version of the schema
+    #[clap(long = "version")]
+    pub version: Option<String>,
+    /// Schema sequence number of a cred def, requires --tag
+    #[clap(long = "claim-def")]
+    pub claim_def: Option<u32>,
+    /// Schema sequence number of the cred def of a revocation registry
+    /// definition, requires --tag and --rev-reg-tag
+    #[clap(long = "rev-reg-def")]
+    pub rev_reg_def: Option<u32>,
+    /// Like --rev-reg-def for a revocation registry entry, optionally at
+    /// --version-time
+    #[clap(long = "rev-reg-entry")]
+    pub rev_reg_entry: Option<u32>,
+    /// Like --rev-reg-def for a revocation registry delta, optionally between
+    /// --from and --to
+    #[clap(long = "rev-reg-delta")]
+    pub rev_reg_delta: Option<u32>,
+    /// Tag of the cred def
+    #[clap(long = "tag")]
+    pub tag: Option<String>,
+    /// Tag of the revocation registry
+    #[clap(long = "rev-reg-tag")]
+    pub rev_reg_tag: Option<String>,
+    /// Time of the revocation registry entry, RFC3339
+    #[clap(long = "version-time")]
+    pub version_time: Option<String>,
+    /// Start of the revocation registry delta, RFC3339
+    #[clap(long = "from")]
+    pub from: Option<String>,
+    /// End of the revocation registry delta, RFC3339
+    #[clap(long = "to")]
+    pub to: Option<String>,
+    /// Genesis file of the pool to use instead of the network directory
+    #[clap(long = "genesis")]
+    pub genesis: Option<PathBuf>,
+    /// File with one DID URL per line, printed as one JSON object per line
+    #[clap(long = "batch")]
+    pub batch: Option<PathBuf>,
+}
+
+/// DID URLs of the batch file, or the one given or built from the parts
+pub fn did_urls(args: &DereferenceArgs) -> Result<Vec<String>, CliError> {
+    match &args.batch {
+        Some(_) if args.did_url.is_some() || args.did.is_some() => Err(
+            CliError::invalid_arguments(String::from("Give either --batch or a DID URL, not both")),
+        ),
+        Some(batch) => read_batch(batch).map_err(CliError::configuration),
+        None => did_url(args)
+            .map(|did_url| vec![did_url])
+            .map_err(CliError::invalid_arguments),
+    }
+}
+
+/// The DID URL given or built from the parts. Fails with a message naming the
+/// conflicting or missing flags.
+pub fn did_url(args: &DereferenceArgs) -> Result<String, String> {
+    let objects: Vec<&str> = [
+        ("--schema", args.schema.is_some()),
+        ("--claim-def", args.claim_def.is_some()),
+        ("--rev-reg-def", args.rev_reg_def.is_some()),
+        ("--rev-reg-entry", args.rev_reg_entry.is_some()),
+        ("--rev-reg-delta", args.rev_reg_delta.is_some()),
+    ]
+    .iter()
+    .filter(|(_, set)| *set)
+    .map(|(flag, _)| *flag)
+    .collect();
+
+    if let Some(did_url) = &args.did_url {
+        if args.did.is_some() || !objects.is_empty() {
+            return Err(String::from(
+                "Give either a DID URL or --did with an object flag, not both",
+            ));
+        }
+        return Ok(did_url.clone());
+    }
+    let did = args
+        .did
+        .as_deref()
+        .ok_or("Give a DID URL or --did with an object flag")?;
+    if objects.len() > 1 {
+        return Err(format!(
+            "Only one ledger object can be dereferenced, got {}",
+            objects.join(" and ")
+        ));
+    }
+    check_only_with(
+        &[("--version-time", args.version_time.is_some())],
+        args.rev_reg_entry.is_some(),
+        "--rev-reg-entry",
+    )?;
+    check_only_with(
+        &[("--from", args.from.is_some()), ("--to", args.to.is_some())],
+        args.rev_reg_delta.is_some(),
+        "--rev-reg-delta",
+    )?;
+
+    let builder = DidUrlBuilder::new(did).map_err(|_| format!("Invalid DID {}", did))?;
+    let builder = if let Some(name) = &args.schema {
+        builder.schema(name, required(&args.version, "--schema", "--version")?)
+    } else if let Some(seq_no) = args.claim_def {
+        builder.claim_def(seq_no, required(&args.tag, "--claim-def", "--tag")?)
+    } else if let Some(seq_no) = args.rev_reg_def {
+        let (tag, rev_reg_tag) = rev_reg_tags(args, "--rev-reg-def")?;
+        builder.rev_reg_def(seq_no, tag, rev_reg_tag)
+    } else if let Some(seq_no) = args.rev_reg_entry {
+        let (tag, rev_reg_tag) = rev_reg_tags(args, "--rev-reg-entry")?;
+        let builder = builder.rev_reg_entry(seq_no, tag, rev_reg_tag);
+        match &args.version_time {
+            Some(time) => builder.query(QueryParameter::VersionTime, time),
+            None => builder,
+        }
+    } else if let Some(seq_no) = args.rev_reg_delta {
+        let (tag, rev_reg_tag) = rev_reg_tags(args, "--rev-reg-delta")?;
+        let mut builder = builder.rev_reg_delta(seq_no, tag, rev_reg_tag);
+        if let Some(from) = &args.from {
+            builder = builder.query(QueryParameter::From, from);
+        }
+        if let Some(to) = &args.to {
+            builder = builder.query(QueryParameter::To, to);
+        }
+        builder
+    } else {
+        return Err(String::from(
+            "--did needs one of --schema, --claim-def, --rev-reg-def, --rev-reg-entry or --rev-reg-delta",
+        ));
+    };
+    builder
+        .build()
+        .map_err(|err| format!("Invalid DID URL from the given parts, {}", err))
+}
+
+fn required<'a>(
+    value: &'a Option<String>,
+    flag: &str,
+    requirement: &str,
+) -> Result<&'a str, String> {
+    value
+        .as_deref()
+        .ok_or_else(|| format!("{} requires {}", flag, requirement))
+}
+
+fn rev_reg_tags<'a>(args: &'a DereferenceArgs, flag: &str) -> Result<(&'a str, &'a str), String> {
+    Ok((
+        required(&args.tag, flag, "--tag")?,
+        required(&args.rev_reg_tag, flag, "--rev-reg-tag")?,
+    ))
+}
+
+// Fails if any of the flags is set without the object flag they belong to
+fn check_only_with(flags: &[(&str, bool)], object_set: bool, object: &str) -> Result<(), String> {
+    match flags.iter().find(|(_, set)| *set) {
+        Some((flag, _)) if !object_set => Err(format!("{} can only be used with {}", flag, object)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static DID: &str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+    fn args() -> DereferenceArgs {
+        DereferenceArgs {
+            did: Some(String::from(DID)),
+            ..DereferenceArgs::default()
+        }
+    }
+
+    fn some(value: &str) -> Option<String> {
+        Some(String::from(value))
+    }
+
+    #[test]
+    fn schema_url() {
+        let args = DereferenceArgs {
+            schema: some("My Schema"),
+            version: some("1.0"),
+            ..args()
+        };
+        assert_eq!(
+            Ok(format!("{}/anoncreds/v0/SCHEMA/My%20Schema/1.0", DID)),
+            did_url(&args)
+        );
+    }
+
+    #[test]
+    fn claim_def_url() {
+        let args = DereferenceArgs {
+            claim_def: Some(104),
+            tag: some("default"),
+            ..args()
+        };
+        assert_eq!(
+            Ok(format!("{}/anoncreds/v0/CLAIM_DEF/104/default", DID)),
+            did_url(&args)
+        );
+    }
+
+    #[test]
+    fn rev_reg_urls() {
+        let rev_reg = |args: DereferenceArgs| DereferenceArgs {
+            tag: some("revocable"),
+            rev_reg_tag: some("a4e25e54"),
+            ..args
+        };
+        assert_eq!(
+            Ok(format!(
+                "{}/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54",
+                DID
+            )),
+            did_url(&rev_reg(DereferenceArgs {
+                rev_reg_def: Some(104),
+                ..args()
+            }))
+        );
+        assert_eq!(
+            Ok(format!(
+                "{}/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14%3A33%3A42Z",
+                DID
+            )),
+            did_url(&rev_reg(DereferenceArgs {
+                rev_reg_entry: Some(104),
+                version_time: some("2021-11-11T14:33:42Z"),
+                ..args()
+            }))
+        );
+        assert_eq!(
+            Ok(format!(
+                "{}/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?to=2021-11-11T14%3A33%3A42Z",
+                DID
+            )),
+            did_url(&rev_reg(DereferenceArgs {
+                rev_reg_delta: Some(104),
+                to: some("2021-11-11T14:33:42Z"),
+                ..args()
+            }))
+        );
+    }
+
+    #[test]
+    fn object_flags_are_exclusive() {
+        let args = DereferenceArgs {
+            schema: some("npdb"),
+            version: some("4.3.4"),
+            claim_def: Some(104),
+            tag: some("default"),
+            ..args()
+        };
+        assert_eq!(
+            Err(String::from(
+                "Only one ledger object can be dereferenced, got --schema and --claim-def"
+            )),
+            did_url(&args)
+        );
+        let args = DereferenceArgs {
+            did_url: some(DID),
+            ..args
+        };
+        assert!(did_url(&args).unwrap_err().contains("not both"));
+    }
+
+    #[test]
+    fn missing_and_misplaced_flags() {
+        let error = |args| did_url(&args).unwrap_err();
+        assert_eq!(
+            "--schema requires --version",
+            error(DereferenceArgs {
+                schema: some("npdb"),
+                ..args()
+            })
+        );
+        assert_eq!(
+            "--rev-reg-def requires --rev-reg-tag",
+            error(DereferenceArgs {
+                rev_reg_def: Some(104),
+                tag: some("revocable"),
+                ..args()
+            })
+        );
+        assert_eq!(
+            "--version-time can only be used with --rev-reg-entry",
+            error(DereferenceArgs {
+                claim_def: Some(104),
+                tag: some("default"),
+                version_time: some("2021-11-11T14:33:42Z"),
+                ..args()
+            })
+        );
+        assert!(error(args()).starts_with("--did needs one of"));
+        assert!(error(DereferenceArgs::default()).starts_with("Give a DID URL"));
+    }
+}
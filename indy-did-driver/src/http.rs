@@ -0,0 +1,771 @@
+use chrono::{DateTime, TimeZone, Utc};
+use indy_didresolver::error::DidIndyError;
+use rouille::{Request, Response};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+static HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+static PROBLEM_JSON: &str = "application/problem+json";
+
+static DEREFERENCING_PROFILE: &str = "https://w3id.org/did-url-dereferencing";
+
+/// Error of a resolution as reported in the resolution or dereferencing metadata
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorMetadata {
+    error: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    /// Supported ledger objects and the one likely meant, for unsupported ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    supported_objects: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestion: Option<String>,
+}
+
+impl ErrorMetadata {
+    pub fn new(error: &'static str, message: String) -> Self {
+        ErrorMetadata {
+            error,
+            message,
+            content_type: None,
+            supported_objects: None,
+            suggestion: None,
+        }
+    }
+
+    pub fn of(error: &DidIndyError) -> Self {
+        let (supported_objects, suggestion) = match error {
+            DidIndyError::UnsupportedLedgerObject {
+                supported,
+                suggestion,
+                ..
+            } => (Some(supported.clone()), suggestion.clone()),
+            _ => (None, None),
+        };
+        ErrorMetadata {
+            supported_objects,
+            suggestion,
+            ..ErrorMetadata::new(error.error_code(), error.to_string())
+        }
+    }
+
+    /// Error of the dereferencing endpoint, where an invalid DID is an invalid
+    /// DID URL
+    pub fn of_dereferencing(error: &DidIndyError) -> Self {
+        let metadata = ErrorMetadata::of(error);
+        match metadata.error {
+            "invalidDid" => ErrorMetadata {
+                error: "invalidDidUrl",
+                ..metadata
+            },
+            _ => metadata,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Serves a resolution result with `ETag` and `Last-Modified` headers and answers
+/// with 304 if the conditional headers of the request match.
+pub fn cacheable_response(request: &Request, result: String) -> Response {
+    let etag = etag(&result);
+    let last_modified = serde_json::from_str(&result)
+        .ok()
+        .and_then(|v: Value| last_modified(&v));
+
+    let response = if not_modified(request, &etag, last_modified) {
+        Response::text("").with_status_code(304)
+    } else {
+        Response::text(result)
+    };
+
+    let response = response.with_unique_header("ETag", etag);
+    match last_modified {
+        Some(last_modified) => response.with_unique_header(
+            "Last-Modified",
+            last_modified.format(HTTP_DATE_FORMAT).to_string(),
+        ),
+        None => response,
+    }
+}
+
+/// Error in the shape expected by the Universal Resolver: the error code and a
+/// message in the resolution or dereferencing metadata
+pub fn error_response(dereferencing: bool, error: &DidIndyError) -> Response {
+    let metadata = ErrorMetadata::of(error);
+    let status = status(error.error_code());
+    let body = if dereferencing {
+        json!({
+            "dereferencingMetadata": metadata,
+            "contentStream": null,
+            "contentMetadata": {}
+        })
+    } else {
+        json!({
+            "didResolutionMetadata": metadata,
+            "didDocument": null,
+            "didDocumentMetadata": {}
+        })
+    };
+
+    Response::json(&body).with_status_code(status)
+}
+
+/// What the dereferencing endpoint answers with, negotiated by the Accept header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dereferenced {
+    /// The DereferencingResult with the content and its metadata, for no Accept
+    /// header, `*/*` or the did-url-dereferencing profile
+    Result,
+    /// The content stream alone, for `application/json`
+    Content,
+    /// The content stream wrapped in a JSON-LD resource, for `application/ld+json`
+    JsonLdContent,
+    /// None of the accepted media types can be served
+    NotAcceptable,
+}
+
+impl Dereferenced {
+    pub fn negotiate(request: &Request) -> Self {
+        let accept = match request.header("Accept") {
+            Some(accept) => accept,
+            None => return Dereferenced::Result,
+        };
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';');
+            let media_type = params.next().unwrap().trim().to_ascii_lowercase();
+            let profile = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("profile=")
+                    .map_or(false, |profile| {
+                        profile.trim_matches('"').contains(DEREFERENCING_PROFILE)
+                    })
+            });
+            match media_type.as_str() {
+                "*/*" => return Dereferenced::Result,
+                "application/json" | "application/ld+json" if profile => {
+                    return Dereferenced::Result
+                }
+                "application/json" | "application/*" => return Dereferenced::Content,
+                "application/ld+json" => return Dereferenced::JsonLdContent,
+                _ => {}
+            }
+        }
+        Dereferenced::NotAcceptable
+    }
+}
+
+/// Serves a dereferencing result in the negotiated form, as cacheable as a
+/// resolution result. The content stream is served with the `contentType` of
+/// its metadata.
+pub fn dereferencing_response(request: &Request, result: String, form: Dereferenced) -> Response {
+    match form {
+        Dereferenced::Result => cacheable_response(request, result).with_unique_header(
+            "Content-Type",
+            format!("application/ld+json;profile=\"{}\"", DEREFERENCING_PROFILE),
+        ),
+        Dereferenced::Content | Dereferenced::JsonLdContent => {
+            let result: Value = serde_json::from_str(&result).unwrap_or_default();
+            let content_type = result["contentMetadata"]["contentType"]
+                .as_str()
+                .unwrap_or("application/json")
+                .to_string();
+            cacheable_response(request, result["contentStream"].to_string())
+                .with_unique_header("Content-Type", content_type)
+        }
+        Dereferenced::NotAcceptable => not_acceptable(),
+    }
+}
+
+/// Error of the dereferencing endpoint in the DereferencingResult envelope,
+/// with the HTTP status of its dereferencing error code
+pub fn dereferencing_error_response(error: &DidIndyError) -> Response {
+    let metadata = ErrorMetadata::of_dereferencing(error);
+    let status = dereferencing_status(metadata.error);
+    dereferencing_error(metadata).with_status_code(status)
+}
+
+/// 406 for media types the dereferencing endpoint cannot serve
+pub fn not_acceptable() -> Response {
+    let code = "contentTypeNotSupported";
+    let metadata = ErrorMetadata::new(
+        code,
+        String::from("Only application/json and application/ld+json can be served"),
+    );
+    dereferencing_error(metadata).with_status_code(dereferencing_status(code))
+}
+
+fn dereferencing_error(metadata: ErrorMetadata) -> Response {
+    Response::json(&json!({
+        "dereferencingMetadata": metadata,
+        "contentStream": null,
+        "contentMetadata": {}
+    }))
+}
+
+/// RFC 7807 problem document of a resolution error
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Problem {
+    #[serde(rename = "type")]
+    type_: String,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    /// DID Resolution error code, as in the DID Resolution error format
+    error: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    supported_objects: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestion: Option<String>,
+}
+
+/// Error as RFC 7807 `application/problem+json` if the request accepts it, in
+/// the DID Resolution error format otherwise
+pub fn negotiated_error_response(
+    request: &Request,
+    dereferencing: bool,
+    error: &DidIndyError,
+) -> Response {
+    let accepts_problem = request.header("Accept").map_or(false, |accept| {
+        accept
+            .split(',')
+            .any(|media_type| media_type.split(';').next().unwrap().trim() == PROBLEM_JSON)
+    });
+    if accepts_problem {
+        problem_response(error)
+    } else {
+        error_response(dereferencing, error)
+    }
+}
+
+/// Error as RFC 7807 problem document, typed by the DID Resolution error code
+pub fn problem_response(error: &DidIndyError) -> Response {
+    let code = error.error_code();
+    let (supported_objects, suggestion) = match error {
+        DidIndyError::UnsupportedLedgerObject {
+            supported,
+            suggestion,
+            ..
+        } => (Some(supported.clone()), suggestion.clone()),
+        _ => (None, None),
+    };
+    let problem = Problem {
+        type_: format!("https://www.w3.org/ns/did#{}", screaming_snake_case(code)),
+        title: title(code),
+        status: status(code),
+        detail: error.to_string(),
+        error: code,
+        supported_objects,
+        suggestion,
+    };
+    Response::from_data(PROBLEM_JSON, serde_json::to_string(&problem).unwrap())
+        .with_status_code(problem.status)
+}
+
+fn status(code: &str) -> u16 {
+    match code {
+        "notFound" => 404,
+        "invalidDid" | "invalidDidUrl" | "unsupportedTransform" => 400,
+        "representationNotSupported" => 406,
+        // A network without genesis file is a namespace the driver does not
+        // support
+        "methodNotSupported" | "unknownNetwork" => 501,
+        _ => 500,
+    }
+}
+
+// Statuses of the dereferencing endpoint, kept apart from the ones of
+// resolution as the dereferencing interface defines its own
+fn dereferencing_status(code: &str) -> u16 {
+    match code {
+        "notFound" => 404,
+        "invalidDidUrl" | "unsupportedTransform" => 400,
+        "contentTypeNotSupported" => 406,
+        "methodNotSupported" | "unknownNetwork" => 501,
+        _ => 500,
+    }
+}
+
+fn title(code: &str) -> &'static str {
+    match code {
+        "invalidDid" => "Invalid DID",
+        "invalidDidUrl" => "Invalid DID URL",
+        "notFound" => "Not found",
+        "methodNotSupported" => "Method not supported",
+        "unsupportedTransform" => "Unsupported transformation",
+        "representationNotSupported" => "Representation not supported",
+        "unknownNetwork" => "Unknown network",
+        _ => "Internal error",
+    }
+}
+
+// invalidDidUrl -> INVALID_DID_URL
+fn screaming_snake_case(code: &str) -> String {
+    let mut converted = String::new();
+    for c in code.chars() {
+        if c.is_ascii_uppercase() {
+            converted.push('_');
+        }
+        converted.push(c.to_ascii_uppercase());
+    }
+    converted
+}
+
+// Hash of what versions the result: the DID document or content stream, the
+// version of the ledger object and the content type. The ledger reply with its
+// request id and state proof, the retrieval time and the duration differ between
+// results of the same version and are left out.
+fn etag(result: &str) -> String {
+    let hashed = match serde_json::from_str::<Value>(result) {
+        Ok(result) => {
+            let resolution = if result.get("didResolutionMetadata").is_some() {
+                &result["didResolutionMetadata"]
+            } else {
+                &result["dereferencingMetadata"]
+            };
+            let metadata = document_metadata(&result);
+            let ledger_result = &node_response(metadata)["result"];
+            json!({
+                "didDocument": result["didDocument"],
+                "contentStream": result["contentStream"],
+                "contentType": resolution["contentType"],
+                "error": resolution["error"],
+                "versionId": metadata["versionId"],
+                "deactivated": metadata["deactivated"],
+                "seqNo": ledger_result["seqNo"],
+                "txnTime": ledger_result["txnTime"],
+            })
+            .to_string()
+        }
+        Err(_) => result.to_string(),
+    };
+    format!("\"{:x}\"", Sha256::digest(hashed.as_bytes()))
+}
+
+fn document_metadata(result: &Value) -> &Value {
+    if result.get("didDocumentMetadata").is_some() {
+        &result["didDocumentMetadata"]
+    } else {
+        &result["contentMetadata"]
+    }
+}
+
+// The ledger reply is nested in methodMetadata unless the legacy shape is used
+fn node_response(metadata: &Value) -> &Value {
+    if metadata.get("methodMetadata").is_some() {
+        &metadata["methodMetadata"]["nodeResponse"]
+    } else {
+        &metadata["nodeResponse"]
+    }
+}
+
+// Ledger time of the transaction the result was built from
+fn last_modified(result: &Value) -> Option<DateTime<Utc>> {
+    node_response(document_metadata(result))["result"]["txnTime"]
+        .as_i64()
+        .and_then(|txn_time| Utc.timestamp_opt(txn_time, 0).single())
+}
+
+fn not_modified(request: &Request, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+    // If-None-Match takes precedence over If-Modified-Since
+    if let Some(if_none_match) = request.header("If-None-Match") {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim())
+            .any(|tag| tag == "*" || tag == etag || tag.strip_prefix("W/") == Some(etag));
+    }
+
+    match (request.header("If-Modified-Since"), last_modified) {
+        (Some(since), Some(last_modified)) => DateTime::parse_from_rfc2822(since)
+            .map(|since| last_modified <= since.with_timezone(&Utc))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn result() -> String {
+        json!({
+            "didDocument": {"id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"},
+            "didDocumentMetadata": {
+                "updated": "2021-11-11T14:33:42Z",
+                "methodMetadata": {
+                    "nodeResponse": {"result": {"txnTime": 1636641222}},
+                    "objectType": "NYM"
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn request(headers: Vec<(&str, &str)>) -> Request {
+        Request::fake_http(
+            "GET",
+            "/1.0/identifiers/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            headers
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            vec![],
+        )
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &**v)
+    }
+
+    #[test]
+    fn response_carries_etag_and_last_modified() {
+        let response = cacheable_response(&request(vec![]), result());
+
+        assert_eq!(200, response.status_code);
+        assert_eq!(Some(etag(&result()).as_str()), header(&response, "ETag"));
+        assert_eq!(
+            Some("Thu, 11 Nov 2021 14:33:42 GMT"),
+            header(&response, "Last-Modified")
+        );
+    }
+
+    #[test]
+    fn last_modified_from_legacy_metadata() {
+        let result = json!({
+            "didDocument": {"id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"},
+            "didDocumentMetadata": {
+                "nodeResponse": {"result": {"txnTime": 1636641222}},
+                "objectType": "NYM"
+            }
+        });
+        assert_eq!(
+            Utc.timestamp_opt(1636641222, 0).single(),
+            last_modified(&result)
+        );
+    }
+
+    #[test]
+    fn matching_if_none_match_returns_not_modified() {
+        let etag = etag(&result());
+        let response =
+            cacheable_response(&request(vec![("If-None-Match", etag.as_str())]), result());
+        assert_eq!(304, response.status_code);
+    }
+
+    #[test]
+    fn etag_ignores_resolution_duration() {
+        let with_duration = |duration: u64| {
+            let mut result: Value = serde_json::from_str(&result()).unwrap();
+            result["didResolutionMetadata"] = json!({
+                "contentType": "application/did+json",
+                "duration": duration
+            });
+            result.to_string()
+        };
+
+        assert_eq!(etag(&with_duration(3)), etag(&with_duration(250)));
+        assert_ne!(etag(&with_duration(3)), etag(&result()));
+    }
+
+    #[test]
+    fn etag_ignores_ledger_request_and_retrieval() {
+        let resolved = |req_id: u64, retrieved: &str| {
+            json!({
+                "didDocument": {"id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"},
+                "didDocumentMetadata": {
+                    "versionId": "12",
+                    "retrieved": retrieved,
+                    "methodMetadata": {
+                        "nodeResponse": {
+                            "result": {
+                                "reqId": req_id,
+                                "seqNo": 12,
+                                "txnTime": 1636641222,
+                                "state_proof": {"multi_signature": {"value": {"timestamp": req_id}}}
+                            }
+                        },
+                        "objectType": "NYM"
+                    }
+                }
+            })
+            .to_string()
+        };
+        let first = resolved(1636641300001, "2021-11-11T14:35:00Z");
+
+        assert_eq!(
+            etag(&first),
+            etag(&resolved(1636641400002, "2021-11-11T14:36:40Z"))
+        );
+        assert_ne!(
+            etag(&first),
+            etag(&first.replace("\"versionId\":\"12\"", "\"versionId\":\"13\""))
+        );
+    }
+
+    #[test]
+    fn other_if_none_match_returns_result() {
+        let response = cacheable_response(&request(vec![("If-None-Match", "\"other\"")]), result());
+        assert_eq!(200, response.status_code);
+    }
+
+    #[test]
+    fn if_modified_since_after_last_modification_returns_not_modified() {
+        let response = cacheable_response(
+            &request(vec![("If-Modified-Since", "Fri, 12 Nov 2021 00:00:00 GMT")]),
+            result(),
+        );
+        assert_eq!(304, response.status_code);
+    }
+
+    #[test]
+    fn if_modified_since_before_last_modification_returns_result() {
+        let response = cacheable_response(
+            &request(vec![("If-Modified-Since", "Wed, 10 Nov 2021 00:00:00 GMT")]),
+            result(),
+        );
+        assert_eq!(200, response.status_code);
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence() {
+        let response = cacheable_response(
+            &request(vec![
+                ("If-None-Match", "\"other\""),
+                ("If-Modified-Since", "Fri, 12 Nov 2021 00:00:00 GMT"),
+            ]),
+            result(),
+        );
+        assert_eq!(200, response.status_code);
+    }
+
+    fn body(response: Response) -> Value {
+        let (mut reader, _) = response.data.into_reader_and_size();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn not_found_error() {
+        let response = error_response(false, &DidIndyError::EmptyData);
+
+        assert_eq!(404, response.status_code);
+        assert_eq!(
+            json!({
+                "didResolutionMetadata": {"error": "notFound", "message": "Empty data"},
+                "didDocument": null,
+                "didDocumentMetadata": {}
+            }),
+            body(response)
+        );
+    }
+
+    #[test]
+    fn invalid_did_error() {
+        let response = error_response(false, &DidIndyError::InvalidDidUrl);
+
+        assert_eq!(400, response.status_code);
+        assert_eq!(
+            json!({"error": "invalidDid", "message": "Invalid DID URL"}),
+            body(response)["didResolutionMetadata"]
+        );
+    }
+
+    #[test]
+    fn representation_not_supported_error() {
+        let response = error_response(
+            false,
+            &DidIndyError::RepresentationNotSupported(String::from("text/html")),
+        );
+
+        assert_eq!(406, response.status_code);
+        assert_eq!(
+            "representationNotSupported",
+            body(response)["didResolutionMetadata"]["error"]
+        );
+    }
+
+    #[test]
+    fn unknown_network_error() {
+        let error = DidIndyError::UnknownNetwork {
+            namespace: String::from("local"),
+            url: String::from("https://genesis.example/local.json"),
+            reason: String::from("connection refused"),
+        };
+
+        for dereferencing in [false, true] {
+            let response = error_response(dereferencing, &error);
+            assert_eq!(501, response.status_code);
+        }
+        assert_eq!(501, dereferencing_status("unknownNetwork"));
+    }
+
+    #[test]
+    fn unsupported_ledger_object_error() {
+        let response = error_response(
+            true,
+            &DidIndyError::UnsupportedLedgerObject {
+                segment: String::from("CREDDEF"),
+                supported: vec![String::from("SCHEMA"), String::from("CLAIM_DEF")],
+                suggestion: Some(String::from("CLAIM_DEF")),
+            },
+        );
+
+        assert_eq!(400, response.status_code);
+        assert_eq!(
+            json!({
+                "error": "invalidDidUrl",
+                "message": "Ledger object CREDDEF not supported (did you mean CLAIM_DEF?), supported objects: SCHEMA, CLAIM_DEF",
+                "supportedObjects": ["SCHEMA", "CLAIM_DEF"],
+                "suggestion": "CLAIM_DEF"
+            }),
+            body(response)["dereferencingMetadata"]
+        );
+    }
+
+    #[test]
+    fn problem_document_of_error() {
+        let response = problem_response(&DidIndyError::InvalidDidUrl);
+
+        assert_eq!(400, response.status_code);
+        assert_eq!(
+            Some("application/problem+json"),
+            header(&response, "Content-Type")
+        );
+        assert_eq!(
+            json!({
+                "type": "https://www.w3.org/ns/did#INVALID_DID",
+                "title": "Invalid DID",
+                "status": 400,
+                "detail": "Invalid DID URL",
+                "error": "invalidDid"
+            }),
+            body(response)
+        );
+    }
+
+    #[test]
+    fn problem_document_negotiated_by_accept_header() {
+        let error = DidIndyError::EmptyData;
+        let problem = negotiated_error_response(
+            &request(vec![(
+                "Accept",
+                "application/json;q=0.5, application/problem+json",
+            )]),
+            false,
+            &error,
+        );
+        assert_eq!(404, problem.status_code);
+        assert_eq!("https://www.w3.org/ns/did#NOT_FOUND", body(problem)["type"]);
+
+        let metadata = negotiated_error_response(&request(vec![]), false, &error);
+        assert_eq!(404, metadata.status_code);
+        assert_eq!("notFound", body(metadata)["didResolutionMetadata"]["error"]);
+    }
+
+    #[test]
+    fn internal_error_while_dereferencing() {
+        let response = error_response(true, &DidIndyError::ResolutionTimeout);
+
+        assert_eq!(500, response.status_code);
+        assert_eq!(
+            json!({
+                "dereferencingMetadata": {"error": "internalError", "message": "Resolution timed out"},
+                "contentStream": null,
+                "contentMetadata": {}
+            }),
+            body(response)
+        );
+    }
+
+    fn dereferencing_result() -> String {
+        json!({
+            "dereferencingMetadata": {"didUrl": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4"},
+            "contentStream": {"name": "npdb", "version": "4.3.4", "attr_names": ["name"]},
+            "contentMetadata": {
+                "contentType": "application/json",
+                "methodMetadata": {"nodeResponse": {"result": {"txnTime": 1636641222}}}
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn dereferencing_result_or_content_negotiated() {
+        let profile = "application/ld+json;profile=\"https://w3id.org/did-url-dereferencing\"";
+        for accept in [None, Some("*/*"), Some(profile)] {
+            let headers = accept
+                .map(|accept| ("Accept", accept))
+                .into_iter()
+                .collect();
+            let request = request(headers);
+            let response = dereferencing_response(
+                &request,
+                dereferencing_result(),
+                Dereferenced::negotiate(&request),
+            );
+
+            assert_eq!(200, response.status_code);
+            assert_eq!(Some(profile), header(&response, "Content-Type"));
+            assert!(header(&response, "ETag").is_some());
+            assert_eq!("npdb", body(response)["contentStream"]["name"]);
+        }
+
+        let response = dereferencing_response(
+            &request(vec![("Accept", "application/json")]),
+            dereferencing_result(),
+            Dereferenced::Content,
+        );
+        assert_eq!(Some("application/json"), header(&response, "Content-Type"));
+        assert_eq!(
+            Some("Thu, 11 Nov 2021 14:33:42 GMT"),
+            header(&response, "Last-Modified")
+        );
+        assert_eq!(
+            json!({"name": "npdb", "version": "4.3.4", "attr_names": ["name"]}),
+            body(response)
+        );
+
+        assert_eq!(
+            Dereferenced::JsonLdContent,
+            Dereferenced::negotiate(&request(vec![("Accept", "application/ld+json")]))
+        );
+        assert_eq!(
+            Dereferenced::NotAcceptable,
+            Dereferenced::negotiate(&request(vec![("Accept", "text/html")]))
+        );
+        let response = not_acceptable();
+        assert_eq!(406, response.status_code);
+        assert_eq!(
+            "contentTypeNotSupported",
+            body(response)["dereferencingMetadata"]["error"]
+        );
+    }
+
+    #[test]
+    fn dereferencing_errors_with_own_codes() {
+        let response = dereferencing_error_response(&DidIndyError::InvalidDidUrl);
+        assert_eq!(400, response.status_code);
+        assert_eq!(
+            json!({"error": "invalidDidUrl", "message": "Invalid DID URL"}),
+            body(response)["dereferencingMetadata"]
+        );
+
+        let response = dereferencing_error_response(&DidIndyError::NotFound);
+        assert_eq!(404, response.status_code);
+        assert_eq!("notFound", body(response)["dereferencingMetadata"]["error"]);
+    }
+}
@@ -1,21 +1,49 @@
-use futures_executor::block_on;
+use admission::{Admission, AdmissionLimits};
+use auth::ApiKeys;
+use cli::{CliError, ErrorFormat};
+use compression::CompressionSettings;
 use git2::Repository;
+use indy_didresolver::config::{Representation, ResolveOptions};
 use indy_didresolver::did::DidUrl;
 use indy_didresolver::error::{DidIndyError, DidIndyResult};
-use indy_didresolver::resolver::Resolver;
-use indy_vdr::pool::{helpers::perform_refresh, PoolBuilder, PoolTransactions, SharedPool};
+use indy_didresolver::networks::DirectoryProvider;
+use indy_didresolver::registry::ResolverRegistry;
+use indy_vdr::pool::SharedPool;
 use regex::Regex;
-use rouille::Response;
+use rouille::{Request, Response};
 
-use clap::Parser;
-use std::collections::HashMap;
+use clap::{Parser, Subcommand};
+use reload::{Defaults, Reloader};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 #[macro_use]
 extern crate log;
 
+mod admission;
+mod auth;
+mod cli;
+mod compression;
+mod dereference;
+mod http;
+mod reload;
+mod status;
+#[cfg(feature = "otel")]
+mod telemetry;
+mod validate;
+
 static POOL_SIZE: Option<usize> = Some(32);
-type Resolvers = HashMap<String, Resolver<SharedPool>>;
+type Resolvers = ResolverRegistry<SharedPool>;
+
+/// State shared by the request handlers
+pub struct Driver {
+    resolvers: Resolvers,
+    api_keys: Option<ApiKeys>,
+    reloader: Reloader,
+    admission: Admission,
+    compression: CompressionSettings,
+}
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -40,48 +68,417 @@ pub struct Args {
         default_value = "pool_transactions_genesis.json"
     )]
     genesis_filename: String,
+    /// Seconds to cache resolution results, 0 disables caching
+    #[clap(long = "cache-ttl", default_value_t = 0)]
+    cache_ttl: u64,
+    /// Emit the ledger reply directly in the result metadata instead of methodMetadata
+    #[clap(long = "legacy-metadata")]
+    legacy_metadata: bool,
+    /// JSON file with the API keys accepted by the driver. Without it, requests
+    /// are not authenticated.
+    #[clap(long = "api-keys")]
+    api_keys: Option<PathBuf>,
+    /// Requests per minute allowed for an API key without its own rate limit
+    #[clap(long = "rate-limit")]
+    rate_limit: Option<u32>,
+    /// JSON file with settings overriding the command line options, read again
+    /// on SIGHUP and POST /admin/reload
+    #[clap(long = "config")]
+    config: Option<PathBuf>,
+    /// Requests resolved at the same time
+    #[clap(long = "max-concurrent", default_value_t = 16)]
+    max_concurrent: usize,
+    /// Requests waiting for resolution, further requests get 503
+    #[clap(long = "queue-size", default_value_t = 16)]
+    queue_size: usize,
+    /// Seconds a request may wait for resolution before it gets 504
+    #[clap(long = "queue-timeout", default_value_t = 10)]
+    queue_timeout: u64,
+    /// Responses of at least this many bytes are compressed if the client
+    /// accepts gzip or deflate
+    #[clap(long = "compress-min-size", default_value_t = 1024)]
+    compress_min_size: usize,
+    /// Compression level from 0 (none) to 9 (best)
+    #[clap(long = "compression-level", default_value_t = 6)]
+    compression_level: u32,
+    /// Format of the errors of the resolve, dereference and validate commands
+    /// printed to stderr, text or json
+    #[clap(long = "error-format", default_value = "text", global = true)]
+    error_format: ErrorFormat,
+    /// Redis server caching results for all driver instances, e.g. redis://127.0.0.1/
+    #[cfg(feature = "redis")]
+    #[clap(long = "redis-url")]
+    redis_url: Option<String>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Connect to the pools and print which nodes reply and how fast, then exit
+    PoolStatus {
+        /// Genesis file of the pool to check instead of the pools of all networks
+        #[clap(long = "genesis")]
+        genesis: Option<PathBuf>,
+        /// Print the status as JSON instead of a table
+        #[clap(long = "json")]
+        json: bool,
+    },
+    /// Check DID URLs without querying the ledger and print their components
+    /// and the ledger request they are resolved with
+    Validate {
+        /// DID URL to check
+        #[clap(required_unless_present = "batch")]
+        did_url: Option<String>,
+        /// File with one DID URL per line, printed as one JSON object per line
+        #[clap(long = "batch")]
+        batch: Option<PathBuf>,
+    },
+    /// Resolve a DID and print the result
+    Resolve {
+        /// DID to resolve
+        #[clap(required_unless_present = "batch")]
+        did: Option<String>,
+        /// File with one DID per line, printed as one JSON object per line
+        #[clap(long = "batch")]
+        batch: Option<PathBuf>,
+        /// Genesis file of the pool to use instead of the network directory
+        #[clap(long = "genesis")]
+        genesis: Option<PathBuf>,
+    },
+    /// Dereference a DID URL, given as a whole or built from the DID and the
+    /// parts of a ledger object, and print the result
+    Dereference(dereference::DereferenceArgs),
 }
 
 fn main() {
     let args = Args::parse();
     let port = &args.port.to_string();
     env_logger::init();
+    #[cfg(feature = "otel")]
+    telemetry::init();
     info!("Starting the indy-did-driver with the following configuration:");
     info!("{:?}", args);
 
-    let resolvers = init_resolvers(args);
+    if let Some(Command::Validate { did_url, batch }) = &args.command {
+        let did_urls = match batch {
+            Some(path) => validate::read_batch(path).unwrap_or_else(|err| {
+                exit_with(&cli::Output::failed(
+                    CliError::configuration(err),
+                    args.error_format,
+                ))
+            }),
+            None => did_url.iter().cloned().collect(),
+        };
+        let (output, valid) = validate::run(&did_urls, batch.is_some());
+        print!("{}", output);
+        std::process::exit(if valid {
+            cli::SUCCESS
+        } else {
+            cli::INVALID_DID_URL
+        });
+    }
+    if let Some(Command::Resolve {
+        did,
+        batch,
+        genesis,
+    }) = &args.command
+    {
+        let dids = match batch {
+            Some(path) => validate::read_batch(path).map_err(CliError::configuration),
+            None => Ok(did.iter().cloned().collect()),
+        };
+        exit_with(&run_cli(&args, dids, batch.is_some(), genesis));
+    }
+    if let Some(Command::Dereference(dereference_args)) = &args.command {
+        exit_with(&run_cli(
+            &args,
+            dereference::did_urls(dereference_args),
+            dereference_args.batch.is_some(),
+            &dereference_args.genesis,
+        ));
+    }
+    if let Some(Command::PoolStatus { genesis, json }) = &args.command {
+        let statuses = match genesis {
+            Some(genesis) => status::statuses(
+                &status::GenesisProvider(genesis.clone()),
+                &[genesis.display().to_string()],
+            ),
+            None => {
+                let provider =
+                    DirectoryProvider::new(network_directory(&args), &args.genesis_filename);
+                status::statuses(&provider, &provider.namespaces())
+            }
+        };
+        print!("{}", status::render(&statuses, *json));
+        let failed = statuses.iter().any(|status| status.error.is_some());
+        std::process::exit(if failed { 1 } else { 0 });
+    }
+
+    let api_keys = args
+        .api_keys
+        .as_ref()
+        .map(|path| ApiKeys::load(path, args.rate_limit).expect("Could not load API keys."));
+    let admission = Admission::new(AdmissionLimits {
+        max_concurrent: args.max_concurrent,
+        queue_size: args.queue_size,
+        queue_timeout: Duration::from_secs(args.queue_timeout),
+        ..AdmissionLimits::default()
+    });
+    let compression = CompressionSettings {
+        min_size: args.compress_min_size,
+        level: args.compression_level.min(9),
+    };
+    let (resolvers, reloader) = init_resolvers(args);
+    let driver = Arc::new(Driver {
+        resolvers,
+        api_keys,
+        reloader,
+        admission,
+        compression,
+    });
+    #[cfg(unix)]
+    reload::reload_on_sighup(driver.clone());
 
     rouille::start_server_with_pool(String::from("0.0.0.0:") + port, POOL_SIZE, move |request| {
-        let url = request.url();
-        debug!("incoming request: {}", url);
-        let request_regex = Regex::new("/1.0/identifiers/(.*)").unwrap();
-
-        let captures = request_regex.captures(&url);
-        if let Some(cap) = captures {
-            let did = cap.get(1).unwrap().as_str();
-
-            match process_request(did, &resolvers) {
-                Ok(result) => {
-                    info!("Serving for {}", &url);
-                    debug!("Serving DID Doc: {:?}", result);
-                    Response::text(result)
-                }
-                Err(err) => {
-                    error!("404: {:?}", err);
-                    Response::text("404").with_status_code(404)
-                }
-            }
+        #[cfg(feature = "otel")]
+        let _span = telemetry::request_span(request).entered();
+        compression::compress(request, route(request, &driver), &driver.compression)
+    });
+}
+
+fn route(request: &Request, driver: &Driver) -> Response {
+    let url = request.url();
+    debug!("incoming request: {}", url);
+
+    // Health checks are open without an API key
+    if url == "/health" {
+        return Response::text("OK");
+    }
+    if url == "/ready" {
+        return if driver.admission.is_ready() {
+            Response::text("OK")
         } else {
-            info!("400: unrecognized path: {}", &url);
-            Response::text("400").with_status_code(400)
+            Response::text("503").with_status_code(503)
+        };
+    }
+    let api_key = match &driver.api_keys {
+        Some(api_keys) => match api_keys.authenticate(request) {
+            Ok(key) => {
+                debug!("Request authenticated with API key {}", key);
+                Some(key)
+            }
+            Err(response) => {
+                info!("{}: rejected API key for {}", response.status_code, &url);
+                return response;
+            }
+        },
+        None => None,
+    };
+    if url == "/metrics" {
+        let api_key_metrics = driver
+            .api_keys
+            .as_ref()
+            .map(ApiKeys::metrics)
+            .unwrap_or_default();
+        return Response::text(api_key_metrics + &driver.admission.metrics());
+    }
+    if url.starts_with("/admin/") {
+        return admin(request, &url, driver, api_key.as_deref());
+    }
+
+    if url == "/1.0/dereference" || url.starts_with("/1.0/dereference/") {
+        return dereference(request, driver);
+    }
+
+    let request_regex = Regex::new("/1.0/identifiers/(.*)").unwrap();
+    let captures = request_regex.captures(&url);
+    if let Some(cap) = captures {
+        let did = cap.get(1).unwrap().as_str();
+        // Reserved parameter of the driver, not part of the DID URL
+        let raw_reply = request.get_param("rawReply").as_deref() == Some("true");
+
+        let _permit = match driver.admission.admit() {
+            Ok(permit) => permit,
+            Err(response) => {
+                warn!("{}: overloaded, not serving {}", response.status_code, &url);
+                return response;
+            }
+        };
+        match process_request(did, raw_reply, &driver.resolvers) {
+            Ok(result) => {
+                info!("Serving for {}", &url);
+                debug!("Serving DID Doc: {:?}", result);
+                http::cacheable_response(request, result)
+            }
+            Err(err) => {
+                error!("{}: {:?}", err.error_code(), err);
+                http::negotiated_error_response(request, did.contains('/'), &err)
+            }
         }
-    });
+    } else {
+        info!("400: unrecognized path: {}", &url);
+        Response::text("400").with_status_code(400)
+    }
+}
+
+// Dereferences the DID URL of the didUrl parameter or of the path, always as
+// dereferencing even for a DID without path
+fn dereference(request: &Request, driver: &Driver) -> Response {
+    let did_url = match dereferenced_did_url(request) {
+        Some(did_url) => did_url,
+        None => {
+            info!("400: no DID URL to dereference in {}", request.raw_url());
+            return http::dereferencing_error_response(&DidIndyError::InvalidDidUrl);
+        }
+    };
+    let form = http::Dereferenced::negotiate(request);
+    let options = match form {
+        http::Dereferenced::NotAcceptable => return http::not_acceptable(),
+        http::Dereferenced::JsonLdContent => {
+            ResolveOptions::new().representation(Representation::JsonLd)
+        }
+        _ => ResolveOptions::new(),
+    };
+    let _permit = match driver.admission.admit() {
+        Ok(permit) => permit,
+        Err(response) => {
+            warn!(
+                "{}: overloaded, not dereferencing {}",
+                response.status_code, &did_url
+            );
+            return response;
+        }
+    };
+    match driver
+        .resolvers
+        .resolver_for(&did_url)
+        .and_then(|resolver| resolver.dereference_with_options(&did_url, &options))
+    {
+        Ok(result) => {
+            info!("Serving dereferencing of {}", &did_url);
+            http::dereferencing_response(request, result, form)
+        }
+        Err(err) => {
+            error!("{}: {:?}", err.error_code(), err);
+            http::dereferencing_error_response(&err)
+        }
+    }
+}
+
+// DID URL of the didUrl parameter of /1.0/dereference, or the rest of the path
+// of /1.0/dereference/<DID URL>. Its query and fragment must be URL encoded in
+// the parameter, the fragment also in the path.
+fn dereferenced_did_url(request: &Request) -> Option<String> {
+    let did_url = match request.raw_url().strip_prefix("/1.0/dereference/") {
+        Some(did_url) => urlencoding::decode(did_url).ok()?.into_owned(),
+        None => request.get_param("didUrl")?,
+    };
+    if did_url.is_empty() {
+        None
+    } else {
+        Some(did_url)
+    }
 }
 
-fn init_resolvers(args: Args) -> Resolvers {
-    let mut resolvers: Resolvers = HashMap::new();
-    let source = args.source;
-    let path = if source == "github" || source.is_empty() {
+// Reloading the configuration requires an admin API key, as it must not be
+// open to anyone, partners included
+fn admin(request: &Request, url: &str, driver: &Driver, api_key: Option<&str>) -> Response {
+    let is_admin = match (&driver.api_keys, api_key) {
+        (Some(api_keys), Some(api_key)) => api_keys.is_admin(api_key),
+        _ => false,
+    };
+    if !is_admin {
+        return Response::text("403").with_status_code(403);
+    }
+    match url {
+        "/admin/status" => Response::json(&driver.reloader.status()),
+        "/admin/pools" => Response::json(&driver.resolvers.pool_infos()),
+        "/admin/reload" if request.method() == "POST" => {
+            let status_code = match driver.reloader.reload(&driver.resolvers) {
+                Ok(_) => 200,
+                Err(_) => 500,
+            };
+            Response::json(&driver.reloader.status()).with_status_code(status_code)
+        }
+        "/admin/reload" => Response::text("405").with_status_code(405),
+        _ => Response::text("404").with_status_code(404),
+    }
+}
+
+fn init_resolvers(args: Args) -> (Resolvers, Reloader) {
+    let mut resolvers: Resolvers = ResolverRegistry::new();
+    let path = network_directory(&args);
+
+    let reloader = Reloader::new(
+        args.config,
+        DirectoryProvider::new(&path, args.genesis_filename.as_str()),
+        Defaults {
+            cache_ttl: args.cache_ttl,
+            legacy_metadata: args.legacy_metadata,
+            #[cfg(feature = "redis")]
+            redis_url: args.redis_url,
+        },
+    );
+    let config = reloader
+        .resolver_config()
+        .expect("Could not load configuration.");
+    reloader.applied(&config);
+
+    let provider = DirectoryProvider::new(path, args.genesis_filename.as_str());
+    let namespaces = provider.namespaces();
+    resolvers.set_provider(Box::new(provider), config);
+
+    // Initialize the pools of all networks in the directory up front
+    for namespace in namespaces {
+        if resolvers.get(&namespace).is_none() {
+            error!("Could not initialize network {}", namespace);
+        }
+    }
+
+    info!("Initialized networks: {:?}", resolvers.namespaces());
+    (resolvers, reloader)
+}
+
+// Resolves or dereferences the inputs with the pools of the genesis file or of
+// the network directory
+fn run_cli(
+    args: &Args,
+    inputs: Result<Vec<String>, CliError>,
+    batch: bool,
+    genesis: &Option<PathBuf>,
+) -> cli::Output {
+    let inputs = match inputs {
+        Ok(inputs) => inputs,
+        Err(err) => return cli::Output::failed(err, args.error_format),
+    };
+    match genesis {
+        Some(genesis) => cli::run(
+            &status::GenesisProvider(genesis.clone()),
+            &inputs,
+            batch,
+            args.error_format,
+        ),
+        None => cli::run(
+            &DirectoryProvider::new(network_directory(args), &args.genesis_filename),
+            &inputs,
+            batch,
+            args.error_format,
+        ),
+    }
+}
+
+fn exit_with(output: &cli::Output) -> ! {
+    print!("{}", output.stdout);
+    eprint!("{}", output.stderr);
+    std::process::exit(output.exit_code)
+}
+
+// Directory with the genesis files of the networks, the clone of the github
+// repository for the github source
+fn network_directory(args: &Args) -> PathBuf {
+    let source = &args.source;
+    if source == "github" || source.is_empty() {
         info!("Obtaining network information from github");
         // Delete folder if it exists and reclone repo
         fs::remove_dir_all("github").ok();
@@ -93,87 +490,280 @@ fn init_resolvers(args: Args) -> Resolvers {
     } else {
         info!("Obtaining network information from local path {}", source);
         PathBuf::from(source)
-    };
-
-    let entries = fs::read_dir(path).expect("Could not read path");
-    for entry in entries {
-        let entry = entry.unwrap();
-        // filter hidden directories starting with "."
-        if !entry.file_name().to_str().unwrap().starts_with(".") && entry.metadata().unwrap().is_dir() {
-            let namespace = entry.path().file_name().unwrap().to_owned();
-            let sub_entries = fs::read_dir(entry.path()).unwrap();
-            for sub_entry in sub_entries {
-                let sub_entry_path = sub_entry.unwrap().path();
-                let sub_namespace = if sub_entry_path.is_dir() {
-                    sub_entry_path.file_name()
-                } else {
-                    None
-                };
-                let (ledger_prefix, genesis_txns) = match sub_namespace {
-                    Some(sub_namespace) => (
-                        format!(
-                            "{}:{}",
-                            namespace.to_str().unwrap(),
-                            sub_namespace.to_str().unwrap()
-                        ),
-                        PoolTransactions::from_json_file(
-                            sub_entry_path.join(args.genesis_filename.as_str()),
-                        )
-                        .unwrap(),
-                    ),
-                    None => (
-                        String::from(namespace.to_str().unwrap()),
-                        PoolTransactions::from_json_file(
-                            entry.path().join(args.genesis_filename.as_str()),
-                        )
-                        .unwrap(),
-                    ),
-                };
-                debug!("Initializing pool for {}", ledger_prefix);
-
-                let pool_builder = PoolBuilder::default()
-                    .transactions(genesis_txns.clone())
-                    .unwrap();
-                let mut pool = pool_builder.into_shared().unwrap();
-
-                // Refresh pool to get current validator set
-                debug!("Refreshing pool for {}", ledger_prefix);
-                let (txns, _timing) = block_on(perform_refresh(&pool)).unwrap();
-
-                pool = if let Some(txns) = txns {
-                    let builder = {
-                        let mut pool_txns = genesis_txns;
-                        pool_txns.extend_from_json(&txns).unwrap();
-                        PoolBuilder::default()
-                            .transactions(pool_txns.clone())
-                            .unwrap()
-                    };
-                    builder.into_shared().unwrap()
-                } else {
-                    pool
-                };
-
-                resolvers.insert(ledger_prefix, Resolver::new(pool));
-            }
-        }
     }
-
-    info!("Initialized networks: {:?}", resolvers.keys());
-    resolvers
 }
 
-fn process_request(request: &str, resolvers: &Resolvers) -> DidIndyResult<String> {
+fn process_request(request: &str, raw_reply: bool, resolvers: &Resolvers) -> DidIndyResult<String> {
     let did = DidUrl::from_str(request)?;
-    let resolver = if let Some(resolver) = resolvers.get(&did.namespace) {
-        resolver
-    } else {
-        error!("Requested Indy Namespace \"{}\" unknown", &did.namespace);
-        return Err(DidIndyError::NamespaceNotSupported);
+    let resolver = match resolvers.resolver_for(request) {
+        Ok(resolver) => resolver,
+        Err(err) => {
+            error!("No resolver for \"{}\": {}", request, err);
+            return Err(err);
+        }
     };
 
     if did.path.is_none() {
         resolver.resolve(request)
     } else {
-        resolver.dereference(request)
+        resolver.dereference_with_options(request, &ResolveOptions::new().raw_reply(raw_reply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use auth::ApiKey;
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    fn driver() -> Driver {
+        Driver {
+            resolvers: Resolvers::new(),
+            api_keys: Some(
+                ApiKeys::new(
+                    vec![
+                        ApiKey {
+                            name: String::from("partner"),
+                            sha256: format!("{:x}", Sha256::digest(b"partner-secret")),
+                            rate_limit: None,
+                            admin: false,
+                        },
+                        ApiKey {
+                            name: String::from("operator"),
+                            sha256: format!("{:x}", Sha256::digest(b"operator-secret")),
+                            rate_limit: None,
+                            admin: true,
+                        },
+                    ],
+                    None,
+                )
+                .unwrap(),
+            ),
+            reloader: Reloader::new(
+                None,
+                DirectoryProvider::new(std::env::temp_dir().join("no-networks"), "genesis.json"),
+                Defaults::default(),
+            ),
+            admission: Admission::new(AdmissionLimits::default()),
+            compression: CompressionSettings::default(),
+        }
+    }
+
+    fn request(method: &str, url: &str, headers: Vec<(&str, &str)>) -> Request {
+        Request::fake_http(
+            method,
+            url,
+            headers
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn health_is_open() {
+        let response = route(&request("GET", "/health", vec![]), &driver());
+        assert_eq!(200, response.status_code);
+    }
+
+    #[test]
+    fn ready_and_metrics_report_admission() {
+        let driver = driver();
+        let response = route(&request("GET", "/ready", vec![]), &driver);
+        assert_eq!(200, response.status_code);
+
+        let key = vec![("X-API-Key", "partner-secret")];
+        let mut metrics = String::new();
+        route(&request("GET", "/metrics", key), &driver)
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut metrics)
+            .unwrap();
+        assert!(metrics.contains("indy_did_driver_queue_depth 0\n"));
+        assert!(metrics.contains("indy_did_driver_shed_total 0\n"));
+    }
+
+    #[test]
+    fn resolution_requires_api_key() {
+        let driver = driver();
+        let url = "/1.0/identifiers/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        let response = route(&request("GET", url, vec![]), &driver);
+        assert_eq!(401, response.status_code);
+        let response = route(
+            &request("GET", url, vec![("X-API-Key", "other-secret")]),
+            &driver,
+        );
+        assert_eq!(403, response.status_code);
+        // No network is registered for the namespace
+        let response = route(
+            &request("GET", url, vec![("X-API-Key", "partner-secret")]),
+            &driver,
+        );
+        assert_eq!(501, response.status_code);
+    }
+
+    #[test]
+    fn resolution_error_as_problem_document() {
+        let driver = driver();
+        let url = "/1.0/identifiers/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        let headers = vec![
+            ("X-API-Key", "partner-secret"),
+            ("Accept", "application/problem+json"),
+        ];
+
+        let response = route(&request("GET", url, headers), &driver);
+        assert_eq!(501, response.status_code);
+        assert!(response
+            .headers
+            .iter()
+            .any(|(name, value)| name == "Content-Type" && value == "application/problem+json"));
+        let mut body = String::new();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        let problem: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            "https://www.w3.org/ns/did#METHOD_NOT_SUPPORTED",
+            problem["type"]
+        );
+        assert_eq!("Method not supported", problem["title"]);
+        assert_eq!(501, problem["status"]);
+        assert_eq!("methodNotSupported", problem["error"]);
+    }
+
+    fn body(response: Response) -> serde_json::Value {
+        let mut body = String::new();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn schema_url_dereferenced() {
+        let url = format!(
+            "/1.0/dereference?didUrl={}",
+            "did%3Aindy%3Aidunion%3ADk1fRRTtNazyMuK2cr64wp%2Fanoncreds%2Fv0%2FSCHEMA%2Fnpdb%2F4.3.4"
+        );
+
+        let response = route(
+            &request("GET", &url, vec![("X-API-Key", "partner-secret")]),
+            &driver(),
+        );
+
+        // Dereferenced, but no network is registered for the namespace
+        assert_eq!(501, response.status_code);
+        let body = body(response);
+        assert_eq!("methodNotSupported", body["dereferencingMetadata"]["error"]);
+        assert_eq!(serde_json::Value::Null, body["contentStream"]);
+    }
+
+    #[test]
+    fn fragment_dereferenced_through_url_encoding() {
+        let did_url = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14:33:42Z#accum";
+
+        let by_parameter = request(
+            "GET",
+            "/1.0/dereference?didUrl=did%3Aindy%3Aidunion%3ADk1fRRTtNazyMuK2cr64wp%2Fanoncreds%2Fv0%2FREV_REG_ENTRY%2F104%2Frevocable%2Fa4e25e54%3FversionTime%3D2021-11-11T14%3A33%3A42Z%23accum",
+            vec![],
+        );
+        assert_eq!(
+            Some(String::from(did_url)),
+            dereferenced_did_url(&by_parameter)
+        );
+        let by_path = request(
+            "GET",
+            "/1.0/dereference/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14:33:42Z%23accum",
+            vec![],
+        );
+        assert_eq!(Some(String::from(did_url)), dereferenced_did_url(&by_path));
+    }
+
+    #[test]
+    fn invalid_did_url_parameter() {
+        let key = vec![("X-API-Key", "partner-secret")];
+        for url in [
+            "/1.0/dereference?didUrl=did%3Aindy%3Aidunion%3Ainvalid",
+            "/1.0/dereference?did=did%3Aindy%3Aidunion%3ADk1fRRTtNazyMuK2cr64wp",
+            "/1.0/dereference/",
+        ] {
+            let response = route(&request("GET", url, key.clone()), &driver());
+
+            assert_eq!(400, response.status_code, "{}", url);
+            assert_eq!(
+                "invalidDidUrl",
+                body(response)["dereferencingMetadata"]["error"],
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn reload_requires_post_with_admin_key() {
+        let driver = driver();
+        let key = vec![("X-API-Key", "operator-secret")];
+
+        let response = route(&request("POST", "/admin/reload", vec![]), &driver);
+        assert_eq!(401, response.status_code);
+        let response = route(
+            &request(
+                "POST",
+                "/admin/reload",
+                vec![("X-API-Key", "partner-secret")],
+            ),
+            &driver,
+        );
+        assert_eq!(403, response.status_code);
+        assert!(driver.reloader.status().last_reload.is_none());
+        let response = route(&request("GET", "/admin/reload", key.clone()), &driver);
+        assert_eq!(405, response.status_code);
+        let response = route(&request("POST", "/admin/reload", key.clone()), &driver);
+        assert_eq!(200, response.status_code);
+        let response = route(&request("GET", "/admin/status", key), &driver);
+        assert_eq!(200, response.status_code);
+        assert!(driver.reloader.status().last_reload.is_some());
+    }
+
+    #[test]
+    fn pool_infos_require_admin_key() {
+        let driver = driver();
+
+        let response = route(&request("GET", "/admin/pools", vec![]), &driver);
+        assert_eq!(401, response.status_code);
+        let response = route(
+            &request("GET", "/admin/pools", vec![("X-API-Key", "partner-secret")]),
+            &driver,
+        );
+        assert_eq!(403, response.status_code);
+        let response = route(
+            &request(
+                "GET",
+                "/admin/pools",
+                vec![("X-API-Key", "operator-secret")],
+            ),
+            &driver,
+        );
+        assert_eq!(200, response.status_code);
+    }
+
+    #[test]
+    fn admin_closed_without_api_keys() {
+        let driver = Driver {
+            api_keys: None,
+            ..driver()
+        };
+        let response = route(&request("POST", "/admin/reload", vec![]), &driver);
+        assert_eq!(403, response.status_code);
     }
 }
@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "redis")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+#[cfg(feature = "redis")]
+use indy_didresolver::cache::RedisCache;
+use indy_didresolver::cache::ResultCache;
+use indy_didresolver::config::ResolverConfig;
+use indy_didresolver::networks::DirectoryProvider;
+use indy_didresolver::registry::ReloadSummary;
+use serde::{Deserialize, Serialize};
+
+use super::Resolvers;
+
+/// Settings read from the file given with --config. They override the matching
+/// command line options and are read again on every reload.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DriverConfig {
+    /// Seconds to cache resolution results, 0 disables caching
+    pub cache_ttl: Option<u64>,
+    /// Upper bound for concurrent ledger requests per network
+    pub max_concurrent_requests: Option<usize>,
+}
+
+impl DriverConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let config = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read config file {:?}, {}", path, err))?;
+        serde_json::from_str(&config)
+            .map_err(|err| format!("Invalid config file {:?}, {}", path, err))
+    }
+}
+
+/// Settings from the command line
+#[derive(Clone, Debug, Default)]
+pub struct Defaults {
+    pub cache_ttl: u64,
+    pub legacy_metadata: bool,
+    #[cfg(feature = "redis")]
+    pub redis_url: Option<String>,
+}
+
+/// Outcome of the last reload
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadStatus {
+    pub last_reload: Option<String>,
+    /// Why the last reload failed, the configuration before it is still in effect
+    pub error: Option<String>,
+    /// Namespaces changed by the last successful reload
+    pub summary: Option<ReloadSummary>,
+}
+
+/// Applies changes of the config file and the network directory to the running
+/// resolvers
+pub struct Reloader {
+    config_path: Option<PathBuf>,
+    networks: DirectoryProvider,
+    defaults: Defaults,
+    // Cache in effect, kept across reloads unless its TTL changes
+    cache: Mutex<Option<ResultCache>>,
+    status: Mutex<ReloadStatus>,
+}
+
+impl Reloader {
+    pub fn new(
+        config_path: Option<PathBuf>,
+        networks: DirectoryProvider,
+        defaults: Defaults,
+    ) -> Self {
+        Reloader {
+            config_path,
+            networks,
+            defaults,
+            cache: Mutex::new(None),
+            status: Mutex::new(ReloadStatus::default()),
+        }
+    }
+
+    /// Resolver config from the command line and the config file
+    pub fn resolver_config(&self) -> Result<ResolverConfig, String> {
+        let config = match &self.config_path {
+            Some(path) => DriverConfig::load(path)?,
+            None => DriverConfig::default(),
+        };
+        let ttl = config.cache_ttl.unwrap_or(self.defaults.cache_ttl);
+        let cache = match &*self.cache.lock().unwrap() {
+            Some(cache) if cache.ttl == Duration::from_secs(ttl) => Some(cache.clone()),
+            _ => self.init_cache(ttl)?,
+        };
+        Ok(ResolverConfig {
+            cache,
+            max_concurrent_requests: config.max_concurrent_requests,
+            legacy_metadata: self.defaults.legacy_metadata,
+            ..ResolverConfig::default()
+        })
+    }
+
+    /// Marks the config as the one in effect
+    pub fn applied(&self, config: &ResolverConfig) {
+        *self.cache.lock().unwrap() = config.cache.clone();
+    }
+
+    /// Reads the config file and the network directory again and applies them
+    /// to the resolvers. On failure the resolvers are left unchanged.
+    pub fn reload(&self, resolvers: &Resolvers) -> Result<ReloadSummary, String> {
+        let result = self.resolver_config().and_then(|config| {
+            let summary = resolvers
+                .reload(&self.networks.namespaces(), config.clone())
+                .map_err(|err| err.to_string())?;
+            self.applied(&config);
+            Ok(summary)
+        });
+
+        let mut status = self.status.lock().unwrap();
+        status.last_reload = Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+        match &result {
+            Ok(summary) => {
+                info!("Reloaded configuration, {:?}", summary);
+                status.error = None;
+                status.summary = Some(summary.clone());
+            }
+            Err(err) => {
+                error!("Reload failed, keeping the previous configuration: {}", err);
+                status.error = Some(err.clone());
+            }
+        }
+        result
+    }
+
+    pub fn status(&self) -> ReloadStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn init_cache(&self, ttl: u64) -> Result<Option<ResultCache>, String> {
+        if ttl == 0 {
+            return Ok(None);
+        }
+        let ttl = Duration::from_secs(ttl);
+
+        #[cfg(feature = "redis")]
+        if let Some(url) = &self.defaults.redis_url {
+            let backend = RedisCache::open(url, "indy-did-driver:")
+                .map_err(|err| format!("Could not connect to Redis cache, {}", err))?;
+            return Ok(Some(ResultCache::new(Arc::new(backend), ttl)));
+        }
+        Ok(Some(ResultCache::in_memory(ttl)))
+    }
+}
+
+/// Reloads on every SIGHUP
+#[cfg(unix)]
+pub fn reload_on_sighup(driver: std::sync::Arc<super::Driver>) {
+    use signal_hook::consts::SIGHUP;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new(&[SIGHUP]).expect("Could not register SIGHUP handler.");
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            info!("Received SIGHUP, reloading configuration");
+            driver.reloader.reload(&driver.resolvers).ok();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "indy-did-driver-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn reloader(config_path: Option<PathBuf>) -> Reloader {
+        Reloader::new(
+            config_path,
+            DirectoryProvider::new(env::temp_dir().join("no-networks"), "genesis.json"),
+            Defaults {
+                cache_ttl: 60,
+                ..Defaults::default()
+            },
+        )
+    }
+
+    #[test]
+    fn config_file_overrides_defaults() {
+        let path = config_file(
+            "overrides",
+            r#"{"cache_ttl": 0, "max_concurrent_requests": 8}"#,
+        );
+        let config = reloader(Some(path.clone())).resolver_config().unwrap();
+        fs::remove_file(path).ok();
+
+        assert!(config.cache.is_none());
+        assert_eq!(Some(8), config.max_concurrent_requests);
+    }
+
+    #[test]
+    fn cache_kept_while_ttl_unchanged() {
+        let reloader = reloader(None);
+        let config = reloader.resolver_config().unwrap();
+        reloader.applied(&config);
+
+        let reloaded = reloader.resolver_config().unwrap();
+        assert!(std::sync::Arc::ptr_eq(
+            &config.cache.unwrap().backend,
+            &reloaded.cache.unwrap().backend
+        ));
+    }
+
+    #[test]
+    fn failed_reload_is_reported() {
+        let path = config_file("invalid", r#"{"cache_ttl": "one hour"}"#);
+        let reloader = reloader(Some(path.clone()));
+        let resolvers = Resolvers::new();
+
+        assert!(reloader.reload(&resolvers).is_err());
+        fs::remove_file(path).ok();
+        let status = reloader.status();
+        assert!(status.last_reload.is_some());
+        assert!(status.error.unwrap().starts_with("Invalid config file"));
+        assert!(status.summary.is_none());
+    }
+
+    #[test]
+    fn successful_reload_clears_error() {
+        let path = config_file("recovering", r#"{"cache_ttl": "one hour"}"#);
+        let reloader = reloader(Some(path.clone()));
+        let resolvers = Resolvers::new();
+        assert!(reloader.reload(&resolvers).is_err());
+
+        fs::write(&path, r#"{"cache_ttl": 30}"#).unwrap();
+        assert!(reloader.reload(&resolvers).is_ok());
+        fs::remove_file(path).ok();
+        let status = reloader.status();
+        assert!(status.error.is_none());
+        assert_eq!(Some(ReloadSummary::default()), status.summary);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use futures_executor::block_on;
+use indy_didresolver::ledger::LedgerRequester;
+use indy_didresolver::networks::{build_pool, NetworkProvider};
+use indy_didresolver::pool_status::{pool_status, PoolStatus};
+use indy_vdr::pool::{PoolTransactions, SharedPool};
+
+/// Provides the pool of a single genesis file, for any namespace
+pub struct GenesisProvider(pub PathBuf);
+
+impl NetworkProvider<SharedPool> for GenesisProvider {
+    fn pool_for(&self, _namespace: &str) -> Option<SharedPool> {
+        let pool = PoolTransactions::from_json_file(&self.0)
+            .map_err(Into::into)
+            .and_then(build_pool);
+        match pool {
+            Ok(pool) => Some(pool),
+            Err(err) => {
+                error!("Could not initialize pool from {:?}, {}", self.0, err);
+                None
+            }
+        }
+    }
+}
+
+/// Connects to the pool of each namespace and requests its status. Namespaces
+/// whose pool cannot be built are reported with an error.
+pub fn statuses<T: LedgerRequester>(
+    provider: &dyn NetworkProvider<T>,
+    namespaces: &[String],
+) -> Vec<PoolStatus> {
+    namespaces
+        .iter()
+        .map(|namespace| match provider.pool_for(namespace) {
+            Some(pool) => block_on(pool_status(&pool, namespace)),
+            None => PoolStatus {
+                namespace: namespace.clone(),
+                nodes: vec![],
+                ledger_time: None,
+                duration_ms: 0,
+                error: Some(String::from("Could not initialize pool")),
+            },
+        })
+        .collect()
+}
+
+/// Statuses as JSON or as a table per namespace
+pub fn render(statuses: &[PoolStatus], json: bool) -> String {
+    if json {
+        return serde_json::to_string_pretty(statuses).unwrap();
+    }
+    let mut table = String::new();
+    for status in statuses {
+        let ledger_time = status
+            .ledger_time
+            .map(|time| serde_json::to_value(time).unwrap())
+            .and_then(|time| time.as_str().map(String::from))
+            .unwrap_or_else(|| String::from("-"));
+        writeln!(
+            table,
+            "{}: ledger time {}, {} ms",
+            status.namespace, ledger_time, status.duration_ms
+        )
+        .unwrap();
+        if let Some(error) = &status.error {
+            writeln!(table, "  error: {}", error).unwrap();
+        }
+        for node in &status.nodes {
+            let latency = match node.latency_ms {
+                Some(latency) => format!("{} ms", latency),
+                None => String::from("no reply"),
+            };
+            writeln!(table, "  {:<20} {}", node.alias, latency).unwrap();
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indy_didresolver::error::DidIndyResult;
+    use indy_vdr::ledger::RequestBuilder;
+    use indy_vdr::pool::{PreparedRequest, ProtocolVersion};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+    struct StubPool;
+
+    impl LedgerRequester for StubPool {
+        fn request_builder(&self) -> RequestBuilder {
+            RequestBuilder::new(ProtocolVersion::Node1_4)
+        }
+
+        fn submit<'a>(
+            &'a self,
+            _request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+            let reply = json!({
+                "op": "REPLY",
+                "result": {
+                    "state_proof": {
+                        "multi_signature": { "value": { "timestamp": 1636641222 } }
+                    }
+                }
+            });
+            Box::pin(async move { Ok(reply.to_string()) })
+        }
+
+        fn node_aliases(&self) -> Vec<String> {
+            vec![String::from("Node1"), String::from("Node2")]
+        }
+
+        fn submit_timed<'a>(
+            &'a self,
+            request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<(String, HashMap<String, f32>)>> {
+            Box::pin(async move {
+                let timing = vec![(String::from("Node1"), 0.25)].into_iter().collect();
+                Ok((self.submit(request).await?, timing))
+            })
+        }
+    }
+
+    // Provides the stub pool for idunion only
+    struct StubProvider;
+
+    impl NetworkProvider<StubPool> for StubProvider {
+        fn pool_for(&self, namespace: &str) -> Option<StubPool> {
+            Some(StubPool).filter(|_| namespace == "idunion")
+        }
+    }
+
+    fn stub_statuses() -> Vec<PoolStatus> {
+        let mut statuses = statuses(
+            &StubProvider,
+            &[String::from("idunion"), String::from("sovrin")],
+        );
+        for status in statuses.iter_mut() {
+            status.duration_ms = 7;
+        }
+        statuses
+    }
+
+    #[test]
+    fn json_output() {
+        let output: serde_json::Value =
+            serde_json::from_str(&render(&stub_statuses(), true)).unwrap();
+        assert_eq!(
+            json!([
+                {
+                    "namespace": "idunion",
+                    "nodes": [
+                        { "alias": "Node1", "replied": true, "latencyMs": 250 },
+                        { "alias": "Node2", "replied": false }
+                    ],
+                    "ledgerTime": "2021-11-11T14:33:42Z",
+                    "durationMs": 7
+                },
+                {
+                    "namespace": "sovrin",
+                    "nodes": [],
+                    "ledgerTime": null,
+                    "durationMs": 7,
+                    "error": "Could not initialize pool"
+                }
+            ]),
+            output
+        );
+    }
+
+    #[test]
+    fn table_output() {
+        assert_eq!(
+            "idunion: ledger time 2021-11-11T14:33:42Z, 7 ms\n\
+             \x20 Node1                250 ms\n\
+             \x20 Node2                no reply\n\
+             sovrin: ledger time -, 7 ms\n\
+             \x20 error: Could not initialize pool\n",
+            render(&stub_statuses(), false)
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::env;
+
+use opentelemetry::propagation::Extractor;
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use rouille::Request;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Exports the spans of resolutions to the OTLP endpoint configured with the
+/// standard OTEL_EXPORTER_OTLP_* environment variables
+pub fn init() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let service_name =
+        env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| String::from("indy-did-driver"));
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().http().with_env())
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name,
+            )])),
+        )
+        .install_simple()
+        .expect("Could not initialize OpenTelemetry exporter.");
+
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("Could not install tracing subscriber.");
+}
+
+/// Span of an incoming request, joining the trace of the caller if the request
+/// carries a traceparent header
+pub fn request_span(request: &Request) -> tracing::Span {
+    let span = tracing::info_span!("http_request", http.url = %request.url());
+    let parent =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(request)));
+    span.set_parent(parent);
+    span
+}
+
+struct HeaderExtractor<'a>(&'a Request);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.header(key)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.headers().map(|(key, _)| key).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn extracts_traceparent_header() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let request = Request::fake_http(
+            "GET",
+            "/1.0/identifiers/did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            vec![(
+                String::from("traceparent"),
+                String::from("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+            )],
+            vec![],
+        );
+
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(&request))
+        });
+        assert_eq!(
+            "4bf92f3577b34da6a3ce929d0e0e4736",
+            parent.span().span_context().trace_id().to_string()
+        );
+        assert!(parent.span().span_context().is_remote());
+    }
+}
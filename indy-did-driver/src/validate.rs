@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+
+use indy_didresolver::did::DidUrl;
+use indy_didresolver::resolver::build_request;
+use indy_vdr::ledger::RequestBuilder;
+use indy_vdr::pool::ProtocolVersion;
+use serde_json::{json, Value};
+
+/// Components of the DID URL and the ledger request it would be resolved with,
+/// or the component failing to parse. Does not query the ledger.
+pub fn validate(did_url: &str) -> Value {
+    let parsed = match DidUrl::parse_strict(did_url) {
+        Ok((parsed, _)) => parsed,
+        Err(err) => {
+            return json!({
+                "didUrl": did_url,
+                "valid": false,
+                "component": err.component,
+                "error": err.error.to_string(),
+            })
+        }
+    };
+    let request = match build_request(&parsed, &RequestBuilder::new(ProtocolVersion::Node1_4)) {
+        Ok(request) => request,
+        Err(err) => {
+            return json!({
+                "didUrl": did_url,
+                "valid": false,
+                "component": "path",
+                "error": err.to_string(),
+            })
+        }
+    };
+
+    let mut result = parsed.components();
+    result["didUrl"] = json!(did_url);
+    result["valid"] = json!(true);
+    result["request"] = json!({
+        "txnType": request.txn_type,
+        "operation": request.req_json["operation"],
+    });
+    result
+}
+
+/// DID URLs of a batch file, one per line. Blank lines and lines starting with
+/// `#` are skipped.
+pub fn read_batch(path: &Path) -> Result<Vec<String>, String> {
+    let batch = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read batch file {:?}, {}", path, err))?;
+    Ok(batch
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Validates the DID URLs. A single DID URL is printed as pretty JSON, a batch
+/// as one JSON object per line. Returns the output and whether all are valid.
+pub fn run(did_urls: &[String], batch: bool) -> (String, bool) {
+    let results: Vec<Value> = did_urls.iter().map(|did_url| validate(did_url)).collect();
+    let valid = results.iter().all(|result| result["valid"] == true);
+    let output = if batch {
+        results
+            .iter()
+            .map(|result| format!("{}\n", result))
+            .collect()
+    } else {
+        results
+            .iter()
+            .map(|result| format!("{}\n", serde_json::to_string_pretty(result).unwrap()))
+            .collect()
+    };
+    (output, valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn valid_schema_url() {
+        let result =
+            validate("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4");
+
+        assert_eq!(true, result["valid"]);
+        assert_eq!("idunion", result["namespace"]);
+        assert_eq!("Dk1fRRTtNazyMuK2cr64wp", result["id"]);
+        assert_eq!(
+            json!({ "objectType": "SCHEMA", "name": "npdb", "version": "4.3.4" }),
+            result["ledgerObject"]
+        );
+        assert_eq!("107", result["request"]["txnType"]);
+        assert_eq!("npdb", result["request"]["operation"]["data"]["name"]);
+    }
+
+    #[test]
+    fn malformed_components() {
+        let component = |did_url| validate(did_url)["component"].clone();
+
+        assert_eq!("did", component("did:indy:idunion:Dk1f"));
+        assert_eq!(
+            "path",
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v5/SCHEMA/npdb/4.3.4")
+        );
+        assert_eq!(
+            "query",
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=yesterday")
+        );
+        assert_eq!(
+            "Query parameter not supported",
+            validate("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?color=blue")["error"]
+        );
+    }
+
+    #[test]
+    fn batch_mode() {
+        let path = env::temp_dir().join(format!("indy-did-driver-batch-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# DIDs of the issuer\n\
+             did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp\n\
+             \n\
+             did:indy:idunion:Dk1f\n",
+        )
+        .unwrap();
+        let did_urls = read_batch(&path).unwrap();
+        fs::remove_file(path).ok();
+        assert_eq!(2, did_urls.len());
+
+        let (output, valid) = run(&did_urls, true);
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(!valid);
+        assert_eq!(true, lines[0]["valid"]);
+        assert_eq!(false, lines[1]["valid"]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Fixed inputs of the benchmarks, so that results of different runs compare.
+//! `tests/bench_fixtures.rs` checks that they stay usable.
+
+// Each benchmark and the guard test use only some of the fixtures
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use futures_util::future::LocalBoxFuture;
+use indy_didresolver::error::{DidIndyError, DidIndyResult};
+use indy_didresolver::ledger::LedgerRequester;
+use indy_vdr::ledger::RequestBuilder;
+use indy_vdr::pool::{PreparedRequest, ProtocolVersion};
+use serde_json::{json, Value};
+
+pub const NAMESPACE: &str = "idunion";
+pub const ID: &str = "Dk1fRRTtNazyMuK2cr64wp";
+pub const DID: &str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+pub const VERKEY: &str = "~4Vr1bJ6V6jEPQ1PdmWyNkK";
+
+/// Representative DID URLs, from a plain DID to ledger objects with queries
+pub const DID_URLS: &[&str] = &[
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14:33:42Z",
+    "did:indy:sovrin:staging:Dk1fRRTtNazyMuK2cr64wp?versionId=12",
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4",
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/CLAIM_DEF/74/default",
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54",
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14%3A33%3A42Z",
+    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from=2021-11-01T00:00:00Z&to=2021-11-11T14:33:42Z",
+];
+
+/// DID URL of each kind of ledger object, named for the benchmark ids. Times are
+/// given, so that the requests do not depend on the current time.
+pub const LEDGER_OBJECT_URLS: &[(&str, &str)] = &[
+    ("NYM", "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+    (
+        "SCHEMA",
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4",
+    ),
+    (
+        "CLAIM_DEF",
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/CLAIM_DEF/74/default",
+    ),
+    (
+        "REV_REG_DEF",
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54",
+    ),
+    (
+        "REV_REG_ENTRY",
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14:33:42Z",
+    ),
+    (
+        "REV_REG_DELTA",
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from=2021-11-01T00:00:00Z&to=2021-11-11T14:33:42Z",
+    ),
+];
+
+pub fn request_builder() -> RequestBuilder {
+    RequestBuilder::new(ProtocolVersion::Node1_4)
+}
+
+pub fn diddoc_content() -> Value {
+    json!({
+        "service": [{
+            "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#didcomm-1",
+            "type": "DIDCommMessaging",
+            "serviceEndpoint": "https://example.com/didcomm",
+            "accept": ["didcomm/v2"],
+            "routingKeys": ["did:example:mediator#key-1"]
+        }]
+    })
+}
+
+fn ledger_reply(txn_type: &str, data: Value, seq_no: u64, txn_time: u64) -> String {
+    json!({
+        "op": "REPLY",
+        "result": {
+            "type": txn_type,
+            "reqId": 1,
+            "seqNo": seq_no,
+            "txnTime": txn_time,
+            "data": data,
+            "state_proof": {}
+        }
+    })
+    .to_string()
+}
+
+/// Data of the reply to GET_NYM, with or without diddocContent
+pub fn nym_data(diddoc_content: Option<Value>) -> String {
+    let mut data = json!({
+        "dest": ID,
+        "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+        "role": null,
+        "seqNo": 12,
+        "txnTime": 1636641222,
+        "verkey": VERKEY,
+    });
+    if let Some(diddoc_content) = diddoc_content {
+        data["diddocContent"] = Value::String(diddoc_content.to_string());
+    }
+    data.to_string()
+}
+
+pub fn nym_reply(diddoc_content: Option<Value>) -> String {
+    ledger_reply(
+        "105",
+        Value::String(nym_data(diddoc_content)),
+        12,
+        1636641222,
+    )
+}
+
+/// Data of the reply to the GET_ATTRIB of the legacy endpoint
+pub fn endpoint_data() -> String {
+    json!({ "endpoint": { "endpoint": "https://example.com/endpoint" } }).to_string()
+}
+
+pub fn endpoint_reply() -> String {
+    ledger_reply("104", Value::String(endpoint_data()), 13, 1636641230)
+}
+
+/// In-process pool answering each type of request with a fixed reply, without
+/// network or timing effects
+pub struct FixedLedger {
+    replies: HashMap<String, String>,
+}
+
+impl FixedLedger {
+    /// Answers GET_NYM with the NYM, and GET_ATTRIB with the legacy endpoint
+    pub fn new(diddoc_content: Option<Value>) -> Self {
+        let mut replies = HashMap::new();
+        replies.insert(String::from("105"), nym_reply(diddoc_content));
+        replies.insert(String::from("104"), endpoint_reply());
+        FixedLedger { replies }
+    }
+}
+
+impl LedgerRequester for FixedLedger {
+    fn request_builder(&self) -> RequestBuilder {
+        request_builder()
+    }
+
+    fn submit<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+        let reply = self
+            .replies
+            .get(&request.txn_type)
+            .cloned()
+            .ok_or(DidIndyError::EmptyData);
+        Box::pin(async move { reply })
+    }
+}
@@ -0,0 +1,93 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use indy_didresolver::config::{CacheMode, ResolveOptions};
+use indy_didresolver::did::DidUrl;
+use indy_didresolver::did_document::DidDocument;
+use indy_didresolver::resolver::{build_request, Resolver};
+use indy_didresolver::responses::{Endpoint, GetNymResultV1};
+use serde_json::Value;
+
+mod fixtures;
+
+use fixtures::*;
+
+fn parse_did_urls(c: &mut Criterion) {
+    let mut group = c.benchmark_group("DidUrl::from_str");
+    for (i, did_url) in DID_URLS.iter().enumerate() {
+        group.bench_with_input(BenchmarkId::from_parameter(i), did_url, |b, did_url| {
+            b.iter(|| DidUrl::from_str(black_box(did_url)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn build_requests(c: &mut Criterion) {
+    let builder = request_builder();
+    let mut group = c.benchmark_group("build_request");
+    for (object, did_url) in LEDGER_OBJECT_URLS {
+        let did_url = DidUrl::from_str(did_url).unwrap();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(object),
+            &did_url,
+            |b, did_url| b.iter(|| build_request(black_box(did_url), &builder).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+// Assembles the document from the data of the NYM reply like the resolver,
+// without the ledger request
+fn assemble(nym_data: &str, endpoint: Option<Endpoint>) -> Value {
+    let nym: GetNymResultV1 = serde_json::from_str(nym_data).unwrap();
+    let diddoc_content = nym
+        .diddoc_content
+        .as_ref()
+        .and_then(Value::as_str)
+        .map(serde_json::from_str)
+        .transpose()
+        .unwrap();
+    DidDocument::new(NAMESPACE, &nym.dest, &nym.verkey, endpoint, diddoc_content)
+        .to_value()
+        .unwrap()
+}
+
+fn assemble_documents(c: &mut Criterion) {
+    let mut group = c.benchmark_group("DidDocument");
+    let without_diddoc_content = nym_data(None);
+    let endpoint: Endpoint = serde_json::from_str(&endpoint_data()).unwrap();
+    group.bench_function("legacy endpoint", |b| {
+        b.iter(|| assemble(black_box(&without_diddoc_content), Some(endpoint.clone())))
+    });
+    let with_diddoc_content = nym_data(Some(diddoc_content()));
+    group.bench_function("diddocContent", |b| {
+        b.iter(|| assemble(black_box(&with_diddoc_content), None))
+    });
+    group.finish();
+}
+
+fn resolve(c: &mut Criterion) {
+    let options = ResolveOptions::new().cache(CacheMode::Bypass);
+    let mut group = c.benchmark_group("resolve");
+    for (name, diddoc_content) in [
+        ("legacy endpoint", None),
+        ("diddocContent", Some(diddoc_content())),
+    ] {
+        let resolver = Resolver::new(FixedLedger::new(diddoc_content));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                resolver
+                    .resolve_string_with_options(black_box(DID), &options)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    parse_did_urls,
+    build_requests,
+    assemble_documents,
+    resolve
+);
+criterion_main!(benches);
@@ -0,0 +1,236 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::warn;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Whether a result was served from the cache
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    /// The cache was bypassed for the resolution
+    Bypassed,
+    /// An expired result was served as the ledger could not be reached
+    Stale,
+    /// No cache is configured
+    Disabled,
+}
+
+/// Audit record of a single resolution or dereferencing
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// RFC3339 time the resolution started
+    pub timestamp: String,
+    pub operation: String,
+    /// DID URL as requested, or its SHA-256 digest if identifiers are hashed
+    pub did_url: String,
+    /// Empty if the DID URL is invalid
+    pub namespace: String,
+    pub object_type: Option<String>,
+    /// `ok` or the error code of the failed resolution
+    pub result: String,
+    pub seq_no: Option<u64>,
+    pub duration_ms: u64,
+    pub cache: CacheStatus,
+}
+
+/// Receiver of audit records. Recording must not block the resolution.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, record: AuditRecord);
+}
+
+/// Audit sink of a resolver together with how identifiers are recorded
+#[derive(Clone)]
+pub struct AuditLog {
+    pub sink: Arc<dyn AuditSink>,
+    /// Record the SHA-256 digest of DID URLs instead of the DID URLs themselves
+    pub hash_identifiers: bool,
+}
+
+impl AuditLog {
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        AuditLog {
+            sink,
+            hash_identifiers: false,
+        }
+    }
+
+    pub(crate) fn identifier(&self, did_url: &str) -> String {
+        if self.hash_identifiers {
+            format!("{:x}", Sha256::digest(did_url.as_bytes()))
+        } else {
+            did_url.to_string()
+        }
+    }
+}
+
+impl fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("hash_identifiers", &self.hash_identifiers)
+            .finish()
+    }
+}
+
+/// Writes one JSON object per line. Records are handed to a writer thread through
+/// a bounded channel; records arriving while the channel is full are dropped and
+/// counted so that a slow disk does not stall resolutions.
+pub struct JsonLinesAuditLog {
+    sender: Mutex<SyncSender<AuditRecord>>,
+    dropped: AtomicU64,
+}
+
+impl JsonLinesAuditLog {
+    /// Appends to the file at the path, creating it if it does not exist
+    pub fn open(path: impl AsRef<Path>, capacity: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(JsonLinesAuditLog::new(file, capacity))
+    }
+
+    pub fn new<W: Write + Send + 'static>(mut writer: W, capacity: usize) -> Self {
+        let (sender, receiver) = sync_channel::<AuditRecord>(capacity);
+        thread::spawn(move || {
+            for record in receiver {
+                let result = serde_json::to_writer(&mut writer, &record)
+                    .map_err(io::Error::from)
+                    .and_then(|_| writer.write_all(b"\n"))
+                    .and_then(|_| writer.flush());
+                if let Err(err) = result {
+                    warn!("Could not write audit record, {}", err);
+                }
+            }
+        });
+        JsonLinesAuditLog {
+            sender: Mutex::new(sender),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of records dropped as the writer could not keep up
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::SeqCst)
+    }
+}
+
+impl AuditSink for JsonLinesAuditLog {
+    fn record(&self, record: AuditRecord) {
+        match self.sender.lock().unwrap().try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                warn!("Audit writer stopped, dropping record");
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::time::Duration;
+
+    fn record(did_url: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp: String::from("2021-11-11T14:33:42Z"),
+            operation: String::from("resolve"),
+            did_url: did_url.to_string(),
+            namespace: String::from("idunion"),
+            object_type: Some(String::from("NYM")),
+            result: String::from("ok"),
+            seq_no: Some(12),
+            duration_ms: 3,
+            cache: CacheStatus::Miss,
+        }
+    }
+
+    // Passes every line written on to a channel
+    struct LineWriter(std::sync::mpsc::Sender<Vec<u8>>);
+
+    impl Write for LineWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.send(buf.to_vec()).unwrap();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Blocks every write until released
+    struct BlockedWriter(Receiver<()>);
+
+    impl Write for BlockedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.recv().ok();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_record_as_json_line() {
+        let (sender, receiver) = channel();
+        let log = JsonLinesAuditLog::new(LineWriter(sender), 8);
+        log.record(record("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"));
+
+        let mut line = Vec::new();
+        while !line.ends_with(b"\n") {
+            line.extend(receiver.recv_timeout(Duration::from_secs(1)).unwrap());
+        }
+        let written: Value = serde_json::from_slice(&line).unwrap();
+
+        assert_eq!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", written["didUrl"]);
+        assert_eq!("idunion", written["namespace"]);
+        assert_eq!("NYM", written["objectType"]);
+        assert_eq!("ok", written["result"]);
+        assert_eq!(12, written["seqNo"]);
+        assert_eq!(3, written["durationMs"]);
+        assert_eq!("miss", written["cache"]);
+        assert_eq!(0, log.dropped());
+    }
+
+    #[test]
+    fn drops_records_while_writer_is_blocked() {
+        let (release, blocked) = channel();
+        let log = JsonLinesAuditLog::new(BlockedWriter(blocked), 2);
+
+        for _ in 0..10 {
+            log.record(record("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"));
+        }
+        // The writer holds one record, the channel two more
+        assert!(log.dropped() >= 7);
+        assert!(log.dropped() <= 8);
+        drop(release);
+    }
+
+    #[test]
+    fn hashed_identifier() {
+        let mut audit = AuditLog::new(Arc::new(JsonLinesAuditLog::new(io::sink(), 1)));
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        assert_eq!(did, audit.identifier(did));
+
+        audit.hash_identifiers = true;
+        let hashed = audit.identifier(did);
+        assert_eq!(64, hashed.len());
+        assert!(!hashed.contains("Dk1fRRTtNazyMuK2cr64wp"));
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use super::did::{DidUrl, LedgerObject, QueryParameter};
+
+/// Lifetime of cached immutable results, unless set otherwise
+pub const DEFAULT_IMMUTABLE_TTL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Storage for cached resolution results. Failures of a backend must not fail a
+/// resolution, so they are reported as cache misses.
+pub trait CacheBackend: Send + Sync {
+    fn get(&self, key: &str) -> Option<String>;
+
+    fn set(&self, key: &str, value: &str, ttl: Duration);
+}
+
+/// Read-through cache for resolution and dereferencing results
+#[derive(Clone)]
+pub struct ResultCache {
+    pub backend: Arc<dyn CacheBackend>,
+    pub ttl: Duration,
+    /// Lifetime of the results of DID URLs classified by `is_immutable`
+    pub immutable_ttl: Duration,
+}
+
+impl ResultCache {
+    pub fn new(backend: Arc<dyn CacheBackend>, ttl: Duration) -> Self {
+        ResultCache {
+            backend,
+            ttl,
+            immutable_ttl: DEFAULT_IMMUTABLE_TTL,
+        }
+    }
+
+    pub fn in_memory(ttl: Duration) -> Self {
+        ResultCache::new(Arc::new(InMemoryCache::default()), ttl)
+    }
+
+    pub fn with_immutable_ttl(mut self, immutable_ttl: Duration) -> Self {
+        self.immutable_ttl = immutable_ttl;
+        self
+    }
+
+    /// Lifetime of the result of the DID URL
+    pub fn ttl_of(&self, did_url: &str) -> Duration {
+        if is_immutable(did_url) {
+            self.immutable_ttl
+        } else {
+            self.ttl
+        }
+    }
+}
+
+/// Whether the result of the DID URL can never change: schemas, cred defs and rev
+/// reg defs, rev reg entries at a `versionTime` in the past and NYMs pinned by
+/// `versionId` or `seqNo`. Everything else, including DID URLs that do not parse,
+/// is taken as mutable.
+pub fn is_immutable(did_url: &str) -> bool {
+    let did_url = match DidUrl::from_str(did_url) {
+        Ok(did_url) => did_url,
+        Err(_) => return false,
+    };
+    let path = match &did_url.path {
+        Some(path) => path,
+        None => {
+            return did_url.query.contains_key(&QueryParameter::VersionId)
+                || did_url.query.contains_key(&QueryParameter::SeqNo)
+        }
+    };
+    match LedgerObject::from_str(path) {
+        Ok(LedgerObject::Schema(_))
+        | Ok(LedgerObject::ClaimDef(_))
+        | Ok(LedgerObject::RevRegDef(_)) => true,
+        // A later entry could still be written for a time that has not passed
+        Ok(LedgerObject::RevRegEntry(_)) => did_url
+            .query
+            .get(&QueryParameter::VersionTime)
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map_or(false, |time| time.timestamp() < Utc::now().timestamp()),
+        _ => false,
+    }
+}
+
+impl fmt::Debug for ResultCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResultCache")
+            .field("ttl", &self.ttl)
+            .field("immutable_ttl", &self.immutable_ttl)
+            .finish()
+    }
+}
+
+/// Cache local to the process. Expired entries are dropped on access. With a
+/// maximum number of entries, the least recently used entry is evicted to make
+/// room for a new one once expired entries are dropped. Entries whose TTL
+/// reaches beyond the range of `Instant`, e.g. `Duration::MAX`, never expire.
+#[derive(Default)]
+pub struct InMemoryCache {
+    entries: Mutex<Entries>,
+    max_entries: Option<usize>,
+}
+
+struct CacheEntry {
+    value: String,
+    // None if the entry never expires
+    expires: Option<Instant>,
+    used: u64,
+}
+
+impl CacheEntry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+}
+
+// Entries indexed by the order of their use and of their expiry, so that
+// neither eviction nor dropping expired entries scans the whole cache
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<String, CacheEntry>,
+    // Keys by the last use of their entry, least recent first
+    by_use: BTreeMap<u64, String>,
+    // Keys of the entries that expire, earliest first
+    by_expiry: BTreeSet<(Instant, String)>,
+    // Counter ordering the uses of entries
+    clock: u64,
+}
+
+impl Entries {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    // Value of the entry, marked as used, unless the entry expired
+    fn use_entry(&mut self, key: &str, now: Instant) -> Option<String> {
+        let used = self.tick();
+        let entry = self.by_key.get_mut(key)?;
+        if entry.is_expired(now) {
+            self.remove(key);
+            return None;
+        }
+        let previous = std::mem::replace(&mut entry.used, used);
+        let value = entry.value.clone();
+        self.by_use.remove(&previous);
+        self.by_use.insert(used, key.to_string());
+        Some(value)
+    }
+
+    fn insert(&mut self, key: &str, value: &str, expires: Option<Instant>) {
+        self.remove(key);
+        let used = self.tick();
+        if let Some(expires) = expires {
+            self.by_expiry.insert((expires, key.to_string()));
+        }
+        self.by_use.insert(used, key.to_string());
+        self.by_key.insert(
+            key.to_string(),
+            CacheEntry {
+                value: value.to_string(),
+                expires,
+                used,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.by_key.remove(key) {
+            self.by_use.remove(&entry.used);
+            if let Some(expires) = entry.expires {
+                self.by_expiry.remove(&(expires, key.to_string()));
+            }
+        }
+    }
+
+    fn drop_expired(&mut self, now: Instant) {
+        while let Some((expires, key)) = self.by_expiry.iter().next().cloned() {
+            if expires > now {
+                break;
+            }
+            self.remove(&key);
+        }
+    }
+
+    // Evicts the least recently used entry, false if there is none
+    fn evict_least_recent(&mut self) -> bool {
+        match self.by_use.values().next().cloned() {
+            Some(key) => {
+                self.remove(&key);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl InMemoryCache {
+    /// Cache holding at most `max_entries` entries
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        InMemoryCache {
+            max_entries: Some(max_entries),
+            ..InMemoryCache::default()
+        }
+    }
+}
+
+impl CacheBackend for InMemoryCache {
+    fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().use_entry(key, Instant::now())
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        // Expired entries are dropped also if they are never read again, so an
+        // unbounded cache does not grow with them
+        entries.drop_expired(now);
+        if let Some(max_entries) = self.max_entries {
+            while !entries.by_key.contains_key(key) && entries.by_key.len() >= max_entries {
+                if !entries.evict_least_recent() {
+                    return;
+                }
+            }
+        }
+        entries.insert(key, value, now.checked_add(ttl));
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use self::redis_cache::RedisCache;
+
+#[cfg(feature = "redis")]
+mod redis_cache {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use log::warn;
+    use redis::{Client, Commands, Connection, RedisResult};
+
+    use super::CacheBackend;
+
+    /// Cache shared by all resolver instances connected to the same Redis server
+    pub struct RedisCache {
+        client: Client,
+        prefix: String,
+        connection: Mutex<Option<Connection>>,
+    }
+
+    impl RedisCache {
+        /// Connects to the server at the given `redis://` URL. Keys are prefixed to
+        /// separate the entries of different deployments sharing a server.
+        pub fn open(url: &str, prefix: &str) -> RedisResult<Self> {
+            let client = Client::open(url)?;
+            let connection = client.get_connection()?;
+            Ok(RedisCache {
+                client,
+                prefix: prefix.to_string(),
+                connection: Mutex::new(Some(connection)),
+            })
+        }
+
+        // Runs the command, reconnecting once if the connection was lost
+        fn with_connection<R>(
+            &self,
+            command: impl Fn(&mut Connection) -> RedisResult<R>,
+        ) -> Option<R> {
+            let mut connection = self.connection.lock().unwrap();
+            if let Some(con) = connection.as_mut() {
+                match command(con) {
+                    Ok(result) => return Some(result),
+                    Err(err) if !err.is_connection_dropped() && !err.is_io_error() => {
+                        warn!("Redis cache command failed, {}", err);
+                        return None;
+                    }
+                    Err(_) => *connection = None,
+                }
+            }
+
+            let result = self
+                .client
+                .get_connection()
+                .and_then(|mut con| command(&mut con).map(|result| (con, result)));
+            match result {
+                Ok((con, result)) => {
+                    *connection = Some(con);
+                    Some(result)
+                }
+                Err(err) => {
+                    warn!("Redis cache not available, {}", err);
+                    None
+                }
+            }
+        }
+    }
+
+    impl CacheBackend for RedisCache {
+        fn get(&self, key: &str) -> Option<String> {
+            let key = format!("{}{}", self.prefix, key);
+            self.with_connection(|con| con.get::<_, Option<String>>(&key))
+                .flatten()
+        }
+
+        fn set(&self, key: &str, value: &str, ttl: Duration) {
+            let key = format!("{}{}", self.prefix, key);
+            // Redis expects a TTL of at least one second
+            let seconds = ttl.as_secs().max(1) as usize;
+            self.with_connection(|con| con.set_ex::<_, _, ()>(&key, value, seconds));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_cache_returns_entry_within_ttl() {
+        let cache = InMemoryCache::default();
+        cache.set("key", "value", Duration::from_secs(60));
+        assert_eq!(Some(String::from("value")), cache.get("key"));
+        assert_eq!(None, cache.get("other"));
+    }
+
+    #[test]
+    fn immutable_did_urls() {
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        let rev_reg = "anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54";
+        for immutable in [
+            format!("{}?versionId=12", did),
+            format!("{}?seqNo=12", did),
+            format!("{}/anoncreds/v0/SCHEMA/npdb/4.3.4", did),
+            format!("{}/anoncreds/v0/CLAIM_DEF/104/revocable", did),
+            format!("{}/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54", did),
+            format!("{}/{}?versionTime=2021-11-11T14:33:42Z", did, rev_reg),
+        ] {
+            assert!(is_immutable(&immutable), "{}", immutable);
+        }
+        for mutable in [
+            did.to_string(),
+            format!("{}?versionTime=2021-11-11T14:33:42Z", did),
+            format!("{}/{}", did, rev_reg),
+            format!("{}/{}?versionTime=2999-01-01T00:00:00Z", did, rev_reg),
+            format!(
+                "{}/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?to=2021-11-11T14:33:42Z",
+                did
+            ),
+            String::from("did:indy:idunion:invalid/anoncreds/v0/SCHEMA/npdb/4.3.4"),
+        ] {
+            assert!(!is_immutable(&mutable), "{}", mutable);
+        }
+    }
+
+    #[test]
+    fn in_memory_cache_drops_expired_entry() {
+        let cache = InMemoryCache::default();
+        cache.set("key", "value", Duration::from_millis(0));
+        assert_eq!(None, cache.get("key"));
+        let entries = cache.entries.lock().unwrap();
+        assert!(entries.by_key.is_empty());
+        assert!(entries.by_use.is_empty());
+        assert!(entries.by_expiry.is_empty());
+    }
+
+    #[test]
+    fn unbounded_in_memory_cache_drops_unread_expired_entries() {
+        let cache = InMemoryCache::default();
+        cache.set("first", "1", Duration::from_millis(0));
+        cache.set("second", "2", Duration::from_millis(0));
+
+        cache.set("third", "3", Duration::from_secs(60));
+
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(1, entries.by_key.len());
+        assert!(entries.by_key.contains_key("third"));
+        assert_eq!(1, entries.by_use.len());
+        assert_eq!(1, entries.by_expiry.len());
+    }
+
+    #[test]
+    fn in_memory_cache_entry_without_expiry() {
+        let cache = InMemoryCache::with_max_entries(2);
+        cache.set("key", "value", Duration::MAX);
+        assert_eq!(Some(String::from("value")), cache.get("key"));
+        assert!(cache.entries.lock().unwrap().by_expiry.is_empty());
+    }
+
+    #[test]
+    fn in_memory_cache_drops_expired_entries_before_evicting() {
+        let cache = InMemoryCache::with_max_entries(2);
+        cache.set("first", "1", Duration::from_secs(60));
+        cache.set("expired", "2", Duration::from_millis(0));
+
+        cache.set("third", "3", Duration::from_secs(60));
+
+        assert_eq!(Some(String::from("1")), cache.get("first"));
+        assert_eq!(Some(String::from("3")), cache.get("third"));
+        assert_eq!(2, cache.entries.lock().unwrap().by_key.len());
+    }
+
+    #[test]
+    fn in_memory_cache_evicts_least_recently_used_entry() {
+        let cache = InMemoryCache::with_max_entries(2);
+        cache.set("first", "1", Duration::from_secs(60));
+        cache.set("second", "2", Duration::from_secs(60));
+        assert_eq!(Some(String::from("1")), cache.get("first"));
+
+        cache.set("third", "3", Duration::from_secs(60));
+
+        assert_eq!(None, cache.get("second"));
+        assert_eq!(Some(String::from("1")), cache.get("first"));
+        assert_eq!(Some(String::from("3")), cache.get("third"));
+    }
+
+    #[test]
+    fn in_memory_cache_replaces_entry_without_evicting() {
+        let cache = InMemoryCache::with_max_entries(2);
+        cache.set("first", "1", Duration::from_secs(60));
+        cache.set("second", "2", Duration::from_secs(60));
+
+        cache.set("first", "one", Duration::from_secs(60));
+        cache.set("third", "3", Duration::from_secs(60));
+
+        assert_eq!(None, cache.get("second"));
+        assert_eq!(Some(String::from("one")), cache.get("first"));
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(2, entries.by_use.len());
+        assert_eq!(2, entries.by_expiry.len());
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::LocalBoxFuture;
+use indy_vdr::ledger::constants;
+use indy_vdr::ledger::identifiers::{CredentialDefinitionId, RevocationRegistryId};
+use indy_vdr::ledger::RequestBuilder;
+use indy_vdr::pool::PreparedRequest;
+use log::debug;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use super::cache::{CacheBackend, InMemoryCache, DEFAULT_IMMUTABLE_TTL};
+use super::error::DidIndyResult;
+use super::ledger::LedgerRequester;
+
+// Prefix of the keys of cached replies, so that a backend can be shared with a
+// `ResultCache`
+static KEY_PREFIX: &str = "ledger:";
+
+// Requests for ledger data that cannot change once written
+const IMMUTABLE_TXN_TYPES: [&str; 4] = [
+    constants::GET_SCHEMA,
+    constants::GET_CRED_DEF,
+    constants::GET_REVOC_REG_DEF,
+    constants::GET_TXN,
+];
+
+/// Settings of a `CachingPool` caching in memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Lifetime of the replies to GET_NYM, GET_ATTR and the revocation
+    /// registry entries and deltas
+    pub ttl: Duration,
+    /// Lifetime of the replies that cannot change, schemas, cred defs,
+    /// revocation registry definitions and transactions by sequence number
+    pub immutable_ttl: Duration,
+    /// Number of replies kept, the least recently used one is evicted beyond
+    /// it. Unbounded if not set.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            ttl: Duration::from_secs(60),
+            immutable_ttl: DEFAULT_IMMUTABLE_TTL,
+            max_entries: Some(10_000),
+        }
+    }
+}
+
+/// Ledger requester caching the replies of another one, e.g. an indy-vdr pool.
+/// Unlike the `ResultCache` of a resolver, it caches every read request of
+/// every code path, including the legacy endpoint ATTRIB and transaction
+/// lookups. Only read requests with a TTL are cached, all other requests are
+/// passed through.
+///
+/// It implements `LedgerRequester`, what a `Resolver` submits its requests to,
+/// and not the indy-vdr `Pool` trait. Requests sent with `Pool` directly, e.g.
+/// by `perform_ledger_request`, would not pass the cache, and the pool of the
+/// `CachingPool` is at hand with `pool()` for them.
+pub struct CachingPool<P> {
+    pool: P,
+    backend: Arc<dyn CacheBackend>,
+    ttls: HashMap<String, Duration>,
+}
+
+impl<P: LedgerRequester> CachingPool<P> {
+    /// Caches the replies to GET_NYM, GET_ATTR and the revocation registry
+    /// entries and deltas for the TTL. Schemas, cred defs, revocation registry
+    /// definitions and transactions by sequence number cannot change and are
+    /// kept for `DEFAULT_IMMUTABLE_TTL`.
+    pub fn new(pool: P, backend: Arc<dyn CacheBackend>, ttl: Duration) -> Self {
+        let mut ttls = HashMap::new();
+        for txn_type in [
+            constants::GET_NYM,
+            constants::GET_ATTR,
+            constants::GET_REVOC_REG,
+            constants::GET_REVOC_REG_DELTA,
+        ] {
+            ttls.insert(txn_type.to_string(), ttl);
+        }
+        for txn_type in IMMUTABLE_TXN_TYPES {
+            ttls.insert(txn_type.to_string(), DEFAULT_IMMUTABLE_TTL);
+        }
+        CachingPool {
+            pool,
+            backend,
+            ttls,
+        }
+    }
+
+    pub fn in_memory(pool: P, ttl: Duration) -> Self {
+        CachingPool::new(pool, Arc::new(InMemoryCache::default()), ttl)
+    }
+
+    /// Caches in memory with the TTLs and number of entries of the config
+    pub fn with_config(pool: P, config: CacheConfig) -> Self {
+        let backend = match config.max_entries {
+            Some(max_entries) => InMemoryCache::with_max_entries(max_entries),
+            None => InMemoryCache::default(),
+        };
+        let mut caching_pool = CachingPool::new(pool, Arc::new(backend), config.ttl);
+        for txn_type in IMMUTABLE_TXN_TYPES {
+            caching_pool = caching_pool.with_ttl(txn_type, config.immutable_ttl);
+        }
+        caching_pool
+    }
+
+    /// Caches the replies to requests of the type for the TTL
+    pub fn with_ttl(mut self, txn_type: &str, ttl: Duration) -> Self {
+        self.ttls.insert(txn_type.to_string(), ttl);
+        self
+    }
+
+    /// Passes requests of the type through to the pool
+    pub fn without_caching(mut self, txn_type: &str) -> Self {
+        self.ttls.remove(txn_type);
+        self
+    }
+
+    pub fn pool(&self) -> &P {
+        &self.pool
+    }
+
+    // Key of the cached reply and its TTL, none if the request is not cached
+    fn cache_key(&self, request: &PreparedRequest) -> Option<(String, Duration)> {
+        let ttl = *self.ttls.get(&request.txn_type)?;
+        Some((format!("{}{}", KEY_PREFIX, fingerprint(request)), ttl))
+    }
+}
+
+/// Fingerprint of what a request asks the ledger, the SHA-256 of its operation
+/// and protocol version as lowercase hex. The request id, submitter and
+/// signatures differ between requests asking the same and are left out.
+pub fn fingerprint(request: &PreparedRequest) -> String {
+    let asked = json!({
+        "operation": request.req_json["operation"],
+        "protocolVersion": request.req_json["protocolVersion"],
+    });
+    let canonical = serde_jcs::to_vec(&asked).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&canonical))
+}
+
+// Only replies with data are cached, not REQNACKs and REJECTs. A reply without
+// data, of an object not written yet, is not cached either, as the object may
+// be written any time, also one that cannot change once written.
+fn is_cacheable(reply: &str) -> bool {
+    serde_json::from_str::<Value>(reply)
+        .map(|reply| reply["op"] == "REPLY" && !reply["result"]["data"].is_null())
+        .unwrap_or(false)
+}
+
+impl<P: LedgerRequester> LedgerRequester for CachingPool<P> {
+    fn request_builder(&self) -> RequestBuilder {
+        self.pool.request_builder()
+    }
+
+    fn submit<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+        Box::pin(async move { Ok(self.submit_timed(request).await?.0) })
+    }
+
+    fn node_aliases(&self) -> Vec<String> {
+        self.pool.node_aliases()
+    }
+
+    fn pool_transactions(&self) -> Vec<String> {
+        self.pool.pool_transactions()
+    }
+
+    /// Cached replies are returned without reply times
+    fn submit_timed<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<(String, HashMap<String, f32>)>> {
+        Box::pin(async move {
+            let (key, ttl) = match self.cache_key(request) {
+                Some(key) => key,
+                None => return self.pool.submit_timed(request).await,
+            };
+            if let Some(reply) = self.backend.get(&key) {
+                debug!("Serving {} request from the reply cache", request.txn_type);
+                return Ok((reply, HashMap::new()));
+            }
+            let (reply, timing) = self.pool.submit_timed(request).await?;
+            if is_cacheable(&reply) {
+                self.backend.set(&key, &reply, ttl);
+            }
+            Ok((reply, timing))
+        })
+    }
+
+    fn rev_reg_def_ids<'a>(
+        &'a self,
+        cred_def_id: &'a CredentialDefinitionId,
+    ) -> LocalBoxFuture<'a, DidIndyResult<Vec<RevocationRegistryId>>> {
+        self.pool.rev_reg_def_ids(cred_def_id)
+    }
+}
+
+impl<P> fmt::Debug for CachingPool<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachingPool")
+            .field("ttls", &self.ttls)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::Resolver;
+    use crate::tests::{nym_reply, MockLedger};
+    use futures_executor::block_on;
+    use indy_vdr::ledger::identifiers::SchemaId;
+    use indy_vdr::pool::ProtocolVersion;
+    use indy_vdr::utils::did::DidValue;
+
+    fn nym_request(dest: &str) -> PreparedRequest {
+        RequestBuilder::new(ProtocolVersion::Node1_4)
+            .build_get_nym_request(None, &DidValue::new(dest, None))
+            .unwrap()
+    }
+
+    fn nym() -> String {
+        nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(json!({})),
+        )
+    }
+
+    #[test]
+    fn fingerprint_ignores_request_id() {
+        let first = nym_request("Dk1fRRTtNazyMuK2cr64wp");
+        let mut second = nym_request("Dk1fRRTtNazyMuK2cr64wp");
+        second.req_json["reqId"] = json!(1);
+        second.req_json["identifier"] = json!("V4SGRU86Z58d6TV7PBUe6f");
+
+        assert_eq!(fingerprint(&first), fingerprint(&second));
+        assert_eq!(64, fingerprint(&first).len());
+        assert_ne!(
+            fingerprint(&first),
+            fingerprint(&nym_request("V4SGRU86Z58d6TV7PBUe6f"))
+        );
+    }
+
+    #[test]
+    fn read_replies_cached() {
+        let pool = CachingPool::in_memory(
+            MockLedger::new().answer(constants::GET_NYM, nym()),
+            Duration::from_secs(60),
+        );
+
+        let first = block_on(pool.submit(&nym_request("Dk1fRRTtNazyMuK2cr64wp"))).unwrap();
+        let second = block_on(pool.submit(&nym_request("Dk1fRRTtNazyMuK2cr64wp"))).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(1, pool.pool().requests().len());
+    }
+
+    #[test]
+    fn replies_without_data_not_cached() {
+        let not_found = json!({
+            "op": "REPLY",
+            "result": {"type": constants::GET_SCHEMA, "reqId": 1, "data": null}
+        });
+        let pool = CachingPool::in_memory(
+            MockLedger::new().answer(constants::GET_SCHEMA, not_found.to_string()),
+            Duration::from_secs(60),
+        );
+        let id = SchemaId::new(
+            &DidValue::new("V4SGRU86Z58d6TV7PBUe6f", None),
+            "npdb",
+            "4.3.4",
+        );
+        let request = RequestBuilder::new(ProtocolVersion::Node1_4)
+            .build_get_schema_request(None, &id)
+            .unwrap();
+
+        block_on(pool.submit(&request)).unwrap();
+        block_on(pool.submit(&request)).unwrap();
+
+        assert_eq!(2, pool.pool().requests().len());
+    }
+
+    #[test]
+    fn write_and_unknown_requests_bypass_cache() {
+        let pool = CachingPool::in_memory(
+            MockLedger::new()
+                .answer(constants::NYM, nym())
+                .answer("9999", nym()),
+            Duration::from_secs(60),
+        );
+        let write = json!({
+            "reqId": 1,
+            "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+            "operation": {"type": constants::NYM, "dest": "Dk1fRRTtNazyMuK2cr64wp"},
+            "protocolVersion": 2
+        });
+        let mut unknown = write.clone();
+        unknown["operation"]["type"] = json!("9999");
+
+        for request in [write, unknown] {
+            let request = PreparedRequest::from_request_json(request.to_string()).unwrap();
+            block_on(pool.submit(&request)).unwrap();
+            block_on(pool.submit(&request)).unwrap();
+        }
+
+        assert_eq!(4, pool.pool().requests().len());
+    }
+
+    #[test]
+    fn resolver_over_caching_pool() {
+        let pool = CachingPool::in_memory(
+            MockLedger::new().answer(constants::GET_NYM, nym()),
+            Duration::from_secs(60),
+        )
+        .without_caching(constants::GET_ATTR);
+        let resolver = Resolver::new(pool);
+
+        for _ in 0..2 {
+            assert!(resolver
+                .resolve("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .is_ok());
+        }
+
+        assert_eq!(1, resolver.pool().pool().requests().len());
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use url::Url;
+
+use super::audit::AuditLog;
+use super::cache::ResultCache;
+use super::did_document::{VerificationMethodType, VerificationRelationships};
+use super::error::{DidIndyError, DidIndyResult};
+use super::signing::SigningConfig;
+use super::timestamp::TimeFormat;
+
+/// `@context` of ledger objects dereferenced with `Representation::JsonLd`
+pub const DEFAULT_CONTENT_CONTEXT: &str = "https://w3id.org/did-indy/anoncreds/v0";
+
+/// Defines which source of services is used when a NYM carries `diddocContent`
+/// and a legacy `endpoint` ATTRIB exists for the same DID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndpointPrecedence {
+    /// `diddocContent` wins: the legacy endpoint is only fetched for NYMs without
+    /// `diddocContent`.
+    DiddocContent,
+    /// The legacy endpoint is always fetched and its services are merged with the
+    /// ones from `diddocContent`. Services sharing an `id` are de-duplicated, the
+    /// entry from `diddocContent` is kept.
+    Merge,
+}
+
+impl Default for EndpointPrecedence {
+    fn default() -> Self {
+        EndpointPrecedence::DiddocContent
+    }
+}
+
+/// Which of two services sharing an `id` is kept when the services of
+/// `diddocContent` are merged with the ones derived from the legacy `endpoint`
+/// ATTRIB
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceConflictPolicy {
+    /// The service from `diddocContent` replaces the one from the ATTRIB
+    PreferDiddoc,
+    /// The service from the ATTRIB is kept, the one from `diddocContent` dropped
+    PreferAttrib,
+    /// The DID document is rejected with `ServiceIdConflict`
+    ErrorOnConflict,
+}
+
+impl Default for ServiceConflictPolicy {
+    fn default() -> Self {
+        ServiceConflictPolicy::PreferDiddoc
+    }
+}
+
+/// Schemes the service endpoints of the DID document may use. Services whose
+/// endpoint is not a URL of one of them are left out with a warning, or fail
+/// the resolution with `InvalidServiceEndpoint` in strict mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EndpointValidation {
+    /// Allowed schemes, compared case-insensitively
+    pub schemes: Vec<String>,
+    /// Validate the services of `diddocContent` too, not only the ones derived
+    /// from the legacy `endpoint` ATTRIB
+    pub diddoc_content: bool,
+}
+
+impl Default for EndpointValidation {
+    fn default() -> Self {
+        EndpointValidation {
+            schemes: ["http", "https", "ws", "wss", "didcomm"]
+                .iter()
+                .map(|scheme| scheme.to_string())
+                .collect(),
+            diddoc_content: false,
+        }
+    }
+}
+
+/// Handling of REV_REG_ENTRY requests for a time before the first entry of the
+/// revocation registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevRegEntryFallback {
+    /// The ledger reply is returned as it is, resulting in an empty data error
+    Disabled,
+    /// The first entry is looked up and its time reported in a `NotFoundBefore`
+    /// error
+    NotFoundBefore,
+    /// The first entry is looked up and returned instead, flagged with
+    /// `earliestEntry` in the content metadata
+    EarliestEntry,
+}
+
+impl Default for RevRegEntryFallback {
+    fn default() -> Self {
+        RevRegEntryFallback::Disabled
+    }
+}
+
+/// Handling of DID URLs carrying both `versionId` and `versionTime`, which may
+/// refer to different versions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionConflict {
+    /// The DID URL is rejected with `ConflictingQueryParameters`
+    Reject,
+    /// `versionTime` is ignored
+    PreferVersionId,
+    /// `versionId` is ignored
+    PreferVersionTime,
+}
+
+impl Default for VersionConflict {
+    fn default() -> Self {
+        VersionConflict::Reject
+    }
+}
+
+/// Timeouts bounding the ledger round-trips of a resolution. A NYM resolution can
+/// take two round-trips (NYM and legacy endpoint ATTRIB).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Upper bound for a single ledger round-trip
+    pub request: Option<Duration>,
+    /// Upper bound for all ledger round-trips of one resolution
+    pub total: Option<Duration>,
+}
+
+/// Nodes of a namespace that must corroborate ledger replies, e.g. the nodes of
+/// designated stewards. A reply is accepted if at least `min_count` of the
+/// required nodes signed it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrustPolicy {
+    /// Aliases of the required nodes
+    pub required_nodes: Vec<String>,
+    pub min_count: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ResolverConfig {
+    pub endpoint_precedence: EndpointPrecedence,
+    /// Never look up the legacy endpoint ATTRIB, also for NYMs without
+    /// diddocContent. Documents of such NYMs have no services, which halves the
+    /// ledger requests of resolutions that only need the verkey. Skipped lookups
+    /// are marked with `legacyEndpointSkipped` in the `methodMetadata`.
+    pub skip_legacy_endpoint: bool,
+    pub service_conflict_policy: ServiceConflictPolicy,
+    pub timeouts: Timeouts,
+    /// Reject DID documents violating DID Core structural rules instead of
+    /// returning them as they are
+    pub strict: bool,
+    /// Return whatever could be resolved of a DID document instead of failing on
+    /// partial errors, e.g. for diagnostic UIs. Failing legacy endpoint lookups,
+    /// verkeys that cannot be encoded, service conflicts, invalid service
+    /// endpoints and DID Core violations are collected as `warnings` in the
+    /// `didResolutionMetadata`.
+    pub best_effort: bool,
+    pub verification_relationships: VerificationRelationships,
+    /// Type of the verification method derived from the NYM verkey, the key
+    /// encoding follows the type
+    pub verification_method_type: VerificationMethodType,
+    /// Normalize service endpoints to absolute URLs, relative ones are resolved
+    /// against this base. Endpoints are returned as they are on the ledger if
+    /// not set.
+    pub endpoint_base: Option<Url>,
+    /// Schemes allowed for service endpoints, by default the ones of the legacy
+    /// endpoint ATTRIB are validated
+    pub endpoint_validation: EndpointValidation,
+    /// Sign resolution and dereferencing results, off if not set
+    pub signing: Option<SigningConfig>,
+    /// Cache for results, resolutions always query the ledger if not set
+    pub cache: Option<ResultCache>,
+    /// Fetch the cred def referenced by a REV_REG_DEF together with it and add
+    /// it to the content metadata
+    pub attach_cred_def: bool,
+    /// Upper bound for concurrent ledger requests of the resolver, unlimited if
+    /// not set
+    pub max_concurrent_requests: Option<usize>,
+    pub rev_reg_entry_fallback: RevRegEntryFallback,
+    pub version_conflict: VersionConflict,
+    /// Put the ledger reply and object type directly into the DID document and
+    /// content metadata as before they moved to `methodMetadata`, for clients
+    /// that are not migrated yet
+    pub legacy_metadata: bool,
+    /// Representation of the times in the DID document and content metadata
+    pub metadata_time_format: TimeFormat,
+    /// Add the NYM data together with the state proof and multi-signature of the
+    /// nodes to the metadata, for clients verifying the ledger signature
+    pub include_signed_txn: bool,
+    /// Record every resolution and dereferencing, nothing is recorded if not set
+    pub audit: Option<AuditLog>,
+    /// Add the self-certifying did:indy form of a resolved NYM whose DID is the
+    /// legacy form derived from its verkey as `canonicalId` to the DID document
+    /// metadata, for clients migrating legacy references
+    pub canonical_id: bool,
+    /// Keep results in the cache for this long after they expire, to serve them
+    /// marked as stale when the ledger cannot be reached. Requires a cache.
+    pub serve_stale_on_error: Option<Duration>,
+    /// Trust policy per namespace, replies failing it are rejected with
+    /// `PolicyNotSatisfied`
+    pub trust_policies: HashMap<String, TrustPolicy>,
+    /// `@context` of ledger objects dereferenced as JSON-LD,
+    /// `DEFAULT_CONTENT_CONTEXT` if not set
+    pub content_context: Option<String>,
+}
+
+/// Use of the result cache for a single resolution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Cached results are served, new results are cached
+    ReadThrough,
+    /// The ledger is queried and the result replaces the cached one
+    Refresh,
+    /// The cache is neither read nor written
+    Bypass,
+}
+
+impl Default for CacheMode {
+    fn default() -> Self {
+        CacheMode::ReadThrough
+    }
+}
+
+/// Representation of a resolved DID document or dereferenced ledger object
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Representation {
+    /// `application/did+json`, ledger objects as plain JSON
+    Json,
+    /// `application/did+ld+json`, the DID Core context is added to the document.
+    /// Ledger objects are wrapped in a JSON-LD resource identified by their DID
+    /// URL.
+    JsonLd,
+}
+
+impl Representation {
+    /// Representation of the media type, e.g. of an Accept header. Fails with
+    /// `RepresentationNotSupported` for other media types than the DID
+    /// document ones.
+    pub fn from_media_type(media_type: &str) -> DidIndyResult<Representation> {
+        match media_type {
+            "application/did+json" => Ok(Representation::Json),
+            "application/did+ld+json" => Ok(Representation::JsonLd),
+            _ => Err(DidIndyError::RepresentationNotSupported(
+                media_type.to_string(),
+            )),
+        }
+    }
+
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            Representation::Json => "application/did+json",
+            Representation::JsonLd => "application/did+ld+json",
+        }
+    }
+}
+
+impl Default for Representation {
+    fn default() -> Self {
+        Representation::Json
+    }
+}
+
+/// Overrides of the `ResolverConfig` for a single resolution. Options that are
+/// not set fall back to the config.
+#[derive(Clone, Debug, Default)]
+pub struct ResolveOptions {
+    pub(crate) timeouts: Option<Timeouts>,
+    pub(crate) cache: CacheMode,
+    pub(crate) representation: Representation,
+    pub(crate) strict: Option<bool>,
+    pub(crate) raw_reply: bool,
+    pub(crate) echo_input: bool,
+    pub(crate) version_conflict: Option<VersionConflict>,
+    pub(crate) skip_legacy_endpoint: Option<bool>,
+    pub(crate) revocation_status_list: bool,
+}
+
+impl ResolveOptions {
+    pub fn new() -> Self {
+        ResolveOptions::default()
+    }
+
+    // Part of the result cache key for the options changing the result, so that
+    // results of different options are cached separately. Every field is named,
+    // a new option has to be placed here or among the ones ignored.
+    pub(crate) fn cache_variant(&self) -> String {
+        let ResolveOptions {
+            // Neither change a cached result, raw replies are never cached
+            timeouts: _,
+            cache: _,
+            raw_reply: _,
+            representation,
+            strict,
+            echo_input,
+            version_conflict,
+            skip_legacy_endpoint,
+            revocation_status_list,
+        } = self;
+        let representation = match representation {
+            Representation::Json => "",
+            Representation::JsonLd => "+ld",
+        };
+        let echo = if *echo_input { "+echo" } else { "" };
+        // Which version parameter applies is part of the input
+        let version = match version_conflict {
+            None => "",
+            Some(VersionConflict::Reject) => "+versionReject",
+            Some(VersionConflict::PreferVersionId) => "+versionId",
+            Some(VersionConflict::PreferVersionTime) => "+versionTime",
+        };
+        let endpoint = match skip_legacy_endpoint {
+            None => "",
+            Some(true) => "+skipEndpoint",
+            Some(false) => "+endpoint",
+        };
+        let status_list = if *revocation_status_list {
+            "+statusList"
+        } else {
+            ""
+        };
+        let strict = match strict {
+            None => "",
+            Some(true) => "+strict",
+            Some(false) => "+lenient",
+        };
+        format!(
+            "{}{}{}{}{}{}",
+            representation, echo, version, endpoint, status_list, strict
+        )
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = Some(timeouts);
+        self
+    }
+
+    pub fn cache(mut self, cache: CacheMode) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn representation(mut self, representation: Representation) -> Self {
+        self.representation = representation;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+
+    /// Dereference to the ledger reply as it was received instead of the ledger
+    /// object, for debugging. The content metadata is marked with `rawReply` and
+    /// the object type `RAW`. Raw replies are not cached.
+    pub fn raw_reply(mut self, raw_reply: bool) -> Self {
+        self.raw_reply = raw_reply;
+        self
+    }
+
+    /// Echo the canonicalized input DID in the `did` of the resolution metadata
+    pub fn echo_input(mut self, echo_input: bool) -> Self {
+        self.echo_input = echo_input;
+        self
+    }
+
+    /// Which of `versionId` and `versionTime` applies if the DID URL has both
+    pub fn version_conflict(mut self, version_conflict: VersionConflict) -> Self {
+        self.version_conflict = Some(version_conflict);
+        self
+    }
+
+    /// Resolve NYMs without looking up the legacy endpoint ATTRIB
+    pub fn skip_legacy_endpoint(mut self, skip_legacy_endpoint: bool) -> Self {
+        self.skip_legacy_endpoint = Some(skip_legacy_endpoint);
+        self
+    }
+
+    /// Attach the AnonCreds revocation status list of the registry at the
+    /// requested time to the content metadata of REV_REG_ENTRY and
+    /// REV_REG_DELTA DID URLs, built from its definition and its delta since its
+    /// creation
+    pub fn revocation_status_list(mut self, revocation_status_list: bool) -> Self {
+        self.revocation_status_list = revocation_status_list;
+        self
+    }
+}
@@ -1,10 +1,14 @@
+use super::config::VersionConflict;
 use super::error::{DidIndyError, DidIndyResult};
+use chrono::DateTime;
 use indy_vdr::utils::did::DidValue;
 use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
 use url::Url;
 use urlencoding::decode;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // Patterns to build regular expressions for ledger objects
 static DID_INDY_PREFIX: &str = "did:indy";
@@ -15,31 +19,64 @@ static INDY_UNQUALIFIED_DID_PATTERN: &str =
 static OBJECT_FAMILY_PATTERN: &str = "([a-z]*)";
 static OBJECT_FAMILY_VERSION_PATTERN: &str = "([a-zA-Z0-9]*)";
 
-static ANONCREDSV0_OBJECTS_PATTERN: &str =
-    "(SCHEMA|CLAIM_DEF|REV_REG_DEF|REV_REG_ENTRY|REV_REG_DELTA)";
+// Misspellings of object types seen in DID URLs, normalized like in
+// `LedgerObjectType::suggest`
+static OBJECT_TYPE_MISSPELLINGS: [(&str, LedgerObjectType); 7] = [
+    ("CREDDEF", LedgerObjectType::ClaimDef),
+    ("CREDENTIALDEF", LedgerObjectType::ClaimDef),
+    ("CREDENTIALDEFINITION", LedgerObjectType::ClaimDef),
+    ("CLAIMDEFINITION", LedgerObjectType::ClaimDef),
+    ("REVOCREGDEF", LedgerObjectType::RevRegDef),
+    ("REVOCREGENTRY", LedgerObjectType::RevRegEntry),
+    ("REVOCREGDELTA", LedgerObjectType::RevRegDelta),
+];
+
+/// Versions of the anoncreds object family DID URLs can refer to
+pub static ANONCREDS_VERSIONS: [&str; 1] = ["v0"];
 
 static CLIENT_DEFINED_NAME_PATTERN: &str = "([\\w -]*)";
 static SEQ_NO_PATTERN: &str = "(\\d*)";
 static VERSION_PATTERN: &str = "((\\d*\\.){1,2}\\d*)";
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QueryParameter {
     VersionId,
+    /// Alias of `VersionId`, settled into it by `DidUrl::settle_version`
+    SeqNo,
     VersionTime,
     From,
     To,
+    TransformKeys,
+    /// SHA-256 of the genesis transactions of the pool to resolve with, for
+    /// namespaces served by several pools, see `ResolverRegistry::resolver_for`
+    GenesisHash,
 }
 
 impl QueryParameter {
     pub fn from_str(input: &str) -> DidIndyResult<QueryParameter> {
         match input {
             "versionId" => Ok(QueryParameter::VersionId),
+            "seqNo" => Ok(QueryParameter::SeqNo),
             "versionTime" => Ok(QueryParameter::VersionTime),
             "from" => Ok(QueryParameter::From),
             "to" => Ok(QueryParameter::To),
+            "transformKeys" => Ok(QueryParameter::TransformKeys),
+            "genesisHash" => Ok(QueryParameter::GenesisHash),
             _ => Err(DidIndyError::QueryParameterNotSupported),
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryParameter::VersionId => "versionId",
+            QueryParameter::SeqNo => "seqNo",
+            QueryParameter::VersionTime => "versionTime",
+            QueryParameter::From => "from",
+            QueryParameter::To => "to",
+            QueryParameter::TransformKeys => "transformKeys",
+            QueryParameter::GenesisHash => "genesisHash",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -56,6 +93,9 @@ impl ObjectFamily {
     }
 }
 
+/// Version of the anoncreds object family. A new version is added as a variant
+/// listed in `ANONCREDS_VERSIONS` together with the parser of its objects in
+/// `parse_object`.
 #[derive(Debug, PartialEq)]
 pub enum Anoncreds {
     AnoncredsV0,
@@ -65,12 +105,52 @@ impl Anoncreds {
     fn from_str(input: &str) -> DidIndyResult<Anoncreds> {
         match input {
             "v0" => Ok(Anoncreds::AnoncredsV0),
-            _ => Err(DidIndyError::VersionNotSupported),
+            _ => Err(DidIndyError::UnsupportedAnoncredsVersion {
+                version: input.to_string(),
+                supported: ANONCREDS_VERSIONS.iter().map(|v| v.to_string()).collect(),
+            }),
+        }
+    }
+
+    // Parses the object type and the object specific part of the path
+    fn parse_object(&self, input: &str) -> DidIndyResult<LedgerObject> {
+        match self {
+            Anoncreds::AnoncredsV0 => parse_anoncreds_v0_object(input),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+fn parse_anoncreds_v0_object(input: &str) -> DidIndyResult<LedgerObject> {
+    let (ledger_object_type_str, ledger_object_type_specific_str) = match input.find('/') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => (input, ""),
+    };
+    let object_type = LedgerObjectType::from_name(ledger_object_type_str)?;
+    if ledger_object_type_specific_str.is_empty() {
+        return Err(DidIndyError::InvalidDidUrl);
+    }
+
+    match object_type {
+        LedgerObjectType::Schema => Ok(LedgerObject::Schema(Schema::from_str(
+            ledger_object_type_specific_str,
+        )?)),
+        LedgerObjectType::ClaimDef => Ok(LedgerObject::ClaimDef(ClaimDef::from_str(
+            ledger_object_type_specific_str,
+        )?)),
+        LedgerObjectType::RevRegDef => Ok(LedgerObject::RevRegDef(RevReg::from_str(
+            ledger_object_type_specific_str,
+        )?)),
+        LedgerObjectType::RevRegEntry => Ok(LedgerObject::RevRegEntry(RevReg::from_str(
+            ledger_object_type_specific_str,
+        )?)),
+        LedgerObjectType::RevRegDelta => Ok(LedgerObject::RevRegDelta(RevReg::from_str(
+            ledger_object_type_specific_str,
+        )?)),
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct Schema {
     pub name: String,
     pub version: String,
@@ -104,7 +184,8 @@ impl Schema {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct ClaimDef {
     pub schema_seq_no: u32,
     pub name: String,
@@ -130,9 +211,8 @@ impl ClaimDef {
                 cap.get(1)
                     .ok_or(DidIndyError::InvalidDidUrl)?
                     .as_str()
-                    .to_string()
                     .parse::<u32>()
-                    .unwrap(),
+                    .map_err(|_| DidIndyError::InvalidDidUrl)?,
                 cap.get(2)
                     .ok_or(DidIndyError::InvalidDidUrl)?
                     .as_str()
@@ -143,7 +223,8 @@ impl ClaimDef {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct RevReg {
     pub schema_seq_no: u32,
     pub claim_def_name: String,
@@ -172,9 +253,8 @@ impl RevReg {
                 cap.get(1)
                     .ok_or(DidIndyError::InvalidDidUrl)?
                     .as_str()
-                    .to_string()
                     .parse::<u32>()
-                    .unwrap(),
+                    .map_err(|_| DidIndyError::InvalidDidUrl)?,
                 cap.get(2)
                     .ok_or(DidIndyError::InvalidDidUrl)?
                     .as_str()
@@ -189,7 +269,109 @@ impl RevReg {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Type of a ledger object, the path segment following the object family
+/// version. Each type is the type of a `LedgerObject` variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerObjectType {
+    Schema,
+    ClaimDef,
+    RevRegDef,
+    RevRegEntry,
+    RevRegDelta,
+}
+
+impl LedgerObjectType {
+    /// All object types, DID URLs are parsed against this list
+    pub const ALL: [LedgerObjectType; 5] = [
+        LedgerObjectType::Schema,
+        LedgerObjectType::ClaimDef,
+        LedgerObjectType::RevRegDef,
+        LedgerObjectType::RevRegEntry,
+        LedgerObjectType::RevRegDelta,
+    ];
+
+    /// Name of the type in DID URLs
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerObjectType::Schema => "SCHEMA",
+            LedgerObjectType::ClaimDef => "CLAIM_DEF",
+            LedgerObjectType::RevRegDef => "REV_REG_DEF",
+            LedgerObjectType::RevRegEntry => "REV_REG_ENTRY",
+            LedgerObjectType::RevRegDelta => "REV_REG_DELTA",
+        }
+    }
+
+    /// Names of all supported object types
+    pub fn names() -> Vec<String> {
+        LedgerObjectType::ALL
+            .iter()
+            .map(|object_type| object_type.name().to_string())
+            .collect()
+    }
+
+    /// Type of the name. Fails with `UnsupportedLedgerObject` listing the
+    /// supported types, with a suggestion for near misses of a type name.
+    pub fn from_name(name: &str) -> DidIndyResult<LedgerObjectType> {
+        LedgerObjectType::ALL
+            .iter()
+            .find(|object_type| object_type.name() == name)
+            .copied()
+            .ok_or_else(|| DidIndyError::UnsupportedLedgerObject {
+                segment: name.to_string(),
+                supported: LedgerObjectType::names(),
+                suggestion: LedgerObjectType::suggest(name).map(|t| t.name().to_string()),
+            })
+    }
+
+    // Type the name is likely meant to be: one differing only in case or
+    // separators, a common misspelling, or one at most two edits away
+    fn suggest(name: &str) -> Option<LedgerObjectType> {
+        let normalize = |name: &str| -> String {
+            name.chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .map(|c| c.to_ascii_uppercase())
+                .collect()
+        };
+        let normalized = normalize(name);
+        if normalized.is_empty() {
+            return None;
+        }
+        let by_distance = |max: usize| {
+            LedgerObjectType::ALL
+                .iter()
+                .map(|t| (edit_distance(&normalize(t.name()), &normalized), *t))
+                .filter(|(distance, _)| *distance <= max)
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, t)| t)
+        };
+        by_distance(0)
+            .or_else(|| {
+                OBJECT_TYPE_MISSPELLINGS
+                    .iter()
+                    .find(|(misspelling, _)| *misspelling == normalized)
+                    .map(|(_, t)| *t)
+            })
+            .or_else(|| by_distance(2))
+    }
+}
+
+// Levenshtein distance of two ASCII strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "objectType", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LedgerObject {
     Schema(Schema),
     ClaimDef(ClaimDef),
@@ -199,58 +381,32 @@ pub enum LedgerObject {
 }
 
 impl LedgerObject {
+    pub fn object_type(&self) -> LedgerObjectType {
+        match self {
+            LedgerObject::Schema(_) => LedgerObjectType::Schema,
+            LedgerObject::ClaimDef(_) => LedgerObjectType::ClaimDef,
+            LedgerObject::RevRegDef(_) => LedgerObjectType::RevRegDef,
+            LedgerObject::RevRegEntry(_) => LedgerObjectType::RevRegEntry,
+            LedgerObject::RevRegDelta(_) => LedgerObjectType::RevRegDelta,
+        }
+    }
+
     pub fn from_str(input: &str) -> DidIndyResult<LedgerObject> {
         let re = Regex::new(
             format!(
-                r"{}/{}/{}/(.+)?",
-                OBJECT_FAMILY_PATTERN, OBJECT_FAMILY_VERSION_PATTERN, ANONCREDSV0_OBJECTS_PATTERN
+                r"^/?{}/{}/(.*)",
+                OBJECT_FAMILY_PATTERN, OBJECT_FAMILY_VERSION_PATTERN
             )
             .as_str(),
         )
         .unwrap();
+        let cap = re.captures(input).ok_or(DidIndyError::InvalidDidUrl)?;
+        let object_family_str = cap.get(1).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
+        let version = cap.get(2).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
+        let object = cap.get(3).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
 
-        let captures = re.captures(input);
-
-        if let Some(cap) = captures {
-            let object_family_str = cap.get(1).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
-            let version = cap.get(2).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
-
-            let object_family = ObjectFamily::from_str(object_family_str)?;
-
-            match object_family {
-                ObjectFamily::Anoncreds => {
-                    let object_family_versioned = Anoncreds::from_str(version)?;
-                    match object_family_versioned {
-                        Anoncreds::AnoncredsV0 => {
-                            let ledger_object_type_str =
-                                cap.get(3).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
-                            let ledger_object_type_specific_str =
-                                cap.get(4).ok_or(DidIndyError::InvalidDidUrl)?.as_str();
-                            match ledger_object_type_str {
-                                "SCHEMA" => Ok(LedgerObject::Schema(Schema::from_str(
-                                    ledger_object_type_specific_str,
-                                )?)),
-                                "CLAIM_DEF" => Ok(LedgerObject::ClaimDef(ClaimDef::from_str(
-                                    ledger_object_type_specific_str,
-                                )?)),
-                                "REV_REG_DEF" => Ok(LedgerObject::RevRegDef(RevReg::from_str(
-                                    ledger_object_type_specific_str,
-                                )?)),
-                                "REV_REG_ENTRY" => Ok(LedgerObject::RevRegEntry(RevReg::from_str(
-                                    ledger_object_type_specific_str,
-                                )?)),
-                                "REV_REG_DELTA" => Ok(LedgerObject::RevRegDelta(RevReg::from_str(
-                                    ledger_object_type_specific_str,
-                                )?)),
-
-                                _ => Err(DidIndyError::InvalidDidUrl),
-                            }
-                        }
-                    }
-                }
-            }
-        } else {
-            Err(DidIndyError::InvalidDidUrl)
+        match ObjectFamily::from_str(object_family_str)? {
+            ObjectFamily::Anoncreds => Anoncreds::from_str(version)?.parse_object(object),
         }
     }
 }
@@ -261,20 +417,46 @@ pub struct DidUrl {
     pub id: DidValue,
     pub path: Option<String>,
     pub query: HashMap<QueryParameter, String>,
+    pub fragment: Option<String>,
     pub url: String,
 }
 
+// A path following the query or a query following the fragment would otherwise be
+// taken as part of the query value or the fragment
+fn check_component_order(input: &str) -> DidIndyResult<()> {
+    let (before_fragment, fragment) = match input.find('#') {
+        Some(i) => (&input[..i], Some(&input[i + 1..])),
+        None => (input, None),
+    };
+    if let Some(i) = before_fragment.find('?') {
+        if before_fragment[i + 1..].contains('/') {
+            return Err(DidIndyError::MisorderedDidUrl {
+                misplaced: "path",
+                preceding: "query",
+            });
+        }
+    }
+    if fragment.map_or(false, |fragment| fragment.contains('?')) {
+        return Err(DidIndyError::MisorderedDidUrl {
+            misplaced: "query",
+            preceding: "fragment",
+        });
+    }
+    Ok(())
+}
+
 impl DidUrl {
     pub fn from_str(input: &str) -> DidIndyResult<DidUrl> {
         let did_regex = Regex::new(
             format!(
-                r"{}:{}:{}([^\?]+)?(?:\?(.+))?$",
+                r"{}:{}:{}([^\?#]+)?(?:\?([^#]+))?(?:#(.*))?$",
                 DID_INDY_PREFIX, NAMESPACE_PATTERN, INDY_UNQUALIFIED_DID_PATTERN
             )
             .as_str(),
         )
         .unwrap();
 
+        check_component_order(input.trim())?;
         let url = Url::parse(input).map_err(|_| DidIndyError::InvalidDidUrl)?;
         let mut query_pairs: HashMap<QueryParameter, String> = HashMap::new();
         let _query_pairs: HashMap<_, _> = url.query_pairs().into_owned().collect();
@@ -295,6 +477,11 @@ impl DidUrl {
                         .get(3)
                         .and_then(|p| Some(decode(p.as_str()).unwrap().to_string())),
                     query: query_pairs,
+                    fragment: url.fragment().map(|f| {
+                        decode(f)
+                            .map(|f| f.to_string())
+                            .unwrap_or_else(|_| f.to_string())
+                    }),
                     url: input.to_string(),
                 };
                 Ok(did)
@@ -302,6 +489,270 @@ impl DidUrl {
             None => Err(DidIndyError::InvalidDidUrl),
         }
     }
+
+    /// Settles a DID URL carrying both `versionId` and `versionTime`: the
+    /// parameter that is not preferred is dropped, or the DID URL is rejected
+    /// with `ConflictingQueryParameters`. The `seqNo` alias is taken as
+    /// `versionId` under the same rule. A `seqNo` and `versionId` naming
+    /// different versions are always rejected, no preference decides between
+    /// them.
+    pub fn settle_version(&mut self, conflict: VersionConflict) -> DidIndyResult<()> {
+        let (mut version_id, version_time) =
+            (QueryParameter::VersionId, QueryParameter::VersionTime);
+        if let Some(seq_no) = self.query.remove(&QueryParameter::SeqNo) {
+            match self.query.get(&version_id) {
+                Some(id) if *id != seq_no => {
+                    return Err(DidIndyError::ConflictingQueryParameters {
+                        first: version_id.name(),
+                        second: QueryParameter::SeqNo.name(),
+                    })
+                }
+                Some(_) => {}
+                None => {
+                    self.query.insert(version_id, seq_no);
+                    // Conflicts are reported for the parameter as it was given
+                    version_id = QueryParameter::SeqNo;
+                }
+            }
+        }
+        if !self.query.contains_key(&QueryParameter::VersionId)
+            || !self.query.contains_key(&version_time)
+        {
+            return Ok(());
+        }
+        match conflict {
+            VersionConflict::Reject => {
+                return Err(DidIndyError::ConflictingQueryParameters {
+                    first: version_id.name(),
+                    second: version_time.name(),
+                })
+            }
+            VersionConflict::PreferVersionId => self.query.remove(&version_time),
+            VersionConflict::PreferVersionTime => self.query.remove(&QueryParameter::VersionId),
+        };
+        Ok(())
+    }
+
+    /// The DID URL with percent-encoded path segments and the query parameters
+    /// ordered by name, so that equivalent DID URLs have the same canonical form
+    pub fn canonical(&self) -> String {
+        let mut canonical = format!("{}:{}:{}", DID_INDY_PREFIX, self.namespace, self.id);
+        if let Some(path) = &self.path {
+            for segment in path.split('/').skip(1) {
+                canonical.push('/');
+                canonical.push_str(&urlencoding::encode(segment));
+            }
+        }
+        let query: BTreeMap<&str, &String> = self
+            .query
+            .iter()
+            .map(|(parameter, value)| (parameter.name(), value))
+            .collect();
+        for (i, (name, value)) in query.iter().enumerate() {
+            canonical.push(if i == 0 { '?' } else { '&' });
+            canonical.push_str(name);
+            canonical.push('=');
+            canonical.push_str(&urlencoding::encode(value));
+        }
+        if let Some(fragment) = &self.fragment {
+            canonical.push('#');
+            canonical.push_str(fragment);
+        }
+        canonical
+    }
+
+    /// Namespace, identifier, path, ledger object and query parameters as JSON.
+    /// The ledger object is null if the path is not a supported ledger object.
+    pub fn components(&self) -> Value {
+        let ledger_object = self
+            .path
+            .as_deref()
+            .and_then(|path| LedgerObject::from_str(path).ok());
+        let query: BTreeMap<&str, &String> = self
+            .query
+            .iter()
+            .map(|(parameter, value)| (parameter.name(), value))
+            .collect();
+        json!({
+            "namespace": self.namespace,
+            "id": self.id.to_string(),
+            "path": self.path,
+            "ledgerObject": ledger_object,
+            "query": query,
+        })
+    }
+
+    /// Parses the DID URL like `from_str`, also checking that the path is a
+    /// supported ledger object and that the query values are valid. Errors name
+    /// the component of the DID URL they are located in.
+    pub fn parse_strict(input: &str) -> Result<(DidUrl, Option<LedgerObject>), ComponentError> {
+        let did_end = input
+            .find(|c| c == '/' || c == '?' || c == '#')
+            .unwrap_or(input.len());
+        DidUrl::from_str(&input[..did_end]).map_err(ComponentError::did)?;
+
+        let did_url = DidUrl::from_str(input).map_err(|err| match err {
+            DidIndyError::QueryParameterNotSupported | DidIndyError::MisorderedDidUrl { .. } => {
+                ComponentError::query(err)
+            }
+            _ => ComponentError::path(err),
+        })?;
+        let ledger_object = match &did_url.path {
+            Some(path) => Some(LedgerObject::from_str(path).map_err(ComponentError::path)?),
+            None => None,
+        };
+
+        for (parameter, value) in &did_url.query {
+            match parameter {
+                QueryParameter::VersionTime | QueryParameter::From | QueryParameter::To => {
+                    DateTime::parse_from_rfc3339(value)
+                        .map_err(|err| ComponentError::query(err.into()))?;
+                }
+                QueryParameter::VersionId | QueryParameter::SeqNo => {
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| ComponentError::query(DidIndyError::InvalidDidUrl))?;
+                }
+                QueryParameter::GenesisHash => {
+                    if value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(ComponentError::query(DidIndyError::InvalidDidUrl));
+                    }
+                }
+                QueryParameter::TransformKeys => {}
+            }
+        }
+        Ok((did_url, ledger_object))
+    }
+}
+
+/// Component of a DID URL
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DidUrlComponent {
+    /// The DID, i.e. method, namespace and identifier
+    Did,
+    Path,
+    Query,
+}
+
+/// Builds the DID URL of a ledger object from its parts, percent-encoding the
+/// client defined names, e.g. `DidUrlBuilder::new("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")?.schema("My Schema", "1.0").build()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DidUrlBuilder {
+    did: String,
+    path: Option<String>,
+    query: Vec<(QueryParameter, String)>,
+}
+
+impl DidUrlBuilder {
+    /// Starts from a did:indy DID without path or query
+    pub fn new(did: &str) -> DidIndyResult<Self> {
+        let did_url = DidUrl::from_str(did)?;
+        if did_url.path.is_some() || !did_url.query.is_empty() {
+            return Err(DidIndyError::InvalidDidUrl);
+        }
+        Ok(DidUrlBuilder {
+            did: did.trim().to_string(),
+            path: None,
+            query: Vec::new(),
+        })
+    }
+
+    pub fn schema(self, name: &str, version: &str) -> Self {
+        self.object("SCHEMA", &[name, version])
+    }
+
+    pub fn claim_def(self, schema_seq_no: u32, tag: &str) -> Self {
+        self.object("CLAIM_DEF", &[&schema_seq_no.to_string(), tag])
+    }
+
+    pub fn rev_reg_def(self, schema_seq_no: u32, claim_def_tag: &str, tag: &str) -> Self {
+        self.object(
+            "REV_REG_DEF",
+            &[&schema_seq_no.to_string(), claim_def_tag, tag],
+        )
+    }
+
+    pub fn rev_reg_entry(self, schema_seq_no: u32, claim_def_tag: &str, tag: &str) -> Self {
+        self.object(
+            "REV_REG_ENTRY",
+            &[&schema_seq_no.to_string(), claim_def_tag, tag],
+        )
+    }
+
+    pub fn rev_reg_delta(self, schema_seq_no: u32, claim_def_tag: &str, tag: &str) -> Self {
+        self.object(
+            "REV_REG_DELTA",
+            &[&schema_seq_no.to_string(), claim_def_tag, tag],
+        )
+    }
+
+    pub fn query(mut self, parameter: QueryParameter, value: &str) -> Self {
+        self.query.push((parameter, value.to_string()));
+        self
+    }
+
+    /// The DID URL, checked like `DidUrl::parse_strict`
+    pub fn build(&self) -> DidIndyResult<String> {
+        let did_url = self.url();
+        DidUrl::parse_strict(&did_url).map_err(|err| err.error)?;
+        Ok(did_url)
+    }
+
+    // The DID URL without checking it
+    pub(crate) fn url(&self) -> String {
+        let mut did_url = self.did.clone();
+        if let Some(path) = &self.path {
+            did_url.push_str(path);
+        }
+        for (i, (parameter, value)) in self.query.iter().enumerate() {
+            did_url.push(if i == 0 { '?' } else { '&' });
+            did_url.push_str(parameter.name());
+            did_url.push('=');
+            did_url.push_str(&urlencoding::encode(value));
+        }
+        did_url
+    }
+
+    fn object(mut self, object_type: &str, parts: &[&str]) -> Self {
+        let mut path = format!("/anoncreds/v0/{}", object_type);
+        for part in parts {
+            path.push('/');
+            path.push_str(&urlencoding::encode(part));
+        }
+        self.path = Some(path);
+        self
+    }
+}
+
+/// Error parsing a DID URL together with the component it is located in
+#[derive(Debug)]
+pub struct ComponentError {
+    pub component: DidUrlComponent,
+    pub error: DidIndyError,
+}
+
+impl ComponentError {
+    fn did(error: DidIndyError) -> Self {
+        ComponentError {
+            component: DidUrlComponent::Did,
+            error,
+        }
+    }
+
+    fn path(error: DidIndyError) -> Self {
+        ComponentError {
+            component: DidUrlComponent::Path,
+            error,
+        }
+    }
+
+    fn query(error: DidIndyError) -> Self {
+        ComponentError {
+            component: DidUrlComponent::Query,
+            error,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -310,10 +761,67 @@ mod tests {
 
     #[test]
     fn parse_unknown_ledger_object_fails() {
+        let err = LedgerObject::from_str("/anoncreds/v0/PANTS/npdb/4.3.4").unwrap_err();
+
         assert!(matches!(
-            LedgerObject::from_str("/anoncreds/v0/PANTS/npdb/4.3.4"),
-            Err(DidIndyError::InvalidDidUrl)
-        ))
+            &err,
+            DidIndyError::UnsupportedLedgerObject { segment, supported, suggestion: None }
+                if segment == "PANTS" && supported == &LedgerObjectType::names()
+        ));
+        assert_eq!(
+            "Ledger object PANTS not supported, supported objects: SCHEMA, CLAIM_DEF, REV_REG_DEF, REV_REG_ENTRY, REV_REG_DELTA",
+            err.to_string()
+        );
+        assert_eq!("invalidDidUrl", err.error_code());
+    }
+
+    #[test]
+    fn supported_object_names_cover_all_types() {
+        assert_eq!(
+            vec![
+                "SCHEMA",
+                "CLAIM_DEF",
+                "REV_REG_DEF",
+                "REV_REG_ENTRY",
+                "REV_REG_DELTA"
+            ],
+            LedgerObjectType::names()
+        );
+        for object_type in LedgerObjectType::ALL.iter() {
+            assert_eq!(
+                *object_type,
+                LedgerObjectType::from_name(object_type.name()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn near_miss_object_type_suggests_supported_one() {
+        let suggestion = |path: &str| match LedgerObject::from_str(path) {
+            Err(DidIndyError::UnsupportedLedgerObject { suggestion, .. }) => suggestion,
+            other => panic!("Unexpected result {:?}", other),
+        };
+        assert_eq!(
+            Some(String::from("CLAIM_DEF")),
+            suggestion("/anoncreds/v0/claim_def/104/default")
+        );
+        assert_eq!(
+            Some(String::from("CLAIM_DEF")),
+            suggestion("/anoncreds/v0/CREDDEF/104/default")
+        );
+        assert_eq!(
+            Some(String::from("REV_REG_ENTRY")),
+            suggestion("/anoncreds/v0/REV_REG_ENTYR/104/default/tag")
+        );
+        assert_eq!(
+            Some(String::from("SCHEMA")),
+            suggestion("/anoncreds/v0/SCHEME/npdb/4.3.4")
+        );
+
+        let err = LedgerObject::from_str("/anoncreds/v0/CRED_DEF/104/default").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Ledger object CRED_DEF not supported (did you mean CLAIM_DEF?), supported objects: SCHEMA"));
     }
 
     #[test]
@@ -328,10 +836,27 @@ mod tests {
     fn parse_unknown_anoncreds_version_fails() {
         assert!(matches!(
             LedgerObject::from_str("/anoncreds/v5/SCHEMA/npdb/4.3.4"),
-            Err(DidIndyError::VersionNotSupported)
+            Err(DidIndyError::UnsupportedAnoncredsVersion { version, .. }) if version == "v5"
         ))
     }
 
+    #[test]
+    fn parse_anoncreds_v1_lists_supported_versions() {
+        let err =
+            LedgerObject::from_str("/anoncreds/v1/CREDENTIAL_DEFINITION/104/default").unwrap_err();
+
+        assert!(matches!(
+            &err,
+            DidIndyError::UnsupportedAnoncredsVersion { version, supported }
+                if version == "v1" && supported == &vec![String::from("v0")]
+        ));
+        assert_eq!(
+            "Anoncreds version v1 not supported, supported versions: v0",
+            err.to_string()
+        );
+        assert_eq!("invalidDidUrl", err.error_code());
+    }
+
     #[test]
     fn parse_to_schema() {
         assert_eq!(
@@ -423,6 +948,138 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parse_to_claim_def_with_empty_seq_no_fails() {
+        assert!(matches!(
+            LedgerObject::from_str("/anoncreds/v0/CLAIM_DEF//npdb"),
+            Err(DidIndyError::InvalidDidUrl)
+        ))
+    }
+
+    #[test]
+    fn build_did_urls() {
+        let builder = DidUrlBuilder::new("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp").unwrap();
+
+        let schema = builder.clone().schema("My Schema", "1.0").build().unwrap();
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/My%20Schema/1.0",
+            schema
+        );
+        assert_eq!(
+            Some(String::from("/anoncreds/v0/SCHEMA/My Schema/1.0")),
+            DidUrl::from_str(&schema).unwrap().path
+        );
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14%3A33%3A42Z",
+            builder
+                .clone()
+                .rev_reg_entry(104, "revocable", "a4e25e54")
+                .query(QueryParameter::VersionTime, "2021-11-11T14:33:42Z")
+                .build()
+                .unwrap()
+        );
+        assert!(builder.schema("My Schema", "latest").build().is_err());
+        assert!(DidUrlBuilder::new("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds").is_err());
+    }
+
+    #[test]
+    fn parse_did_url_with_query_before_path_fails() {
+        let err = DidUrl::from_str(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=1/anoncreds/v0/SCHEMA/npdb/4.3.4",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            DidIndyError::MisorderedDidUrl {
+                misplaced: "path",
+                preceding: "query"
+            }
+        ));
+        assert_eq!("invalidDidUrl", err.error_code());
+
+        let err = DidUrl::from_str(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?x=y/anoncreds/v0/SCHEMA/npdb/4.3.4",
+        )
+        .unwrap_err();
+        assert!(matches!(err, DidIndyError::MisorderedDidUrl { .. }));
+    }
+
+    #[test]
+    fn parse_did_url_with_fragment_before_query_fails() {
+        let err = DidUrl::from_str("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#verkey?versionId=1")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DidIndyError::MisorderedDidUrl {
+                misplaced: "query",
+                preceding: "fragment"
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_did_url_with_ordered_components() {
+        let did_url = DidUrl::from_str(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4?versionId=1#name",
+        )
+        .unwrap();
+        assert_eq!(
+            Some(String::from("/anoncreds/v0/SCHEMA/npdb/4.3.4")),
+            did_url.path
+        );
+        assert_eq!(
+            Some(&String::from("1")),
+            did_url.query.get(&QueryParameter::VersionId)
+        );
+        assert_eq!(Some(String::from("name")), did_url.fragment);
+    }
+
+    #[test]
+    fn parse_strict_valid_did_url() {
+        let (did_url, ledger_object) = DidUrl::parse_strict(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4?versionTime=2021-11-11T14:33:42Z",
+        )
+        .unwrap();
+        assert_eq!("idunion", did_url.namespace);
+        assert_eq!(
+            Some(LedgerObject::Schema(Schema::new(
+                String::from("npdb"),
+                String::from("4.3.4")
+            ))),
+            ledger_object
+        );
+    }
+
+    #[test]
+    fn parse_strict_names_failing_component() {
+        let component = |input| DidUrl::parse_strict(input).unwrap_err().component;
+
+        assert_eq!(
+            DidUrlComponent::Did,
+            component("did:indy:idunion:Dk1f/anoncreds/v0/SCHEMA/npdb/4.3.4")
+        );
+        assert_eq!(
+            DidUrlComponent::Path,
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/othercreds/v0/SCHEMA/npdb/4.3.4")
+        );
+        assert_eq!(
+            DidUrlComponent::Path,
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/CLAIM_DEF//npdb")
+        );
+        assert_eq!(
+            DidUrlComponent::Query,
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?color=blue")
+        );
+        assert_eq!(
+            DidUrlComponent::Query,
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=yesterday")
+        );
+        assert_eq!(
+            DidUrlComponent::Query,
+            component("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=first")
+        );
+    }
+
     mod did_syntax_tests {
 
         use super::*;
@@ -438,6 +1095,7 @@ mod tests {
                     id: DidValue::new("BDrEcHc8Tb4Lb2VyQZWEDE", None),
                     path: None,
                     query: HashMap::new(),
+                    fragment: None,
                     url: String::from("did:indy:idunion:BDrEcHc8Tb4Lb2VyQZWEDE"),
                 }
             );
@@ -449,6 +1107,7 @@ mod tests {
                     id: DidValue::new("6cgbu8ZPoWTnR5Rv5JcSMB", None),
                     path: None,
                     query: HashMap::new(),
+                    fragment: None,
                     url: String::from("did:indy:sovrin:staging:6cgbu8ZPoWTnR5Rv5JcSMB"),
                 }
             );
@@ -482,11 +1141,37 @@ mod tests {
                     id: DidValue::new("BDrEcHc8Tb4Lb2VyQZWEDE", None),
                     path: None,
                     query: q,
+                    fragment: None,
                     url: String::from("did:indy:idunion:BDrEcHc8Tb4Lb2VyQZWEDE?versionId=1"),
                 }
             );
         }
 
+        #[test]
+        fn parse_did_url_with_fragment() {
+            let did_url =
+                DidUrl::from_str("did:indy:idunion:BDrEcHc8Tb4Lb2VyQZWEDE?versionId=1#verkey")
+                    .unwrap();
+            assert_eq!(None, did_url.path);
+            assert_eq!(
+                Some(String::from("1")),
+                did_url.query.get(&QueryParameter::VersionId).cloned()
+            );
+            assert_eq!(Some(String::from("verkey")), did_url.fragment);
+        }
+
+        #[test]
+        fn canonical_did_url() {
+            let did_url = DidUrl::from_str(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?to=2021-11-11T14:33:42Z&from=2021-11-10T14%3A33%3A42Z#delta",
+            )
+            .unwrap();
+            assert_eq!(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from=2021-11-10T14%3A33%3A42Z&to=2021-11-11T14%3A33%3A42Z#delta",
+                did_url.canonical()
+            );
+        }
+
         #[test]
         fn parse_did_url_fails_with_arbitrary_query_parameter() {
             assert!(matches!(
@@ -505,6 +1190,7 @@ mod tests {
                     id: DidValue::new("Dk1fRRTtNazyMuK2cr64wp", None),
                     path: Some(String::from("/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54-e028-462b-a4d6-b1d1712d51a1")),
                     query: HashMap::new(),
+                    fragment: None,
                     url: String::from(
                         "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54-e028-462b-a4d6-b1d1712d51a1"
                     ),
@@ -525,6 +1211,7 @@ mod tests {
                     id: DidValue::new("Dk1fRRTtNazyMuK2cr64wp", None),
                     path: Some(String::from("/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54-e028-462b-a4d6-b1d1712d51a1")),
                     query: q,
+                    fragment: None,
                     url: String::from(
                         "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54-e028-462b-a4d6-b1d1712d51a1?versionTime=someXmlDateTime"
                     ),
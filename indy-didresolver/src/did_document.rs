@@ -1,11 +1,23 @@
+use super::config::{EndpointValidation, ServiceConflictPolicy};
 use super::error::{DidIndyError, DidIndyResult};
-use super::responses::Endpoint;
+use super::keys::ed25519_multibase;
+use super::responses::{Endpoint, EndpointValue};
+use super::validation::{validate_did_document, ValidationIssue, VERIFICATION_RELATIONSHIPS};
+use super::verification::self_certifying_form;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
+use url::Url;
 
 pub const LEGACY_INDY_SERVICE: &str = "endpoint";
+/// Service of the legacy endpoint ATTRIB listing the origins of the DID for
+/// the Well Known DID Configuration
+pub const LEGACY_LINKED_DOMAINS_SERVICE: &str = "linked_domains";
 pub const DID_CORE_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
 
+// Schemes accepted for normalized service endpoints
+static ENDPOINT_SCHEMES: [&str; 4] = ["http", "https", "ws", "wss"];
+
 #[derive(Serialize, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Ed25519VerificationKey2018 {
@@ -45,20 +57,253 @@ pub struct GenericService {
     pub service_endpoint: String,
 }
 
-#[derive(Serialize, PartialEq, Debug)]
-pub enum Service {
-    GenericService(GenericService),
-    DidCommService(DidCommService),
+/// Verification method of a `Document`. Key properties other than the ones
+/// below, e.g. `publicKeyJwk`, are kept in `properties`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub controller: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_base58: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// Entry of a verification relationship like `authentication`, either the id of
+/// a verification method of the document or an embedded one
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum VerificationRelationship {
+    Reference(String),
+    Embedded(VerificationMethod),
+}
+
+/// Service of a `Document`. The type is a string or a set of strings and the
+/// endpoint a URL, a map or a set of them, as allowed by DID Core. Properties
+/// like `recipientKeys` are kept in `properties`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: Value,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub service_endpoint: Value,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+/// DID document as a typed struct, for consuming resolution results without
+/// handling JSON. Deserializes the documents of this crate and of other DID
+/// implementations; properties without a field, like `alsoKnownAs`, are kept
+/// in `properties`, so documents serialize to what they were read from.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    /// JSON-LD context, a string or a set of contexts
+    #[serde(rename = "@context", default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub verification_method: Vec<VerificationMethod>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authentication: Vec<VerificationRelationship>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertion_method: Vec<VerificationRelationship>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_agreement: Vec<VerificationRelationship>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capability_invocation: Vec<VerificationRelationship>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capability_delegation: Vec<VerificationRelationship>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub service: Vec<Service>,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+impl Document {
+    pub fn from_value(value: Value) -> DidIndyResult<Self> {
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn to_value(&self) -> DidIndyResult<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    /// Verification method with the id, relative ids like `#verkey` are
+    /// resolved against the document id
+    pub fn verification_method(&self, id: &str) -> Option<&VerificationMethod> {
+        let id = match id.strip_prefix('#') {
+            Some(fragment) => format!("{}#{}", self.id, fragment),
+            None => id.to_string(),
+        };
+        self.verification_method
+            .iter()
+            .find(|method| method.id == id)
+    }
+}
+
+/// Verification relationships referencing the verification method derived from
+/// the NYM verkey. Relationships contributed by diddocContent are not affected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VerificationRelationships {
+    pub authentication: bool,
+    pub assertion_method: bool,
+    pub capability_invocation: bool,
+    pub capability_delegation: bool,
+}
+
+impl VerificationRelationships {
+    pub fn authentication_only() -> Self {
+        VerificationRelationships {
+            authentication: true,
+            assertion_method: false,
+            capability_invocation: false,
+            capability_delegation: false,
+        }
+    }
+
+    pub fn all() -> Self {
+        VerificationRelationships {
+            authentication: true,
+            assertion_method: true,
+            capability_invocation: true,
+            capability_delegation: true,
+        }
+    }
+
+    fn names(&self) -> Vec<&'static str> {
+        let flags = [
+            ("authentication", self.authentication),
+            ("assertionMethod", self.assertion_method),
+            ("capabilityInvocation", self.capability_invocation),
+            ("capabilityDelegation", self.capability_delegation),
+        ];
+        flags
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl Default for VerificationRelationships {
+    fn default() -> Self {
+        VerificationRelationships::authentication_only()
+    }
+}
+
+/// Encoding of the NYM verkey in a verification method
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyEncoding {
+    /// Base58 as on the ledger
+    Base58,
+    /// Multibase of the key with its multicodec prefix
+    Multibase,
+}
+
+impl KeyEncoding {
+    /// DID Core property of the verification method holding the encoded key
+    pub fn property(&self) -> &'static str {
+        match self {
+            KeyEncoding::Base58 => "publicKeyBase58",
+            KeyEncoding::Multibase => "publicKeyMultibase",
+        }
+    }
+
+    fn encode(&self, verkey: &str) -> DidIndyResult<String> {
+        match self {
+            KeyEncoding::Base58 => Ok(verkey.to_string()),
+            KeyEncoding::Multibase => ed25519_multibase(verkey),
+        }
+    }
+}
+
+/// Type of the verification method derived from the NYM verkey. The type
+/// determines how the key is encoded, see `key_encoding`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerificationMethodType {
+    Ed25519VerificationKey2018,
+    Ed25519VerificationKey2020,
+    Multikey,
+}
+
+impl VerificationMethodType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerificationMethodType::Ed25519VerificationKey2018 => "Ed25519VerificationKey2018",
+            VerificationMethodType::Ed25519VerificationKey2020 => "Ed25519VerificationKey2020",
+            VerificationMethodType::Multikey => "Multikey",
+        }
+    }
+
+    pub fn key_encoding(&self) -> KeyEncoding {
+        match self {
+            VerificationMethodType::Ed25519VerificationKey2018 => KeyEncoding::Base58,
+            VerificationMethodType::Ed25519VerificationKey2020
+            | VerificationMethodType::Multikey => KeyEncoding::Multibase,
+        }
+    }
+
+    fn verification_method(
+        &self,
+        id: &str,
+        controller: &str,
+        verkey: &str,
+    ) -> DidIndyResult<Value> {
+        let encoding = self.key_encoding();
+        let mut method = json!({
+            "id": id,
+            "type": self.name(),
+            "controller": controller,
+        });
+        method[encoding.property()] = json!(encoding.encode(verkey)?);
+        Ok(method)
+    }
+}
+
+impl Default for VerificationMethodType {
+    fn default() -> Self {
+        VerificationMethodType::Ed25519VerificationKey2018
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct DidDocument {
     namespace: String,
     id: String,
     verkey: String,
+    // Verkey as written to the ledger, possibly abbreviated
+    #[serde(skip)]
+    nym_verkey: String,
     endpoint: Option<Endpoint>,
     diddoc_content: Option<Value>,
+    #[serde(skip)]
+    relationships: VerificationRelationships,
+    #[serde(skip)]
+    method_type: VerificationMethodType,
+    #[serde(skip)]
+    endpoint_base: Option<Url>,
+    #[serde(skip)]
+    service_conflict_policy: ServiceConflictPolicy,
+    #[serde(skip)]
+    endpoint_validation: EndpointValidation,
+    #[serde(skip)]
+    strict_endpoints: bool,
+    #[serde(skip)]
+    best_effort: bool,
+    #[serde(skip)]
+    deactivated: bool,
+    // Warnings of the steps before assembling the document
+    #[serde(skip)]
+    warnings: Vec<String>,
 }
 
 pub fn expand_verkey(id: &str, verkey: &str) -> String {
@@ -81,66 +326,427 @@ impl DidDocument {
             namespace: namespace.to_string(),
             id: id.to_string(),
             verkey: expand_verkey(id, verkey),
+            nym_verkey: verkey.to_string(),
             endpoint,
             diddoc_content,
+            relationships: VerificationRelationships::default(),
+            method_type: VerificationMethodType::default(),
+            endpoint_base: None,
+            service_conflict_policy: ServiceConflictPolicy::default(),
+            endpoint_validation: EndpointValidation::default(),
+            strict_endpoints: false,
+            best_effort: false,
+            deactivated: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn with_relationships(mut self, relationships: VerificationRelationships) -> Self {
+        self.relationships = relationships;
+        self
+    }
+
+    pub fn with_method_type(mut self, method_type: VerificationMethodType) -> Self {
+        self.method_type = method_type;
+        self
+    }
+
+    /// Normalizes service endpoints to absolute URLs, relative ones are resolved
+    /// against the base
+    pub fn with_endpoint_base(mut self, endpoint_base: Option<Url>) -> Self {
+        self.endpoint_base = endpoint_base;
+        self
+    }
+
+    /// Decides which service is kept when a service of diddocContent shares its
+    /// id with one of the legacy endpoint ATTRIB
+    pub fn with_service_conflict_policy(mut self, policy: ServiceConflictPolicy) -> Self {
+        self.service_conflict_policy = policy;
+        self
+    }
+
+    /// Leaves out services whose endpoints use a scheme that is not allowed, see
+    /// `EndpointValidation`
+    pub fn with_endpoint_validation(mut self, endpoint_validation: EndpointValidation) -> Self {
+        self.endpoint_validation = endpoint_validation;
+        self
+    }
+
+    /// Fails with `InvalidServiceEndpoint` on a service endpoint that is not
+    /// allowed instead of leaving the service out, unless with best effort
+    pub fn with_strict_endpoints(mut self, strict_endpoints: bool) -> Self {
+        self.strict_endpoints = strict_endpoints;
+        self
+    }
+
+    /// Assembles the document from whatever is usable instead of failing on the
+    /// first problem. Verkeys that cannot be encoded are kept in base58, invalid
+    /// diddocContent is left out, conflicting services are resolved as with
+    /// `PreferDiddoc` and invalid service endpoints are kept as they are. Each of
+    /// them is reported as a warning by `to_value_with_warnings`.
+    pub fn with_best_effort(mut self, best_effort: bool) -> Self {
+        self.best_effort = best_effort;
+        self
+    }
+
+    /// Marks the DID as deactivated, its document then only holds the DID.
+    /// The verkey, endpoint and diddocContent are left out.
+    pub fn with_deactivated(mut self, deactivated: bool) -> Self {
+        self.deactivated = deactivated;
+        self
+    }
+
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivated
+    }
+
+    /// Adds a warning of a step before assembling the document, e.g. fetching
+    /// the legacy endpoint. These warnings are reported also without best effort.
+    pub fn with_warning(mut self, warning: String) -> Self {
+        self.warnings.push(warning);
+        self
+    }
+
+    pub fn did(&self) -> String {
+        format!("did:indy:{}:{}", self.namespace, self.id)
+    }
+
+    /// The did:indy DID in its self-certifying form, if the DID is the legacy
+    /// form derived from the verkey, see `verification::self_certifying_form`
+    pub fn self_certifying_did(&self) -> Option<String> {
+        if self.deactivated {
+            return None;
         }
+        self_certifying_form(&self.id, &self.nym_verkey)
+            .map(|id| format!("did:indy:{}:{}", self.namespace, id))
+    }
+
+    /// Checks the assembled document against the structural rules of DID Core.
+    /// Returns all violations, an empty list means the document is valid.
+    pub fn validate(&self) -> DidIndyResult<Vec<ValidationIssue>> {
+        let doc = self.to_value()?;
+        Ok(validate_did_document(&doc, &self.did()))
     }
 
     pub fn to_value(&self) -> DidIndyResult<Value> {
+        Ok(self.to_value_with_warnings()?.0)
+    }
+
+    /// The assembled document together with the warnings about what could not be
+    /// used. Warnings about assembling the document are only reported with best
+    /// effort, services left out for their endpoints are always reported.
+    pub fn to_value_with_warnings(&self) -> DidIndyResult<(Value, Vec<String>)> {
+        if self.deactivated {
+            return Ok((json!({ "id": self.did() }), self.warnings.clone()));
+        }
+        let mut warnings = Vec::new();
+        let mut endpoint_warnings = Vec::new();
+        let verification_method = self.verification_method(&mut warnings)?;
         let mut doc = json!({
              "id": format!("did:indy:{}:{}", self.namespace, self.id),
-            "verificationMethod": [Ed25519VerificationKey2018 {
-                id: format!("did:indy:{}:{}#verkey", self.namespace, self.id),
-                type_: format!("Ed25519VerificationKey2018"),
-                controller: format!("did:indy:{}:{}", self.namespace, self.id),
-                public_key_base58: format!("{}", self.verkey),
-            }],
-            "authentication": [format!("did:indy:{}:{}#verkey", self.namespace, self.id)],
+            "verificationMethod": [verification_method],
         });
 
-        if self.diddoc_content.is_some() {
-            let is_valid = validate_diddoc_content(&(self.diddoc_content.as_ref().unwrap()));
-
-            if is_valid {
-                merge_diddoc(&mut doc, self.diddoc_content.as_ref().unwrap());
-            } else {
-                return Err(DidIndyError::InvalidDidDoc);
+        if let Value::Object(ref mut map) = doc {
+            for relationship in self.relationships.names() {
+                map.insert(
+                    relationship.to_string(),
+                    json!([format!("did:indy:{}:{}#verkey", self.namespace, self.id)]),
+                );
             }
+        }
 
-            // Handling of legacy services
-        } else if self.endpoint.is_some() {
+        // Handling of legacy services
+        if self.endpoint.is_some() {
             let mut services = Vec::new();
-            let endpoints = self.endpoint.clone();
-            for (service, service_endpoint) in endpoints.unwrap().endpoint.into_iter() {
+            // Sorted by service, the order of the map is not stable
+            let mut endpoints: Vec<(String, EndpointValue)> = self
+                .endpoint
+                .clone()
+                .unwrap()
+                .endpoint
+                .into_iter()
+                .collect();
+            endpoints.sort();
+            for (service, service_endpoint) in endpoints {
+                if service == LEGACY_LINKED_DOMAINS_SERVICE {
+                    services.extend(
+                        self.linked_domains_service(&service_endpoint, &mut endpoint_warnings)?,
+                    );
+                    continue;
+                }
+                if !self.allow_service(
+                    &service,
+                    &service_endpoint.urls(),
+                    &mut endpoint_warnings,
+                )? {
+                    continue;
+                }
                 let s = match service.as_str() {
                     LEGACY_INDY_SERVICE => json!(DidCommService::new(
                         format!("did:indy:{}:{}#did-communication", self.namespace, self.id),
                         vec![format!("did:indy:{}:{}#verkey", self.namespace, self.id)],
                         vec![],
                     )),
-                    type_ => json!(GenericService {
-                        id: format!("did:indy:{}:{}#{}", self.namespace, self.id, type_),
-                        type_: type_.to_string(),
-                        service_endpoint,
-                    }),
+                    type_ => match service_endpoint {
+                        EndpointValue::Url(service_endpoint) => json!(GenericService {
+                            id: format!("did:indy:{}:{}#{}", self.namespace, self.id, type_),
+                            type_: type_.to_string(),
+                            service_endpoint,
+                        }),
+                        EndpointValue::Urls(service_endpoints) => json!({
+                            "id": format!("did:indy:{}:{}#{}", self.namespace, self.id, type_),
+                            "type": type_,
+                            "serviceEndpoint": service_endpoints,
+                        }),
+                    },
                 };
                 services.push(s);
             }
 
-            if let Value::Object(ref mut map) = doc {
-                map.insert("service".to_string(), serde_json::Value::Array(services));
+            // All services may have been left out for their endpoints
+            if !services.is_empty() {
+                if let Value::Object(ref mut map) = doc {
+                    map.insert("service".to_string(), serde_json::Value::Array(services));
+                }
+            }
+        }
+
+        // Services from diddocContent are merged on top of legacy services,
+        // services sharing an id with a legacy service are resolved by the
+        // conflict policy
+        if self.diddoc_content.is_some() {
+            let is_valid = validate_diddoc_content(&(self.diddoc_content.as_ref().unwrap()));
+
+            let policy = match self.service_conflict_policy {
+                ServiceConflictPolicy::ErrorOnConflict if self.best_effort => {
+                    ServiceConflictPolicy::PreferDiddoc
+                }
+                policy => policy,
+            };
+            if is_valid {
+                let mut content = self.diddoc_content.clone().unwrap();
+                if self.endpoint_validation.diddoc_content {
+                    if let Some(Value::Array(services)) = content.get_mut("service") {
+                        let mut allowed = Vec::new();
+                        for service in services.drain(..) {
+                            let id = service["id"].as_str().unwrap_or_default().to_string();
+                            let endpoints = service_endpoints(&service);
+                            if self.allow_service(&id, &endpoints, &mut endpoint_warnings)? {
+                                allowed.push(service);
+                            }
+                        }
+                        *services = allowed;
+                    }
+                }
+                reject_duplicate_verification_methods(
+                    &doc,
+                    &mut content,
+                    self.best_effort,
+                    &mut warnings,
+                )?;
+                merge_diddoc(&mut doc, &content, policy, &mut warnings)?;
+            } else if self.best_effort {
+                warnings.push(String::from("Invalid diddocContent left out"));
+            } else {
+                return Err(DidIndyError::InvalidDidDoc);
+            }
+        }
+
+        if let Some(base) = &self.endpoint_base {
+            let lenient = if self.best_effort {
+                Some(&mut warnings)
+            } else {
+                None
+            };
+            normalize_service_endpoints(&mut doc, base, lenient)?;
+        }
+
+        if !self.best_effort {
+            warnings.clear();
+        }
+        warnings.extend(endpoint_warnings);
+        Ok((doc, [self.warnings.clone(), warnings].concat()))
+    }
+
+    // Whether the service is kept, a service with an endpoint that is not allowed
+    // is reported unless strict
+    fn allow_service(
+        &self,
+        service: &str,
+        endpoints: &[&str],
+        warnings: &mut Vec<String>,
+    ) -> DidIndyResult<bool> {
+        for endpoint in endpoints {
+            if let Err(err) = self.check_endpoint_scheme(endpoint) {
+                if self.strict_endpoints && !self.best_effort {
+                    return Err(err);
+                }
+                warnings.push(format!("{}, service {} left out", err, service));
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // LinkedDomains service with the origins of the linked_domains ATTRIB, each
+    // origin once. Invalid origins are left out with a warning unless strict, no
+    // service is returned if none is left.
+    fn linked_domains_service(
+        &self,
+        value: &EndpointValue,
+        warnings: &mut Vec<String>,
+    ) -> DidIndyResult<Option<Value>> {
+        let mut origins: Vec<String> = Vec::new();
+        for origin in value.urls() {
+            match self.check_origin(origin) {
+                Ok(origin) if !origins.contains(&origin) => origins.push(origin),
+                Ok(_) => {}
+                Err(err) if self.strict_endpoints && !self.best_effort => return Err(err),
+                Err(err) => warnings.push(format!("{}, origin left out", err)),
+            }
+        }
+        if origins.is_empty() {
+            warnings.push(String::from(
+                "No valid origin, LinkedDomains service left out",
+            ));
+            return Ok(None);
+        }
+        Ok(Some(json!({
+            "id": format!("did:indy:{}:{}#linked-domains", self.namespace, self.id),
+            "type": "LinkedDomains",
+            "serviceEndpoint": { "origins": origins },
+        })))
+    }
+
+    // The origin as serialized by the URL standard, e.g. https://example.com.
+    // URLs with a path other than /, a query or a fragment are not origins.
+    fn check_origin(&self, origin: &str) -> DidIndyResult<String> {
+        self.check_endpoint_scheme(origin)?;
+        match Url::parse(origin.trim()) {
+            Ok(url)
+                if url.host_str().is_some()
+                    && url.path() == "/"
+                    && url.query().is_none()
+                    && url.fragment().is_none() =>
+            {
+                Ok(url.origin().ascii_serialization())
+            }
+            _ => Err(DidIndyError::InvalidServiceEndpoint(origin.to_string())),
+        }
+    }
+
+    fn check_endpoint_scheme(&self, endpoint: &str) -> DidIndyResult<()> {
+        // Relative and host:port endpoints are checked as normalized against the
+        // endpoint base
+        let normalized = self
+            .endpoint_base
+            .as_ref()
+            .and_then(|base| normalize_endpoint(endpoint, base).ok());
+        match Url::parse(normalized.as_deref().unwrap_or_else(|| endpoint.trim())) {
+            Ok(url)
+                if self
+                    .endpoint_validation
+                    .schemes
+                    .iter()
+                    .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme())) =>
+            {
+                Ok(())
             }
+            _ => Err(DidIndyError::InvalidServiceEndpoint(endpoint.to_string())),
+        }
+    }
+
+    // Verification method of the verkey. With best effort, a verkey that is not
+    // an Ed25519 key is reported, and one that cannot be encoded for the method
+    // type is kept in base58 as Ed25519VerificationKey2018.
+    fn verification_method(&self, warnings: &mut Vec<String>) -> DidIndyResult<Value> {
+        let id = format!("did:indy:{}:{}#verkey", self.namespace, self.id);
+        let controller = format!("did:indy:{}:{}", self.namespace, self.id);
+        if !self.best_effort {
+            return self
+                .method_type
+                .verification_method(&id, &controller, &self.verkey);
         }
 
-        Ok(doc)
+        match bs58::decode(&self.verkey).into_vec() {
+            Ok(key) if key.len() == 32 => {}
+            _ => warnings.push(format!(
+                "Verkey {} is not a 32 byte Ed25519 key",
+                self.verkey
+            )),
+        }
+        self.method_type
+            .verification_method(&id, &controller, &self.verkey)
+            .or_else(|err| {
+                warnings.push(format!(
+                    "Verkey could not be encoded for {}, kept in base58: {}",
+                    self.method_type.name(),
+                    err
+                ));
+                VerificationMethodType::Ed25519VerificationKey2018.verification_method(
+                    &id,
+                    &controller,
+                    &self.verkey,
+                )
+            })
+    }
+
+    /// The assembled document as typed struct
+    pub fn to_document(&self) -> DidIndyResult<Document> {
+        Document::from_value(self.to_value()?)
     }
 
     pub fn to_string(&self) -> DidIndyResult<String> {
-        let doc = self.to_value()?;
+        let doc = sort_keys(self.to_value()?);
         Ok(serde_json::to_string_pretty(&doc).unwrap())
     }
 }
 
+/// Rebuilds the objects of the value with their keys in sorted order, so that
+/// serializing it gives the same bytes for equal values, also if serde_json
+/// keeps the insertion order with its `preserve_order` feature
+pub fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+/// Adds the DID Core context to a DID document for the JSON-LD representation,
+/// keeping contexts from diddocContent
+pub fn add_did_core_context(doc: &mut Value) {
+    let context = match doc.get("@context") {
+        None | Some(Value::Null) => json!(DID_CORE_CONTEXT),
+        Some(Value::String(context)) if context == DID_CORE_CONTEXT => return,
+        Some(Value::Array(contexts)) if contexts.first() == Some(&json!(DID_CORE_CONTEXT)) => {
+            return
+        }
+        Some(Value::Array(contexts)) => {
+            let mut with_core = vec![json!(DID_CORE_CONTEXT)];
+            with_core.extend(
+                contexts
+                    .iter()
+                    .filter(|context| **context != json!(DID_CORE_CONTEXT))
+                    .cloned(),
+            );
+            Value::Array(with_core)
+        }
+        Some(context) => json!([DID_CORE_CONTEXT, context]),
+    };
+    doc["@context"] = context;
+}
+
 fn validate_context(context: &str) -> bool {
     context == DID_CORE_CONTEXT
 }
@@ -170,21 +776,213 @@ fn validate_diddoc_content(diddoc_content: &Value) -> bool {
     }
 }
 
-fn merge_diddoc(base: &mut Value, content: &Value) {
+fn merge_diddoc(
+    base: &mut Value,
+    content: &Value,
+    policy: ServiceConflictPolicy,
+    warnings: &mut Vec<String>,
+) -> DidIndyResult<()> {
     match (base, content) {
         (Value::Object(base), Value::Object(content)) => {
             for (k, v) in content {
-                if k == "authentication" || k == "verificationMethod" {
-                    let mut _tmp = base[k].as_array().unwrap().to_owned();
+                if k == "verificationMethod" || VERIFICATION_RELATIONSHIPS.contains(&k.as_str()) {
+                    let mut _tmp = base
+                        .get(k)
+                        .and_then(|b| b.as_array())
+                        .cloned()
+                        .unwrap_or_default();
                     _tmp.append(&mut v.as_array().unwrap_or(&vec![v.to_owned()]).to_owned());
-                    base[k] = Value::from(_tmp);
+                    base.insert(k.to_owned(), Value::from(_tmp));
+                } else if k == "service" {
+                    merge_services(base.entry(k).or_insert(Value::Null), v, policy, warnings)?;
                 } else {
-                    merge_diddoc(base.entry(k).or_insert(Value::Null), v);
+                    merge_diddoc(base.entry(k).or_insert(Value::Null), v, policy, warnings)?;
                 }
             }
         }
         (a, b) => *a = b.clone(),
     }
+    Ok(())
+}
+
+// Verification methods of diddocContent must not share an id with the verkey
+// or with each other, relative ids are compared as resolved against the DID.
+// With best effort, the duplicates are left out and reported instead.
+fn reject_duplicate_verification_methods(
+    base: &Value,
+    content: &mut Value,
+    best_effort: bool,
+    warnings: &mut Vec<String>,
+) -> DidIndyResult<()> {
+    let did = base["id"].as_str().unwrap_or_default();
+    let absolute = |method: &Value| {
+        method["id"].as_str().map(|id| match id.strip_prefix('#') {
+            Some(fragment) => format!("{}#{}", did, fragment),
+            None => id.to_string(),
+        })
+    };
+    let mut ids: Vec<String> = base["verificationMethod"]
+        .as_array()
+        .map(|methods| methods.iter().filter_map(absolute).collect())
+        .unwrap_or_default();
+    let methods = match content.get_mut("verificationMethod") {
+        Some(Value::Array(methods)) => methods,
+        _ => return Ok(()),
+    };
+
+    let mut unique = Vec::new();
+    for method in methods.drain(..) {
+        match absolute(&method) {
+            Some(id) if ids.contains(&id) => {
+                if !best_effort {
+                    return Err(DidIndyError::VerificationMethodIdConflict(id));
+                }
+                warnings.push(format!(
+                    "Verification method {} of diddocContent left out, its id is taken",
+                    id
+                ));
+            }
+            id => {
+                ids.extend(id);
+                unique.push(method);
+            }
+        }
+    }
+    *methods = unique;
+    Ok(())
+}
+
+// Adds the services of diddocContent to the legacy services. Services of
+// diddocContent sharing an id replace each other, conflicts with a legacy
+// service are resolved by the policy and reported as warning.
+fn merge_services(
+    base: &mut Value,
+    content: &Value,
+    policy: ServiceConflictPolicy,
+    warnings: &mut Vec<String>,
+) -> DidIndyResult<()> {
+    let mut services = base.as_array().cloned().unwrap_or_default();
+    let legacy_ids: Vec<Value> = services
+        .iter()
+        .filter_map(|s| s.get("id").cloned())
+        .collect();
+    let additional = content
+        .as_array()
+        .cloned()
+        .unwrap_or_else(|| vec![content.to_owned()]);
+
+    for service in additional {
+        if let Some(id) = service.get("id") {
+            if legacy_ids.contains(id) {
+                let id_str = id
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| id.to_string());
+                match policy {
+                    ServiceConflictPolicy::PreferDiddoc => warnings.push(format!(
+                        "Service {} of the legacy endpoint replaced by diddocContent",
+                        id_str
+                    )),
+                    ServiceConflictPolicy::PreferAttrib => {
+                        warnings.push(format!(
+                            "Service {} of diddocContent replaced by the legacy endpoint",
+                            id_str
+                        ));
+                        continue;
+                    }
+                    ServiceConflictPolicy::ErrorOnConflict => {
+                        return Err(DidIndyError::ServiceIdConflict(id_str))
+                    }
+                }
+            }
+            services.retain(|s| s.get("id") != Some(id));
+        }
+        services.push(service);
+    }
+    *base = Value::from(services);
+    Ok(())
+}
+
+// Normalizes the string service endpoints of all services, including the ones
+// in a list of endpoints. Structured endpoints are left as they are. If
+// warnings are collected, invalid endpoints are kept and reported instead of
+// failing.
+// URLs of a service endpoint given as string, as map with `uri` or as a list
+// of them, and the origins of a LinkedDomains service
+fn service_endpoints(service: &Value) -> Vec<&str> {
+    fn uri(endpoint: &Value) -> Option<&str> {
+        match endpoint {
+            Value::Object(endpoint) => endpoint.get("uri").and_then(Value::as_str),
+            endpoint => endpoint.as_str(),
+        }
+    }
+    match service.get("serviceEndpoint") {
+        Some(Value::Array(endpoints)) => endpoints.iter().filter_map(uri).collect(),
+        Some(Value::Object(endpoint)) if endpoint.contains_key("origins") => endpoint["origins"]
+            .as_array()
+            .map(|origins| origins.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default(),
+        Some(endpoint) => uri(endpoint).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+fn normalize_service_endpoints(
+    doc: &mut Value,
+    base: &Url,
+    mut warnings: Option<&mut Vec<String>>,
+) -> DidIndyResult<()> {
+    let services = match doc.get_mut("service") {
+        Some(Value::Array(services)) => services,
+        _ => return Ok(()),
+    };
+    let mut normalize = |endpoint: &mut String| match normalize_endpoint(endpoint, base) {
+        Ok(normalized) => {
+            *endpoint = normalized;
+            Ok(())
+        }
+        Err(err) => match warnings.as_mut() {
+            Some(warnings) => {
+                warnings.push(format!("{}, kept as it is", err));
+                Ok(())
+            }
+            None => Err(err),
+        },
+    };
+    for service in services.iter_mut() {
+        match service.get_mut("serviceEndpoint") {
+            Some(Value::String(endpoint)) => normalize(endpoint)?,
+            Some(Value::Array(endpoints)) => {
+                for endpoint in endpoints.iter_mut() {
+                    if let Value::String(endpoint) = endpoint {
+                        normalize(endpoint)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn normalize_endpoint(endpoint: &str, base: &Url) -> DidIndyResult<String> {
+    // Shorthand host:port form as used by legacy agents, e.g. 10.0.0.2:8020
+    let host_port = Regex::new(r"^[A-Za-z0-9.-]+:\d+(/.*)?$").unwrap();
+
+    let endpoint = endpoint.trim();
+    let url = if endpoint.contains("://") {
+        Url::parse(endpoint)
+    } else if host_port.is_match(endpoint) {
+        Url::parse(&format!("{}://{}", base.scheme(), endpoint))
+    } else {
+        base.join(endpoint)
+    }
+    .map_err(|_| DidIndyError::InvalidServiceEndpoint(endpoint.to_string()))?;
+
+    if !ENDPOINT_SCHEMES.contains(&url.scheme()) || url.host_str().is_none() {
+        return Err(DidIndyError::InvalidServiceEndpoint(endpoint.to_string()));
+    }
+    Ok(url.to_string())
 }
 
 #[cfg(test)]
@@ -425,8 +1223,11 @@ mod tests {
 
     #[test]
     fn serialze_diddoc_with_legacy_did_comm_endpoint() {
-        let mut endpoint_map: HashMap<String, String> = HashMap::new();
-        endpoint_map.insert(String::from("endpoint"), String::from("https://agent.com"));
+        let mut endpoint_map: HashMap<String, EndpointValue> = HashMap::new();
+        endpoint_map.insert(
+            String::from("endpoint"),
+            EndpointValue::from("https://agent.com"),
+        );
 
         let doc = DidDocument::new(
             "idunion",
@@ -466,11 +1267,14 @@ mod tests {
 
     #[test]
     fn serialze_diddoc_with_multiple_legacy_endpoints() {
-        let mut endpoint_map: HashMap<String, String> = HashMap::new();
-        endpoint_map.insert(String::from("endpoint"), String::from("https://agent.com"));
+        let mut endpoint_map: HashMap<String, EndpointValue> = HashMap::new();
+        endpoint_map.insert(
+            String::from("endpoint"),
+            EndpointValue::from("https://agent.com"),
+        );
         endpoint_map.insert(
             String::from("profile"),
-            String::from("https://agent.com/profile"),
+            EndpointValue::from("https://agent.com/profile"),
         );
 
         let doc = DidDocument::new(
@@ -489,38 +1293,680 @@ mod tests {
     }
 
     #[test]
-    fn validate_diddoc_with_context_as_string() {
+    fn serialze_diddoc_with_legacy_endpoint_and_diddoc_content() {
+        let mut endpoint_map: HashMap<String, EndpointValue> = HashMap::new();
+        endpoint_map.insert(
+            String::from("endpoint"),
+            EndpointValue::from("https://agent.com"),
+        );
+        endpoint_map.insert(
+            String::from("profile"),
+            EndpointValue::from("https://agent.com/profile"),
+        );
+
         let diddoc_content = json!({
-            "@context" : "https://www.w3.org/ns/did/v1"
+            "service": [{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#did-communication",
+                "type": "did-communication",
+                "serviceEndpoint": "https://example.com",
+                "recipientKeys": [ "#verkey" ],
+                "routingKeys": [],
+                "priority": 0
+            }]
         });
-        assert!(validate_diddoc_content(&diddoc_content))
+
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            Some(Endpoint {
+                endpoint: endpoint_map,
+            }),
+            Some(diddoc_content),
+        );
+
+        let v_from_doc: Value = serde_json::from_str(doc.to_string().unwrap().as_str()).unwrap();
+        let services = v_from_doc["service"].as_array().unwrap();
+
+        // Legacy did-communication service is replaced, profile service is kept
+        assert_eq!(2, services.len());
+        let did_comm = services
+            .iter()
+            .find(|s| s["id"] == "did:indy:idunion:QowxFtwciWceMFr7WbwnM#did-communication")
+            .unwrap();
+        assert_eq!("https://example.com", did_comm["serviceEndpoint"]);
+        assert!(services
+            .iter()
+            .any(|s| s["id"] == "did:indy:idunion:QowxFtwciWceMFr7WbwnM#profile"));
     }
 
     #[test]
-    fn validate_diddoc_without_context() {
-        let diddoc_content = json!({
-        "service": [
-          {
-            "id": "did:indy:sovrin:123456#did-communication",
-            "type": "did-communication",
-            "serviceEndpoint": "https://example.com",
-            "recipientKeys": [ "#verkey" ],
-            "routingKeys": [ ]
-          }
-        ]
-        });
-        assert!(validate_diddoc_content(&diddoc_content))
+    fn merge_services_deduplicates_by_id() {
+        let mut base = json!([
+            {"id": "#a", "serviceEndpoint": "https://a.com"},
+            {"id": "#b", "serviceEndpoint": "https://b.com"}
+        ]);
+        let content = json!([
+            {"id": "#b", "serviceEndpoint": "https://other.com"},
+            {"id": "#c", "serviceEndpoint": "https://c.com"}
+        ]);
+
+        merge_services(
+            &mut base,
+            &content,
+            ServiceConflictPolicy::PreferDiddoc,
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            base,
+            json!([
+                {"id": "#a", "serviceEndpoint": "https://a.com"},
+                {"id": "#b", "serviceEndpoint": "https://other.com"},
+                {"id": "#c", "serviceEndpoint": "https://c.com"}
+            ])
+        );
     }
 
-    #[test]
-    fn validate_diddoc_with_context_as_array() {
+    fn conflicting_services(policy: ServiceConflictPolicy) -> DidIndyResult<Vec<Value>> {
+        let mut endpoint_map: HashMap<String, EndpointValue> = HashMap::new();
+        endpoint_map.insert(
+            String::from("endpoint"),
+            EndpointValue::from("https://agent.com"),
+        );
+        endpoint_map.insert(
+            String::from("profile"),
+            EndpointValue::from("https://agent.com/profile"),
+        );
         let diddoc_content = json!({
-            "@context" : [
-                "https://www.w3.org/ns/did/v1",
-                "https://identity.foundation/didcomm-messaging/service-endpoint/v1"
-        ],
+            "service": [{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#profile",
+                "type": "profile",
+                "serviceEndpoint": "https://example.com/profile"
+            }, {
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#linked-domains",
+                "type": "LinkedDomains",
+                "serviceEndpoint": "https://example.com"
+            }]
         });
-        assert!(validate_diddoc_content(&diddoc_content))
+
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            Some(Endpoint {
+                endpoint: endpoint_map,
+            }),
+            Some(diddoc_content),
+        )
+        .with_service_conflict_policy(policy);
+
+        let value = doc.to_value()?;
+        Ok(value["service"].as_array().unwrap().clone())
+    }
+
+    fn endpoint_of<'a>(services: &'a [Value], fragment: &str) -> &'a Value {
+        &services
+            .iter()
+            .find(|s| {
+                s["id"]
+                    .as_str()
+                    .unwrap()
+                    .ends_with(&format!("#{}", fragment))
+            })
+            .unwrap()["serviceEndpoint"]
+    }
+
+    #[test]
+    fn service_conflict_prefer_diddoc() {
+        let services = conflicting_services(ServiceConflictPolicy::PreferDiddoc).unwrap();
+
+        assert_eq!(3, services.len());
+        assert_eq!(
+            "https://example.com/profile",
+            endpoint_of(&services, "profile")
+        );
+        assert_eq!(
+            "https://example.com",
+            endpoint_of(&services, "linked-domains")
+        );
+    }
+
+    #[test]
+    fn service_conflict_prefer_attrib() {
+        let services = conflicting_services(ServiceConflictPolicy::PreferAttrib).unwrap();
+
+        assert_eq!(3, services.len());
+        assert_eq!(
+            "https://agent.com/profile",
+            endpoint_of(&services, "profile")
+        );
+        assert_eq!(
+            "https://example.com",
+            endpoint_of(&services, "linked-domains")
+        );
+    }
+
+    #[test]
+    fn service_conflict_error() {
+        let err = conflicting_services(ServiceConflictPolicy::ErrorOnConflict).unwrap_err();
+
+        assert!(matches!(
+            &err,
+            DidIndyError::ServiceIdConflict(id) if id == "did:indy:idunion:QowxFtwciWceMFr7WbwnM#profile"
+        ));
+        assert_eq!("internalError", err.error_code());
+    }
+
+    #[test]
+    fn duplicate_verification_method_ids_rejected() {
+        let diddoc_content = json!({
+            "verificationMethod": [{
+                "id": "#keys-2",
+                "type": "Ed25519VerificationKey2018",
+                "controller": "did:indy:idunion:QowxFtwciWceMFr7WbwnM",
+                "publicKeyBase58": "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBc",
+            }, {
+                "id": "#verkey",
+                "type": "Ed25519VerificationKey2018",
+                "controller": "did:indy:idunion:QowxFtwciWceMFr7WbwnM",
+                "publicKeyBase58": "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBc",
+            }]
+        });
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            Some(diddoc_content),
+        );
+
+        assert!(matches!(
+            doc.to_value(),
+            Err(DidIndyError::VerificationMethodIdConflict(id))
+                if id == "did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey"
+        ));
+
+        let (value, warnings) = doc.with_best_effort(true).to_value_with_warnings().unwrap();
+        assert_eq!(2, value["verificationMethod"].as_array().unwrap().len());
+        assert_eq!("#keys-2", value["verificationMethod"][1]["id"]);
+        assert_eq!(
+            vec!["Verification method did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey of diddocContent left out, its id is taken"],
+            warnings
+        );
+    }
+
+    #[test]
+    fn best_effort_collects_warnings() {
+        let mut endpoint_map: HashMap<String, EndpointValue> = HashMap::new();
+        endpoint_map.insert(
+            String::from("profile"),
+            EndpointValue::from("https://agent.com/profile"),
+        );
+        let diddoc_content = json!({
+            "service": [{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#profile",
+                "type": "profile",
+                "serviceEndpoint": "https://example.com/profile"
+            }, {
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#files",
+                "type": "files",
+                "serviceEndpoint": "ftp://example.com"
+            }]
+        });
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "~0OIl",
+            Some(Endpoint {
+                endpoint: endpoint_map,
+            }),
+            Some(diddoc_content),
+        )
+        .with_method_type(VerificationMethodType::Multikey)
+        .with_service_conflict_policy(ServiceConflictPolicy::ErrorOnConflict)
+        .with_endpoint_base(Some(Url::parse("https://agent.com").unwrap()))
+        .with_warning(String::from("Legacy endpoint not fetched"));
+        assert!(doc.to_value().is_err());
+
+        let (value, warnings) = doc.with_best_effort(true).to_value_with_warnings().unwrap();
+        assert_eq!(
+            "QowxFtwciWceMFr7WbwnM0OIl",
+            value["verificationMethod"][0]["publicKeyBase58"]
+        );
+        assert_eq!("ftp://example.com", value["service"][1]["serviceEndpoint"]);
+        assert_eq!(
+            vec![
+                "Legacy endpoint not fetched",
+                "Verkey QowxFtwciWceMFr7WbwnM0OIl is not a 32 byte Ed25519 key",
+                "Verkey could not be encoded for Multikey, kept in base58: Invalid DID Document",
+                "Service did:indy:idunion:QowxFtwciWceMFr7WbwnM#profile of the legacy endpoint replaced by diddocContent",
+                "Invalid service endpoint ftp://example.com, kept as it is",
+            ],
+            warnings
+        );
+    }
+
+    #[test]
+    fn typed_document_round_trip() {
+        let mut endpoint_map: HashMap<String, EndpointValue> = HashMap::new();
+        endpoint_map.insert(
+            String::from("endpoint"),
+            EndpointValue::from("https://agent.com"),
+        );
+        let diddoc_content = json!({
+            "@context": ["https://www.w3.org/ns/did/v1", "https://w3id.org/security/suites/x25519-2019/v1"],
+            "alsoKnownAs": ["https://example.com/alice"],
+            "keyAgreement": [{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#key-agreement-1",
+                "type": "X25519KeyAgreementKey2019",
+                "controller": "did:indy:idunion:QowxFtwciWceMFr7WbwnM",
+                "publicKeyBase58": "FxfdY3DCQxVZddKGAtSjZdFW9bCCW7oRwZn1NFJ2Tbg2"
+            }],
+            "service": [{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#didcomm",
+                "type": "DIDCommMessaging",
+                "serviceEndpoint": {"uri": "https://example.com", "accept": ["didcomm/v2"]}
+            }]
+        });
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            Some(Endpoint {
+                endpoint: endpoint_map,
+            }),
+            Some(diddoc_content),
+        )
+        .with_relationships(VerificationRelationships::all());
+        let value = doc.to_value().unwrap();
+
+        let typed = doc.to_document().unwrap();
+
+        assert_eq!(value, typed.to_value().unwrap());
+        assert_eq!(typed, Document::from_value(value).unwrap());
+        assert_eq!("did:indy:idunion:QowxFtwciWceMFr7WbwnM", typed.id);
+        assert_eq!(
+            Some("67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk"),
+            typed
+                .verification_method("#verkey")
+                .unwrap()
+                .public_key_base58
+                .as_deref()
+        );
+        assert_eq!(
+            vec![VerificationRelationship::Reference(String::from(
+                "did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey"
+            ))],
+            typed.assertion_method
+        );
+        assert!(matches!(
+            &typed.key_agreement[..],
+            [VerificationRelationship::Embedded(method)] if method.type_ == "X25519KeyAgreementKey2019"
+        ));
+        assert_eq!(2, typed.service.len());
+        let did_comm = &typed.service[0];
+        assert_eq!(json!("did-communication"), did_comm.type_);
+        assert_eq!(
+            json!(["did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey"]),
+            did_comm.properties["recipientKeys"]
+        );
+        assert_eq!(
+            "https://example.com",
+            typed.service[1].service_endpoint["uri"]
+        );
+        assert_eq!(
+            json!(["https://example.com/alice"]),
+            typed.properties["alsoKnownAs"]
+        );
+    }
+
+    #[test]
+    fn typed_document_from_other_implementation() {
+        // Document of a did:sov DID as returned by another resolver, with
+        // properties this crate does not produce
+        let value = json!({
+            "@context": [
+                "https://www.w3.org/ns/did/v1",
+                "https://w3id.org/security/suites/ed25519-2018/v1"
+            ],
+            "id": "did:sov:WRfXPg8dantKVubE3HX8pw",
+            "controller": "did:sov:WRfXPg8dantKVubE3HX8pw",
+            "verificationMethod": [{
+                "id": "did:sov:WRfXPg8dantKVubE3HX8pw#key-1",
+                "type": "Ed25519VerificationKey2018",
+                "controller": "did:sov:WRfXPg8dantKVubE3HX8pw",
+                "publicKeyBase58": "H3C2AVvLMv6gmMNam3uVAjZpfkcJCwDwnZn6z3wXmqPV"
+            }, {
+                "id": "did:sov:WRfXPg8dantKVubE3HX8pw#key-agreement-1",
+                "type": "JsonWebKey2020",
+                "controller": "did:sov:WRfXPg8dantKVubE3HX8pw",
+                "publicKeyJwk": {"kty": "OKP", "crv": "X25519", "x": "W_Vcc7guviK-gPNDBmevVw-uJVamQV5rMNQGUwCqlH0"}
+            }],
+            "authentication": ["did:sov:WRfXPg8dantKVubE3HX8pw#key-1"],
+            "keyAgreement": ["did:sov:WRfXPg8dantKVubE3HX8pw#key-agreement-1"],
+            "service": [{
+                "id": "did:sov:WRfXPg8dantKVubE3HX8pw#endpoint",
+                "type": ["endpoint", "LinkedDomains"],
+                "serviceEndpoint": ["https://agent.example.com", "https://example.com"]
+            }],
+            "x-resolver": {"driver": "uni-resolver-driver-did-sov"}
+        });
+
+        let typed = Document::from_value(value.clone()).unwrap();
+
+        assert_eq!(value, typed.to_value().unwrap());
+        assert_eq!(2, typed.verification_method.len());
+        assert_eq!(
+            "OKP",
+            typed
+                .verification_method("#key-agreement-1")
+                .unwrap()
+                .properties["publicKeyJwk"]["kty"]
+        );
+        assert_eq!(json!(["endpoint", "LinkedDomains"]), typed.service[0].type_);
+        assert!(typed.properties.contains_key("controller"));
+        assert!(typed.properties.contains_key("x-resolver"));
+        assert!(typed.assertion_method.is_empty());
+    }
+
+    fn relationships_of(doc: &DidDocument) -> Vec<String> {
+        let value = doc.to_value().unwrap();
+        let verkey = "did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey";
+        [
+            "authentication",
+            "assertionMethod",
+            "capabilityInvocation",
+            "capabilityDelegation",
+        ]
+        .iter()
+        .filter(|r| {
+            value[**r]
+                .as_array()
+                .map(|refs| refs.contains(&json!(verkey)))
+                .unwrap_or(false)
+        })
+        .map(|r| r.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn nym_key_referenced_for_authentication_by_default() {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            None,
+        );
+        assert_eq!(vec!["authentication"], relationships_of(&doc));
+    }
+
+    #[test]
+    fn nym_key_referenced_for_authentication_only() {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            Some(json!({ "assertionMethod": ["#keys-2"] })),
+        )
+        .with_relationships(VerificationRelationships::authentication_only());
+
+        assert_eq!(vec!["authentication"], relationships_of(&doc));
+        // Relationship contributed by diddocContent is kept
+        assert_eq!(
+            json!(["#keys-2"]),
+            doc.to_value().unwrap()["assertionMethod"]
+        );
+    }
+
+    #[test]
+    fn nym_key_referenced_for_all_relationships() {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            Some(json!({ "assertionMethod": ["#keys-2"] })),
+        )
+        .with_relationships(VerificationRelationships::all());
+
+        assert_eq!(
+            vec![
+                "authentication",
+                "assertionMethod",
+                "capabilityInvocation",
+                "capabilityDelegation"
+            ],
+            relationships_of(&doc)
+        );
+        assert_eq!(
+            json!(["did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey", "#keys-2"]),
+            doc.to_value().unwrap()["assertionMethod"]
+        );
+    }
+
+    fn verification_method_of(method_type: VerificationMethodType) -> Value {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "B12NYF8RrR3h41TDCTJojY59usg3mbtbjnFs7Eud1Y6u",
+            None,
+            None,
+        )
+        .with_method_type(method_type);
+        doc.to_value().unwrap()["verificationMethod"][0].clone()
+    }
+
+    #[test]
+    fn verification_method_ed25519_2018_uses_base58() {
+        assert_eq!(
+            json!({
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey",
+                "type": "Ed25519VerificationKey2018",
+                "controller": "did:indy:idunion:QowxFtwciWceMFr7WbwnM",
+                "publicKeyBase58": "B12NYF8RrR3h41TDCTJojY59usg3mbtbjnFs7Eud1Y6u"
+            }),
+            verification_method_of(VerificationMethodType::Ed25519VerificationKey2018)
+        );
+    }
+
+    #[test]
+    fn verification_method_ed25519_2020_uses_multibase() {
+        assert_eq!(
+            json!({
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey",
+                "type": "Ed25519VerificationKey2020",
+                "controller": "did:indy:idunion:QowxFtwciWceMFr7WbwnM",
+                "publicKeyMultibase": "z6MkpTHR8VNsBxYAAWHut2Geadd9jSwuBV8xRoAnwWsdvktH"
+            }),
+            verification_method_of(VerificationMethodType::Ed25519VerificationKey2020)
+        );
+    }
+
+    #[test]
+    fn verification_method_multikey_uses_multibase() {
+        assert_eq!(
+            json!({
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#verkey",
+                "type": "Multikey",
+                "controller": "did:indy:idunion:QowxFtwciWceMFr7WbwnM",
+                "publicKeyMultibase": "z6MkpTHR8VNsBxYAAWHut2Geadd9jSwuBV8xRoAnwWsdvktH"
+            }),
+            verification_method_of(VerificationMethodType::Multikey)
+        );
+    }
+
+    #[test]
+    fn verification_method_key_properties() {
+        for method_type in [
+            VerificationMethodType::Ed25519VerificationKey2018,
+            VerificationMethodType::Ed25519VerificationKey2020,
+            VerificationMethodType::Multikey,
+        ] {
+            let property = method_type.key_encoding().property();
+            let method = verification_method_of(method_type);
+            assert!(method.get(property).is_some());
+            assert_eq!(4, method.as_object().unwrap().len());
+        }
+    }
+
+    #[test]
+    fn verification_method_type_defaults_to_ed25519_2018() {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "B12NYF8RrR3h41TDCTJojY59usg3mbtbjnFs7Eud1Y6u",
+            None,
+            None,
+        );
+        assert_eq!(
+            verification_method_of(VerificationMethodType::Ed25519VerificationKey2018),
+            doc.to_value().unwrap()["verificationMethod"][0]
+        );
+    }
+
+    fn doc_with_endpoint(endpoint: &str) -> DidDocument {
+        DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            Some(json!({
+                "service": [{
+                    "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#linked-domain",
+                    "type": "LinkedDomains",
+                    "serviceEndpoint": endpoint
+                }]
+            })),
+        )
+        .with_endpoint_base(Some(
+            Url::parse("https://agents.example.com/idunion/").unwrap(),
+        ))
+    }
+
+    fn normalized(endpoint: &str) -> DidIndyResult<Value> {
+        let doc = doc_with_endpoint(endpoint).to_value()?;
+        Ok(doc["service"][0]["serviceEndpoint"].clone())
+    }
+
+    #[test]
+    fn relative_endpoint_becomes_absolute() {
+        assert_eq!(
+            json!("https://agents.example.com/idunion/agent/inbox"),
+            normalized("agent/inbox").unwrap()
+        );
+        assert_eq!(
+            json!("https://agents.example.com/inbox"),
+            normalized("/inbox").unwrap()
+        );
+    }
+
+    #[test]
+    fn shorthand_endpoint_gets_scheme_of_base() {
+        assert_eq!(
+            json!("https://10.0.0.2:8020/"),
+            normalized("10.0.0.2:8020").unwrap()
+        );
+    }
+
+    #[test]
+    fn absolute_endpoint_is_kept() {
+        assert_eq!(
+            json!("wss://mediator.example.org/ws"),
+            normalized("wss://mediator.example.org/ws").unwrap()
+        );
+    }
+
+    #[test]
+    fn endpoint_with_other_scheme_is_rejected() {
+        assert!(matches!(
+            normalized("ftp://files.example.org/inbox"),
+            Err(DidIndyError::InvalidServiceEndpoint(_))
+        ));
+        assert!(matches!(
+            normalized("mailto:admin@example.org"),
+            Err(DidIndyError::InvalidServiceEndpoint(_))
+        ));
+    }
+
+    #[test]
+    fn endpoints_are_kept_without_base() {
+        let doc = doc_with_endpoint("agent/inbox")
+            .with_endpoint_base(None)
+            .to_value()
+            .unwrap();
+        assert_eq!(json!("agent/inbox"), doc["service"][0]["serviceEndpoint"]);
+    }
+
+    #[test]
+    fn did_core_context_added() {
+        let mut doc = json!({"id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM"});
+        add_did_core_context(&mut doc);
+        assert_eq!(json!(DID_CORE_CONTEXT), doc["@context"]);
+
+        let mut doc = json!({"@context": "https://w3id.org/security/suites/ed25519-2020/v1"});
+        add_did_core_context(&mut doc);
+        assert_eq!(
+            json!([
+                DID_CORE_CONTEXT,
+                "https://w3id.org/security/suites/ed25519-2020/v1"
+            ]),
+            doc["@context"]
+        );
+    }
+
+    #[test]
+    fn did_core_context_moved_first() {
+        let mut doc = json!({"@context": [
+            "https://identity.foundation/didcomm-messaging/service-endpoint/v1",
+            DID_CORE_CONTEXT
+        ]});
+        add_did_core_context(&mut doc);
+        assert_eq!(
+            json!([
+                DID_CORE_CONTEXT,
+                "https://identity.foundation/didcomm-messaging/service-endpoint/v1"
+            ]),
+            doc["@context"]
+        );
+    }
+
+    #[test]
+    fn validate_diddoc_with_context_as_string() {
+        let diddoc_content = json!({
+            "@context" : "https://www.w3.org/ns/did/v1"
+        });
+        assert!(validate_diddoc_content(&diddoc_content))
+    }
+
+    #[test]
+    fn validate_diddoc_without_context() {
+        let diddoc_content = json!({
+        "service": [
+          {
+            "id": "did:indy:sovrin:123456#did-communication",
+            "type": "did-communication",
+            "serviceEndpoint": "https://example.com",
+            "recipientKeys": [ "#verkey" ],
+            "routingKeys": [ ]
+          }
+        ]
+        });
+        assert!(validate_diddoc_content(&diddoc_content))
+    }
+
+    #[test]
+    fn validate_diddoc_with_context_as_array() {
+        let diddoc_content = json!({
+            "@context" : [
+                "https://www.w3.org/ns/did/v1",
+                "https://identity.foundation/didcomm-messaging/service-endpoint/v1"
+        ],
+        });
+        assert!(validate_diddoc_content(&diddoc_content))
     }
 
     #[test]
@@ -546,4 +1992,212 @@ mod tests {
         });
         assert!(!validate_diddoc_content(&diddoc_content))
     }
+
+    fn doc_with_legacy_endpoints(endpoints: &[(&str, &str)]) -> DidDocument {
+        DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            Some(Endpoint {
+                endpoint: endpoints
+                    .iter()
+                    .map(|(service, endpoint)| {
+                        (service.to_string(), EndpointValue::from(*endpoint))
+                    })
+                    .collect(),
+            }),
+            None,
+        )
+    }
+
+    #[test]
+    fn legacy_endpoints_with_allowed_schemes_kept() {
+        let doc = doc_with_legacy_endpoints(&[
+            ("a-http", "http://agent.com"),
+            ("b-https", "https://agent.com"),
+            ("c-ws", "ws://agent.com"),
+            ("d-wss", "WSS://agent.com"),
+            ("e-didcomm", "didcomm://agent.com"),
+        ]);
+
+        let (value, warnings) = doc.to_value_with_warnings().unwrap();
+
+        assert_eq!(5, value["service"].as_array().unwrap().len());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn legacy_endpoints_with_other_schemes_left_out() {
+        let doc = doc_with_legacy_endpoints(&[
+            ("a-javascript", "javascript:alert(1)"),
+            ("b-file", "file:///etc/passwd"),
+            ("c-ftp", "ftp://agent.com"),
+            ("d-text", "not a url"),
+            ("e-https", "https://agent.com"),
+        ]);
+
+        let (value, warnings) = doc.clone().to_value_with_warnings().unwrap();
+
+        assert_eq!(
+            json!([{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#e-https",
+                "type": "e-https",
+                "serviceEndpoint": "https://agent.com"
+            }]),
+            value["service"]
+        );
+        assert_eq!(
+            vec![
+                "Invalid service endpoint javascript:alert(1), service a-javascript left out",
+                "Invalid service endpoint file:///etc/passwd, service b-file left out",
+                "Invalid service endpoint ftp://agent.com, service c-ftp left out",
+                "Invalid service endpoint not a url, service d-text left out",
+            ],
+            warnings
+        );
+
+        let err = doc.with_strict_endpoints(true).to_value().unwrap_err();
+        assert!(matches!(
+            err,
+            DidIndyError::InvalidServiceEndpoint(endpoint) if endpoint == "javascript:alert(1)"
+        ));
+    }
+
+    #[test]
+    fn legacy_endpoint_schemes_configurable() {
+        let doc =
+            doc_with_legacy_endpoints(&[("ftp", "ftp://agent.com"), ("http", "http://a.com")])
+                .with_endpoint_validation(EndpointValidation {
+                    schemes: vec![String::from("ftp")],
+                    ..EndpointValidation::default()
+                });
+
+        let value = doc.to_value().unwrap();
+
+        assert_eq!(1, value["service"].as_array().unwrap().len());
+        assert_eq!("ftp://agent.com", value["service"][0]["serviceEndpoint"]);
+    }
+
+    #[test]
+    fn diddoc_content_endpoints_validated_if_enabled() {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            Some(json!({
+                "service": [{
+                    "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#didcomm",
+                    "type": "DIDCommMessaging",
+                    "serviceEndpoint": [{"uri": "https://agent.com"}, {"uri": "file:///tmp/agent"}]
+                }, {
+                    "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#linked-domain",
+                    "type": "LinkedDomains",
+                    "serviceEndpoint": "https://example.com"
+                }]
+            })),
+        );
+        assert_eq!(
+            2,
+            doc.to_value().unwrap()["service"].as_array().unwrap().len()
+        );
+
+        let (value, warnings) = doc
+            .with_endpoint_validation(EndpointValidation {
+                diddoc_content: true,
+                ..EndpointValidation::default()
+            })
+            .to_value_with_warnings()
+            .unwrap();
+
+        assert_eq!(1, value["service"].as_array().unwrap().len());
+        assert_eq!("LinkedDomains", value["service"][0]["type"]);
+        assert_eq!(
+            vec!["Invalid service endpoint file:///tmp/agent, service did:indy:idunion:QowxFtwciWceMFr7WbwnM#didcomm left out"],
+            warnings
+        );
+    }
+
+    fn doc_with_linked_domains(endpoint: Value) -> DidDocument {
+        DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            Some(serde_json::from_value(json!({ "endpoint": endpoint })).unwrap()),
+            None,
+        )
+    }
+
+    #[test]
+    fn linked_domains_with_single_origin() {
+        let doc = doc_with_linked_domains(json!({ "linked_domains": "https://example.com" }));
+
+        assert_eq!(
+            json!([{
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#linked-domains",
+                "type": "LinkedDomains",
+                "serviceEndpoint": { "origins": ["https://example.com"] }
+            }]),
+            doc.to_value().unwrap()["service"]
+        );
+    }
+
+    #[test]
+    fn linked_domains_with_origins_deduplicated() {
+        let doc = doc_with_linked_domains(json!({
+            "endpoint": "https://agent.com",
+            "linked_domains": [
+                "https://example.com",
+                "https://example.com/",
+                "https://EXAMPLE.org",
+                "https://example.org:443"
+            ]
+        }));
+
+        let value = doc.to_value().unwrap();
+
+        assert_eq!(2, value["service"].as_array().unwrap().len());
+        assert_eq!("did-communication", value["service"][0]["type"]);
+        assert_eq!(
+            json!({ "origins": ["https://example.com", "https://example.org"] }),
+            value["service"][1]["serviceEndpoint"]
+        );
+    }
+
+    #[test]
+    fn linked_domains_with_invalid_origins() {
+        let doc = doc_with_linked_domains(json!({
+            "linked_domains": ["https://example.com/path", "javascript:alert(1)", "https://example.com"]
+        }));
+
+        let (value, warnings) = doc.clone().to_value_with_warnings().unwrap();
+        assert_eq!(
+            json!({ "origins": ["https://example.com"] }),
+            value["service"][0]["serviceEndpoint"]
+        );
+        assert_eq!(
+            vec![
+                "Invalid service endpoint https://example.com/path, origin left out",
+                "Invalid service endpoint javascript:alert(1), origin left out",
+            ],
+            warnings
+        );
+
+        assert!(matches!(
+            doc.with_strict_endpoints(true).to_value(),
+            Err(DidIndyError::InvalidServiceEndpoint(origin)) if origin == "https://example.com/path"
+        ));
+
+        let (value, warnings) = doc_with_linked_domains(json!({ "linked_domains": "not a url" }))
+            .to_value_with_warnings()
+            .unwrap();
+        assert!(value["service"].is_null());
+        assert_eq!(
+            vec![
+                "Invalid service endpoint not a url, origin left out",
+                "No valid origin, LinkedDomains service left out",
+            ],
+            warnings
+        );
+    }
 }
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use super::config::ResolveOptions;
+use super::error::DidIndyResult;
+use super::ledger::LedgerRequester;
+use super::resolver::Resolver;
+
+/// What a resolver offers beyond resolving and dereferencing
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Namespace the resolver is bound to, None if it takes DIDs of any namespace
+    pub namespace: Option<String>,
+    /// Whether results are cached
+    pub caching: bool,
+    /// Whether results are signed
+    pub signing: bool,
+}
+
+/// Resolution and dereferencing of did:indy DIDs independent of the ledger
+/// backend. Object safe, so resolvers for different backends can be stored
+/// together as `Box<dyn DidIndyResolver + Send + Sync>`. Implemented by every
+/// `Resolver`; direct users of a `Resolver` call its methods without dynamic
+/// dispatch.
+pub trait DidIndyResolver {
+    /// Whether the DID or DID URL is valid did:indy syntax
+    fn supports(&self, did: &str) -> bool;
+
+    fn resolve_with_options(&self, did: &str, options: &ResolveOptions) -> DidIndyResult<String>;
+
+    fn dereference_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<String>;
+
+    fn capabilities(&self) -> Capabilities;
+
+    fn resolve(&self, did: &str) -> DidIndyResult<String> {
+        self.resolve_with_options(did, &ResolveOptions::default())
+    }
+
+    fn dereference(&self, did_url: &str) -> DidIndyResult<String> {
+        self.dereference_with_options(did_url, &ResolveOptions::default())
+    }
+
+    /// The DID document of the resolution result, without metadata
+    fn resolve_document(&self, did: &str) -> DidIndyResult<Value> {
+        let mut result: Value = serde_json::from_str(&self.resolve(did)?)?;
+        Ok(result["didDocument"].take())
+    }
+}
+
+impl<T: LedgerRequester> DidIndyResolver for Resolver<T> {
+    fn supports(&self, did: &str) -> bool {
+        Resolver::supports(self, did)
+    }
+
+    fn resolve_with_options(&self, did: &str, options: &ResolveOptions) -> DidIndyResult<String> {
+        Resolver::resolve_string_with_options(self, did, options)
+    }
+
+    fn dereference_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<String> {
+        Resolver::dereference_string_with_options(self, did_url, options)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            namespace: self.namespace().map(String::from),
+            caching: self.config().cache.is_some(),
+            signing: self.config().signing.is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ResolverConfig;
+    use crate::tests::{nym_reply, MockLedger};
+    use futures_util::future::LocalBoxFuture;
+    use indy_vdr::ledger::RequestBuilder;
+    use indy_vdr::pool::{PreparedRequest, ProtocolVersion};
+
+    // Ledger of a different type than MockLedger, answering every request with
+    // the NYM of another DID
+    struct FixedLedger;
+
+    impl LedgerRequester for FixedLedger {
+        fn request_builder(&self) -> RequestBuilder {
+            RequestBuilder::new(ProtocolVersion::Node1_4)
+        }
+
+        fn submit<'a>(
+            &'a self,
+            _request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+            let reply = nym_reply(
+                "V4SGRU86Z58d6TV7PBUe6f",
+                "~CoRER63DVYnWZtK8uAzNbx",
+                Some(serde_json::json!({})),
+            );
+            Box::pin(async move { Ok(reply) })
+        }
+    }
+
+    #[test]
+    fn resolvers_of_different_backends_behind_trait() {
+        let mock = MockLedger::new().reply(nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        ));
+        let resolvers: Vec<(&str, Box<dyn DidIndyResolver + Send + Sync>)> = vec![
+            (
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                Box::new(Resolver::new(mock).with_namespace("idunion")),
+            ),
+            (
+                "did:indy:sovrin:V4SGRU86Z58d6TV7PBUe6f",
+                Box::new(Resolver::with_config(
+                    FixedLedger,
+                    ResolverConfig::default(),
+                )),
+            ),
+        ];
+
+        for (did, resolver) in &resolvers {
+            assert!(resolver.supports(did));
+            let document = resolver.resolve_document(did).unwrap();
+            assert_eq!(*did, document["id"]);
+        }
+        assert_eq!(
+            Capabilities {
+                namespace: Some(String::from("idunion")),
+                caching: false,
+                signing: false,
+            },
+            resolvers[0].1.capabilities()
+        );
+        assert_eq!(None, resolvers[1].1.capabilities().namespace);
+    }
+}
@@ -1,6 +1,9 @@
-use indy_vdr::common::error::VdrError;
+use indy_vdr::common::error::{VdrError, VdrErrorKind};
+use serde_json::Value;
 use thiserror::Error;
 
+use super::validation::ValidationIssue;
+
 pub type DidIndyResult<T> = std::result::Result<T, DidIndyError>;
 
 #[derive(Debug, Error)]
@@ -11,28 +14,178 @@ pub enum DidIndyError {
     DateTimeError(#[from] chrono::ParseError),
     #[error("Namespace not supported")]
     NamespaceNotSupported,
+    #[error("No pool added for namespace {0}")]
+    UnknownNamespace(String),
+    #[error("No pool available for namespace {0}")]
+    PoolUnavailable(String),
     #[error("Query parameter not supported")]
     QueryParameterNotSupported,
     #[error("Empty data")]
     EmptyData,
     #[error("Invalid DID URL")]
     InvalidDidUrl,
+    #[error("DID URL has the {misplaced} after the {preceding}, the components must be ordered path, query, fragment")]
+    MisorderedDidUrl {
+        misplaced: &'static str,
+        preceding: &'static str,
+    },
+    #[error("No namespace given for {0}, which is not a did:indy DID")]
+    NamespaceRequired(String),
+    #[error("Identifier {input} is ambiguous, it may be any of {}", .candidates.join(", "))]
+    AmbiguousIdentifier {
+        input: String,
+        candidates: Vec<String>,
+    },
+    #[error("DID URL has both {first} and {second}, which may refer to different versions")]
+    ConflictingQueryParameters {
+        first: &'static str,
+        second: &'static str,
+    },
     #[error("Invalid DID Document")]
     InvalidDidDoc,
+    #[error("Invalid service endpoint {0}")]
+    InvalidServiceEndpoint(String),
+    #[error("Services of diddocContent and the endpoint ATTRIB share the id {0}")]
+    ServiceIdConflict(String),
+    #[error("Verification methods of the NYM and diddocContent share the id {0}")]
+    VerificationMethodIdConflict(String),
+    #[error("DID Document violates DID Core: {0:?}")]
+    DidDocumentValidation(Vec<ValidationIssue>),
+    #[error("Content could not be parsed into the requested type: {message}")]
+    ParseContent { message: String, content: Value },
+    #[error("Invalid timestamp {0}")]
+    InvalidTimestamp(i64),
     #[error("Object family not supported")]
     ObjectFamilyNotSupported,
-    #[error("Object family version not supported")]
-    VersionNotSupported,
+    #[error("Anoncreds version {version} not supported, supported versions: {}", .supported.join(", "))]
+    UnsupportedAnoncredsVersion {
+        version: String,
+        supported: Vec<String>,
+    },
     #[error("Object type not supported")]
     ObjectTypeNotSuported,
+    #[error(
+        "Ledger object {segment} not supported{}, supported objects: {}",
+        .suggestion.as_ref().map(|s| format!(" (did you mean {}?)", s)).unwrap_or_default(),
+        .supported.join(", ")
+    )]
+    UnsupportedLedgerObject {
+        segment: String,
+        supported: Vec<String>,
+        suggestion: Option<String>,
+    },
+    #[error("Revocation index {index} outside of the registry of {max_cred_num} credentials")]
+    RevocationIndexOutOfRange { index: u64, max_cred_num: u64 },
     #[error("Object not found")]
     NotFound,
+    #[error(
+        "No revocation registry entry before the requested time, first entry at {first_entry}"
+    )]
+    NotFoundBefore { first_entry: i64 },
     #[error("Function not implemented")]
     NotImplemented,
+    #[error("Ledger does not support listing the revocation registries of a cred def")]
+    RevRegListingNotSupported,
+    #[error("Key transformation not supported")]
+    UnsupportedTransform,
+    #[error("Representation {0} not supported")]
+    RepresentationNotSupported(String),
+    #[error("Invalid signing key")]
+    InvalidSigningKey,
+    #[error("Could not sign the ledger request: {0}")]
+    RequestSigningFailed(String),
+    #[error("Ledger rejected the {txn_type} request for a version and has no older form keeping it: {reason}")]
+    VersionedRequestRejected { txn_type: String, reason: String },
+    #[error("Ledger request timed out")]
+    RequestTimeout,
+    #[error("Resolution timed out")]
+    ResolutionTimeout,
+    #[error("Trust policy not satisfied, missing signatures of {}", .missing.join(", "))]
+    PolicyNotSatisfied { missing: Vec<String> },
+    #[error("Ledger not reachable: {0}")]
+    LedgerUnreachable(String),
+    #[error(
+        "Unknown network {namespace}, could not obtain genesis transactions from {url}: {reason}"
+    )]
+    UnknownNetwork {
+        namespace: String,
+        url: String,
+        reason: String,
+    },
+    #[error("No pool with genesis hash {hash} configured for namespace {namespace}")]
+    GenesisHashNotFound { namespace: String, hash: String },
     #[error("VDR error")]
     VdrError(#[from] VdrError),
 }
 
+impl DidIndyError {
+    /// Error code of the DID Resolution specification reported in the resolution
+    /// or dereferencing metadata
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            DidIndyError::InvalidDidUrl
+            | DidIndyError::NamespaceRequired(_)
+            | DidIndyError::AmbiguousIdentifier { .. } => "invalidDid",
+            DidIndyError::DateTimeError(_)
+            | DidIndyError::InvalidTimestamp(_)
+            | DidIndyError::QueryParameterNotSupported
+            | DidIndyError::MisorderedDidUrl { .. }
+            | DidIndyError::ConflictingQueryParameters { .. }
+            | DidIndyError::ObjectFamilyNotSupported
+            | DidIndyError::UnsupportedAnoncredsVersion { .. }
+            | DidIndyError::ObjectTypeNotSuported
+            | DidIndyError::UnsupportedLedgerObject { .. } => "invalidDidUrl",
+            DidIndyError::NamespaceNotSupported | DidIndyError::UnknownNamespace(_) => {
+                "methodNotSupported"
+            }
+            DidIndyError::EmptyData
+            | DidIndyError::NotFound
+            | DidIndyError::NotFoundBefore { .. } => "notFound",
+            DidIndyError::UnsupportedTransform => "unsupportedTransform",
+            DidIndyError::RepresentationNotSupported(_) => "representationNotSupported",
+            DidIndyError::UnknownNetwork { .. } | DidIndyError::GenesisHashNotFound { .. } => {
+                "unknownNetwork"
+            }
+            DidIndyError::ParsingError(_)
+            | DidIndyError::ParseContent { .. }
+            | DidIndyError::RevocationIndexOutOfRange { .. }
+            | DidIndyError::InvalidDidDoc
+            | DidIndyError::InvalidServiceEndpoint(_)
+            | DidIndyError::ServiceIdConflict(_)
+            | DidIndyError::VerificationMethodIdConflict(_)
+            | DidIndyError::DidDocumentValidation(_)
+            | DidIndyError::NotImplemented
+            | DidIndyError::RevRegListingNotSupported
+            | DidIndyError::PolicyNotSatisfied { .. }
+            | DidIndyError::InvalidSigningKey
+            | DidIndyError::RequestSigningFailed(_)
+            | DidIndyError::VersionedRequestRejected { .. }
+            | DidIndyError::PoolUnavailable(_)
+            | DidIndyError::RequestTimeout
+            | DidIndyError::ResolutionTimeout
+            | DidIndyError::LedgerUnreachable(_)
+            | DidIndyError::VdrError(_) => "internalError",
+        }
+    }
+
+    /// Whether the ledger could not be reached, as opposed to an answer of the
+    /// ledger or an invalid request
+    pub fn is_transport(&self) -> bool {
+        match self {
+            DidIndyError::RequestTimeout
+            | DidIndyError::ResolutionTimeout
+            | DidIndyError::LedgerUnreachable(_) => true,
+            DidIndyError::VdrError(err) => matches!(
+                err.kind(),
+                VdrErrorKind::Connection
+                    | VdrErrorKind::PoolNoConsensus
+                    | VdrErrorKind::PoolTimeout
+            ),
+            _ => false,
+        }
+    }
+}
+
 // impl fmt::Display for DidIndyError {
 //     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 //         write!(f, "DID Indy Error")
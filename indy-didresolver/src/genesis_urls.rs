@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use indy_vdr::pool::{PoolTransactions, SharedPool};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+use super::error::{DidIndyError, DidIndyResult};
+use super::networks::{build_pool, NetworkProvider, GENESIS_FILENAME};
+
+// Genesis files of the indy-did-networks repository
+static WELL_KNOWN_TEMPLATE: &str =
+    "https://raw.githubusercontent.com/IDunion/indy-did-networks/main/{path}/pool_transactions_genesis.json";
+static WELL_KNOWN_NAMESPACES: [&str; 6] = [
+    "sovrin",
+    "sovrin:staging",
+    "sovrin:builder",
+    "idunion",
+    "indicio",
+    "bcovrin:test",
+];
+
+struct GenesisSource {
+    url_template: String,
+    sha256: Option<String>,
+}
+
+/// Provides pools from genesis files downloaded from a URL configured per
+/// namespace. A file is downloaded when its namespace is first used, verified
+/// against the SHA-256 hash configured for the namespace, if any, and cached in
+/// a directory. Cached files older than the refresh interval are downloaded
+/// again; if that fails, the cached file is used. Cached files not matching the
+/// configured hash are never used and downloaded again.
+///
+/// In URL templates, `{namespace}` is replaced by the namespace and `{path}` by
+/// the namespace with `:` replaced by `/`, e.g. `sovrin/staging`.
+pub struct GenesisUrlProvider {
+    sources: HashMap<String, GenesisSource>,
+    cache_dir: PathBuf,
+    refresh_interval: Duration,
+    agent: ureq::Agent,
+}
+
+impl GenesisUrlProvider {
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        GenesisUrlProvider {
+            sources: HashMap::new(),
+            cache_dir: cache_dir.into(),
+            refresh_interval: Duration::from_secs(24 * 60 * 60),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    /// Maps the namespace to the URL template of its genesis file. With a hash,
+    /// downloaded files not matching the hex encoded SHA-256 hash are rejected.
+    pub fn with_namespace(
+        mut self,
+        namespace: &str,
+        url_template: &str,
+        sha256: Option<&str>,
+    ) -> Self {
+        self.sources.insert(
+            namespace.to_string(),
+            GenesisSource {
+                url_template: url_template.to_string(),
+                sha256: sha256.map(str::to_lowercase),
+            },
+        );
+        self
+    }
+
+    /// Maps the public networks of the indy-did-networks repository, without
+    /// hashes. Namespaces mapped before are kept.
+    pub fn with_well_known_networks(mut self) -> Self {
+        for namespace in WELL_KNOWN_NAMESPACES {
+            if !self.sources.contains_key(namespace) {
+                self = self.with_namespace(namespace, WELL_KNOWN_TEMPLATE, None);
+            }
+        }
+        self
+    }
+
+    /// Age after which a cached genesis file is downloaded again, one day by
+    /// default
+    pub fn with_refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    /// URL of the genesis file of the namespace, if the namespace is mapped
+    pub fn url_for(&self, namespace: &str) -> Option<String> {
+        self.sources.get(namespace).map(|source| {
+            source
+                .url_template
+                .replace("{namespace}", namespace)
+                .replace("{path}", &namespace.replace(':', "/"))
+        })
+    }
+
+    /// Path of the cached genesis file of the namespace, downloading it if it is
+    /// not cached or older than the refresh interval. Fails with `UnknownNetwork`
+    /// if no valid genesis file can be obtained.
+    pub fn genesis_file(&self, namespace: &str) -> DidIndyResult<PathBuf> {
+        let url = self
+            .url_for(namespace)
+            .ok_or(DidIndyError::NamespaceNotSupported)?;
+        let path = self.cache_path(namespace);
+        if self.is_fresh(&path) && self.is_verified(namespace, &path) {
+            return Ok(path);
+        }
+
+        match self.download(namespace, &url) {
+            Ok(genesis) => {
+                fs::create_dir_all(path.parent().unwrap())
+                    .and_then(|_| fs::write(&path, genesis))
+                    .map_err(|err| unknown_network(namespace, &url, &err.to_string()))?;
+                debug!("Cached genesis file of {} from {}", namespace, url);
+                Ok(path)
+            }
+            Err(err) if path.is_file() && self.is_verified(namespace, &path) => {
+                warn!("Using outdated genesis file of {}, {}", namespace, err);
+                Ok(path)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn download(&self, namespace: &str, url: &str) -> DidIndyResult<String> {
+        let genesis = self
+            .agent
+            .get(url)
+            .call()
+            .map_err(|err| unknown_network(namespace, url, &err.to_string()))?
+            .into_string()
+            .map_err(|err| unknown_network(namespace, url, &err.to_string()))?;
+
+        if let Some(expected) = &self.sources[namespace].sha256 {
+            let actual = sha256_hex(genesis.as_bytes());
+            if &actual != expected {
+                return Err(unknown_network(
+                    namespace,
+                    url,
+                    &format!("SHA-256 hash {} does not match {}", actual, expected),
+                ));
+            }
+        }
+        PoolTransactions::from_json(&genesis)
+            .map_err(|err| unknown_network(namespace, url, &err.to_string()))?;
+        Ok(genesis)
+    }
+
+    fn cache_path(&self, namespace: &str) -> PathBuf {
+        let mut path = self.cache_dir.clone();
+        // Namespaces must not point outside of the directory
+        for segment in namespace.split(':') {
+            path.push(segment.trim_start_matches('.'));
+        }
+        path.push(GENESIS_FILENAME);
+        path
+    }
+
+    // Whether the cached file matches the hash configured for the namespace, a
+    // file changed on disk or cached before the hash was configured does not
+    fn is_verified(&self, namespace: &str, path: &Path) -> bool {
+        let expected = match &self.sources[namespace].sha256 {
+            Some(expected) => expected,
+            None => return true,
+        };
+        match fs::read(path) {
+            Ok(genesis) if &sha256_hex(&genesis) == expected => true,
+            Ok(_) => {
+                warn!(
+                    "Cached genesis file of {} does not match its SHA-256 hash",
+                    namespace
+                );
+                false
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn is_fresh(&self, path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age < self.refresh_interval)
+            .unwrap_or(false)
+    }
+}
+
+impl NetworkProvider<SharedPool> for GenesisUrlProvider {
+    fn pool_for(&self, namespace: &str) -> Option<SharedPool> {
+        self.sources.get(namespace)?;
+        let pool = self.genesis_file(namespace).and_then(|path| {
+            PoolTransactions::from_json_file(&path)
+                .map_err(Into::into)
+                .and_then(build_pool)
+        });
+        match pool {
+            Ok(pool) => Some(pool),
+            Err(err) => {
+                warn!("Could not initialize pool for {}, {}", namespace, err);
+                None
+            }
+        }
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn unknown_network(namespace: &str, url: &str, reason: &str) -> DidIndyError {
+    DidIndyError::UnknownNetwork {
+        namespace: namespace.to_string(),
+        url: url.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn genesis() -> String {
+        fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../networks/local/pool_transactions_genesis.json"),
+        )
+        .unwrap()
+    }
+
+    // Serves the body to every GET request. Returns the base URL and the number
+    // of requests served so far.
+    fn fixture_server(body: String) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (base_url, served)
+    }
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "indy-didresolver-genesis-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::remove_dir_all(&path).ok();
+        path
+    }
+
+    #[test]
+    fn url_templates() {
+        let provider = GenesisUrlProvider::new(cache_dir("templates"))
+            .with_namespace("test", "https://genesis.example/{namespace}.json", None)
+            .with_well_known_networks();
+
+        assert_eq!(
+            Some(String::from("https://genesis.example/test.json")),
+            provider.url_for("test")
+        );
+        assert_eq!(
+            Some(String::from("https://raw.githubusercontent.com/IDunion/indy-did-networks/main/sovrin/staging/pool_transactions_genesis.json")),
+            provider.url_for("sovrin:staging")
+        );
+        assert_eq!(None, provider.url_for("local"));
+    }
+
+    #[test]
+    fn downloads_and_caches_genesis() {
+        let genesis = genesis();
+        let (base_url, served) = fixture_server(genesis.clone());
+        let sha256 = format!("{:x}", Sha256::digest(genesis.as_bytes()));
+        let cache_dir = cache_dir("cached");
+        let provider = GenesisUrlProvider::new(&cache_dir).with_namespace(
+            "local:test",
+            &format!("{}/{{path}}/genesis.json", base_url),
+            Some(&sha256),
+        );
+
+        let path = provider.genesis_file("local:test").unwrap();
+        assert_eq!(cache_dir.join("local/test").join(GENESIS_FILENAME), path);
+        assert_eq!(genesis, fs::read_to_string(&path).unwrap());
+
+        provider.genesis_file("local:test").unwrap();
+        assert_eq!(1, served.load(Ordering::SeqCst));
+
+        let provider = provider.with_refresh_interval(Duration::ZERO);
+        provider.genesis_file("local:test").unwrap();
+        assert_eq!(2, served.load(Ordering::SeqCst));
+        fs::remove_dir_all(cache_dir).ok();
+    }
+
+    #[test]
+    fn cached_genesis_not_matching_hash_downloaded_again() {
+        let genesis = genesis();
+        let (base_url, served) = fixture_server(genesis.clone());
+        let cache_dir = cache_dir("tampered");
+        let provider = GenesisUrlProvider::new(&cache_dir).with_namespace(
+            "local",
+            &format!("{}/genesis.json", base_url),
+            Some(&sha256_hex(genesis.as_bytes())),
+        );
+        let path = cache_dir.join("local").join(GENESIS_FILENAME);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{}").unwrap();
+
+        assert_eq!(path, provider.genesis_file("local").unwrap());
+        assert_eq!(1, served.load(Ordering::SeqCst));
+        assert_eq!(genesis, fs::read_to_string(&path).unwrap());
+        fs::remove_dir_all(cache_dir).ok();
+    }
+
+    #[test]
+    fn outdated_genesis_not_matching_hash_not_used() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/genesis.json", listener.local_addr().unwrap());
+        drop(listener);
+        let cache_dir = cache_dir("outdated");
+        let provider = GenesisUrlProvider::new(&cache_dir)
+            .with_namespace("local", &url, Some(&sha256_hex(genesis().as_bytes())))
+            .with_refresh_interval(Duration::ZERO);
+        let path = cache_dir.join("local").join(GENESIS_FILENAME);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, genesis().replace("Node1", "Evil1")).unwrap();
+
+        let err = provider.genesis_file("local").unwrap_err();
+        assert_eq!("unknownNetwork", err.error_code());
+        fs::remove_dir_all(cache_dir).ok();
+    }
+
+    #[test]
+    fn hash_mismatch_is_unknown_network() {
+        let (base_url, _) = fixture_server(genesis());
+        let url = format!("{}/genesis.json", base_url);
+        let provider = GenesisUrlProvider::new(cache_dir("mismatch")).with_namespace(
+            "local",
+            &url,
+            Some(&format!("{:x}", Sha256::digest(b"other genesis"))),
+        );
+
+        let err = provider.genesis_file("local").unwrap_err();
+        assert_eq!("unknownNetwork", err.error_code());
+        assert!(matches!(
+            &err,
+            DidIndyError::UnknownNetwork { url: attempted, .. } if attempted == &url
+        ));
+        assert!(err.to_string().contains("does not match"));
+        assert!(provider.pool_for("local").is_none());
+    }
+
+    #[test]
+    fn unreachable_url_is_unknown_network() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/genesis.json", listener.local_addr().unwrap());
+        drop(listener);
+        let provider =
+            GenesisUrlProvider::new(cache_dir("unreachable")).with_namespace("local", &url, None);
+
+        let err = provider.genesis_file("local").unwrap_err();
+        assert!(err.to_string().contains(&url));
+    }
+}
@@ -0,0 +1,241 @@
+use regex::Regex;
+use serde::Serialize;
+
+use super::did::{DidUrl, DidUrlBuilder};
+use super::error::{DidIndyError, DidIndyResult};
+
+static DID_INDY_PREFIX: &str = "did:indy:";
+static DID_SOV_PREFIX: &str = "did:sov:";
+static UNQUALIFIED_DID_PATTERN: &str =
+    "^[123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz]{21,22}$";
+
+// Markers of the legacy anoncreds identifiers following the issuer DID
+static SCHEMA_MARKER: &str = "2";
+static CRED_DEF_MARKER: &str = "3";
+static REV_REG_DEF_MARKER: &str = "4";
+static SIGNATURE_TYPE: &str = "CL";
+static REV_REG_TYPE: &str = "CL_ACCUM";
+
+/// Kind of identifier an input of `Resolver::resolve_any` was taken as
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum InputType {
+    /// did:indy DID or DID URL, used as it is
+    DidIndy,
+    /// did:sov DID, e.g. `did:sov:Dk1fRRTtNazyMuK2cr64wp`
+    DidSov,
+    /// DID without method, e.g. the issuer DID of a credential
+    UnqualifiedDid,
+    /// Legacy schema id, `<did>:2:<name>:<version>`
+    SchemaId,
+    /// Legacy cred def id, `<did>:3:CL:<schema seqNo>:<tag>`
+    CredDefId,
+    /// Legacy revocation registry id,
+    /// `<did>:4:<did>:3:CL:<schema seqNo>:<cred def tag>:CL_ACCUM:<tag>`
+    RevRegDefId,
+}
+
+/// How an input was converted to a did:indy DID URL
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Interpretation {
+    pub input_type: InputType,
+    pub did_url: String,
+}
+
+/// Converts a did:indy DID or DID URL, a did:sov or unqualified DID, or a
+/// legacy anoncreds identifier to a did:indy DID URL. Inputs other than did:indy
+/// are taken to be of the namespace, they cannot be converted without one.
+/// Fails with `AmbiguousIdentifier` if the input can be read as more than one
+/// DID URL, e.g. a schema id whose name or version contains a colon.
+pub fn interpret(input: &str, namespace: Option<&str>) -> DidIndyResult<Interpretation> {
+    let input = input.trim();
+    if input.starts_with(DID_INDY_PREFIX) {
+        DidUrl::from_str(input)?;
+        return Ok(Interpretation {
+            input_type: InputType::DidIndy,
+            did_url: input.to_string(),
+        });
+    }
+    if input.starts_with("did:") && !input.starts_with(DID_SOV_PREFIX) {
+        return Err(DidIndyError::NamespaceNotSupported);
+    }
+    let namespace = namespace.ok_or_else(|| DidIndyError::NamespaceRequired(input.to_string()))?;
+
+    if let Some(did) = input.strip_prefix(DID_SOV_PREFIX) {
+        let did_url = format!("{}{}:{}", DID_INDY_PREFIX, namespace, did);
+        DidUrl::from_str(&did_url)?;
+        return Ok(Interpretation {
+            input_type: InputType::DidSov,
+            did_url,
+        });
+    }
+
+    let parts: Vec<&str> = input.split(':').collect();
+    if !Regex::new(UNQUALIFIED_DID_PATTERN)
+        .unwrap()
+        .is_match(parts[0])
+    {
+        return Err(DidIndyError::InvalidDidUrl);
+    }
+    let did = format!("{}{}:{}", DID_INDY_PREFIX, namespace, parts[0]);
+    let builder = DidUrlBuilder::new(&did)?;
+    let (input_type, candidates): (InputType, Vec<DidUrlBuilder>) = match parts[1..] {
+        [] => {
+            return Ok(Interpretation {
+                input_type: InputType::UnqualifiedDid,
+                did_url: did,
+            })
+        }
+        [marker, ref rest @ ..] if marker == SCHEMA_MARKER && rest.len() >= 2 => {
+            // Any of the colons may separate the name from the version
+            let candidates = (1..rest.len())
+                .map(|i| {
+                    builder
+                        .clone()
+                        .schema(&rest[..i].join(":"), &rest[i..].join(":"))
+                })
+                .collect();
+            (InputType::SchemaId, candidates)
+        }
+        [marker, signature_type, seq_no, ref tag @ ..]
+            if marker == CRED_DEF_MARKER && signature_type == SIGNATURE_TYPE && !tag.is_empty() =>
+        {
+            let seq_no = seq_no.parse().map_err(|_| DidIndyError::InvalidDidUrl)?;
+            (
+                InputType::CredDefId,
+                vec![builder.claim_def(seq_no, &tag.join(":"))],
+            )
+        }
+        [marker, issuer, cred_def_marker, signature_type, seq_no, ref rest @ ..]
+            if marker == REV_REG_DEF_MARKER
+                && issuer == parts[0]
+                && cred_def_marker == CRED_DEF_MARKER
+                && signature_type == SIGNATURE_TYPE =>
+        {
+            let seq_no = seq_no.parse().map_err(|_| DidIndyError::InvalidDidUrl)?;
+            // Any CL_ACCUM may separate the tag of the cred def from the tag of
+            // the revocation registry
+            let candidates = (0..rest.len())
+                .filter(|i| rest[*i] == REV_REG_TYPE)
+                .map(|i| {
+                    builder.clone().rev_reg_def(
+                        seq_no,
+                        &rest[..i].join(":"),
+                        &rest[i + 1..].join(":"),
+                    )
+                })
+                .collect();
+            (InputType::RevRegDefId, candidates)
+        }
+        _ => return Err(DidIndyError::InvalidDidUrl),
+    };
+
+    match candidates.as_slice() {
+        [] => Err(DidIndyError::InvalidDidUrl),
+        [candidate] => Ok(Interpretation {
+            input_type,
+            did_url: candidate.build()?,
+        }),
+        _ => Err(DidIndyError::AmbiguousIdentifier {
+            input: input.to_string(),
+            candidates: candidates.iter().map(DidUrlBuilder::url).collect(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpretation(input_type: InputType, did_url: &str) -> Interpretation {
+        Interpretation {
+            input_type,
+            did_url: did_url.to_string(),
+        }
+    }
+
+    #[test]
+    fn identifiers_interpreted_as_did_urls() {
+        let cases = [
+            (
+                "did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4",
+                interpretation(
+                    InputType::DidIndy,
+                    "did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4",
+                ),
+            ),
+            (
+                "did:sov:Dk1fRRTtNazyMuK2cr64wp",
+                interpretation(InputType::DidSov, "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            ),
+            (
+                "Dk1fRRTtNazyMuK2cr64wp",
+                interpretation(
+                    InputType::UnqualifiedDid,
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                ),
+            ),
+            (
+                "Dk1fRRTtNazyMuK2cr64wp:2:npdb:4.3.4",
+                interpretation(
+                    InputType::SchemaId,
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4",
+                ),
+            ),
+            (
+                "Dk1fRRTtNazyMuK2cr64wp:3:CL:74:default",
+                interpretation(
+                    InputType::CredDefId,
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/CLAIM_DEF/74/default",
+                ),
+            ),
+            (
+                "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:74:default:CL_ACCUM:a4e25e54",
+                interpretation(
+                    InputType::RevRegDefId,
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DEF/74/default/a4e25e54",
+                ),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                expected,
+                interpret(input, Some("idunion")).unwrap(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn ambiguous_identifier_rejected() {
+        match interpret("Dk1fRRTtNazyMuK2cr64wp:2:npdb:4.3:4.3", Some("idunion")) {
+            Err(DidIndyError::AmbiguousIdentifier { candidates, .. }) => assert_eq!(
+                vec![
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3%3A4.3",
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb%3A4.3/4.3",
+                ],
+                candidates
+            ),
+            other => panic!("Expected AmbiguousIdentifier, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unqualified_identifiers_need_namespace() {
+        assert!(matches!(
+            interpret("Dk1fRRTtNazyMuK2cr64wp:2:npdb:4.3.4", None),
+            Err(DidIndyError::NamespaceRequired(_))
+        ));
+        assert!(interpret("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", None).is_ok());
+        assert!(matches!(
+            interpret("did:web:example.com", Some("idunion")),
+            Err(DidIndyError::NamespaceNotSupported)
+        ));
+        assert!(matches!(
+            interpret("Dk1fRRTtNazyMuK2cr64wp:5:npdb", Some("idunion")),
+            Err(DidIndyError::InvalidDidUrl)
+        ));
+    }
+}
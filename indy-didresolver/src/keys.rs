@@ -0,0 +1,312 @@
+use serde_json::{json, Map, Value};
+
+use super::error::{DidIndyError, DidIndyResult};
+use super::validation::{KEY_MATERIAL_PROPERTIES, VERIFICATION_RELATIONSHIPS};
+
+// Multicodec prefixes (unsigned varint) of the supported public key types
+static ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+static X25519_MULTICODEC: [u8; 2] = [0xec, 0x01];
+
+/// Representation of public keys requested with the `transformKeys` parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyFormat {
+    PublicKeyBase58,
+    PublicKeyMultibase,
+    PublicKeyJwk,
+}
+
+impl KeyFormat {
+    pub fn from_str(input: &str) -> DidIndyResult<KeyFormat> {
+        match input {
+            "publicKeyBase58" => Ok(KeyFormat::PublicKeyBase58),
+            "publicKeyMultibase" => Ok(KeyFormat::PublicKeyMultibase),
+            "publicKeyJwk" => Ok(KeyFormat::PublicKeyJwk),
+            _ => Err(DidIndyError::UnsupportedTransform),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyType {
+    Ed25519,
+    X25519,
+}
+
+impl KeyType {
+    fn from_method_type(type_: &str) -> Option<KeyType> {
+        match type_ {
+            "Ed25519VerificationKey2018" | "Ed25519VerificationKey2020" => Some(KeyType::Ed25519),
+            "X25519KeyAgreementKey2019" | "X25519KeyAgreementKey2020" => Some(KeyType::X25519),
+            _ => None,
+        }
+    }
+
+    fn from_crv(crv: &str) -> Option<KeyType> {
+        match crv {
+            "Ed25519" => Some(KeyType::Ed25519),
+            "X25519" => Some(KeyType::X25519),
+            _ => None,
+        }
+    }
+
+    fn crv(&self) -> &'static str {
+        match self {
+            KeyType::Ed25519 => "Ed25519",
+            KeyType::X25519 => "X25519",
+        }
+    }
+
+    fn multicodec(&self) -> &'static [u8] {
+        match self {
+            KeyType::Ed25519 => &ED25519_MULTICODEC,
+            KeyType::X25519 => &X25519_MULTICODEC,
+        }
+    }
+
+    fn method_type(&self, format: KeyFormat) -> &'static str {
+        match (self, format) {
+            (_, KeyFormat::PublicKeyJwk) => "JsonWebKey2020",
+            (KeyType::Ed25519, KeyFormat::PublicKeyBase58) => "Ed25519VerificationKey2018",
+            (KeyType::Ed25519, KeyFormat::PublicKeyMultibase) => "Ed25519VerificationKey2020",
+            (KeyType::X25519, KeyFormat::PublicKeyBase58) => "X25519KeyAgreementKey2019",
+            (KeyType::X25519, KeyFormat::PublicKeyMultibase) => "X25519KeyAgreementKey2020",
+        }
+    }
+}
+
+/// Converts the key material of all verification methods of a DID document,
+/// including the ones embedded in verification relationships, to the given format.
+/// The type of a converted method is changed to the one matching the format.
+/// Methods with keys other than Ed25519 and X25519 are left as they are.
+pub fn transform_keys(doc: &mut Value, format: KeyFormat) -> DidIndyResult<()> {
+    if let Some(Value::Array(methods)) = doc.get_mut("verificationMethod") {
+        for method in methods.iter_mut() {
+            transform_method(method, format)?;
+        }
+    }
+
+    for relationship in VERIFICATION_RELATIONSHIPS {
+        if let Some(Value::Array(entries)) = doc.get_mut(relationship) {
+            for entry in entries.iter_mut().filter(|entry| entry.is_object()) {
+                transform_method(entry, format)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn transform_method(method: &mut Value, format: KeyFormat) -> DidIndyResult<()> {
+    let method = match method {
+        Value::Object(method) => method,
+        _ => return Ok(()),
+    };
+
+    let (key_type, key) = match read_key(method)? {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+
+    for property in KEY_MATERIAL_PROPERTIES {
+        method.remove(property);
+    }
+
+    let (property, value) = match format {
+        KeyFormat::PublicKeyBase58 => ("publicKeyBase58", json!(bs58::encode(key).into_string())),
+        KeyFormat::PublicKeyMultibase => ("publicKeyMultibase", json!(multibase(key_type, &key))),
+        KeyFormat::PublicKeyJwk => (
+            "publicKeyJwk",
+            json!({
+                "kty": "OKP",
+                "crv": key_type.crv(),
+                "x": base64::encode_config(&key, base64::URL_SAFE_NO_PAD),
+            }),
+        ),
+    };
+    method.insert(property.to_string(), value);
+    method.insert("type".to_string(), json!(key_type.method_type(format)));
+
+    Ok(())
+}
+
+// Raw public key of a verification method, None if the key type is not supported
+fn read_key(method: &Map<String, Value>) -> DidIndyResult<Option<(KeyType, Vec<u8>)>> {
+    let method_key_type = method
+        .get("type")
+        .and_then(|t| t.as_str())
+        .and_then(KeyType::from_method_type);
+
+    if let Some(base58) = method.get("publicKeyBase58").and_then(|k| k.as_str()) {
+        return match method_key_type {
+            Some(key_type) => Ok(Some((key_type, decode_base58(base58)?))),
+            None => Ok(None),
+        };
+    }
+
+    if let Some(multibase) = method.get("publicKeyMultibase").and_then(|k| k.as_str()) {
+        // Only base58btc is used for Ed25519 and X25519 keys
+        let decoded = match multibase.strip_prefix('z') {
+            Some(base58) => decode_base58(base58)?,
+            None => return Err(DidIndyError::InvalidDidDoc),
+        };
+        for key_type in [KeyType::Ed25519, KeyType::X25519] {
+            if let Some(key) = decoded.strip_prefix(key_type.multicodec()) {
+                return Ok(Some((key_type, key.to_vec())));
+            }
+        }
+        return Ok(method_key_type.map(|key_type| (key_type, decoded)));
+    }
+
+    if let Some(jwk) = method.get("publicKeyJwk") {
+        let key_type = match jwk.get("crv").and_then(|c| c.as_str()) {
+            Some(crv) if jwk["kty"] == "OKP" => KeyType::from_crv(crv),
+            _ => None,
+        };
+        return match (key_type, jwk.get("x").and_then(|x| x.as_str())) {
+            (Some(key_type), Some(x)) => {
+                let key = base64::decode_config(x, base64::URL_SAFE_NO_PAD)
+                    .map_err(|_| DidIndyError::InvalidDidDoc)?;
+                Ok(Some((key_type, key)))
+            }
+            _ => Ok(None),
+        };
+    }
+
+    Ok(None)
+}
+
+/// Multibase (base58btc) encoding of a base58 encoded Ed25519 public key, as used
+/// by `publicKeyMultibase`
+pub fn ed25519_multibase(base58: &str) -> DidIndyResult<String> {
+    Ok(multibase(KeyType::Ed25519, &decode_base58(base58)?))
+}
+
+fn multibase(key_type: KeyType, key: &[u8]) -> String {
+    let prefixed = [key_type.multicodec(), key].concat();
+    format!("z{}", bs58::encode(prefixed).into_string())
+}
+
+fn decode_base58(input: &str) -> DidIndyResult<Vec<u8>> {
+    bs58::decode(input)
+        .into_vec()
+        .map_err(|_| DidIndyError::InvalidDidDoc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static DID: &str = "did:indy:idunion:QowxFtwciWceMFr7WbwnM";
+    static BASE58: &str = "B12NYF8RrR3h41TDCTJojY59usg3mbtbjnFs7Eud1Y6u";
+    static MULTIBASE: &str = "z6MkpTHR8VNsBxYAAWHut2Geadd9jSwuBV8xRoAnwWsdvktH";
+    static JWK_X: &str = "lJZrfAjkBXdfjebMHEUI9usidAPhAlssitLXR3OYxbI";
+
+    fn doc() -> Value {
+        json!({
+            "id": DID,
+            "verificationMethod": [{
+                "id": format!("{}#verkey", DID),
+                "type": "Ed25519VerificationKey2018",
+                "controller": DID,
+                "publicKeyBase58": BASE58,
+            }],
+            "authentication": [format!("{}#verkey", DID)],
+        })
+    }
+
+    fn method(doc: &Value) -> &Value {
+        &doc["verificationMethod"][0]
+    }
+
+    #[test]
+    fn parse_key_format() {
+        assert_eq!(
+            KeyFormat::PublicKeyMultibase,
+            KeyFormat::from_str("publicKeyMultibase").unwrap()
+        );
+        assert!(matches!(
+            KeyFormat::from_str("publicKeyHex"),
+            Err(DidIndyError::UnsupportedTransform)
+        ));
+    }
+
+    #[test]
+    fn transform_to_multibase() {
+        let mut doc = doc();
+        transform_keys(&mut doc, KeyFormat::PublicKeyMultibase).unwrap();
+
+        assert_eq!(MULTIBASE, method(&doc)["publicKeyMultibase"]);
+        assert_eq!("Ed25519VerificationKey2020", method(&doc)["type"]);
+        assert!(method(&doc).get("publicKeyBase58").is_none());
+    }
+
+    #[test]
+    fn transform_to_jwk() {
+        let mut doc = doc();
+        transform_keys(&mut doc, KeyFormat::PublicKeyJwk).unwrap();
+
+        assert_eq!(
+            json!({"kty": "OKP", "crv": "Ed25519", "x": JWK_X}),
+            method(&doc)["publicKeyJwk"]
+        );
+        assert_eq!("JsonWebKey2020", method(&doc)["type"]);
+        assert!(method(&doc).get("publicKeyBase58").is_none());
+    }
+
+    #[test]
+    fn transform_to_base58() {
+        let mut doc = doc();
+        transform_keys(&mut doc, KeyFormat::PublicKeyJwk).unwrap();
+        transform_keys(&mut doc, KeyFormat::PublicKeyBase58).unwrap();
+        assert_eq!(self::doc(), doc);
+
+        let mut doc = self::doc();
+        transform_keys(&mut doc, KeyFormat::PublicKeyMultibase).unwrap();
+        transform_keys(&mut doc, KeyFormat::PublicKeyBase58).unwrap();
+        assert_eq!(self::doc(), doc);
+    }
+
+    #[test]
+    fn transform_embedded_x25519_key() {
+        let mut doc = json!({
+            "id": DID,
+            "keyAgreement": [{
+                "id": format!("{}#x25519", DID),
+                "type": "X25519KeyAgreementKey2019",
+                "controller": DID,
+                "publicKeyBase58": BASE58,
+            }],
+        });
+        transform_keys(&mut doc, KeyFormat::PublicKeyJwk).unwrap();
+
+        assert_eq!(
+            json!({"kty": "OKP", "crv": "X25519", "x": JWK_X}),
+            doc["keyAgreement"][0]["publicKeyJwk"]
+        );
+    }
+
+    #[test]
+    fn unsupported_key_type_is_left_unchanged() {
+        let mut doc = json!({
+            "id": DID,
+            "verificationMethod": [{
+                "id": format!("{}#secp256k1", DID),
+                "type": "EcdsaSecp256k1VerificationKey2019",
+                "controller": DID,
+                "publicKeyBase58": BASE58,
+            }],
+        });
+        let expected = doc.clone();
+        transform_keys(&mut doc, KeyFormat::PublicKeyJwk).unwrap();
+        assert_eq!(expected, doc);
+    }
+
+    #[test]
+    fn ed25519_multibase_from_base58() {
+        assert_eq!(MULTIBASE, ed25519_multibase(BASE58).unwrap());
+        assert!(matches!(
+            ed25519_multibase("0OIl"),
+            Err(DidIndyError::InvalidDidDoc)
+        ));
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use async_lock::Semaphore;
+use futures_timer::Delay;
+use futures_util::future::{select, Either, LocalBoxFuture};
+use indy_vdr::common::error::VdrErrorKind;
+use indy_vdr::ledger::constants;
+use indy_vdr::ledger::identifiers::{CredentialDefinitionId, RevocationRegistryId};
+use indy_vdr::ledger::RequestBuilder;
+use indy_vdr::pool::helpers::perform_ledger_request;
+use indy_vdr::pool::{Pool, PreparedRequest, RequestResult};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::config::Timeouts;
+use super::error::{DidIndyError, DidIndyResult};
+
+/// Submits read requests to an Indy ledger.
+///
+/// Implemented for every indy-vdr `Pool`. Other implementations can route requests
+/// through a different transport or stand in for the ledger in tests.
+pub trait LedgerRequester {
+    fn request_builder(&self) -> RequestBuilder;
+
+    fn submit<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<String>>;
+
+    /// Aliases of the nodes of the pool, empty if not known
+    fn node_aliases(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// JSON transactions of the pool ledger known to the pool as of its last
+    /// refresh, empty if not known. Does not query the ledger.
+    fn pool_transactions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Submits the request like `submit`, also returning the seconds each node
+    /// that replied took to answer. Empty if the reply times are not measured.
+    fn submit_timed<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<(String, HashMap<String, f32>)>> {
+        Box::pin(async move { Ok((self.submit(request).await?, HashMap::new())) })
+    }
+
+    /// Ids of the revocation registries of the cred def. Indy ledgers have no
+    /// read request listing them, so only requesters backed by an index of the
+    /// ledger can; the default fails with `RevRegListingNotSupported`.
+    fn rev_reg_def_ids<'a>(
+        &'a self,
+        _cred_def_id: &'a CredentialDefinitionId,
+    ) -> LocalBoxFuture<'a, DidIndyResult<Vec<RevocationRegistryId>>> {
+        Box::pin(async { Err(DidIndyError::RevRegListingNotSupported) })
+    }
+}
+
+impl<T: Pool> LedgerRequester for T {
+    fn request_builder(&self) -> RequestBuilder {
+        self.get_request_builder()
+    }
+
+    fn submit<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+        Box::pin(async move { Ok(self.submit_timed(request).await?.0) })
+    }
+
+    fn node_aliases(&self) -> Vec<String> {
+        self.get_node_aliases()
+    }
+
+    fn pool_transactions(&self) -> Vec<String> {
+        self.get_transactions().unwrap_or_default()
+    }
+
+    fn submit_timed<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<(String, HashMap<String, f32>)>> {
+        Box::pin(async move {
+            let (result, timing) = perform_ledger_request(self, request).await?;
+            match result {
+                RequestResult::Reply(data) => Ok((data, timing.unwrap_or_default())),
+                RequestResult::Failed(error) => {
+                    error!("Error requesting data from ledger, {}", error.to_string());
+                    Err(DidIndyError::VdrError(error))
+                }
+            }
+        })
+    }
+}
+
+/// Tracks the time spent on the ledger round-trips of a single resolution.
+pub(crate) struct Deadline {
+    timeouts: Timeouts,
+    started: Instant,
+}
+
+impl Deadline {
+    pub(crate) fn start(timeouts: Timeouts) -> Self {
+        Deadline {
+            timeouts,
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns the time available for the next round-trip and whether running out
+    /// of it is caused by the total timeout.
+    fn budget(&self) -> DidIndyResult<Option<(Duration, bool)>> {
+        let remaining = match self.timeouts.total {
+            Some(total) => match total.checked_sub(self.started.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => Some(remaining),
+                _ => return Err(DidIndyError::ResolutionTimeout),
+            },
+            None => None,
+        };
+
+        Ok(match (self.timeouts.request, remaining) {
+            (Some(request), Some(remaining)) if remaining < request => Some((remaining, true)),
+            (Some(request), _) => Some((request, false)),
+            (None, Some(remaining)) => Some((remaining, true)),
+            (None, None) => None,
+        })
+    }
+}
+
+/// Number of ledger requests of a resolver
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStats {
+    /// Requests submitted to the ledger and not answered yet
+    pub in_flight: usize,
+    /// Requests waiting for the concurrency limit
+    pub queued: usize,
+}
+
+/// Limits the number of concurrent ledger requests of a resolver. A permit is
+/// held for a single round-trip only, so a resolution needing several
+/// round-trips never waits for a permit while holding another one.
+pub(crate) struct RequestLimiter {
+    semaphore: Option<Semaphore>,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(max_concurrent_requests: Option<usize>) -> Self {
+        RequestLimiter {
+            semaphore: max_concurrent_requests.map(Semaphore::new),
+            in_flight: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> RequestStats {
+        RequestStats {
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            queued: self.queued.load(Ordering::SeqCst),
+        }
+    }
+
+    async fn submit<T: LedgerRequester>(
+        &self,
+        ledger: &T,
+        request: &PreparedRequest,
+    ) -> DidIndyResult<String> {
+        let _permit = match &self.semaphore {
+            Some(semaphore) => {
+                let _queued = Gauge::increment(&self.queued);
+                Some(semaphore.acquire().await)
+            }
+            None => None,
+        };
+        let _in_flight = Gauge::increment(&self.in_flight);
+        ledger.submit(request).await
+    }
+}
+
+// Keeps a counter incremented while alive, also if a timed out request is dropped
+struct Gauge<'a>(&'a AtomicUsize);
+
+impl<'a> Gauge<'a> {
+    fn increment(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Gauge(counter)
+    }
+}
+
+impl Drop for Gauge<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Submits the request, failing with a timeout error if the round-trip exceeds
+/// either the per-request timeout or the time left for the whole resolution.
+/// Time spent waiting for the concurrency limit counts towards the round-trip.
+pub(crate) async fn submit_within<T: LedgerRequester>(
+    ledger: &T,
+    limiter: &RequestLimiter,
+    request: &PreparedRequest,
+    deadline: &Deadline,
+) -> DidIndyResult<String> {
+    match deadline.budget()? {
+        None => limiter.submit(ledger, request).await,
+        Some((budget, total_exceeded)) => {
+            let submit = Box::pin(limiter.submit(ledger, request));
+            match select(submit, Delay::new(budget)).await {
+                Either::Left((result, _)) => result,
+                Either::Right(_) if total_exceeded => Err(DidIndyError::ResolutionTimeout),
+                Either::Right(_) => Err(DidIndyError::RequestTimeout),
+            }
+        }
+    }
+}
+
+// Parts of the reason of a REQNACK or REJECT telling that the nodes do not
+// understand the form of the request, as opposed to rejecting its content
+static REJECTED_FORM_MARKERS: [&str; 5] = [
+    "unknown field",
+    "unrecognized field",
+    "not allowed",
+    "unsupported operation",
+    "protocol version",
+];
+
+// Operation fields selecting a version of the ledger object, by request type
+static VERSION_FIELDS: [(&str, &[&str]); 2] = [
+    (constants::GET_NYM, &["seqNo", "timestamp"]),
+    (constants::GET_ATTR, &["seqNo", "timestamp"]),
+];
+
+/// Older form of a request the nodes rejected, sent instead of it
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestDowngrade {
+    pub txn_type: String,
+    /// Changes made to the request, e.g. `protocolVersion 2 -> 1`
+    pub changes: Vec<String>,
+    /// The older form drops the requested version, so its reply is the current
+    /// state of the ledger object
+    pub lossy: bool,
+    /// Reason the nodes gave for rejecting the request
+    pub reason: String,
+}
+
+/// Reason of a rejection of the request form by the nodes, None for other errors
+pub(crate) fn rejected_form(err: &DidIndyError) -> Option<String> {
+    let reason = match err {
+        DidIndyError::VdrError(err) => match err.kind() {
+            VdrErrorKind::PoolRequestFailed(reason) => reason.to_string(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let lowercase = reason.to_lowercase();
+    REJECTED_FORM_MARKERS
+        .iter()
+        .any(|marker| lowercase.contains(marker))
+        .then(|| reason)
+}
+
+/// Request JSON in the older form for the rejection, None if there is none. A
+/// rejected protocol version is lowered to 1, otherwise the version fields of
+/// the operation are dropped.
+pub(crate) fn older_form(request: &Value, reason: &str) -> Option<(Value, RequestDowngrade)> {
+    let txn_type = request["operation"]["type"].as_str().unwrap_or_default();
+    let mut older = request.clone();
+    let mut changes = Vec::new();
+    let mut lossy = false;
+    match request["protocolVersion"].as_u64() {
+        Some(version) if version > 1 && reason.to_lowercase().contains("protocol version") => {
+            older["protocolVersion"] = Value::from(1);
+            changes.push(format!("protocolVersion {} -> 1", version));
+        }
+        _ => {
+            let fields = VERSION_FIELDS
+                .iter()
+                .find(|(version_txn_type, _)| *version_txn_type == txn_type)
+                .map_or(&[][..], |(_, fields)| fields);
+            for field in fields {
+                if let Some(operation) = older["operation"].as_object_mut() {
+                    if operation.remove(*field).is_some() {
+                        changes.push(format!("operation.{} removed", field));
+                        lossy = true;
+                    }
+                }
+            }
+        }
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    Some((
+        older,
+        RequestDowngrade {
+            txn_type: txn_type.to_string(),
+            changes,
+            lossy,
+            reason: reason.to_string(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_budget_without_timeouts() {
+        let deadline = Deadline::start(Timeouts::default());
+        assert!(deadline.budget().unwrap().is_none());
+    }
+
+    #[test]
+    fn request_timeout_used_while_total_has_time_left() {
+        let deadline = Deadline::start(Timeouts {
+            request: Some(Duration::from_secs(5)),
+            total: Some(Duration::from_secs(60)),
+        });
+        assert_eq!(
+            Some((Duration::from_secs(5), false)),
+            deadline.budget().unwrap()
+        );
+    }
+
+    #[test]
+    fn remaining_total_used_when_shorter_than_request_timeout() {
+        let deadline = Deadline::start(Timeouts {
+            request: Some(Duration::from_secs(60)),
+            total: Some(Duration::from_secs(5)),
+        });
+        let (budget, total_exceeded) = deadline.budget().unwrap().unwrap();
+        assert!(budget <= Duration::from_secs(5));
+        assert!(total_exceeded);
+    }
+
+    fn rejection(reason: &str) -> DidIndyError {
+        DidIndyError::VdrError(VdrErrorKind::PoolRequestFailed(reason.to_string()).into())
+    }
+
+    #[test]
+    fn rejected_form_recognized_by_reason() {
+        assert_eq!(
+            Some(String::from("client request invalid: unknown field seqNo")),
+            rejected_form(&rejection("client request invalid: unknown field seqNo"))
+        );
+        assert_eq!(None, rejected_form(&rejection("insufficient privileges")));
+        assert_eq!(None, rejected_form(&DidIndyError::RequestTimeout));
+    }
+
+    #[test]
+    fn older_form_lowers_rejected_protocol_version() {
+        let request = serde_json::json!({
+            "protocolVersion": 2,
+            "operation": {"type": constants::GET_NYM, "dest": "Dk1fRRTtNazyMuK2cr64wp", "seqNo": 12}
+        });
+        let (older, downgrade) = older_form(&request, "Unknown protocol version 2").unwrap();
+        assert_eq!(1, older["protocolVersion"]);
+        assert_eq!(12, older["operation"]["seqNo"]);
+        assert!(!downgrade.lossy);
+        assert_eq!(vec!["protocolVersion 2 -> 1"], downgrade.changes);
+    }
+
+    #[test]
+    fn older_form_drops_version_fields() {
+        let request = serde_json::json!({
+            "protocolVersion": 2,
+            "operation": {"type": constants::GET_ATTR, "raw": "endpoint", "timestamp": 1636641222}
+        });
+        let (older, downgrade) = older_form(&request, "unknown field timestamp").unwrap();
+        assert_eq!(None, older["operation"].get("timestamp"));
+        assert_eq!("endpoint", older["operation"]["raw"]);
+        assert!(downgrade.lossy);
+
+        // The timestamp of GET_REVOC_REG is no version field
+        let request = serde_json::json!({
+            "protocolVersion": 2,
+            "operation": {"type": constants::GET_REVOC_REG, "timestamp": 1636641222}
+        });
+        assert_eq!(None, older_form(&request, "unknown field timestamp"));
+    }
+
+    #[test]
+    fn exhausted_total_fails_before_request() {
+        let deadline = Deadline::start(Timeouts {
+            request: None,
+            total: Some(Duration::from_millis(0)),
+        });
+        assert!(matches!(
+            deadline.budget(),
+            Err(DidIndyError::ResolutionTimeout)
+        ));
+    }
+}
@@ -1,8 +1,258 @@
+pub mod audit;
+pub mod cache;
+pub mod caching_pool;
+pub mod config;
 pub mod did;
 pub mod did_document;
+pub mod did_indy_resolver;
 pub mod error;
+#[cfg(feature = "genesis-urls")]
+pub mod genesis_urls;
+#[cfg(feature = "http-ledger")]
+pub mod http_ledger;
+pub mod identifiers;
+pub mod keys;
+pub mod ledger;
+pub mod networks;
+pub mod pool_info;
+pub mod pool_status;
+pub mod registry;
 pub mod resolver;
 pub mod responses;
+pub mod signing;
+pub mod standby;
+pub mod status_list;
+mod telemetry;
+pub mod timestamp;
+pub mod trust;
+pub mod validation;
+pub mod verification;
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures_timer::Delay;
+    use futures_util::future::LocalBoxFuture;
+    use indy_vdr::ledger::identifiers::{CredentialDefinitionId, RevocationRegistryId};
+    use indy_vdr::ledger::RequestBuilder;
+    use indy_vdr::pool::{PreparedRequest, ProtocolVersion};
+    use indy_vdr::utils::Qualifiable;
+    use serde_json::{json, Value};
+
+    use crate::error::{DidIndyError, DidIndyResult};
+    use crate::ledger::LedgerRequester;
+
+    /// Ledger stub answering requests with scripted replies in order. Once they
+    /// are used up, requests are answered with the fixed answer for their type.
+    pub(crate) struct MockLedger {
+        replies: Mutex<VecDeque<DidIndyResult<String>>>,
+        answers: HashMap<String, String>,
+        delay: Option<Duration>,
+        requests: Mutex<Vec<Value>>,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+        // Alias and reply time in seconds of each node
+        nodes: Vec<(String, Option<f32>)>,
+        // Listed revocation registries, None if listing is not supported
+        rev_reg_def_ids: Option<Vec<RevocationRegistryId>>,
+        pool_transactions: Vec<String>,
+    }
+
+    impl MockLedger {
+        pub(crate) fn new() -> Self {
+            MockLedger {
+                replies: Mutex::new(VecDeque::new()),
+                answers: HashMap::new(),
+                delay: None,
+                requests: Mutex::new(Vec::new()),
+                in_flight: Arc::new(AtomicUsize::new(0)),
+                max_in_flight: Arc::new(AtomicUsize::new(0)),
+                nodes: Vec::new(),
+                rev_reg_def_ids: None,
+                pool_transactions: Vec::new(),
+            }
+        }
+
+        /// Nodes of the pool with the seconds each takes to reply, None for
+        /// nodes not replying
+        pub(crate) fn with_nodes(mut self, nodes: &[(&str, Option<f32>)]) -> Self {
+            self.nodes = nodes
+                .iter()
+                .map(|(alias, time)| (alias.to_string(), *time))
+                .collect();
+            self
+        }
+
+        /// Lists the revocation registries for any cred def
+        pub(crate) fn with_rev_reg_defs(mut self, ids: &[&str]) -> Self {
+            self.rev_reg_def_ids = Some(
+                ids.iter()
+                    .map(|id| RevocationRegistryId::from_str(id).unwrap())
+                    .collect(),
+            );
+            self
+        }
+
+        /// JSON pool ledger transactions held by the pool
+        pub(crate) fn with_pool_transactions(mut self, transactions: Vec<String>) -> Self {
+            self.pool_transactions = transactions;
+            self
+        }
+
+        /// Delays every reply by the given duration
+        pub(crate) fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = Some(delay);
+            self
+        }
+
+        pub(crate) fn reply(self, reply: String) -> Self {
+            self.replies.lock().unwrap().push_back(Ok(reply));
+            self
+        }
+
+        pub(crate) fn fail(self, error: DidIndyError) -> Self {
+            self.replies.lock().unwrap().push_back(Err(error));
+            self
+        }
+
+        /// Answers every request of the transaction type with the reply
+        pub(crate) fn answer(mut self, txn_type: &str, reply: String) -> Self {
+            self.answers.insert(txn_type.to_string(), reply);
+            self
+        }
+
+        /// Highest number of requests in flight at the same time so far
+        pub(crate) fn max_in_flight(&self) -> usize {
+            self.max_in_flight.load(Ordering::SeqCst)
+        }
+
+        /// JSON of all requests submitted so far
+        pub(crate) fn requests(&self) -> Vec<Value> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl LedgerRequester for MockLedger {
+        fn request_builder(&self) -> RequestBuilder {
+            RequestBuilder::new(ProtocolVersion::Node1_4)
+        }
+
+        fn submit<'a>(
+            &'a self,
+            request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+            self.requests.lock().unwrap().push(request.req_json.clone());
+            let reply = self
+                .replies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .or_else(|| self.answers.get(&request.txn_type).cloned().map(Ok))
+                .unwrap_or(Err(DidIndyError::EmptyData));
+            let delay = self.delay;
+            let in_flight = self.in_flight.clone();
+            let max_in_flight = self.max_in_flight.clone();
+
+            Box::pin(async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                if let Some(delay) = delay {
+                    Delay::new(delay).await;
+                }
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                reply
+            })
+        }
+
+        fn node_aliases(&self) -> Vec<String> {
+            self.nodes.iter().map(|(alias, _)| alias.clone()).collect()
+        }
+
+        fn pool_transactions(&self) -> Vec<String> {
+            self.pool_transactions.clone()
+        }
+
+        fn submit_timed<'a>(
+            &'a self,
+            request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<(String, HashMap<String, f32>)>> {
+            let timing = self
+                .nodes
+                .iter()
+                .filter_map(|(alias, time)| time.map(|time| (alias.clone(), time)))
+                .collect();
+            Box::pin(async move { Ok((self.submit(request).await?, timing)) })
+        }
+
+        fn rev_reg_def_ids<'a>(
+            &'a self,
+            _cred_def_id: &'a CredentialDefinitionId,
+        ) -> LocalBoxFuture<'a, DidIndyResult<Vec<RevocationRegistryId>>> {
+            let ids = self
+                .rev_reg_def_ids
+                .clone()
+                .ok_or(DidIndyError::RevRegListingNotSupported);
+            Box::pin(async move { ids })
+        }
+    }
+
+    /// Ledger reply with the given result data. Transactions like NYM and ATTRIB
+    /// carry their data as JSON encoded string.
+    pub(crate) fn ledger_reply(txn_type: &str, data: Value, seq_no: u64, txn_time: u64) -> String {
+        json!({
+            "op": "REPLY",
+            "result": {
+                "type": txn_type,
+                "reqId": 1,
+                "seqNo": seq_no,
+                "txnTime": txn_time,
+                "data": data,
+                "state_proof": {}
+            }
+        })
+        .to_string()
+    }
+
+    pub(crate) fn nym_reply(dest: &str, verkey: &str, diddoc_content: Option<Value>) -> String {
+        let mut data = json!({
+            "dest": dest,
+            "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+            "role": null,
+            "seqNo": 12,
+            "txnTime": 1636641222,
+            "verkey": verkey,
+        });
+        if let Some(diddoc_content) = diddoc_content {
+            data["diddocContent"] = Value::String(diddoc_content.to_string());
+        }
+        ledger_reply("105", Value::String(data.to_string()), 12, 1636641222)
+    }
+
+    /// NODE transaction of the pool ledger with the alias and the node data
+    pub(crate) fn node_txn(seq_no: u64, alias: &str, dest: &str, data: Value) -> String {
+        let mut node_data = json!({ "alias": alias });
+        for (field, value) in data.as_object().unwrap() {
+            node_data[field] = value.clone();
+        }
+        json!({
+            "reqSignature": {},
+            "txn": {
+                "data": { "data": node_data, "dest": dest },
+                "metadata": { "from": "Th7MpTaRZVRYnPiabds81Y" },
+                "type": "0"
+            },
+            "txnMetadata": { "seqNo": seq_no },
+            "ver": "1"
+        })
+        .to_string()
+    }
+
+    pub(crate) fn endpoint_reply(endpoint: &str) -> String {
+        let data = json!({ "endpoint": { "endpoint": endpoint } });
+        ledger_reply("104", Value::String(data.to_string()), 13, 1636641230)
+    }
+}
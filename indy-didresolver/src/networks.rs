@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use futures_executor::block_on;
+use indy_vdr::pool::helpers::perform_refresh;
+use indy_vdr::pool::{Pool, PoolBuilder, PoolTransactions, SharedPool};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+use super::error::{DidIndyError, DidIndyResult};
+
+/// Name of the genesis files of the indy-did-networks layout
+pub static GENESIS_FILENAME: &str = "pool_transactions_genesis.json";
+
+/// Supplies pools for namespaces that are not registered with a
+/// `ResolverRegistry`. Returns None if the namespace is unknown to the provider.
+pub trait NetworkProvider<T>: Send + Sync {
+    fn pool_for(&self, namespace: &str) -> Option<T>;
+}
+
+/// Builds a pool from genesis transactions and refreshes it to get the current
+/// validator set.
+pub fn build_pool(genesis_txns: PoolTransactions) -> DidIndyResult<SharedPool> {
+    let pool = PoolBuilder::default()
+        .transactions(genesis_txns.clone())?
+        .into_shared()?;
+
+    let (txns, _timing) = block_on(perform_refresh(&pool))?;
+
+    match txns {
+        Some(txns) => {
+            let mut pool_txns = genesis_txns;
+            pool_txns.extend_from_json(&txns)?;
+            Ok(PoolBuilder::default()
+                .transactions(pool_txns)?
+                .into_shared()?)
+        }
+        None => Ok(pool),
+    }
+}
+
+/// Builds a new pool from the transactions of the pool and refreshes it, for
+/// `StandbyPool::refresh`
+pub fn refresh_pool(pool: &SharedPool) -> DidIndyResult<SharedPool> {
+    build_pool(PoolTransactions::from_json_transactions(
+        pool.get_transactions()?,
+    )?)
+}
+
+/// SHA-256 of genesis transactions as lowercase hex, identifying a pool for
+/// `ResolverRegistry::register_genesis`
+pub fn genesis_hash(genesis: &str) -> String {
+    format!("{:x}", Sha256::digest(genesis.as_bytes()))
+}
+
+/// Provides pools from a directory following the indy-did-networks layout: a
+/// folder per namespace holding the genesis file, sub-namespaces in sub folders,
+/// e.g. `sovrin/staging/pool_transactions_genesis.json` for `sovrin:staging`.
+pub struct DirectoryProvider {
+    path: PathBuf,
+    genesis_filename: String,
+}
+
+impl DirectoryProvider {
+    pub fn new(path: impl Into<PathBuf>, genesis_filename: &str) -> Self {
+        DirectoryProvider {
+            path: path.into(),
+            genesis_filename: genesis_filename.to_string(),
+        }
+    }
+
+    /// Namespaces with a genesis file in the directory, sorted by name
+    pub fn namespaces(&self) -> Vec<String> {
+        let mut namespaces = Vec::new();
+        for namespace in sub_directories(&self.path) {
+            let namespace_path = self.path.join(&namespace);
+            if namespace_path.join(&self.genesis_filename).is_file() {
+                namespaces.push(namespace.clone());
+            }
+            for sub_namespace in sub_directories(&namespace_path) {
+                if namespace_path
+                    .join(&sub_namespace)
+                    .join(&self.genesis_filename)
+                    .is_file()
+                {
+                    namespaces.push(format!("{}:{}", namespace, sub_namespace));
+                }
+            }
+        }
+        namespaces.sort();
+        namespaces
+    }
+
+    /// Builds the pool of the namespace from its genesis file. Fails with
+    /// `PoolUnavailable` if the directory has no genesis file for it.
+    pub fn build_pool_for(&self, namespace: &str) -> DidIndyResult<SharedPool> {
+        let genesis_file = self
+            .genesis_file(namespace)
+            .ok_or_else(|| DidIndyError::PoolUnavailable(namespace.to_string()))?;
+        debug!(
+            "Initializing pool for {} from {:?}",
+            namespace, genesis_file
+        );
+        build_pool(PoolTransactions::from_json_file(&genesis_file)?)
+    }
+
+    fn genesis_file(&self, namespace: &str) -> Option<PathBuf> {
+        let mut path = self.path.clone();
+        for segment in namespace.split(':') {
+            // Namespaces must not point outside of the directory
+            if segment.is_empty() || segment.starts_with('.') {
+                return None;
+            }
+            path.push(segment);
+        }
+        path.push(&self.genesis_filename);
+        Some(path).filter(|path| path.is_file())
+    }
+}
+
+impl NetworkProvider<SharedPool> for DirectoryProvider {
+    fn pool_for(&self, namespace: &str) -> Option<SharedPool> {
+        self.genesis_file(namespace)?;
+        match self.build_pool_for(namespace) {
+            Ok(pool) => Some(pool),
+            Err(err) => {
+                warn!("Could not initialize pool for {}, {}", namespace, err);
+                None
+            }
+        }
+    }
+}
+
+// Names of the directories in path, hidden directories starting with "." are skipped
+fn sub_directories(path: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn networks() -> DirectoryProvider {
+        DirectoryProvider::new(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../networks"),
+            GENESIS_FILENAME,
+        )
+    }
+
+    #[test]
+    fn directory_namespaces() {
+        assert_eq!(vec![String::from("local")], networks().namespaces());
+    }
+
+    #[test]
+    fn directory_genesis_file() {
+        let networks = networks();
+        assert!(networks.genesis_file("local").is_some());
+        assert!(networks.genesis_file("sovrin:staging").is_none());
+        assert!(networks.genesis_file("..:networks:local").is_none());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+// Type of NODE transactions on the pool ledger
+static NODE_TXN_TYPE: &str = "0";
+
+/// Validator node of a pool as listed on the pool ledger
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub alias: String,
+    /// DID of the node, the base58 encoded verkey of its node transactions
+    pub did: String,
+    /// Address clients send requests to, as `ip:port`
+    pub client_address: Option<String>,
+    /// Address other nodes reach the node at, as `ip:port`
+    pub node_address: Option<String>,
+}
+
+/// Validator set of a pool as of its last refresh, for monitoring
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolInfo {
+    pub namespace: Option<String>,
+    /// Nodes with the validator service, sorted by alias
+    pub nodes: Vec<NodeInfo>,
+    /// Sequence number of the latest pool ledger transaction known to the pool
+    pub ledger_seq_no: Option<u64>,
+    /// SHA-256 of the first pool ledger transaction as lowercase hex. It stays
+    /// the same across refreshes and versions of the genesis file of a network,
+    /// unlike `networks::genesis_hash`.
+    pub genesis_fingerprint: Option<String>,
+}
+
+impl PoolInfo {
+    /// Info of the pool holding the JSON pool ledger transactions. Later NODE
+    /// transactions of a node update the fields they carry, nodes whose services
+    /// were emptied are no longer validators.
+    pub fn from_transactions(namespace: Option<&str>, transactions: &[String]) -> Self {
+        let transactions: Vec<Value> = transactions
+            .iter()
+            .filter_map(|txn| serde_json::from_str(txn).ok())
+            .collect();
+        let seq_no = |txn: &Value| txn["txnMetadata"]["seqNo"].as_u64();
+
+        let mut nodes: BTreeMap<String, Value> = BTreeMap::new();
+        for txn in &transactions {
+            let txn = &txn["txn"];
+            let (dest, data) = match (txn["data"]["dest"].as_str(), &txn["data"]["data"]) {
+                (Some(dest), Value::Object(data)) if txn["type"] == NODE_TXN_TYPE => (dest, data),
+                _ => continue,
+            };
+            let node = nodes
+                .entry(dest.to_string())
+                .or_insert_with(|| Value::Object(Default::default()));
+            for (field, value) in data {
+                node[field] = value.clone();
+            }
+        }
+        let mut nodes: Vec<NodeInfo> = nodes
+            .into_iter()
+            .filter(|(_, data)| {
+                data["services"]
+                    .as_array()
+                    .map_or(false, |services| services.iter().any(|s| s == "VALIDATOR"))
+            })
+            .map(|(did, data)| NodeInfo {
+                alias: data["alias"].as_str().unwrap_or_default().to_string(),
+                did,
+                client_address: address(&data, "client_ip", "client_port"),
+                node_address: address(&data, "node_ip", "node_port"),
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.alias.cmp(&b.alias));
+
+        let genesis_fingerprint = transactions
+            .iter()
+            .min_by_key(|txn| seq_no(txn))
+            .and_then(|txn| serde_jcs::to_vec(txn).ok())
+            .map(|txn| format!("{:x}", Sha256::digest(&txn)));
+
+        PoolInfo {
+            namespace: namespace.map(String::from),
+            nodes,
+            ledger_seq_no: transactions.iter().filter_map(seq_no).max(),
+            genesis_fingerprint,
+        }
+    }
+}
+
+fn address(data: &Value, ip: &str, port: &str) -> Option<String> {
+    Some(format!("{}:{}", data[ip].as_str()?, data[port].as_u64()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::node_txn;
+    use serde_json::json;
+
+    #[test]
+    fn nodes_merged_from_node_transactions() {
+        let node = |ip: &str| {
+            json!({
+                "client_ip": ip,
+                "client_port": 9702,
+                "node_ip": ip,
+                "node_port": 9701,
+                "services": ["VALIDATOR"]
+            })
+        };
+        let transactions = vec![
+            node_txn(
+                1,
+                "Node1",
+                "Gw6pDLhcBcoQesN72qfotTgFa7cbuqZpkX3Xo6pLhPhv",
+                node("10.0.0.1"),
+            ),
+            node_txn(
+                2,
+                "Node2",
+                "8ECVSk179mjsjKRLWiQtssMLgp6EPhWXtaYyStWPSGAb",
+                node("10.0.0.2"),
+            ),
+            node_txn(
+                3,
+                "Node3",
+                "DKVxG2fXXTU8yT5N7hGEbXB3dfdAnYv1JczDUHpmDxya",
+                node("10.0.0.3"),
+            ),
+            // Node1 moved, Node3 demoted
+            node_txn(
+                4,
+                "Node1",
+                "Gw6pDLhcBcoQesN72qfotTgFa7cbuqZpkX3Xo6pLhPhv",
+                json!({ "client_ip": "10.0.1.1", "client_port": 9802 }),
+            ),
+            node_txn(
+                5,
+                "Node3",
+                "DKVxG2fXXTU8yT5N7hGEbXB3dfdAnYv1JczDUHpmDxya",
+                json!({ "services": [] }),
+            ),
+        ];
+
+        let info = PoolInfo::from_transactions(Some("idunion"), &transactions);
+
+        assert_eq!(Some(String::from("idunion")), info.namespace);
+        assert_eq!(
+            vec![
+                NodeInfo {
+                    alias: String::from("Node1"),
+                    did: String::from("Gw6pDLhcBcoQesN72qfotTgFa7cbuqZpkX3Xo6pLhPhv"),
+                    client_address: Some(String::from("10.0.1.1:9802")),
+                    node_address: Some(String::from("10.0.0.1:9701")),
+                },
+                NodeInfo {
+                    alias: String::from("Node2"),
+                    did: String::from("8ECVSk179mjsjKRLWiQtssMLgp6EPhWXtaYyStWPSGAb"),
+                    client_address: Some(String::from("10.0.0.2:9702")),
+                    node_address: Some(String::from("10.0.0.2:9701")),
+                },
+            ],
+            info.nodes
+        );
+        assert_eq!(Some(5), info.ledger_seq_no);
+
+        // The fingerprint does not change with later transactions
+        let genesis = PoolInfo::from_transactions(None, &transactions[..3]);
+        assert_eq!(64, info.genesis_fingerprint.as_ref().unwrap().len());
+        assert_eq!(genesis.genesis_fingerprint, info.genesis_fingerprint);
+    }
+
+    #[test]
+    fn info_of_pool_without_transactions() {
+        let info = PoolInfo::from_transactions(None, &[]);
+        assert!(info.nodes.is_empty());
+        assert_eq!(None, info.ledger_seq_no);
+        assert_eq!(None, info.genesis_fingerprint);
+    }
+}
@@ -0,0 +1,161 @@
+use std::time::Instant;
+
+use indy_vdr::utils::did::DidValue;
+use log::warn;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::ledger::LedgerRequester;
+use super::timestamp::{TimeFormat, Timestamp};
+
+// The status is requested as GET_NYM of this DID. Its state proof carries the
+// multi-signature of the nodes also if the DID is not on the ledger.
+static PROBE_DID: &str = "V4SGRU86Z58d6TV7PBUe6f";
+
+/// Reply of a node to the status request
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+    pub alias: String,
+    /// Whether the node replied. Read requests are only sent to as many nodes
+    /// as needed for a valid reply, so a node without reply may not have been
+    /// asked.
+    pub replied: bool,
+    /// Milliseconds until the reply of the node arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+/// Status of the pool of a namespace
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStatus {
+    pub namespace: String,
+    /// Nodes of the pool sorted by alias
+    pub nodes: Vec<NodeStatus>,
+    /// Ledger time of the latest batch signed by the nodes, taken from the
+    /// state proof of the reply
+    pub ledger_time: Option<Timestamp>,
+    /// Milliseconds until the pool returned a valid reply
+    pub duration_ms: u64,
+    /// Why the status request failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Sends a read request to the pool and reports which nodes replied and how
+/// fast. A failed request is reported in the status, not as an error.
+pub async fn pool_status<T: LedgerRequester>(pool: &T, namespace: &str) -> PoolStatus {
+    let started = Instant::now();
+    let request = pool
+        .request_builder()
+        .build_get_nym_request(None, &DidValue::new(PROBE_DID, None));
+    let result = match request {
+        Ok(request) => pool.submit_timed(&request).await,
+        Err(err) => Err(err.into()),
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let (reply, timing, error) = match result {
+        Ok((reply, timing)) => (Some(reply), timing, None),
+        Err(err) => {
+            warn!("Status request to pool of {} failed, {}", namespace, err);
+            (None, Default::default(), Some(err.to_string()))
+        }
+    };
+
+    let mut aliases = pool.node_aliases();
+    aliases.sort();
+    let nodes = aliases
+        .into_iter()
+        .map(|alias| {
+            let latency = timing.get(&alias);
+            NodeStatus {
+                replied: latency.is_some(),
+                latency_ms: latency.map(|seconds| (seconds * 1000.0).round() as u64),
+                alias,
+            }
+        })
+        .collect();
+
+    PoolStatus {
+        namespace: namespace.to_string(),
+        nodes,
+        ledger_time: reply.as_deref().and_then(ledger_time),
+        duration_ms,
+        error,
+    }
+}
+
+fn ledger_time(reply: &str) -> Option<Timestamp> {
+    let reply: Value = serde_json::from_str(reply).ok()?;
+    reply["result"]["state_proof"]["multi_signature"]["value"]["timestamp"]
+        .as_i64()
+        .map(|seconds| Timestamp::new(seconds, TimeFormat::Rfc3339))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DidIndyError;
+    use crate::tests::MockLedger;
+    use futures_executor::block_on;
+    use serde_json::json;
+
+    fn nodes() -> [(&'static str, Option<f32>); 3] {
+        [
+            ("Node2", None),
+            ("Node1", Some(0.042)),
+            ("Node3", Some(0.1)),
+        ]
+    }
+
+    #[test]
+    fn status_of_replying_pool() {
+        let reply = json!({
+            "op": "REPLY",
+            "result": {
+                "type": "105",
+                "data": null,
+                "state_proof": {
+                    "multi_signature": {
+                        "value": { "ledger_id": 1, "timestamp": 1636641222 }
+                    }
+                }
+            }
+        });
+        let pool = MockLedger::new()
+            .reply(reply.to_string())
+            .with_nodes(&nodes());
+
+        let mut status = serde_json::to_value(block_on(pool_status(&pool, "idunion"))).unwrap();
+        status["durationMs"] = json!(0);
+
+        assert_eq!(
+            json!({
+                "namespace": "idunion",
+                "nodes": [
+                    { "alias": "Node1", "replied": true, "latencyMs": 42 },
+                    { "alias": "Node2", "replied": false },
+                    { "alias": "Node3", "replied": true, "latencyMs": 100 }
+                ],
+                "ledgerTime": "2021-11-11T14:33:42Z",
+                "durationMs": 0
+            }),
+            status
+        );
+    }
+
+    #[test]
+    fn status_of_failing_pool() {
+        let pool = MockLedger::new()
+            .fail(DidIndyError::RequestTimeout)
+            .with_nodes(&nodes());
+
+        let status = block_on(pool_status(&pool, "idunion"));
+
+        assert_eq!(Some(String::from("Ledger request timed out")), status.error);
+        assert_eq!(None, status.ledger_time);
+        assert!(status.nodes.iter().all(|node| !node.replied));
+    }
+}
@@ -0,0 +1,1017 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use log::{debug, warn};
+
+use serde::Serialize;
+
+use indy_vdr::pool::SharedPool;
+
+use super::config::{ResolveOptions, ResolverConfig};
+use super::did::{DidUrl, QueryParameter};
+use super::did_indy_resolver::DidIndyResolver;
+use super::error::{DidIndyError, DidIndyResult};
+use super::ledger::LedgerRequester;
+use super::networks::{DirectoryProvider, NetworkProvider, GENESIS_FILENAME};
+use super::pool_info::PoolInfo;
+use super::resolver::{DereferencingResult, ResolutionResult, Resolver};
+
+/// Namespaces changed by `ResolverRegistry::reload`, each sorted by name
+#[derive(Serialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Namespaces keeping their pool, with the new config applied
+    pub kept: Vec<String>,
+}
+
+/// A registered namespace and the aliases resolving to it
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct NamespaceInfo {
+    pub namespace: String,
+    pub aliases: Vec<String>,
+}
+
+/// Resolvers for the namespaces of several Indy networks. Pools for namespaces
+/// that are not registered can be obtained from a `NetworkProvider`. Resolvers
+/// of other ledger backends than `T` can be registered as `DidIndyResolver`.
+pub struct ResolverRegistry<T: LedgerRequester> {
+    resolvers: RwLock<HashMap<String, Arc<Resolver<T>>>>,
+    // Resolvers of other backends, not affected by reloads
+    backends: HashMap<String, Arc<dyn DidIndyResolver + Send + Sync>>,
+    // (namespace, genesis hash) -> resolver, not affected by reloads
+    genesis: HashMap<(String, String), Arc<Resolver<T>>>,
+    // alias -> namespace
+    aliases: HashMap<String, String>,
+    provider: Option<Box<dyn NetworkProvider<T>>>,
+    // Config of resolvers for provided pools
+    config: RwLock<ResolverConfig>,
+    // Namespaces the provider has no pool for
+    unavailable: RwLock<HashSet<String>>,
+    // Serializes provider calls so each namespace is requested only once
+    provisioning: Mutex<()>,
+}
+
+impl<T: LedgerRequester> Default for ResolverRegistry<T> {
+    fn default() -> Self {
+        ResolverRegistry::new()
+    }
+}
+
+impl<T: LedgerRequester> ResolverRegistry<T> {
+    pub fn new() -> Self {
+        ResolverRegistry {
+            resolvers: RwLock::new(HashMap::new()),
+            backends: HashMap::new(),
+            genesis: HashMap::new(),
+            aliases: HashMap::new(),
+            provider: None,
+            config: RwLock::new(ResolverConfig::default()),
+            unavailable: RwLock::new(HashSet::new()),
+            provisioning: Mutex::new(()),
+        }
+    }
+
+    pub fn register(&mut self, namespace: &str, resolver: Resolver<T>) {
+        self.resolvers.get_mut().unwrap().insert(
+            namespace.to_string(),
+            Arc::new(resolver.registered_as(namespace)),
+        );
+    }
+
+    /// Registers a resolver of another ledger backend for the namespace. It is
+    /// only reachable through `resolver`, and kept as it is by `reload`.
+    pub fn register_resolver(
+        &mut self,
+        namespace: &str,
+        resolver: Box<dyn DidIndyResolver + Send + Sync>,
+    ) {
+        self.backends
+            .insert(namespace.to_string(), Arc::from(resolver));
+    }
+
+    /// Registers the resolver of one of several pools sharing the namespace,
+    /// selected by the SHA-256 of its genesis transactions, see
+    /// `networks::genesis_hash`. It is only used for DID URLs with the
+    /// `genesisHash` query parameter and kept as it is by `reload`. DIDs without
+    /// the parameter resolve with the resolver registered for the namespace.
+    pub fn register_genesis(&mut self, namespace: &str, genesis_hash: &str, resolver: Resolver<T>) {
+        self.genesis.insert(
+            (namespace.to_string(), genesis_hash.to_lowercase()),
+            Arc::new(resolver.registered_as(namespace)),
+        );
+    }
+
+    /// Resolver of the pool of the namespace or alias with the genesis hash.
+    /// Fails with `GenesisHashNotFound` if no such pool is registered.
+    pub fn get_by_genesis(
+        &self,
+        namespace: &str,
+        genesis_hash: &str,
+    ) -> DidIndyResult<Arc<Resolver<T>>> {
+        let namespace = self.namespace_for(namespace);
+        self.genesis
+            .get(&(namespace.to_string(), genesis_hash.to_lowercase()))
+            .cloned()
+            .ok_or_else(|| DidIndyError::GenesisHashNotFound {
+                namespace: namespace.to_string(),
+                hash: genesis_hash.to_string(),
+            })
+    }
+
+    /// Makes DIDs of the alias namespace resolve on the network of a registered
+    /// namespace, e.g. `sov` for `sovrin`.
+    pub fn register_alias(&mut self, alias: &str, namespace: &str) -> DidIndyResult<()> {
+        if !self.resolvers.get_mut().unwrap().contains_key(namespace)
+            && !self.backends.contains_key(namespace)
+        {
+            return Err(DidIndyError::NamespaceNotSupported);
+        }
+        self.aliases
+            .insert(alias.to_string(), namespace.to_string());
+        Ok(())
+    }
+
+    /// Installs a provider consulted once for every namespace that is not
+    /// registered. Resolvers for provided pools are created with the given config.
+    pub fn set_provider(&mut self, provider: Box<dyn NetworkProvider<T>>, config: ResolverConfig) {
+        self.provider = Some(provider);
+        *self.config.get_mut().unwrap() = config;
+    }
+
+    /// Resolver for a namespace or alias. Unknown namespaces are looked up with
+    /// the provider, the outcome is kept for subsequent calls.
+    pub fn get(&self, namespace: &str) -> Option<Arc<Resolver<T>>> {
+        let namespace = self.namespace_for(namespace);
+        if let Some(resolver) = self.registered(namespace) {
+            return Some(resolver);
+        }
+
+        let provider = self.provider.as_ref()?;
+        let _provisioning = self.provisioning.lock().unwrap();
+        // Another caller may have provided the namespace while waiting
+        if let Some(resolver) = self.registered(namespace) {
+            return Some(resolver);
+        }
+        if self.unavailable.read().unwrap().contains(namespace) {
+            return None;
+        }
+
+        match provider.pool_for(namespace) {
+            Some(pool) => {
+                debug!("Provided pool for namespace {}", namespace);
+                let config = self.config.read().unwrap().clone();
+                let resolver =
+                    Arc::new(Resolver::with_config(pool, config).registered_as(namespace));
+                self.resolvers
+                    .write()
+                    .unwrap()
+                    .insert(namespace.to_string(), resolver.clone());
+                Some(resolver)
+            }
+            None => {
+                warn!("No pool available for namespace {}", namespace);
+                self.unavailable
+                    .write()
+                    .unwrap()
+                    .insert(namespace.to_string());
+                None
+            }
+        }
+    }
+
+    /// Registered and provided namespaces sorted by name, each with its aliases
+    pub fn namespaces(&self) -> Vec<NamespaceInfo> {
+        let mut namespaces: Vec<NamespaceInfo> = self
+            .resolvers
+            .read()
+            .unwrap()
+            .keys()
+            .chain(self.backends.keys())
+            .map(|namespace| {
+                let mut aliases: Vec<String> = self
+                    .aliases
+                    .iter()
+                    .filter(|(_, target)| *target == namespace)
+                    .map(|(alias, _)| alias.clone())
+                    .collect();
+                aliases.sort();
+                NamespaceInfo {
+                    namespace: namespace.clone(),
+                    aliases,
+                }
+            })
+            .collect();
+        namespaces.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+        namespaces
+    }
+
+    /// Validator sets of the pools of the namespaces with a pool, sorted by
+    /// namespace. Pools of namespaces not provisioned yet are not built for it.
+    pub fn pool_infos(&self) -> Vec<PoolInfo> {
+        let mut infos: Vec<PoolInfo> = self
+            .resolvers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(namespace, resolver)| PoolInfo {
+                namespace: Some(namespace.clone()),
+                ..resolver.pool_info()
+            })
+            .collect();
+        infos.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+        infos
+    }
+
+    /// Whether the DID or DID URL is valid did:indy syntax for a registered
+    /// namespace or alias. With a provider installed, unknown namespaces are
+    /// supported unless the provider had no pool for them. Neither the ledger nor
+    /// the provider is queried.
+    pub fn supports(&self, did: &str) -> bool {
+        let did_url = match DidUrl::from_str(did) {
+            Ok(did_url) => did_url,
+            Err(_) => return false,
+        };
+        let namespace = self.namespace_for(&did_url.namespace);
+        if let Some(backend) = self.backends.get(namespace) {
+            return backend.supports(did);
+        }
+        match self.registered(namespace) {
+            Some(resolver) => resolver.supports(did),
+            None => {
+                self.provider.is_some() && !self.unavailable.read().unwrap().contains(namespace)
+            }
+        }
+    }
+
+    fn namespace_for<'a>(&'a self, namespace: &'a str) -> &'a str {
+        self.aliases
+            .get(namespace)
+            .map(|namespace| namespace.as_str())
+            .unwrap_or(namespace)
+    }
+
+    fn registered(&self, namespace: &str) -> Option<Arc<Resolver<T>>> {
+        self.resolvers.read().unwrap().get(namespace).cloned()
+    }
+}
+
+impl<T: LedgerRequester + Send + Sync + 'static> ResolverRegistry<T> {
+    /// Resolver for a namespace or alias, of any backend. Unknown namespaces are
+    /// looked up with the provider like with `get`.
+    pub fn resolver(&self, namespace: &str) -> Option<Arc<dyn DidIndyResolver + Send + Sync>> {
+        if let Some(backend) = self.backends.get(self.namespace_for(namespace)) {
+            return Some(backend.clone());
+        }
+        self.get(namespace)
+            .map(|resolver| resolver as Arc<dyn DidIndyResolver + Send + Sync>)
+    }
+
+    /// Resolver for the DID or DID URL: the one of the pool selected by its
+    /// `genesisHash` query parameter, otherwise the one for its namespace like
+    /// with `resolver`
+    pub fn resolver_for(&self, did: &str) -> DidIndyResult<Arc<dyn DidIndyResolver + Send + Sync>> {
+        let did_url = DidUrl::from_str(did)?;
+        if let Some(hash) = did_url.query.get(&QueryParameter::GenesisHash) {
+            return Ok(self.get_by_genesis(&did_url.namespace, hash)?);
+        }
+        self.resolver(&did_url.namespace)
+            .ok_or(DidIndyError::NamespaceNotSupported)
+    }
+}
+
+impl<T: LedgerRequester + Clone> ResolverRegistry<T> {
+    /// Switches to the namespaces and config without interrupting resolutions.
+    /// Pools for added namespaces are obtained from the provider, removed
+    /// namespaces are retired and kept namespaces reuse their pool with the new
+    /// config, keeping their request hook, read request signing and namespace.
+    /// Resolutions in flight finish with the resolver they started with, so the
+    /// pool of a removed namespace is dropped once they are drained. If there is
+    /// no pool for an added namespace, the registry is left unchanged.
+    pub fn reload(
+        &self,
+        namespaces: &[String],
+        config: ResolverConfig,
+    ) -> DidIndyResult<ReloadSummary> {
+        let _provisioning = self.provisioning.lock().unwrap();
+        let current = self.resolvers.read().unwrap().clone();
+
+        let mut summary = ReloadSummary::default();
+        let mut resolvers = HashMap::new();
+        for namespace in namespaces {
+            let resolver = match current.get(namespace) {
+                Some(resolver) => {
+                    summary.kept.push(namespace.clone());
+                    resolver.reconfigured(config.clone())
+                }
+                None => {
+                    let pool = self
+                        .provider
+                        .as_ref()
+                        .and_then(|provider| provider.pool_for(namespace))
+                        .ok_or_else(|| DidIndyError::PoolUnavailable(namespace.clone()))?;
+                    summary.added.push(namespace.clone());
+                    Resolver::with_config(pool, config.clone()).registered_as(namespace)
+                }
+            };
+            resolvers.insert(namespace.clone(), Arc::new(resolver));
+        }
+        summary.removed = current
+            .keys()
+            .filter(|namespace| !resolvers.contains_key(*namespace))
+            .cloned()
+            .collect();
+        summary.added.sort();
+        summary.removed.sort();
+        summary.kept.sort();
+
+        *self.resolvers.write().unwrap() = resolvers;
+        *self.config.write().unwrap() = config;
+        self.unavailable.write().unwrap().clear();
+        debug!("Reloaded namespaces, {:?}", summary);
+        Ok(summary)
+    }
+}
+
+/// Namespaces of a networks directory, see `MultiResolver::load_networks_dir`
+#[derive(Debug, Default)]
+pub struct NetworksLoad {
+    /// Namespaces a pool was added for, sorted by name
+    pub loaded: Vec<String>,
+    /// Namespaces whose genesis file could not be used, with the reason
+    pub skipped: Vec<(String, DidIndyError)>,
+}
+
+/// Resolves and dereferences DIDs of several namespaces, each on the pool added
+/// for it. A `ResolverRegistry` for callers that have pools rather than
+/// resolvers, with the same config for all of them.
+pub struct MultiResolver<T: LedgerRequester = SharedPool> {
+    registry: ResolverRegistry<T>,
+    config: ResolverConfig,
+}
+
+impl<T: LedgerRequester> Default for MultiResolver<T> {
+    fn default() -> Self {
+        MultiResolver::new()
+    }
+}
+
+impl<T: LedgerRequester> MultiResolver<T> {
+    pub fn new() -> Self {
+        MultiResolver::with_config(ResolverConfig::default())
+    }
+
+    /// Resolvers of pools added later are created with the config
+    pub fn with_config(config: ResolverConfig) -> Self {
+        MultiResolver {
+            registry: ResolverRegistry::new(),
+            config,
+        }
+    }
+
+    /// Resolves DIDs of the namespace, e.g. `idunion` or `sovrin:staging`, on
+    /// the pool. Replaces the pool added for the namespace before.
+    pub fn add_pool(&mut self, namespace: &str, pool: T) {
+        self.registry
+            .register(namespace, Resolver::with_config(pool, self.config.clone()));
+    }
+
+    /// Same as `add_pool`
+    pub fn register(&mut self, namespace: &str, pool: T) {
+        self.add_pool(namespace, pool);
+    }
+
+    /// The registry of the added pools, e.g. for their namespaces
+    pub fn registry(&self) -> &ResolverRegistry<T> {
+        &self.registry
+    }
+
+    /// The registry of the added pools, e.g. to register aliases
+    pub fn registry_mut(&mut self) -> &mut ResolverRegistry<T> {
+        &mut self.registry
+    }
+
+    pub fn resolve(&self, did: &str) -> DidIndyResult<ResolutionResult> {
+        self.resolve_with_options(did, &ResolveOptions::default())
+    }
+
+    pub fn resolve_with_options(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<ResolutionResult> {
+        self.resolver_for(did)?.resolve_with_options(did, options)
+    }
+
+    /// Resolves the DID into the serialized resolution result
+    pub fn resolve_string(&self, did: &str) -> DidIndyResult<String> {
+        self.resolve_string_with_options(did, &ResolveOptions::default())
+    }
+
+    pub fn resolve_string_with_options(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<String> {
+        self.resolver_for(did)?
+            .resolve_string_with_options(did, options)
+    }
+
+    pub fn dereference(&self, did_url: &str) -> DidIndyResult<DereferencingResult> {
+        self.dereference_with_options(did_url, &ResolveOptions::default())
+    }
+
+    pub fn dereference_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<DereferencingResult> {
+        self.resolver_for(did_url)?
+            .dereference_with_options(did_url, options)
+    }
+
+    /// Dereferences the DID URL into the serialized dereferencing result
+    pub fn dereference_string(&self, did_url: &str) -> DidIndyResult<String> {
+        self.dereference_string_with_options(did_url, &ResolveOptions::default())
+    }
+
+    pub fn dereference_string_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<String> {
+        self.resolver_for(did_url)?
+            .dereference_string_with_options(did_url, options)
+    }
+
+    // Resolver of the pool added for the namespace of the DID, failing with
+    // `UnknownNamespace` naming it if there is none
+    fn resolver_for(&self, did: &str) -> DidIndyResult<Arc<Resolver<T>>> {
+        let did_url = DidUrl::from_str(did)?;
+        if let Some(hash) = did_url.query.get(&QueryParameter::GenesisHash) {
+            return self.registry.get_by_genesis(&did_url.namespace, hash);
+        }
+        self.registry
+            .get(&did_url.namespace)
+            .ok_or(DidIndyError::UnknownNamespace(did_url.namespace))
+    }
+}
+
+impl MultiResolver<SharedPool> {
+    /// Multi resolver with the default config and a pool for each genesis file
+    /// of the networks directory, see `load_networks_dir`
+    pub fn from_networks_dir(path: impl Into<PathBuf>) -> (Self, NetworksLoad) {
+        let mut resolver = MultiResolver::new();
+        let load = resolver.load_networks_dir(path);
+        (resolver, load)
+    }
+
+    /// Adds a pool for each `pool_transactions_genesis.json` of a directory
+    /// following the indy-did-networks layout, e.g. for `sovrin:staging` from
+    /// `sovrin/staging/pool_transactions_genesis.json`. Genesis files that
+    /// cannot be read or whose pool cannot be built are skipped with a warning
+    /// rather than failing the others.
+    pub fn load_networks_dir(&mut self, path: impl Into<PathBuf>) -> NetworksLoad {
+        let networks = DirectoryProvider::new(path, GENESIS_FILENAME);
+        let mut load = NetworksLoad::default();
+        for namespace in networks.namespaces() {
+            match networks.build_pool_for(&namespace) {
+                Ok(pool) => {
+                    self.add_pool(&namespace, pool);
+                    load.loaded.push(namespace);
+                }
+                Err(err) => {
+                    warn!("Skipping genesis file of {}, {}", namespace, err);
+                    load.skipped.push((namespace, err));
+                }
+            }
+        }
+        load
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::genesis_hash;
+    use crate::signing::{ReadRequestSigning, RequestSigner};
+    use crate::tests::{node_txn, nym_reply, MockLedger};
+    use futures_util::future::LocalBoxFuture;
+    use indy_vdr::ledger::RequestBuilder;
+    use indy_vdr::pool::{PreparedRequest, ProtocolVersion};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn registry() -> ResolverRegistry<MockLedger> {
+        let mut registry = ResolverRegistry::new();
+        registry.register("sovrin", Resolver::new(MockLedger::new()));
+        registry.register("sovrin:staging", Resolver::new(MockLedger::new()));
+        registry.register("idunion", Resolver::new(MockLedger::new()));
+        registry.register_alias("sov", "sovrin").unwrap();
+        registry
+    }
+
+    #[test]
+    fn pool_infos_of_registered_namespaces() {
+        let validator = serde_json::json!({
+            "client_ip": "10.0.0.1",
+            "client_port": 9702,
+            "node_ip": "10.0.0.1",
+            "node_port": 9701,
+            "services": ["VALIDATOR"]
+        });
+        let pool = MockLedger::new().with_pool_transactions(vec![node_txn(
+            1,
+            "Node1",
+            "Gw6pDLhcBcoQesN72qfotTgFa7cbuqZpkX3Xo6pLhPhv",
+            validator,
+        )]);
+        let mut registry = ResolverRegistry::new();
+        registry.register("sovrin", Resolver::new(MockLedger::new()));
+        registry.register("idunion", Resolver::new(pool));
+
+        let infos = registry.pool_infos();
+
+        assert_eq!(2, infos.len());
+        assert_eq!(Some(String::from("idunion")), infos[0].namespace);
+        assert_eq!(1, infos[0].nodes.len());
+        assert_eq!(
+            Some(String::from("10.0.0.1:9702")),
+            infos[0].nodes[0].client_address
+        );
+        assert_eq!(Some(1), infos[0].ledger_seq_no);
+        assert!(infos[0].genesis_fingerprint.is_some());
+        assert_eq!(Some(String::from("sovrin")), infos[1].namespace);
+        assert!(infos[1].nodes.is_empty());
+    }
+
+    #[test]
+    fn supports_registered_namespace() {
+        let registry = registry();
+        assert!(registry.supports("did:indy:sovrin:staging:6cgbu8ZPoWTnR5Rv5JcSMB"));
+        assert!(registry
+            .supports("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4"));
+    }
+
+    #[test]
+    fn supports_alias() {
+        assert!(registry().supports("did:indy:sov:6cgbu8ZPoWTnR5Rv5JcSMB"));
+    }
+
+    #[test]
+    fn does_not_support_other_method() {
+        assert!(!registry().supports("did:sov:6cgbu8ZPoWTnR5Rv5JcSMB"));
+        assert!(!registry().supports("did:key:z6MkpTHR8VNsBxYAAWHut2Geadd9jSwuBV8xRoAnwWsdvktH"));
+    }
+
+    #[test]
+    fn does_not_support_unknown_namespace() {
+        assert!(!registry().supports("did:indy:bcovrin:6cgbu8ZPoWTnR5Rv5JcSMB"));
+    }
+
+    #[test]
+    fn does_not_support_invalid_syntax() {
+        assert!(!registry().supports("did:indy:sovrin:0cgbu8ZPoWTnR5Rv5JcSMB"));
+        assert!(!registry().supports("did:indy:sovrin"));
+    }
+
+    #[test]
+    fn namespaces_with_aliases() {
+        assert_eq!(
+            vec![
+                NamespaceInfo {
+                    namespace: String::from("idunion"),
+                    aliases: vec![],
+                },
+                NamespaceInfo {
+                    namespace: String::from("sovrin"),
+                    aliases: vec![String::from("sov")],
+                },
+                NamespaceInfo {
+                    namespace: String::from("sovrin:staging"),
+                    aliases: vec![],
+                },
+            ],
+            registry().namespaces()
+        );
+    }
+
+    struct StubProvider {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl NetworkProvider<MockLedger> for StubProvider {
+        fn pool_for(&self, namespace: &str) -> Option<MockLedger> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match namespace {
+                "bcovrin:test" => Some(MockLedger::new()),
+                _ => None,
+            }
+        }
+    }
+
+    fn registry_with_provider() -> (ResolverRegistry<MockLedger>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = registry();
+        registry.set_provider(
+            Box::new(StubProvider {
+                calls: calls.clone(),
+            }),
+            ResolverConfig::default(),
+        );
+        (registry, calls)
+    }
+
+    #[test]
+    fn provider_not_called_for_registered_namespace() {
+        let (registry, calls) = registry_with_provider();
+        assert!(registry.get("sovrin").is_some());
+        assert!(registry.get("sov").is_some());
+        assert_eq!(0, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn provided_pool_is_kept() {
+        let (registry, calls) = registry_with_provider();
+        assert!(registry.get("bcovrin:test").is_some());
+        assert!(registry.get("bcovrin:test").is_some());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        assert!(registry
+            .namespaces()
+            .iter()
+            .any(|info| info.namespace == "bcovrin:test"));
+    }
+
+    #[test]
+    fn unavailable_namespace_is_negative_cached() {
+        let (registry, calls) = registry_with_provider();
+        assert!(registry.get("indicio").is_none());
+        assert!(registry.get("indicio").is_none());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn supports_namespace_of_provider_without_calling_it() {
+        let (registry, calls) = registry_with_provider();
+        assert!(registry.supports("did:indy:indicio:6cgbu8ZPoWTnR5Rv5JcSMB"));
+        assert_eq!(0, calls.load(Ordering::SeqCst));
+
+        registry.get("indicio");
+        assert!(!registry.supports("did:indy:indicio:6cgbu8ZPoWTnR5Rv5JcSMB"));
+    }
+
+    // Pool stand-in that can be cloned like a SharedPool, identified by its
+    // generation
+    #[derive(Clone, PartialEq, Debug)]
+    struct StubPool(usize);
+
+    impl LedgerRequester for StubPool {
+        fn request_builder(&self) -> RequestBuilder {
+            RequestBuilder::new(ProtocolVersion::Node1_4)
+        }
+
+        fn submit<'a>(
+            &'a self,
+            _request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+            Box::pin(async { Err(DidIndyError::NotFound) })
+        }
+    }
+
+    // Provides pools for the namespaces, counting the pools built
+    struct StubPoolFactory {
+        namespaces: Vec<&'static str>,
+        built: Arc<AtomicUsize>,
+    }
+
+    impl NetworkProvider<StubPool> for StubPoolFactory {
+        fn pool_for(&self, namespace: &str) -> Option<StubPool> {
+            if self.namespaces.contains(&namespace) {
+                Some(StubPool(self.built.fetch_add(1, Ordering::SeqCst)))
+            } else {
+                None
+            }
+        }
+    }
+
+    fn reloadable_registry() -> (ResolverRegistry<StubPool>, Arc<AtomicUsize>) {
+        let built = Arc::new(AtomicUsize::new(0));
+        let mut registry = ResolverRegistry::new();
+        registry.set_provider(
+            Box::new(StubPoolFactory {
+                namespaces: vec!["sovrin", "idunion", "indicio"],
+                built: built.clone(),
+            }),
+            ResolverConfig::default(),
+        );
+        registry
+            .reload(
+                &[String::from("sovrin"), String::from("idunion")],
+                ResolverConfig::default(),
+            )
+            .unwrap();
+        (registry, built)
+    }
+
+    fn namespaces(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn reload_adds_and_removes_namespaces() {
+        let (registry, built) = reloadable_registry();
+        let idunion = registry.get("idunion").unwrap();
+        let sovrin = registry.get("sovrin").unwrap();
+
+        let summary = registry
+            .reload(
+                &namespaces(&["idunion", "indicio"]),
+                ResolverConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            ReloadSummary {
+                added: namespaces(&["indicio"]),
+                removed: namespaces(&["sovrin"]),
+                kept: namespaces(&["idunion"]),
+            },
+            summary
+        );
+        assert_eq!(3, built.load(Ordering::SeqCst));
+        assert!(registry.get("indicio").is_some());
+        assert!(!registry.resolvers.read().unwrap().contains_key("sovrin"));
+        // Holders of the retired resolver keep its pool until they are done
+        assert_eq!(&StubPool(0), sovrin.pool());
+        // The kept namespace keeps its pool
+        assert_eq!(idunion.pool(), registry.get("idunion").unwrap().pool());
+    }
+
+    #[test]
+    fn reload_applies_config_to_kept_namespaces() {
+        let (registry, built) = reloadable_registry();
+        let config = ResolverConfig {
+            max_concurrent_requests: Some(4),
+            strict: true,
+            ..ResolverConfig::default()
+        };
+
+        registry
+            .reload(&namespaces(&["idunion", "sovrin"]), config)
+            .unwrap();
+        assert_eq!(2, built.load(Ordering::SeqCst));
+        assert!(registry.get("sovrin").unwrap().config().strict);
+        assert!(registry.get("idunion").unwrap().config().strict);
+    }
+
+    struct CountingSigner(Arc<AtomicUsize>);
+
+    impl RequestSigner for CountingSigner {
+        fn sign(&self, _message: &[u8]) -> std::result::Result<Vec<u8>, String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![0; 64])
+        }
+    }
+
+    #[test]
+    fn reload_keeps_setup_of_registered_resolvers() {
+        let hooked = Arc::new(AtomicUsize::new(0));
+        let signed = Arc::new(AtomicUsize::new(0));
+        let counted = hooked.clone();
+        let resolver = Resolver::new(StubPool(0))
+            .with_namespace("idunion")
+            .with_request_hook(move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+            })
+            .with_read_request_signing(ReadRequestSigning::new(
+                "V4SGRU86Z58d6TV7PBUe6f",
+                Arc::new(CountingSigner(signed.clone())),
+            ));
+        let mut registry = ResolverRegistry::new();
+        registry.register("idunion", resolver);
+        let config = ResolverConfig {
+            strict: true,
+            ..ResolverConfig::default()
+        };
+
+        let summary = registry.reload(&namespaces(&["idunion"]), config).unwrap();
+        assert_eq!(namespaces(&["idunion"]), summary.kept);
+        let resolver = registry.get("idunion").unwrap();
+        assert!(resolver.config().strict);
+        assert_eq!(&StubPool(0), resolver.pool());
+        assert_eq!(Some("idunion"), resolver.namespace());
+        assert!(matches!(
+            resolver.resolve("did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::NamespaceNotSupported)
+        ));
+        assert!(resolver
+            .resolve("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_err());
+        assert_eq!(1, hooked.load(Ordering::SeqCst));
+        assert_eq!(1, signed.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn failed_reload_keeps_old_configuration() {
+        let (registry, _) = reloadable_registry();
+        let config = ResolverConfig {
+            strict: true,
+            ..ResolverConfig::default()
+        };
+
+        assert!(matches!(
+            registry.reload(&namespaces(&["idunion", "bcovrin"]), config),
+            Err(DidIndyError::PoolUnavailable(namespace)) if namespace == "bcovrin"
+        ));
+        let names: Vec<String> = registry
+            .namespaces()
+            .into_iter()
+            .map(|info| info.namespace)
+            .collect();
+        assert_eq!(namespaces(&["idunion", "sovrin"]), names);
+        assert!(!registry.get("idunion").unwrap().config().strict);
+    }
+
+    #[test]
+    fn resolver_of_other_backend() {
+        let mut registry = registry();
+        let backend = Resolver::new(MockLedger::new().reply(nym_reply(
+            "V4SGRU86Z58d6TV7PBUe6f",
+            "~CoRER63DVYnWZtK8uAzNbx",
+            None,
+        )));
+        registry.register_resolver("indicio", Box::new(backend));
+        registry.register_alias("ind", "indicio").unwrap();
+
+        assert!(registry.supports("did:indy:ind:V4SGRU86Z58d6TV7PBUe6f"));
+        assert!(registry.get("indicio").is_none());
+        let resolver = registry.resolver("ind").unwrap();
+        let document = resolver
+            .resolve_document("did:indy:indicio:V4SGRU86Z58d6TV7PBUe6f")
+            .unwrap();
+        assert_eq!("did:indy:indicio:V4SGRU86Z58d6TV7PBUe6f", document["id"]);
+        assert!(registry.resolver("sovrin").is_some());
+        assert!(registry
+            .namespaces()
+            .iter()
+            .any(|info| info.namespace == "indicio"));
+    }
+
+    #[test]
+    fn resolver_selected_by_genesis_hash() {
+        let resolver = |verkey: &str| {
+            Resolver::new(MockLedger::new().answer(
+                indy_vdr::ledger::constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    verkey,
+                    Some(serde_json::json!({})),
+                ),
+            ))
+        };
+        let main = genesis_hash("main genesis");
+        let test = genesis_hash("test genesis");
+        let mut registry = ResolverRegistry::new();
+        registry.register("idunion", resolver("~4Vr1bJ6V6jEPQ1PdmWyNkK"));
+        registry.register_genesis("idunion", &main, resolver("~4Vr1bJ6V6jEPQ1PdmWyNkK"));
+        registry.register_genesis("idunion", &test, resolver("~CoRER63DVYnWZtK8uAzNbx"));
+
+        let verkey = |did: &str| {
+            let document = registry
+                .resolver_for(did)
+                .unwrap()
+                .resolve_document(did)
+                .unwrap();
+            document["verificationMethod"][0]["publicKeyBase58"].clone()
+        };
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        assert_eq!(
+            verkey(did),
+            verkey(&format!("{}?genesisHash={}", did, main))
+        );
+        assert_ne!(
+            verkey(did),
+            verkey(&format!("{}?genesisHash={}", did, test))
+        );
+
+        let unknown = genesis_hash("other genesis");
+        let err = registry
+            .resolver_for(&format!("{}?genesisHash={}", did, unknown))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &err,
+            DidIndyError::GenesisHashNotFound { namespace, hash } if namespace == "idunion" && *hash == unknown
+        ));
+        assert_eq!("unknownNetwork", err.error_code());
+    }
+
+    #[test]
+    fn alias_for_unknown_namespace_fails() {
+        let mut registry = registry();
+        assert!(matches!(
+            registry.register_alias("bc", "bcovrin"),
+            Err(DidIndyError::NamespaceNotSupported)
+        ));
+    }
+
+    fn multi_resolver() -> MultiResolver<MockLedger> {
+        let mut resolver = MultiResolver::new();
+        resolver.add_pool(
+            "idunion",
+            MockLedger::new().answer(
+                indy_vdr::ledger::constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    Some(serde_json::json!({})),
+                ),
+            ),
+        );
+        resolver.add_pool("sovrin:staging", MockLedger::new());
+        resolver
+    }
+
+    #[test]
+    fn multi_resolver_routes_by_namespace() {
+        let resolver = multi_resolver();
+
+        assert!(resolver
+            .resolve("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+
+        let pool = |namespace: &str| resolver.registry().get(namespace).unwrap();
+        assert_eq!(1, pool("idunion").pool().requests().len());
+        assert!(pool("sovrin:staging").pool().requests().is_empty());
+    }
+
+    #[test]
+    fn multi_resolver_fails_for_unknown_namespace() {
+        let resolver = multi_resolver();
+        let err = resolver
+            .resolve("did:indy:bcovrin:test:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, DidIndyError::UnknownNamespace(namespace) if namespace == "bcovrin:test")
+        );
+        assert_eq!("No pool added for namespace bcovrin:test", err.to_string());
+        assert_eq!("methodNotSupported", err.error_code());
+        // The DID is not looked up on any other pool
+        let pool = |namespace: &str| resolver.registry().get(namespace).unwrap();
+        assert!(pool("idunion").pool().requests().is_empty());
+        assert!(pool("sovrin:staging").pool().requests().is_empty());
+    }
+
+    #[test]
+    fn multi_resolver_registers_pool_of_namespace() {
+        let mut resolver = MultiResolver::new();
+        resolver.register(
+            "sovrin:staging",
+            MockLedger::new().answer(
+                indy_vdr::ledger::constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    Some(serde_json::json!({})),
+                ),
+            ),
+        );
+
+        assert!(resolver
+            .resolve("did:indy:sovrin:staging:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+        assert!(matches!(
+            resolver.resolve("did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::UnknownNamespace(namespace)) if namespace == "sovrin"
+        ));
+    }
+
+    #[test]
+    fn invalid_genesis_files_of_networks_dir_skipped() {
+        let path =
+            std::env::temp_dir().join(format!("indy-didresolver-networks-{}", std::process::id()));
+        std::fs::remove_dir_all(&path).ok();
+        for namespace in ["broken", "sovrin/staging"] {
+            let dir = path.join(namespace);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(GENESIS_FILENAME), "not a genesis transaction").unwrap();
+        }
+        std::fs::create_dir_all(path.join("empty")).unwrap();
+
+        let (resolver, load) = MultiResolver::from_networks_dir(&path);
+
+        assert!(load.loaded.is_empty());
+        assert_eq!(
+            vec!["broken", "sovrin:staging"],
+            load.skipped
+                .iter()
+                .map(|(namespace, _)| namespace.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert!(matches!(
+            resolver.resolve("did:indy:broken:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::UnknownNamespace(_))
+        ));
+        std::fs::remove_dir_all(&path).ok();
+    }
+}
@@ -1,23 +1,50 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 
 use futures_executor::block_on;
+use futures_util::future::{join, join_all};
+use futures_util::stream::{self, StreamExt};
 use indy_vdr::utils::Qualifiable;
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use super::did::{DidUrl, LedgerObject, QueryParameter};
-use super::did_document::{DidDocument, LEGACY_INDY_SERVICE};
+use super::audit::{AuditLog, AuditRecord, CacheStatus};
+use super::caching_pool::{CacheConfig, CachingPool};
+use super::config::{
+    CacheMode, EndpointPrecedence, Representation, ResolveOptions, ResolverConfig,
+    RevRegEntryFallback, DEFAULT_CONTENT_CONTEXT,
+};
+use super::did::{DidUrl, LedgerObject, LedgerObjectType, QueryParameter, RevReg};
+use super::did_document::{
+    add_did_core_context, sort_keys, DidDocument, Document, LEGACY_INDY_SERVICE,
+};
 use super::error::{DidIndyError, DidIndyResult};
-use super::responses::{Endpoint, GetNymResultV1};
+use super::identifiers::interpret;
+use super::keys::{transform_keys, KeyFormat};
+use super::ledger::{
+    older_form, rejected_form, submit_within, Deadline, LedgerRequester, RequestDowngrade,
+    RequestLimiter, RequestStats,
+};
+use super::pool_info::PoolInfo;
+use super::responses::{Endpoint, GetNymResultV1, RevRegEntry, SignedTransaction};
+use super::signing::{sign_request, sign_result, take_proof, ReadRequestSigning};
+use super::status_list::{revocation_status_list, RevocationStatusList};
+use super::telemetry::{record_txn_type, ResolutionSpan};
+use super::timestamp::{TimeFormat, Timestamp};
+use super::trust::{evaluate as evaluate_trust_policy, PolicyEvaluation};
+use super::validation::validate_did_document;
+use super::verification::VerificationReport;
 
-use indy_vdr::common::error::VdrResult;
 use indy_vdr::ledger::constants;
 use indy_vdr::ledger::identifiers::{CredentialDefinitionId, RevocationRegistryId, SchemaId};
 use indy_vdr::ledger::RequestBuilder;
-use indy_vdr::pool::helpers::perform_ledger_request;
-use indy_vdr::pool::{Pool, PreparedRequest, RequestResult, TimingResult};
+use indy_vdr::pool::PreparedRequest;
 use indy_vdr::utils::did::DidValue;
-use log::error;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -26,386 +53,6483 @@ pub enum Result {
     Content(Value),
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct ContentMetadata {
-    node_response: Value,
-    object_type: String,
+/// Type of the ledger object a result was taken from, serialized as the
+/// `objectType` of the method metadata
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ObjectType {
+    Nym,
+    Schema,
+    CredDef,
+    #[serde(rename = "REVOC_REG_DEF")]
+    RevRegDef,
+    #[serde(rename = "REVOC_REG_DELTA")]
+    RevRegDelta,
+    #[serde(rename = "REVOC_REG_ENTRY")]
+    RevRegEntry,
+    /// Unprocessed ledger reply
+    Raw,
+    // Results serialized before the typo was fixed still deserialize
+    #[serde(alias = "UNKOWN")]
+    Unknown,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct ResolutionResult {
-    did_resolution_metadata: Option<String>,
-    did_document: Option<Value>,
-    did_document_metadata: Option<ContentMetadata>,
+impl ObjectType {
+    /// The name of the ledger object as serialized, e.g. `SCHEMA`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectType::Nym => "NYM",
+            ObjectType::Schema => "SCHEMA",
+            ObjectType::CredDef => "CRED_DEF",
+            ObjectType::RevRegDef => "REVOC_REG_DEF",
+            ObjectType::RevRegDelta => "REVOC_REG_DELTA",
+            ObjectType::RevRegEntry => "REVOC_REG_ENTRY",
+            ObjectType::Raw => "RAW",
+            ObjectType::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+// Callers comparing the object type with its name, as when it was a string
+impl PartialEq<&str> for ObjectType {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<ObjectType> for &str {
+    fn eq(&self, other: &ObjectType) -> bool {
+        *self == other.as_str()
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct DereferencingResult {
-    dereferencing_metadata: Option<String>,
-    content_stream: Option<Value>,
-    content_metadata: Option<ContentMetadata>,
+pub struct ContentMetadata {
+    node_response: Value,
+    object_type: ObjectType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cred_def: Option<Value>,
+    /// Set if the first entry of a revocation registry is returned as the
+    /// requested time is before it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    earliest_entry: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signed_txn: Option<SignedTransaction>,
+    /// Set if the content is the unprocessed ledger reply
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    raw_reply: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trust_policy: Option<PolicyEvaluation>,
+    /// Requests sent in an older form after the nodes rejected their form
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    request_downgrades: Vec<RequestDowngrade>,
+    /// Set if the legacy endpoint ATTRIB of a NYM without diddocContent was not
+    /// looked up, as asked with `skip_legacy_endpoint`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    legacy_endpoint_skipped: bool,
+    /// Status list of the revocation registry at the requested time, if asked
+    /// for with `ResolveOptions::revocation_status_list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revocation_status_list: Option<RevocationStatusList>,
+    /// Sequence number and time of the transaction of the ledger object, taken
+    /// from where the reply of its request type has them
+    #[serde(skip)]
+    seq_no: Option<u64>,
+    #[serde(skip)]
+    txn_time: Option<i64>,
 }
 
-pub struct Resolver<T: Pool> {
-    pool: T,
-}
+impl ContentMetadata {
+    /// The ledger reply the content was taken from
+    pub fn node_response(&self) -> &Value {
+        &self.node_response
+    }
 
-impl<T: Pool> Resolver<T> {
-    pub fn new(pool: T) -> Resolver<T> {
-        Resolver { pool }
+    /// Type of the ledger object, e.g. `ObjectType::Schema`
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
     }
 
-    pub fn dereference(&self, did_url: &str) -> DidIndyResult<String> {
-        let (data, metadata) = self._resolve(did_url)?;
+    /// Cred def of a revocation registry definition, if requested
+    pub fn cred_def(&self) -> Option<&Value> {
+        self.cred_def.as_ref()
+    }
 
-        let content = match data {
-            Result::Content(c) => Some(c),
-            _ => None,
-        };
+    pub fn earliest_entry(&self) -> bool {
+        self.earliest_entry
+    }
 
-        let result = DereferencingResult {
-            dereferencing_metadata: None,
-            content_stream: content,
-            content_metadata: Some(metadata),
-        };
+    pub fn signed_txn(&self) -> Option<&SignedTransaction> {
+        self.signed_txn.as_ref()
+    }
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+    pub fn raw_reply(&self) -> bool {
+        self.raw_reply
     }
 
-    pub fn resolve(&self, did: &str) -> DidIndyResult<String> {
-        let (data, metadata) = self._resolve(did)?;
+    pub fn trust_policy(&self) -> Option<&PolicyEvaluation> {
+        self.trust_policy.as_ref()
+    }
 
-        let diddoc = match data {
-            Result::DidDocument(doc) => Some(doc.to_value()?),
-            _ => None,
-        };
-        let result = ResolutionResult {
-            did_resolution_metadata: None,
-            did_document: diddoc,
-            did_document_metadata: Some(metadata),
-        };
+    pub fn request_downgrades(&self) -> &[RequestDowngrade] {
+        &self.request_downgrades
+    }
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+    pub fn legacy_endpoint_skipped(&self) -> bool {
+        self.legacy_endpoint_skipped
     }
 
-    fn _resolve(&self, did: &str) -> DidIndyResult<(Result, ContentMetadata)> {
-        let did_url = DidUrl::from_str(did)?;
+    pub fn revocation_status_list(&self) -> Option<&RevocationStatusList> {
+        self.revocation_status_list.as_ref()
+    }
 
-        let builder = self.pool.get_request_builder();
-        let request = build_request(&did_url, &builder)?;
+    /// Sequence number of the transaction of the ledger object
+    pub fn seq_no(&self) -> Option<u64> {
+        self.seq_no.or_else(|| self.ledger_position().0)
+    }
 
-        let ledger_data = handle_request(&self.pool, &request)?;
-        let data = parse_ledger_data(&ledger_data)?;
+    /// Ledger time of the transaction of the ledger object
+    pub fn txn_time(&self) -> Option<i64> {
+        self.txn_time.or_else(|| self.ledger_position().1)
+    }
 
-        let (result, object_type) = match request.txn_type.as_str() {
-            constants::GET_NYM => {
-                let get_nym_result: GetNymResultV1 = serde_json::from_str(data.as_str().unwrap())?;
+    // Position of a deserialized result, e.g. one read from the cache, is taken
+    // from the ledger reply again
+    fn ledger_position(&self) -> (Option<u64>, Option<i64>) {
+        let result = &self.node_response["result"];
+        match result["type"].as_str() {
+            Some(txn_type) => ledger_position(txn_type, result),
+            None => (None, None),
+        }
+    }
+}
 
-                let endpoint: Option<Endpoint> = if get_nym_result.diddoc_content.is_none() {
-                    // Legacy: Try to find an attached ATTRIBUTE transacation with raw endpoint
-                    self.fetch_legacy_endpoint(&did_url.id).ok()
-                } else {
-                    None
-                };
+/// DID document metadata holding only the properties defined by DID Core. The
+/// ledger reply and object type are kept in the method specific `methodMetadata`.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocumentMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deactivated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    equivalent_id: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    canonical_id: Option<String>,
+    method_metadata: ContentMetadata,
+}
 
-                let did_document = DidDocument::new(
-                    &did_url.namespace,
-                    &get_nym_result.dest,
-                    &get_nym_result.verkey,
-                    endpoint,
-                    None,
-                );
-                (Result::DidDocument(did_document), String::from("NYM"))
-            }
-            constants::GET_CRED_DEF => (Result::Content(data), String::from("CRED_DEF")),
-            constants::GET_SCHEMA => (Result::Content(data), String::from("SCHEMA")),
-            constants::GET_REVOC_REG_DEF => (Result::Content(data), String::from("REVOC_REG_DEF")),
-            constants::GET_REVOC_REG_DELTA => {
-                (Result::Content(data), String::from("REVOC_REG_DELTA"))
-            }
-            _ => (Result::Content(data), String::from("UNKOWN")),
-        };
+impl DidDocumentMetadata {
+    pub fn updated(&self) -> Option<&Timestamp> {
+        self.updated.as_ref()
+    }
 
-        let metadata = ContentMetadata {
-            node_response: serde_json::from_str(&ledger_data).unwrap(),
-            object_type,
-        };
+    /// Sequence number of the NYM transaction
+    pub fn version_id(&self) -> Option<&str> {
+        self.version_id.as_deref()
+    }
 
-        let result_with_metadata = (result, metadata);
+    pub fn canonical_id(&self) -> Option<&str> {
+        self.canonical_id.as_deref()
+    }
 
-        Ok(result_with_metadata)
+    /// Whether the DID is deactivated, only set for deactivated DIDs
+    pub fn deactivated(&self) -> Option<bool> {
+        self.deactivated
     }
 
-    fn fetch_legacy_endpoint(&self, did: &DidValue) -> DidIndyResult<Endpoint> {
-        let builder = self.pool.get_request_builder();
-        let request = builder.build_get_attrib_request(
-            None,
-            did,
-            Some(String::from(LEGACY_INDY_SERVICE)),
-            None,
-            None,
-        )?;
-        let ledger_data = handle_request(&self.pool, &request)?;
-        let endpoint_data = parse_ledger_data(&ledger_data)?;
-        let endpoint_data: Endpoint = serde_json::from_str(endpoint_data.as_str().unwrap())?;
-        Ok(endpoint_data)
+    pub fn method_metadata(&self) -> &ContentMetadata {
+        &self.method_metadata
     }
-}
 
-fn build_request(did: &DidUrl, builder: &RequestBuilder) -> DidIndyResult<PreparedRequest> {
-    let request = if did.path.is_some() {
-        match LedgerObject::from_str(did.path.as_ref().unwrap().as_str())? {
-            LedgerObject::Schema(schema) => builder.build_get_schema_request(
-                None,
-                &SchemaId::new(&did.id, &schema.name, &schema.version),
-            ),
-            LedgerObject::ClaimDef(claim_def) => builder.build_get_cred_def_request(
-                None,
-                &CredentialDefinitionId::from_str(
-                    format!(
-                        "{}:3:CL:{}:{}",
-                        &did.id, claim_def.schema_seq_no, claim_def.name
-                    )
-                    .as_str(),
-                )
-                .unwrap(),
-            ),
-            LedgerObject::RevRegDef(rev_reg_def) => builder.build_get_revoc_reg_def_request(
-                None,
-                &RevocationRegistryId::from_str(
-                    format!(
-                        "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
-                        &did.id,
-                        &did.id,
-                        rev_reg_def.schema_seq_no,
-                        rev_reg_def.claim_def_name,
-                        rev_reg_def.tag
-                    )
-                    .as_str(),
-                )
-                .unwrap(),
+    /// Takes the time and sequence number of the NYM from the ledger reply
+    fn new(method_metadata: ContentMetadata, time_format: TimeFormat) -> Self {
+        let reply = &method_metadata.node_response["result"];
+        DidDocumentMetadata {
+            created: None,
+            updated: txn_time(
+                reply["txnTime"].as_i64(),
+                reply["seqNo"].as_u64(),
+                time_format,
             ),
-            LedgerObject::RevRegEntry(rev_reg_entry) => {
-                let timestamp = parse_or_now(did.query.get(&QueryParameter::VersionTime))?;
+            version_id: reply["seqNo"].as_u64().map(|seq_no| seq_no.to_string()),
+            deactivated: None,
+            next_version_id: None,
+            equivalent_id: None,
+            canonical_id: None,
+            method_metadata,
+        }
+    }
+}
 
-                builder.build_get_revoc_reg_request(
-                    None,
-                    &RevocationRegistryId::from_str(
-                        format!(
-                            "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
-                            &did.id,
-                            &did.id,
-                            rev_reg_entry.schema_seq_no,
-                            rev_reg_entry.claim_def_name,
-                            rev_reg_entry.tag
-                        )
-                        .as_str(),
-                    )
-                    .unwrap(),
-                    timestamp,
-                )
-            }
-            LedgerObject::RevRegDelta(rev_reg_delta) => {
-                let mut from: Option<i64> = None;
-                if did.query.contains_key(&QueryParameter::From) {
-                    from = did
-                        .query
-                        .get(&QueryParameter::From)
-                        .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
-                        .and_then(|d| Some(d.timestamp()));
-                }
+/// Content metadata of a dereferenced ledger object with interoperable
+/// properties. The ledger reply and object type are kept in the method specific
+/// `methodMetadata`.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DereferencedContentMetadata {
+    content_type: String,
+    retrieved: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seq_no: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    txn_time: Option<Timestamp>,
+    method_metadata: ContentMetadata,
+}
 
-                let to = parse_or_now(did.query.get(&QueryParameter::To))?;
+impl DereferencedContentMetadata {
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
 
-                builder.build_get_revoc_reg_delta_request(
-                    None,
-                    &RevocationRegistryId::from_str(
-                        format!(
-                            "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
-                            &did.id,
-                            &did.id,
-                            rev_reg_delta.schema_seq_no,
-                            rev_reg_delta.claim_def_name,
-                            rev_reg_delta.tag
-                        )
-                        .as_str(),
-                    )
-                    .unwrap(),
-                    from,
-                    to,
-                )
-            }
-        }
-    } else {
-        // TODO: Use with new get nym request
-
-        let _seq_no: Option<i64> = did
-            .query
-            .get(&QueryParameter::VersionId)
-            .and_then(|v| v.parse().ok());
-        let _timestamp: Option<i64> = did
-            .query
-            .get(&QueryParameter::VersionTime)
-            .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
-            .and_then(|d| Some(d.timestamp()));
-
-        builder.build_get_nym_request(Option::None, &did.id)
-    };
-    request.map_err(|e| DidIndyError::from(e))
-}
+    pub fn retrieved(&self) -> &Timestamp {
+        &self.retrieved
+    }
+
+    pub fn seq_no(&self) -> Option<u64> {
+        self.seq_no
+    }
+
+    pub fn txn_time(&self) -> Option<&Timestamp> {
+        self.txn_time.as_ref()
+    }
 
-fn handle_request<T: Pool>(pool: &T, request: &PreparedRequest) -> DidIndyResult<String> {
-    let (result, _timing) = block_on(request_transaction(pool, &request))?;
-    match result {
-        RequestResult::Reply(data) => Ok(data),
-        RequestResult::Failed(error) => {
-            error!("Error requesting data from ledger, {}", error.to_string());
-            Err(DidIndyError::VdrError(error))
+    pub fn method_metadata(&self) -> &ContentMetadata {
+        &self.method_metadata
+    }
+
+    /// Takes the sequence number and time of the object found in the ledger
+    /// reply, they are omitted if the reply has none
+    fn new(method_metadata: ContentMetadata, time_format: TimeFormat) -> Self {
+        DereferencedContentMetadata {
+            content_type: String::from("application/json"),
+            retrieved: Timestamp::now(time_format),
+            seq_no: method_metadata.seq_no,
+            txn_time: txn_time(
+                method_metadata.txn_time,
+                method_metadata.seq_no,
+                time_format,
+            ),
+            method_metadata,
         }
     }
 }
 
-async fn request_transaction<T: Pool>(
-    pool: &T,
-    request: &PreparedRequest,
-) -> VdrResult<(RequestResult<String>, Option<TimingResult>)> {
-    perform_ledger_request(pool, &request).await
+// Type of a ledger object dereferenced as JSON-LD
+fn json_ld_type(object_type: LedgerObjectType) -> &'static str {
+    match object_type {
+        LedgerObjectType::Schema => "AnonCredsSchema",
+        LedgerObjectType::ClaimDef => "AnonCredsCredentialDefinition",
+        LedgerObjectType::RevRegDef => "AnonCredsRevocationRegistryDefinition",
+        LedgerObjectType::RevRegEntry => "AnonCredsRevocationRegistryEntry",
+        LedgerObjectType::RevRegDelta => "AnonCredsRevocationRegistryDelta",
+    }
 }
 
-fn parse_ledger_data(ledger_data: &str) -> DidIndyResult<Value> {
-    let v: Value = serde_json::from_str(&ledger_data)?;
-    let data: &Value = &v["result"]["data"];
-    if *data == Value::Null {
-        Err(DidIndyError::EmptyData)
-    } else {
-        Ok(data.to_owned())
+// Time of the transaction with the sequence number. Genesis-era transactions of
+// older ledgers have no txnTime, the timestamp is then omitted.
+fn txn_time(
+    txn_time: Option<i64>,
+    seq_no: Option<u64>,
+    time_format: TimeFormat,
+) -> Option<Timestamp> {
+    if let (None, Some(seq_no)) = (txn_time, seq_no) {
+        warn!(
+            "Ledger reply of transaction {} has no txnTime, omitting timestamps",
+            seq_no
+        );
     }
+    txn_time.map(|txn_time| Timestamp::new(txn_time, time_format))
 }
 
-fn parse_or_now(datetime: Option<&String>) -> DidIndyResult<i64> {
-    match datetime {
-        Some(datetime) => {
-            let dt = DateTime::parse_from_rfc3339(datetime)?;
-            Ok(dt.timestamp())
+// Sequence number and time of the transaction of the object in the result of a
+// ledger reply. Most replies have them in the result itself, the delta of a
+// revocation registry may only have them in its accum_to entry and GET_TXN has
+// them in the metadata of the transaction.
+fn ledger_position(txn_type: &str, result: &Value) -> (Option<u64>, Option<i64>) {
+    let fallback = match txn_type {
+        constants::GET_REVOC_REG_DELTA => result["data"]["value"].get("accum_to"),
+        constants::GET_TXN => result["data"].get("txnMetadata"),
+        _ => None,
+    };
+    let seq_no = result["seqNo"]
+        .as_u64()
+        .or_else(|| fallback.and_then(|fallback| fallback["seqNo"].as_u64()));
+    let txn_time = result["txnTime"]
+        .as_i64()
+        .or_else(|| fallback.and_then(|fallback| fallback["txnTime"].as_i64()));
+    (seq_no, txn_time)
+}
+
+/// Shape of the metadata of a result, see `ResolverConfig::legacy_metadata`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum Metadata<T> {
+    Spec(T),
+    Legacy(ContentMetadata),
+}
+
+impl Metadata<DidDocumentMetadata> {
+    /// The ledger reply and object type, in either shape
+    pub fn method_metadata(&self) -> &ContentMetadata {
+        match self {
+            Metadata::Spec(metadata) => &metadata.method_metadata,
+            Metadata::Legacy(metadata) => metadata,
         }
-        None => Ok(Utc::now().timestamp()),
     }
 }
 
-#[cfg(test)]
-mod tests {
+impl Metadata<DereferencedContentMetadata> {
+    /// The ledger reply and object type, in either shape
+    pub fn method_metadata(&self) -> &ContentMetadata {
+        match self {
+            Metadata::Spec(metadata) => &metadata.method_metadata,
+            Metadata::Legacy(metadata) => metadata,
+        }
+    }
 
-    use urlencoding::encode;
+    fn into_method_metadata(self) -> ContentMetadata {
+        match self {
+            Metadata::Spec(metadata) => metadata.method_metadata,
+            Metadata::Legacy(metadata) => metadata,
+        }
+    }
+}
 
-    use super::*;
-    use rstest::*;
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionResult {
+    did_resolution_metadata: Option<ResolutionMetadata>,
+    did_document: Option<Value>,
+    did_document_metadata: Option<Metadata<DidDocumentMetadata>>,
+}
 
-    use indy_vdr::pool::ProtocolVersion;
+impl ResolutionResult {
+    pub fn resolution_metadata(&self) -> Option<&ResolutionMetadata> {
+        self.did_resolution_metadata.as_ref()
+    }
 
-    #[fixture]
-    fn request_builder() -> RequestBuilder {
-        RequestBuilder::new(ProtocolVersion::Node1_4)
+    /// The DID document as it is serialized, None if the resolution failed
+    pub fn did_document_value(&self) -> Option<&Value> {
+        self.did_document.as_ref()
     }
 
-    #[rstest]
-    fn build_get_revoc_reg_request_from_version_time(request_builder: RequestBuilder) {
-        let datetime_as_str = "2020-12-20T19:17:47Z";
-        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime={}",datetime_as_str);
-        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
-        let request = build_request(&did_url, &request_builder).unwrap();
-        let timestamp = (*(request.req_json).get("operation").unwrap())
-            .get("timestamp")
-            .unwrap()
-            .as_u64()
-            .unwrap() as i64;
-        assert_eq!(constants::GET_REVOC_REG, request.txn_type);
+    pub fn did_document_metadata(&self) -> Option<&Metadata<DidDocumentMetadata>> {
+        self.did_document_metadata.as_ref()
+    }
 
-        assert_eq!(
-            DateTime::parse_from_rfc3339(datetime_as_str)
-                .unwrap()
-                .timestamp(),
-            timestamp
-        );
+    /// The DID document as typed struct, None if the resolution failed
+    pub fn did_document(&self) -> DidIndyResult<Option<Document>> {
+        self.did_document
+            .clone()
+            .map(Document::from_value)
+            .transpose()
     }
 
-    #[rstest]
-    fn build_get_revoc_reg_without_version_time(request_builder: RequestBuilder) {
-        let now = chrono::Utc::now().timestamp();
+    /// The canonicalized input DID, if it is echoed with `ResolveOptions::echo_input`
+    pub fn did(&self) -> Option<&str> {
+        self.did_resolution_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.did.as_deref())
+    }
 
-        let did_url_as_str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54";
-        let did_url = DidUrl::from_str(did_url_as_str).unwrap();
-        let request = build_request(&did_url, &request_builder).unwrap();
-        let timestamp = (*(request.req_json).get("operation").unwrap())
-            .get("timestamp")
-            .unwrap()
-            .as_u64()
-            .unwrap() as i64;
+    /// Warnings of a best effort resolution
+    pub fn warnings(&self) -> &[String] {
+        self.did_resolution_metadata
+            .as_ref()
+            .map(|metadata| metadata.warnings.as_slice())
+            .unwrap_or_default()
+    }
 
-        assert_eq!(constants::GET_REVOC_REG, request.txn_type);
-        assert!(timestamp >= now);
+    /// Error code of a failed resolution, e.g. `notFound`
+    pub fn error(&self) -> Option<&str> {
+        self.did_resolution_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.error.as_deref())
     }
 
-    #[rstest]
-    fn build_get_revoc_reg_request_fails_with_unparsable_version_time(
+    /// Result of a failed resolution, for reporting the error in the shape of
+    /// a resolution result
+    pub fn failed(error: &DidIndyError) -> Self {
+        ResolutionResult {
+            did_resolution_metadata: Some(ResolutionMetadata {
+                error: Some(error.error_code().to_string()),
+                ..ResolutionMetadata::default()
+            }),
+            did_document: None,
+            did_document_metadata: None,
+        }
+    }
+}
+
+/// Resolution metadata of DID Resolution. A successful resolution has the media
+/// type of the DID document and how long the resolution took, a failed one the
+/// error code.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Milliseconds the resolution took, including the ledger requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    did: Option<String>,
+    /// What could not be resolved of a best effort resolution
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    /// Signature of the result, if signing is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proof: Option<Value>,
+    // Properties added to the serialized result, e.g. marking a result served
+    // stale from the cache, kept so that its proof still verifies
+    #[serde(flatten)]
+    additional: Map<String, Value>,
+}
+
+impl ResolutionMetadata {
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn duration(&self) -> Option<u64> {
+        self.duration
+    }
+
+    /// Signature of the result, see `signing::verify_result`
+    pub fn proof(&self) -> Option<&Value> {
+        self.proof.as_ref()
+    }
+}
+
+/// Dereferencing metadata of DID URL Dereferencing echoing the dereferenced DID
+/// URL, with the media type of the content and how long dereferencing took, or
+/// the error code if it failed
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DereferencingMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Milliseconds dereferencing took, including the ledger requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+    /// The canonicalized input DID URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    did_url: Option<String>,
+    /// The fragment of the input DID URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fragment: Option<String>,
+    /// Signature of the result, if signing is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proof: Option<Value>,
+    // Properties added to the serialized result, e.g. marking a result served
+    // stale from the cache, kept so that its proof still verifies
+    #[serde(flatten)]
+    additional: Map<String, Value>,
+}
+
+impl DereferencingMetadata {
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn duration(&self) -> Option<u64> {
+        self.duration
+    }
+
+    /// Signature of the result, see `signing::verify_result`
+    pub fn proof(&self) -> Option<&Value> {
+        self.proof.as_ref()
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DereferencingResult {
+    dereferencing_metadata: Option<DereferencingMetadata>,
+    content_stream: Option<Value>,
+    content_metadata: Option<Metadata<DereferencedContentMetadata>>,
+}
+
+impl DereferencingResult {
+    pub fn dereferencing_metadata(&self) -> Option<&DereferencingMetadata> {
+        self.dereferencing_metadata.as_ref()
+    }
+
+    /// The dereferenced ledger object, None if dereferencing failed
+    pub fn content_stream(&self) -> Option<&Value> {
+        self.content_stream.as_ref()
+    }
+
+    pub fn content_metadata(&self) -> Option<&Metadata<DereferencedContentMetadata>> {
+        self.content_metadata.as_ref()
+    }
+
+    /// The canonicalized input DID URL
+    pub fn did_url(&self) -> Option<&str> {
+        self.dereferencing_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.did_url.as_deref())
+    }
+
+    /// The fragment of the input DID URL, None if it has none
+    pub fn fragment(&self) -> Option<&str> {
+        self.dereferencing_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.fragment.as_deref())
+    }
+
+    /// Error code of a failed dereferencing, e.g. `notFound`
+    pub fn error(&self) -> Option<&str> {
+        self.dereferencing_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.error.as_deref())
+    }
+
+    /// Result of a failed dereferencing, for reporting the error in the shape of
+    /// a dereferencing result
+    pub fn failed(error: &DidIndyError) -> Self {
+        DereferencingResult {
+            dereferencing_metadata: Some(DereferencingMetadata {
+                error: Some(error.error_code().to_string()),
+                ..DereferencingMetadata::default()
+            }),
+            content_stream: None,
+            content_metadata: None,
+        }
+    }
+}
+
+// Resolution and dereferencing results, which are signed, cached and audited
+// alike
+trait SignedResult: Serialize + DeserializeOwned {
+    // Property of the metadata holding the proof
+    const METADATA_PROPERTY: &'static str;
+
+    fn set_proof(&mut self, proof: Value);
+
+    fn method_metadata(&self) -> Option<&ContentMetadata>;
+}
+
+impl SignedResult for ResolutionResult {
+    const METADATA_PROPERTY: &'static str = "didResolutionMetadata";
+
+    fn set_proof(&mut self, proof: Value) {
+        self.did_resolution_metadata
+            .get_or_insert_with(ResolutionMetadata::default)
+            .proof = Some(proof);
+    }
+
+    fn method_metadata(&self) -> Option<&ContentMetadata> {
+        self.did_document_metadata
+            .as_ref()
+            .map(|metadata| metadata.method_metadata())
+    }
+}
+
+impl SignedResult for DereferencingResult {
+    const METADATA_PROPERTY: &'static str = "dereferencingMetadata";
+
+    fn set_proof(&mut self, proof: Value) {
+        self.dereferencing_metadata
+            .get_or_insert_with(DereferencingMetadata::default)
+            .proof = Some(proof);
+    }
+
+    fn method_metadata(&self) -> Option<&ContentMetadata> {
+        self.content_metadata
+            .as_ref()
+            .map(|metadata| metadata.method_metadata())
+    }
+}
+
+// Cached result kept beyond its TTL for serving it stale
+#[derive(Serialize, Deserialize)]
+struct StaleEntry {
+    retrieved: i64,
+    result: String,
+}
+
+// Records what a resolution actually does, for `explain`: the requests as
+// submitted, the ledger reply and the steps producing the result. Resolutions
+// that are not explained pass a disabled trace, which records nothing. The
+// trace is shared across awaits, so it locks to keep the futures `Send`.
+#[derive(Default)]
+struct Trace {
+    enabled: bool,
+    // Request of the ledger object, in the form that was answered
+    request: Mutex<Value>,
+    requests: Mutex<Vec<Value>>,
+    reply: Mutex<Value>,
+    steps: Mutex<Vec<Value>>,
+}
+
+impl Trace {
+    fn enabled() -> Self {
+        Trace {
+            enabled: true,
+            ..Trace::default()
+        }
+    }
+
+    // Steps are only built if the trace is enabled
+    fn step<F: FnOnce() -> Value>(&self, step: F) {
+        if self.enabled {
+            self.steps.lock().unwrap().push(step());
+        }
+    }
+
+    fn object_request(&self, request: &PreparedRequest) {
+        if self.enabled {
+            *self.request.lock().unwrap() = traced_request(request);
+        }
+    }
+
+    fn submitted(&self, request: &PreparedRequest) {
+        if self.enabled {
+            self.requests.lock().unwrap().push(traced_request(request));
+        }
+    }
+
+    fn downgraded(
+        &self,
+        request: &PreparedRequest,
+        older: &PreparedRequest,
+        downgrade: &RequestDowngrade,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let is_object_request = *self.request.lock().unwrap() == traced_request(request);
+        if is_object_request {
+            self.object_request(older);
+        }
+        self.step(|| {
+            json!({
+                "step": "requestDowngrade",
+                "txnType": downgrade.txn_type,
+                "changes": downgrade.changes,
+                "reason": downgrade.reason,
+            })
+        });
+    }
+
+    fn reply(&self, reply: &str) {
+        if self.enabled {
+            *self.reply.lock().unwrap() = serde_json::from_str(reply).unwrap_or_default();
+        }
+    }
+}
+
+fn traced_request(request: &PreparedRequest) -> Value {
+    json!({
+        "txnType": request.txn_type,
+        "protocolVersion": request.req_json["protocolVersion"],
+        "operation": request.req_json["operation"],
+    })
+}
+
+/// Change of an on-ledger attribute of a NYM between two versions
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AttributeChange {
+    /// `verkey`, `role` or `diddocContent`
+    pub attribute: String,
+    /// Value in the older version, null if it is not set by its transaction
+    pub from: Value,
+    /// Value in the newer version, null if it was removed
+    pub to: Value,
+}
+
+/// Attributes of a NYM changed between two versions, identified by the
+/// sequence numbers of their NYM transactions
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NymChanges {
+    pub did: String,
+    pub from_version: u64,
+    pub to_version: u64,
+    pub changes: Vec<AttributeChange>,
+}
+
+/// Outcome of warming the cache
+#[derive(Debug, Default)]
+pub struct WarmupSummary {
+    /// DIDs resolved and cached
+    pub resolved: Vec<String>,
+    /// DIDs that could not be resolved
+    pub failed: Vec<(String, DidIndyError)>,
+}
+
+/// Hook post-processing every ledger request before it is submitted
+pub type RequestHook = Arc<dyn Fn(&mut PreparedRequest) + Send + Sync>;
+
+pub struct Resolver<T: LedgerRequester> {
+    pool: T,
+    config: ResolverConfig,
+    limiter: RequestLimiter,
+    // Namespace of the network of the pool, if known
+    namespace: Option<String>,
+    // Namespace the resolver is registered for in a registry, which DIDs of its
+    // aliases resolve with
+    registered_namespace: Option<String>,
+    request_hook: Option<RequestHook>,
+    read_request_signing: Option<ReadRequestSigning>,
+}
+
+impl<T: LedgerRequester> Resolver<CachingPool<T>> {
+    /// Resolver caching the ledger replies of the pool in memory, so that
+    /// resolving a DID again within the TTL does not query the ledger. Replies
+    /// that cannot change are kept for the `immutable_ttl`.
+    pub fn with_cache(pool: T, cache: CacheConfig) -> Resolver<CachingPool<T>> {
+        Resolver::new(CachingPool::with_config(pool, cache))
+    }
+}
+
+impl<T: LedgerRequester> Resolver<T> {
+    pub fn new(pool: T) -> Resolver<T> {
+        Resolver::with_config(pool, ResolverConfig::default())
+    }
+
+    pub fn with_config(pool: T, config: ResolverConfig) -> Resolver<T> {
+        let limiter = RequestLimiter::new(config.max_concurrent_requests);
+        Resolver {
+            pool,
+            config,
+            limiter,
+            namespace: None,
+            registered_namespace: None,
+            request_hook: None,
+            read_request_signing: None,
+        }
+    }
+
+    /// Binds the resolver to the namespace of the network its pool connects to.
+    /// DIDs of other namespaces are then rejected with `NamespaceNotSupported`
+    /// instead of being looked up on the wrong network. A `ResolverRegistry`
+    /// already routes DIDs by namespace, so this is meant for resolvers used on
+    /// their own.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    // Records the namespace of the registry the resolver is reached through, so
+    // that the config of the namespace applies to DIDs of its aliases
+    pub(crate) fn registered_as(mut self, namespace: &str) -> Self {
+        self.registered_namespace = Some(namespace.to_string());
+        self
+    }
+
+    // Resolver with the config, keeping the pool, the namespaces, the request
+    // hook and the read request signing, e.g. for a reload of a registry
+    pub(crate) fn reconfigured(&self, config: ResolverConfig) -> Self
+    where
+        T: Clone,
+    {
+        Resolver {
+            pool: self.pool.clone(),
+            limiter: RequestLimiter::new(config.max_concurrent_requests),
+            config,
+            namespace: self.namespace.clone(),
+            registered_namespace: self.registered_namespace.clone(),
+            request_hook: self.request_hook.clone(),
+            read_request_signing: self.read_request_signing.clone(),
+        }
+    }
+
+    /// Sets a hook called with every ledger request after it is built and
+    /// before it is submitted, e.g. to add extensions to the request JSON.
+    pub fn with_request_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut PreparedRequest) + Send + Sync + 'static,
+    {
+        self.request_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Signs every ledger request as the submitter DID, for networks that only
+    /// answer authenticated read requests. Requests are sent unsigned if not set.
+    pub fn with_read_request_signing(mut self, signing: ReadRequestSigning) -> Self {
+        self.read_request_signing = Some(signing);
+        self
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub fn config(&self) -> &ResolverConfig {
+        &self.config
+    }
+
+    /// The pool requests are submitted to, e.g. to refresh a `StandbyPool`
+    pub fn pool(&self) -> &T {
+        &self.pool
+    }
+
+    /// Validator set of the pool as of its last refresh. Does not query the
+    /// ledger.
+    pub fn pool_info(&self) -> PoolInfo {
+        PoolInfo::from_transactions(self.namespace.as_deref(), &self.pool.pool_transactions())
+    }
+
+    /// Current number of in-flight and queued ledger requests
+    pub fn stats(&self) -> RequestStats {
+        self.limiter.stats()
+    }
+
+    /// Whether the DID or DID URL is valid did:indy syntax of the namespace the
+    /// resolver is bound to with `with_namespace`, if any. Does not query the
+    /// ledger.
+    pub fn supports(&self, did: &str) -> bool {
+        match DidUrl::from_str(did) {
+            Ok(did_url) => self.check_namespace(&did_url).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Checks that the input is a did:indy DID or DID URL this resolver can
+    /// resolve: valid namespace and DID, a known ledger object path and supported
+    /// query parameters with valid values. Runs the parsing and validation of a
+    /// resolution up to building the ledger request, without querying the ledger.
+    pub fn validate_did_url(&self, input: &str) -> DidIndyResult<()> {
+        let (mut did_url, _) = DidUrl::parse_strict(input).map_err(|err| err.error)?;
+        did_url.settle_version(self.config.version_conflict)?;
+        self.check_namespace(&did_url)?;
+        build_request(&did_url, &self.pool.request_builder())?;
+        Ok(())
+    }
+
+    /// Dereferences the DID URL into the dereferencing result, leaving its
+    /// serialization to the caller, e.g. a driver serializing it compactly
+    pub fn dereference(&self, did_url: &str) -> DidIndyResult<DereferencingResult> {
+        self.dereference_with_options(did_url, &ResolveOptions::default())
+    }
+
+    /// Dereferences the DID URL with the options overriding the resolver config
+    pub fn dereference_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<DereferencingResult> {
+        block_on(self.dereference_with_options_async(did_url, options))
+    }
+
+    /// Dereferences the DID URL like `dereference`, awaiting the ledger requests
+    /// instead of blocking the thread on them
+    pub async fn dereference_async(&self, did_url: &str) -> DidIndyResult<DereferencingResult> {
+        self.dereference_with_options_async(did_url, &ResolveOptions::default())
+            .await
+    }
+
+    pub async fn dereference_with_options_async(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<DereferencingResult> {
+        self.dereference_cached(did_url, options).await
+    }
+
+    /// Dereferences the DID URL into the dereferencing result serialized as
+    /// pretty-printed JSON with sorted keys
+    pub fn dereference_string(&self, did_url: &str) -> DidIndyResult<String> {
+        self.dereference_string_with_options(did_url, &ResolveOptions::default())
+    }
+
+    pub fn dereference_string_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<String> {
+        serialize_result(&self.dereference_with_options(did_url, options)?)
+    }
+
+    /// Same as `dereference`, which returned the serialized result before
+    pub fn dereference_typed(&self, did_url: &str) -> DidIndyResult<DereferencingResult> {
+        self.dereference(did_url)
+    }
+
+    /// Same as `dereference_with_options`
+    pub fn dereference_typed_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<DereferencingResult> {
+        self.dereference_with_options(did_url, options)
+    }
+
+    /// Dereferences the DID URL and deserializes the content into the type,
+    /// e.g. a schema or cred def type of the caller. Fails with `ParseContent`
+    /// carrying the content if it does not have the shape of the type.
+    pub fn dereference_content<C: DeserializeOwned>(
+        &self,
+        did_url: &str,
+    ) -> DidIndyResult<(C, ContentMetadata)> {
+        let result = self.dereference(did_url)?;
+        let metadata = match result.content_metadata {
+            Some(metadata) => metadata.into_method_metadata(),
+            None => return Err(DidIndyError::EmptyData),
+        };
+        let content = result.content_stream.unwrap_or(Value::Null);
+        let typed = C::deserialize(&content).map_err(|err| DidIndyError::ParseContent {
+            message: err.to_string(),
+            content: content.clone(),
+        })?;
+        Ok((typed, metadata))
+    }
+
+    /// Resolves the DID into the resolution result, leaving its serialization to
+    /// the caller, e.g. a driver serializing it compactly
+    pub fn resolve(&self, did: &str) -> DidIndyResult<ResolutionResult> {
+        self.resolve_with_options(did, &ResolveOptions::default())
+    }
+
+    /// Resolves the DID with the options overriding the resolver config
+    pub fn resolve_with_options(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<ResolutionResult> {
+        block_on(self.resolve_with_options_async(did, options))
+    }
+
+    /// Resolves the DID like `resolve`, awaiting the ledger requests instead of
+    /// blocking the thread on them. The future is not `Send`, as the futures of
+    /// a `LedgerRequester` are not; within tokio it is awaited on a `LocalSet`,
+    /// e.g. in a task spawned with `spawn_local`.
+    pub async fn resolve_async(&self, did: &str) -> DidIndyResult<ResolutionResult> {
+        self.resolve_with_options_async(did, &ResolveOptions::default())
+            .await
+    }
+
+    pub async fn resolve_with_options_async(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<ResolutionResult> {
+        self.resolve_cached(did, options).await
+    }
+
+    /// Resolves the DID into the resolution result serialized as pretty-printed
+    /// JSON with sorted keys
+    pub fn resolve_string(&self, did: &str) -> DidIndyResult<String> {
+        self.resolve_string_with_options(did, &ResolveOptions::default())
+    }
+
+    pub fn resolve_string_with_options(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<String> {
+        serialize_result(&self.resolve_with_options(did, options)?)
+    }
+
+    /// Same as `resolve`, which returned the serialized result before
+    pub fn resolve_typed(&self, did: &str) -> DidIndyResult<ResolutionResult> {
+        self.resolve(did)
+    }
+
+    /// Same as `resolve_with_options`
+    pub fn resolve_typed_with_options(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<ResolutionResult> {
+        self.resolve_with_options(did, options)
+    }
+
+    /// Resolves the DID into a resolution result also if the resolution fails,
+    /// then with the error code of DID Resolution as `error` of the
+    /// `didResolutionMetadata`: `invalidDid` for input that is no did:indy DID,
+    /// `notFound` for DIDs not on the ledger, `internalError` for failing ledger
+    /// requests, see `DidIndyError::error_code`.
+    pub fn resolve_to_result(&self, did: &str, options: &ResolveOptions) -> String {
+        self.resolve_string_with_options(did, options)
+            .unwrap_or_else(|err| self.failed_result(&ResolutionResult::failed(&err)))
+    }
+
+    /// Resolves the DID together with a report of what was verified of its NYM:
+    /// the state proof and its signers, the trust policy, whether the DID is
+    /// derived from the verkey and who signed `diddocContent`. The report needs
+    /// the ledger reply, so the cache is bypassed.
+    pub fn resolve_with_report(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<(String, VerificationReport)> {
+        if DidUrl::from_str(did)?.path.is_some() {
+            return Err(DidIndyError::InvalidDidUrl);
+        }
+        let (result, report) = block_on(self.resolve_reported(did, options))?;
+        Ok((result, report.ok_or(DidIndyError::InvalidDidDoc)?))
+    }
+
+    /// Resolves a did:indy DID, dereferences a did:indy DID URL, or does either
+    /// for a did:sov DID, an unqualified DID or a legacy schema, cred def or
+    /// revocation registry id converted to a did:indy DID URL. Inputs other than
+    /// did:indy are taken to be of the default namespace, or else the namespace
+    /// of the resolver. How the input was interpreted is added as
+    /// `interpretation` to the `didResolutionMetadata` or
+    /// `dereferencingMetadata`. Fails for ambiguous inputs rather than guessing.
+    pub fn resolve_any(
+        &self,
+        input: &str,
+        default_namespace: Option<&str>,
+    ) -> DidIndyResult<String> {
+        let interpretation = interpret(input, default_namespace.or_else(|| self.namespace()))?;
+        debug!(
+            "Interpreted {} as {:?} {}",
+            input, interpretation.input_type, interpretation.did_url
+        );
+        let options = ResolveOptions::default();
+        let (mut result, metadata_property) =
+            if DidUrl::from_str(&interpretation.did_url)?.path.is_some() {
+                (
+                    serde_json::to_value(
+                        self.dereference_with_options(&interpretation.did_url, &options)?,
+                    )?,
+                    "dereferencingMetadata",
+                )
+            } else {
+                (
+                    serde_json::to_value(
+                        self.resolve_with_options(&interpretation.did_url, &options)?,
+                    )?,
+                    "didResolutionMetadata",
+                )
+            };
+
+        // The proof must cover the interpretation, the result is signed again
+        take_proof(&mut result);
+        result[metadata_property]["interpretation"] = serde_json::to_value(&interpretation)?;
+        self.to_json(&result, metadata_property)
+    }
+
+    /// Resolves the DIDs concurrently. The results are in the order of the DIDs.
+    pub fn resolve_batch(&self, dids: &[&str]) -> Vec<DidIndyResult<String>> {
+        self.resolve_batch_with_options(dids, &ResolveOptions::default())
+    }
+
+    /// Resolves the DIDs concurrently with the options overriding the resolver
+    /// config, e.g. `skip_legacy_endpoint` if only the verkeys are needed
+    pub fn resolve_batch_with_options(
+        &self,
+        dids: &[&str],
+        options: &ResolveOptions,
+    ) -> Vec<DidIndyResult<String>> {
+        block_on(join_all(dids.iter().map(|did| async move {
+            serialize_result(&self.resolve_cached(did, options).await?)
+        })))
+    }
+
+    /// Services of the DID document of the DID, only the ones of the type if
+    /// given. A service matches if its type is the type or, for services with a
+    /// set of types, includes it.
+    pub fn resolve_services(
+        &self,
+        did: &str,
+        service_type: Option<&str>,
+    ) -> DidIndyResult<Vec<Value>> {
+        let mut document = self.resolve(did)?.did_document.unwrap_or_default();
+        let services = match document["service"].take() {
+            Value::Array(services) => services,
+            _ => return Ok(Vec::new()),
+        };
+        Ok(services
+            .into_iter()
+            .filter(|service| match (service_type, &service["type"]) {
+                (None, _) => true,
+                (Some(wanted), Value::String(type_)) => type_ == wanted,
+                (Some(wanted), Value::Array(types)) => types.iter().any(|type_| type_ == wanted),
+                _ => false,
+            })
+            .collect())
+    }
+
+    /// Resolves the DIDs concurrently so that later resolutions are served from
+    /// the cache. Failed resolutions are reported in the summary and do not
+    /// affect the others. Without a cache configured, the DIDs are only resolved.
+    pub fn warm_cache(&self, dids: &[&str]) -> WarmupSummary {
+        self.warm_cache_with_options(dids, &ResolveOptions::default())
+    }
+
+    /// Warms the cache for later resolutions with the same options
+    pub fn warm_cache_with_options(
+        &self,
+        dids: &[&str],
+        options: &ResolveOptions,
+    ) -> WarmupSummary {
+        let mut summary = WarmupSummary::default();
+        for (did, result) in dids
+            .iter()
+            .zip(self.resolve_batch_with_options(dids, options))
+        {
+            match result {
+                Ok(_) => summary.resolved.push(did.to_string()),
+                Err(err) => {
+                    warn!("Could not warm cache for {}, {}", did, err);
+                    summary.failed.push((did.to_string(), err))
+                }
+            }
+        }
+        summary
+    }
+
+    /// Dereferences the cred def with the tag for the schema of the DID URL. The
+    /// cred def is looked up for the DID of the schema DID URL as issuer. The
+    /// schema is dereferenced first, as its sequence number is part of the cred
+    /// def id.
+    pub fn resolve_cred_def_by_schema(
+        &self,
+        schema_did_url: &str,
+        tag: &str,
+    ) -> DidIndyResult<String> {
+        let options = ResolveOptions::default();
+        block_on(async {
+            let did_url = DidUrl::from_str(schema_did_url)?;
+            match did_url.path.as_deref().map(LedgerObject::from_str) {
+                Some(Ok(LedgerObject::Schema(_))) => {}
+                Some(Err(err)) => return Err(err),
+                _ => return Err(DidIndyError::InvalidDidUrl),
+            }
+
+            let schema = self.dereference_cached(schema_did_url, &options).await?;
+            let seq_no = schema
+                .method_metadata()
+                .and_then(|metadata| metadata.node_response()["result"]["seqNo"].as_u64())
+                .ok_or(DidIndyError::EmptyData)?;
+
+            let cred_def_did_url = format!(
+                "did:indy:{}:{}/anoncreds/v0/CLAIM_DEF/{}/{}",
+                did_url.namespace,
+                did_url.id,
+                seq_no,
+                urlencoding::encode(tag)
+            );
+            debug!("Cred def of {} is {}", schema_did_url, cred_def_did_url);
+            serialize_result(&self.dereference_cached(&cred_def_did_url, &options).await?)
+        })
+    }
+
+    /// Revocation registry definitions of the cred def of the DID URL, as stored
+    /// on the ledger. The ledger cannot be asked for the revocation registries of
+    /// a cred def, the pool must be able to list them with
+    /// `LedgerRequester::rev_reg_def_ids`. Fails with `RevRegListingNotSupported`
+    /// for pools that cannot, like the indy-vdr pool.
+    pub fn resolve_rev_reg_defs_for_cred_def(
+        &self,
+        cred_def_did_url: &str,
+    ) -> DidIndyResult<Vec<Value>> {
+        block_on(async {
+            let did_url = DidUrl::from_str(cred_def_did_url)?;
+            let claim_def = match did_url.path.as_deref().map(LedgerObject::from_str) {
+                Some(Ok(LedgerObject::ClaimDef(claim_def))) => claim_def,
+                Some(Err(err)) => return Err(err),
+                _ => return Err(DidIndyError::InvalidDidUrl),
+            };
+            self.check_namespace(&did_url)?;
+            let cred_def_id = CredentialDefinitionId::from_str(&format!(
+                "{}:3:CL:{}:{}",
+                did_url.id, claim_def.schema_seq_no, claim_def.name
+            ))
+            .map_err(|_| DidIndyError::InvalidDidUrl)?;
+            let deadline = Deadline::start(self.config.timeouts);
+
+            let ids = self.pool.rev_reg_def_ids(&cred_def_id).await?;
+            debug!(
+                "Cred def {} has {} revocation registries",
+                cred_def_id,
+                ids.len()
+            );
+            let builder = self.pool.request_builder();
+            let requests = ids
+                .iter()
+                .map(|id| self.prepare(builder.build_get_revoc_reg_def_request(None, id)?))
+                .collect::<DidIndyResult<Vec<PreparedRequest>>>()?;
+            join_all(
+                requests
+                    .iter()
+                    .map(|request| self.handle_request(request, &deadline)),
+            )
+            .await
+            .into_iter()
+            .map(|reply| parse_ledger_data(&reply?))
+            .collect()
+        })
+    }
+
+    /// Entries of the revocation registry of the REV_REG_ENTRY DID URL at each of
+    /// the times, in seconds since the epoch, e.g. for the non-revocation
+    /// intervals of a presentation. The DID URL must not have a `versionTime`.
+    /// Duplicate times are requested once and at most eight requests are in
+    /// flight at the same time. Each time maps to its entry or to the error of
+    /// its request, `EmptyData` if the registry has no entry before the time.
+    /// Fails as a whole for an invalid DID URL or negative time.
+    pub fn get_rev_reg_entries(
+        &self,
+        did_url_base: &str,
+        timestamps: &[i64],
+    ) -> DidIndyResult<BTreeMap<i64, DidIndyResult<RevRegEntry>>> {
+        let did_url = DidUrl::from_str(did_url_base)?;
+        let rev_reg = match did_url.path.as_deref().map(LedgerObject::from_str) {
+            Some(Ok(LedgerObject::RevRegEntry(rev_reg))) if did_url.query.is_empty() => rev_reg,
+            Some(Err(err)) => return Err(err),
+            _ => return Err(DidIndyError::InvalidDidUrl),
+        };
+        self.check_namespace(&did_url)?;
+        if let Some(timestamp) = timestamps.iter().find(|timestamp| **timestamp < 0) {
+            return Err(DidIndyError::InvalidTimestamp(*timestamp));
+        }
+        let rev_reg_id = rev_reg_id(&did_url.id, &rev_reg)?;
+        let mut timestamps = timestamps.to_vec();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+        debug!("Requesting {} entries of {}", timestamps.len(), rev_reg_id);
+
+        Ok(block_on(
+            stream::iter(timestamps)
+                .map(|timestamp| {
+                    let rev_reg_id = &rev_reg_id;
+                    async move { (timestamp, self.rev_reg_entry(rev_reg_id, timestamp).await) }
+                })
+                .buffer_unordered(MAX_CONCURRENT_ENTRY_REQUESTS)
+                .collect(),
+        ))
+    }
+
+    /// Attributes of the NYM that changed between two of its versions. The
+    /// versions are the sequence numbers of NYM transactions of the DID, as
+    /// given in `versionId`. A NYM transaction only carries the attributes it
+    /// sets, attributes not set by the newer transaction are unchanged.
+    pub fn nym_changes(
+        &self,
+        did: &str,
+        from_version: u64,
+        to_version: u64,
+    ) -> DidIndyResult<NymChanges> {
+        block_on(async {
+            let did_url = DidUrl::from_str(did)?;
+            if did_url.path.is_some() {
+                return Err(DidIndyError::InvalidDidUrl);
+            }
+            self.check_namespace(&did_url)?;
+            let deadline = Deadline::start(self.config.timeouts);
+
+            let (from, to) = join(
+                self.fetch_nym_version(&did_url.id, from_version, &deadline),
+                self.fetch_nym_version(&did_url.id, to_version, &deadline),
+            )
+            .await;
+            let (from, to) = (from?, to?);
+
+            let changes = NYM_ATTRIBUTES
+                .iter()
+                .filter_map(|attribute| {
+                    let new = to.get(*attribute)?;
+                    let old = from.get(*attribute).unwrap_or(&Value::Null);
+                    if new == old {
+                        return None;
+                    }
+                    Some(AttributeChange {
+                        attribute: attribute.to_string(),
+                        from: nym_attribute_value(old),
+                        to: nym_attribute_value(new),
+                    })
+                })
+                .collect();
+            Ok(NymChanges {
+                did: did.to_string(),
+                from_version,
+                to_version,
+                changes,
+            })
+        })
+    }
+
+    // Data of the NYM transaction of the DID with the sequence number
+    async fn fetch_nym_version(
+        &self,
+        did: &DidValue,
+        seq_no: u64,
+        deadline: &Deadline,
+    ) -> DidIndyResult<Value> {
+        let seq_no = i32::try_from(seq_no).map_err(|_| DidIndyError::NotFound)?;
+        let request = self.prepare(self.pool.request_builder().build_get_txn_request(
+            None,
+            DOMAIN_LEDGER_ID,
+            seq_no,
+        )?)?;
+        let txn = parse_ledger_data(&self.handle_request(&request, deadline).await?)?;
+        let txn = &txn["txn"];
+        if txn["type"] != constants::NYM || txn["data"]["dest"] != did.to_string() {
+            warn!("Transaction {} is not a NYM of {}", seq_no, did);
+            return Err(DidIndyError::NotFound);
+        }
+        Ok(txn["data"].clone())
+    }
+
+    /// Resolves or dereferences the DID URL bypassing the cache and traces how
+    /// the result is produced: the components of the DID URL, the ledger request
+    /// as it was answered, all requests submitted, the ledger reply, the steps
+    /// applied to the reply and the result. A failed resolution is traced with
+    /// its error, only an invalid DID URL fails.
+    pub fn explain(&self, did_url: &str) -> DidIndyResult<Value> {
+        self.explain_with_options(did_url, &ResolveOptions::default())
+    }
+
+    /// Explains the resolution or dereferencing of the DID URL with the options
+    /// overriding the resolver config
+    pub fn explain_with_options(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<Value> {
+        let (mut parsed, _) = DidUrl::parse_strict(did_url).map_err(|err| err.error)?;
+        parsed.settle_version(
+            options
+                .version_conflict
+                .unwrap_or(self.config.version_conflict),
+        )?;
+        let trace = Trace::enabled();
+        let (operation, result) = if parsed.path.is_some() {
+            let result = block_on(self.dereferencing_result(did_url, options, &trace))
+                .and_then(|result| self.to_json(&result, "dereferencingMetadata"));
+            ("dereference", result)
+        } else {
+            let result = block_on(self.resolution_result(did_url, options, &trace))
+                .and_then(|(result, _)| self.to_json(&result, "didResolutionMetadata"));
+            ("resolve", result)
+        };
+
+        if result.is_ok() && self.config.signing.is_some() {
+            trace.step(|| json!({ "step": "signing" }));
+        }
+        let Trace {
+            request,
+            requests,
+            reply,
+            steps,
+            ..
+        } = trace;
+        let mut trace_json = json!({
+            "didUrl": did_url,
+            "operation": operation,
+            "components": parsed.components(),
+            "request": request.into_inner().unwrap_or_default(),
+            "requests": requests.into_inner().unwrap_or_default(),
+        });
+        let reply = reply.into_inner().unwrap_or_default();
+        if !reply.is_null() {
+            trace_json["reply"] = reply;
+        }
+        match result {
+            Ok(result) => {
+                trace_json["result"] = serde_json::from_str(&result)?;
+            }
+            Err(err) => {
+                trace_json["error"] = json!({
+                    "code": err.error_code(),
+                    "message": err.to_string(),
+                });
+            }
+        }
+        trace_json["steps"] = Value::Array(steps.into_inner().unwrap_or_default());
+        Ok(trace_json)
+    }
+
+    async fn resolve_cached(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<ResolutionResult> {
+        self.cached("resolve", did, options, self.resolve_uncached(did, options))
+            .await
+    }
+
+    async fn dereference_cached(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<DereferencingResult> {
+        self.cached(
+            "dereference",
+            did_url,
+            options,
+            self.dereference_uncached(did_url, options),
+        )
+        .await
+    }
+
+    // Resolution within a tracing span, recorded in the audit log if one is
+    // configured
+    async fn cached<R, F>(
+        &self,
+        operation: &str,
+        did_url: &str,
+        options: &ResolveOptions,
+        resolve: F,
+    ) -> DidIndyResult<R>
+    where
+        R: SignedResult,
+        F: Future<Output = DidIndyResult<R>>,
+    {
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let started = Instant::now();
+        let span = ResolutionSpan::new(operation, did_url);
+        let (result, cache) = span
+            .instrument(self.read_through(operation, did_url, options, resolve))
+            .await;
+        let method_metadata = result
+            .as_ref()
+            .ok()
+            .and_then(|result| result.method_metadata());
+        span.record_result(method_metadata.map(ContentMetadata::object_type), cache);
+
+        if let Some(audit) = &self.config.audit {
+            audit.sink.record(audit_record(
+                audit,
+                operation,
+                did_url,
+                &result,
+                timestamp,
+                started.elapsed(),
+                cache,
+            ));
+        }
+        result
+    }
+
+    // Read-through lookup, only successful results are cached, serialized. The
+    // resolution future is not polled on a cache hit.
+    async fn read_through<R, F>(
+        &self,
+        operation: &str,
+        did_url: &str,
+        options: &ResolveOptions,
+        resolve: F,
+    ) -> (DidIndyResult<R>, CacheStatus)
+    where
+        R: SignedResult,
+        F: Future<Output = DidIndyResult<R>>,
+    {
+        let cache = match &self.config.cache {
+            Some(cache) => cache,
+            None => return (resolve.await, CacheStatus::Disabled),
+        };
+        // Raw replies are for debugging and never cached
+        if options.cache == CacheMode::Bypass || options.raw_reply {
+            return (resolve.await, CacheStatus::Bypassed);
+        }
+
+        let key = format!("{}{}:{}", operation, options.cache_variant(), did_url);
+        if options.cache == CacheMode::ReadThrough {
+            if let Some(result) = cache.backend.get(&key) {
+                match serde_json::from_str(&result) {
+                    Ok(result) => {
+                        debug!("Serving {} from cache", did_url);
+                        return (Ok(result), CacheStatus::Hit);
+                    }
+                    Err(err) => warn!("Ignoring cached result of {}, {}", did_url, err),
+                }
+            }
+        }
+        let stale_key = format!("stale:{}", key);
+        let result = resolve.await;
+        match (&result, self.config.serve_stale_on_error) {
+            (Ok(result), stale_ttl) => match serialize_result(result) {
+                Ok(result) => {
+                    let ttl = cache.ttl_of(did_url);
+                    cache.backend.set(&key, &result, ttl);
+                    if let Some(stale_ttl) = stale_ttl {
+                        let entry = StaleEntry {
+                            retrieved: Utc::now().timestamp(),
+                            result,
+                        };
+                        match serde_json::to_string(&entry) {
+                            Ok(entry) => {
+                                cache
+                                    .backend
+                                    .set(&stale_key, &entry, ttl.saturating_add(stale_ttl))
+                            }
+                            Err(err) => warn!("Not keeping {} to serve stale, {}", did_url, err),
+                        }
+                    }
+                }
+                Err(err) => warn!("Not caching the result of {}, {}", did_url, err),
+            },
+            // Results that have to be fresh are never served stale
+            (Err(err), Some(_))
+                if err.is_transport() && options.cache == CacheMode::ReadThrough =>
+            {
+                let stale = cache
+                    .backend
+                    .get(&stale_key)
+                    .and_then(|entry| self.stale_result(&entry).ok());
+                if let Some(stale) = stale {
+                    warn!("Serving stale result for {}, {}", did_url, err);
+                    return (Ok(stale), CacheStatus::Stale);
+                }
+            }
+            _ => {}
+        }
+        (result, CacheStatus::Miss)
+    }
+
+    // Result of a stale cache entry, marked in the resolution or dereferencing
+    // metadata and signed again if signing is configured
+    fn stale_result<R: SignedResult>(&self, entry: &str) -> DidIndyResult<R> {
+        let entry: StaleEntry = serde_json::from_str(entry)?;
+        let mut result: Value = serde_json::from_str(&entry.result)?;
+        let property = R::METADATA_PROPERTY;
+
+        let mut metadata = match result[property].take() {
+            Value::Object(metadata) => metadata,
+            _ => Map::new(),
+        };
+        metadata.remove("proof");
+        metadata.insert(String::from("fromCache"), Value::Bool(true));
+        metadata.insert(String::from("stale"), Value::Bool(true));
+        metadata.insert(
+            String::from("retrieved"),
+            serde_json::to_value(Timestamp::new(
+                entry.retrieved,
+                self.config.metadata_time_format,
+            ))?,
+        );
+        result[property] = Value::Object(metadata);
+        self.signed(serde_json::from_value(result)?)
+    }
+
+    async fn dereference_uncached(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<DereferencingResult> {
+        let result = self
+            .dereferencing_result(did_url, options, &Trace::default())
+            .await?;
+        self.signed(result)
+    }
+
+    async fn dereferencing_result(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+        trace: &Trace,
+    ) -> DidIndyResult<DereferencingResult> {
+        let started = Instant::now();
+        let (data, metadata) = if options.raw_reply {
+            self.resolve_raw(did_url, options, trace).await?
+        } else {
+            self._resolve(did_url, options, trace).await?
+        };
+
+        // Parsed again, the input was already validated by the resolution
+        let input = self.parse(did_url, options)?;
+        let json_ld = options.representation == Representation::JsonLd && !options.raw_reply;
+        let content = match data {
+            Result::Content(c) if json_ld => Some(self.json_ld_content(c, &input)?),
+            Result::Content(c) => Some(c),
+            _ => None,
+        };
+        let content_type = if json_ld && content.is_some() {
+            trace.step(|| json!({ "step": "jsonLd" }));
+            "application/ld+json"
+        } else {
+            "application/json"
+        };
+        self.trace_metadata_step(trace);
+
+        let metadata = if self.config.legacy_metadata {
+            Metadata::Legacy(metadata)
+        } else {
+            let mut metadata =
+                DereferencedContentMetadata::new(metadata, self.config.metadata_time_format);
+            metadata.content_type = content_type.to_string();
+            Metadata::Spec(metadata)
+        };
+        let result = DereferencingResult {
+            dereferencing_metadata: Some(DereferencingMetadata {
+                content_type: Some(content_type.to_string()),
+                duration: Some(started.elapsed().as_millis() as u64),
+                did_url: Some(input.canonical()),
+                fragment: input.fragment,
+                ..DereferencingMetadata::default()
+            }),
+            content_stream: content,
+            content_metadata: Some(metadata),
+        };
+
+        Ok(result)
+    }
+
+    // Ledger object as JSON-LD resource with the configured context, its DID URL
+    // without fragment as id and a type following its object type. The content
+    // is kept as it is under `content`.
+    fn json_ld_content(&self, content: Value, input: &DidUrl) -> DidIndyResult<Value> {
+        let object_type = match &input.path {
+            Some(path) => LedgerObject::from_str(path)?.object_type(),
+            None => return Ok(content),
+        };
+        let canonical = input.canonical();
+        Ok(json!({
+            "@context": [self
+                .config
+                .content_context
+                .as_deref()
+                .unwrap_or(DEFAULT_CONTENT_CONTEXT)],
+            "id": canonical.split('#').next().unwrap(),
+            "type": json_ld_type(object_type),
+            "content": content,
+        }))
+    }
+
+    async fn resolve_uncached(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<ResolutionResult> {
+        let (result, _) = self
+            .resolution_result(did, options, &Trace::default())
+            .await?;
+        self.signed(result)
+    }
+
+    // Serialized resolution together with the verification report of the NYM,
+    // if a DID document was resolved
+    async fn resolve_reported(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+    ) -> DidIndyResult<(String, Option<VerificationReport>)> {
+        let (result, report) = self
+            .resolution_result(did, options, &Trace::default())
+            .await?;
+        Ok((self.to_json(&result, "didResolutionMetadata")?, report))
+    }
+
+    async fn resolution_result(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+        trace: &Trace,
+    ) -> DidIndyResult<(ResolutionResult, Option<VerificationReport>)> {
+        let started = Instant::now();
+        // Fail on unsupported transformations before querying the ledger
+        let input = self.parse(did, options)?;
+        let transform = match input.query.get(&QueryParameter::TransformKeys) {
+            Some(format) => Some(KeyFormat::from_str(format)?),
+            None => None,
+        };
+        let (data, metadata) = self._resolve(did, options, trace).await?;
+
+        let mut canonical_id = None;
+        let mut deactivated = None;
+        let mut warnings = Vec::new();
+        let mut report = None;
+        let diddoc = match data {
+            Result::DidDocument(doc) => {
+                report = Some(VerificationReport::of_nym_reply(
+                    &metadata.node_response,
+                    metadata.trust_policy.clone(),
+                )?);
+                if self.config.canonical_id {
+                    canonical_id = doc.self_certifying_did();
+                    trace.step(|| json!({ "step": "canonicalId", "canonicalId": canonical_id }));
+                }
+                if doc.is_deactivated() {
+                    deactivated = Some(true);
+                }
+                let (mut value, doc_warnings) = doc.to_value_with_warnings()?;
+                warnings = doc_warnings;
+                if let Some(format) = transform {
+                    transform_keys(&mut value, format)?;
+                    trace.step(|| {
+                        json!({
+                            "step": "transformKeys",
+                            "format": input.query.get(&QueryParameter::TransformKeys),
+                        })
+                    });
+                }
+                if options.representation == Representation::JsonLd {
+                    add_did_core_context(&mut value);
+                    trace.step(|| json!({ "step": "jsonLd" }));
+                }
+                if options.strict.unwrap_or(self.config.strict) {
+                    let issues = validate_did_document(&value, &doc.did());
+                    trace.step(|| json!({ "step": "validation", "issues": issues.len() }));
+                    if self.config.best_effort {
+                        warnings.extend(issues.iter().map(ToString::to_string));
+                    } else if !issues.is_empty() {
+                        return Err(DidIndyError::DidDocumentValidation(issues));
+                    }
+                }
+                if self.config.best_effort && !warnings.is_empty() {
+                    trace.step(|| json!({ "step": "bestEffort", "warnings": warnings }));
+                }
+                Some(value)
+            }
+            _ => None,
+        };
+        self.trace_metadata_step(trace);
+        let metadata = if self.config.legacy_metadata {
+            Metadata::Legacy(metadata)
+        } else {
+            Metadata::Spec(DidDocumentMetadata {
+                canonical_id,
+                deactivated,
+                ..DidDocumentMetadata::new(metadata, self.config.metadata_time_format)
+            })
+        };
+        let result = ResolutionResult {
+            did_resolution_metadata: Some(ResolutionMetadata {
+                content_type: Some(options.representation.media_type().to_string()),
+                error: None,
+                duration: Some(started.elapsed().as_millis() as u64),
+                did: options.echo_input.then(|| input.canonical()),
+                warnings,
+                ..ResolutionMetadata::default()
+            }),
+            did_document: diddoc,
+            did_document_metadata: Some(metadata),
+        };
+
+        Ok((result, report))
+    }
+
+    fn trace_metadata_step(&self, trace: &Trace) {
+        trace.step(|| {
+            json!({
+                "step": "metadata",
+                "legacy": self.config.legacy_metadata,
+                "timeFormat": format!("{:?}", self.config.metadata_time_format),
+            })
+        });
+    }
+
+    // Result of a failed resolution, signed like any other result. It is left
+    // unsigned if signing fails too, the error is what is reported.
+    fn failed_result(&self, result: &ResolutionResult) -> String {
+        self.to_json(result, "didResolutionMetadata")
+            .unwrap_or_else(|_| serde_json::to_string_pretty(result).unwrap_or_default())
+    }
+
+    // Serializes the result with sorted keys, signed if signing is configured
+    fn to_json<R: Serialize>(&self, result: &R, metadata_property: &str) -> DidIndyResult<String> {
+        let mut result = serde_json::to_value(result)?;
+        if let Some(signing) = &self.config.signing {
+            sign_result(&mut result, metadata_property, signing)?;
+        }
+        Ok(serde_json::to_string_pretty(&sort_keys(result))?)
+    }
+
+    // The result with its proof, if signing is configured
+    fn signed<R: SignedResult>(&self, mut result: R) -> DidIndyResult<R> {
+        if let Some(signing) = &self.config.signing {
+            let mut value = serde_json::to_value(&result)?;
+            sign_result(&mut value, R::METADATA_PROPERTY, signing)?;
+            result.set_proof(value[R::METADATA_PROPERTY]["proof"].take());
+        }
+        Ok(result)
+    }
+
+    // Parses the DID URL and settles conflicting version parameters, the one
+    // place deciding which of them applies
+    fn parse(&self, did_url: &str, options: &ResolveOptions) -> DidIndyResult<DidUrl> {
+        let mut did_url = DidUrl::from_str(did_url)?;
+        did_url.settle_version(
+            options
+                .version_conflict
+                .unwrap_or(self.config.version_conflict),
+        )?;
+        Ok(did_url)
+    }
+
+    fn check_namespace(&self, did_url: &DidUrl) -> DidIndyResult<()> {
+        match &self.namespace {
+            Some(namespace) if *namespace != did_url.namespace => {
+                warn!(
+                    "DID of namespace {} requested from the resolver for {}",
+                    did_url.namespace, namespace
+                );
+                Err(DidIndyError::NamespaceNotSupported)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Evaluates the trust policy of the namespace of the network, not of an
+    // alias the DID may use, against the signatures of the ledger reply
+    fn check_trust_policy(
+        &self,
+        did_url: &DidUrl,
+        ledger_reply: &str,
+        trace: &Trace,
+    ) -> DidIndyResult<Option<PolicyEvaluation>> {
+        let namespace = self
+            .namespace
+            .as_ref()
+            .or_else(|| self.registered_namespace.as_ref())
+            .unwrap_or(&did_url.namespace);
+        let policy = match self.config.trust_policies.get(namespace) {
+            Some(policy) => policy,
+            None => return Ok(None),
+        };
+        let evaluation = evaluate_trust_policy(
+            policy,
+            &serde_json::from_str(ledger_reply)?,
+            &self.pool.node_aliases(),
+        );
+        trace.step(|| json!({ "step": "trustPolicy", "evaluation": evaluation }));
+        if !evaluation.satisfied {
+            return Err(DidIndyError::PolicyNotSatisfied {
+                missing: evaluation.missing,
+            });
+        }
+        Ok(Some(evaluation))
+    }
+
+    // The ledger reply as content, without extracting the ledger object
+    async fn resolve_raw(
+        &self,
+        did_url: &str,
+        options: &ResolveOptions,
+        trace: &Trace,
+    ) -> DidIndyResult<(Result, ContentMetadata)> {
+        let did_url = self.parse(did_url, options)?;
+        self.check_namespace(&did_url)?;
+        let deadline = Deadline::start(options.timeouts.unwrap_or(self.config.timeouts));
+
+        let request = self.prepare(build_request(&did_url, &self.pool.request_builder())?)?;
+        record_txn_type(&request.txn_type);
+        trace.object_request(&request);
+        let mut request_downgrades = Vec::new();
+        let ledger_reply = self
+            .submit_downgrading(&request, &deadline, &mut request_downgrades, trace)
+            .await?;
+        trace.reply(&ledger_reply);
+        let trust_policy = self.check_trust_policy(&did_url, &ledger_reply, trace)?;
+        let reply: Value = serde_json::from_str(&ledger_reply)?;
+        trace.step(|| json!({ "step": "rawReply" }));
+
+        let metadata = ContentMetadata {
+            node_response: Value::Null,
+            object_type: ObjectType::Raw,
+            cred_def: None,
+            earliest_entry: false,
+            signed_txn: None,
+            raw_reply: true,
+            trust_policy,
+            request_downgrades,
+            legacy_endpoint_skipped: false,
+            revocation_status_list: None,
+            seq_no: None,
+            txn_time: None,
+        };
+        Ok((Result::Content(reply), metadata))
+    }
+
+    async fn _resolve(
+        &self,
+        did: &str,
+        options: &ResolveOptions,
+        trace: &Trace,
+    ) -> DidIndyResult<(Result, ContentMetadata)> {
+        let did_url = self.parse(did, options)?;
+        self.check_namespace(&did_url)?;
+        let deadline = Deadline::start(options.timeouts.unwrap_or(self.config.timeouts));
+
+        let builder = self.pool.request_builder();
+        let request = self.prepare(build_request(&did_url, &builder)?)?;
+        record_txn_type(&request.txn_type);
+        trace.object_request(&request);
+
+        let cred_def_request = if self.config.attach_cred_def {
+            build_cred_def_request_for_rev_reg(&did_url, &builder)?
+                .map(|request| self.prepare(request))
+                .transpose()?
+        } else {
+            None
+        };
+
+        let mut request_downgrades = Vec::new();
+        let (ledger_data, cred_def) = match cred_def_request {
+            Some(cred_def_request) => {
+                trace.submitted(&cred_def_request);
+                let (ledger_data, cred_def_data) = join(
+                    self.submit_downgrading(&request, &deadline, &mut request_downgrades, trace),
+                    submit_within(&self.pool, &self.limiter, &cred_def_request, &deadline),
+                )
+                .await;
+                let ledger_data = ledger_data?;
+                let cred_def = parse_ledger_data(&cred_def_data?)?;
+                (ledger_data, Some(cred_def))
+            }
+            None => (
+                self.submit_downgrading(&request, &deadline, &mut request_downgrades, trace)
+                    .await?,
+                None,
+            ),
+        };
+
+        let (ledger_data, earliest_entry) = if request.txn_type == constants::GET_REVOC_REG
+            && self.config.rev_reg_entry_fallback != RevRegEntryFallback::Disabled
+            && matches!(
+                parse_ledger_data(&ledger_data),
+                Err(DidIndyError::EmptyData)
+            ) {
+            match self.find_first_rev_reg_entry(&request, &deadline).await? {
+                Some((_, first_entry))
+                    if self.config.rev_reg_entry_fallback
+                        == RevRegEntryFallback::NotFoundBefore =>
+                {
+                    return Err(DidIndyError::NotFoundBefore { first_entry })
+                }
+                Some((entry, _)) => {
+                    trace.step(|| {
+                        json!({
+                            "step": "revRegEntryFallback",
+                            "fallback": format!("{:?}", self.config.rev_reg_entry_fallback),
+                        })
+                    });
+                    (entry, true)
+                }
+                None => (ledger_data, false),
+            }
+        } else {
+            (ledger_data, false)
+        };
+        trace.reply(&ledger_data);
+        let data = parse_ledger_data(&ledger_data)?;
+
+        let trust_policy = self.check_trust_policy(&did_url, &ledger_data, trace)?;
+
+        let mut legacy_endpoint_skipped = false;
+        let (result, object_type) = match request.txn_type.as_str() {
+            constants::GET_NYM => {
+                let get_nym_result: GetNymResultV1 = serde_json::from_str(data.as_str().unwrap())?;
+
+                let mut warnings = Vec::new();
+                let diddoc_content =
+                    match parse_diddoc_content(get_nym_result.diddoc_content.clone()) {
+                        Err(err) if self.config.best_effort => {
+                            warnings.push(format!("diddocContent left out: {}", err));
+                            None
+                        }
+                        diddoc_content => diddoc_content?,
+                    };
+                trace.step(|| {
+                    json!({
+                        "step": "didDocument",
+                        "diddocContent": get_nym_result.diddoc_content.is_some(),
+                        "deactivated": get_nym_result.is_deactivated(),
+                        "verificationMethodType":
+                            format!("{:?}", self.config.verification_method_type),
+                    })
+                });
+                // A deactivated DID has no services to look up
+                let needs_endpoint = !get_nym_result.is_deactivated()
+                    && needs_legacy_endpoint(
+                        &get_nym_result.diddoc_content,
+                        self.config.endpoint_precedence,
+                    );
+                legacy_endpoint_skipped = needs_endpoint
+                    && options
+                        .skip_legacy_endpoint
+                        .unwrap_or(self.config.skip_legacy_endpoint);
+                let endpoint: Option<Endpoint> = if needs_endpoint && !legacy_endpoint_skipped {
+                    // Legacy: Try to find an attached ATTRIBUTE transacation with raw endpoint
+                    let (seq_no, timestamp) = requested_version(&did_url)?;
+                    match self
+                        .fetch_legacy_endpoint(
+                            &did_url.id,
+                            seq_no,
+                            timestamp,
+                            &deadline,
+                            &mut request_downgrades,
+                            trace,
+                        )
+                        .await
+                    {
+                        Ok(endpoint) => Some(endpoint),
+                        Err(DidIndyError::EmptyData) => None,
+                        Err(err) if self.config.best_effort => {
+                            warnings.push(format!("Legacy endpoint not resolved: {}", err));
+                            None
+                        }
+                        Err(err @ DidIndyError::RequestTimeout)
+                        | Err(err @ DidIndyError::ResolutionTimeout)
+                        | Err(err @ DidIndyError::LedgerUnreachable(_))
+                        | Err(err @ DidIndyError::VersionedRequestRejected { .. }) => {
+                            return Err(err)
+                        }
+                        Err(_) => None,
+                    }
+                } else {
+                    None
+                };
+                if needs_endpoint {
+                    trace.step(|| {
+                        json!({
+                            "step": "legacyEndpoint",
+                            "precedence": format!("{:?}", self.config.endpoint_precedence),
+                            "skipped": legacy_endpoint_skipped,
+                            "found": endpoint.is_some(),
+                        })
+                    });
+                }
+                if !get_nym_result.is_deactivated() {
+                    if let Some(base) = &self.config.endpoint_base {
+                        trace.step(|| json!({ "step": "endpointBase", "base": base.as_str() }));
+                    }
+                    if endpoint.is_some()
+                        || (self.config.endpoint_validation.diddoc_content
+                            && diddoc_content.is_some())
+                    {
+                        trace.step(|| {
+                            json!({
+                                "step": "endpointValidation",
+                                "schemes": self.config.endpoint_validation.schemes,
+                                "diddocContent": self.config.endpoint_validation.diddoc_content,
+                            })
+                        });
+                    }
+                }
+                // Only sent under best effort
+                warnings.extend(
+                    request_downgrades
+                        .iter()
+                        .filter(|downgrade| downgrade.lossy)
+                        .map(|downgrade| {
+                            format!(
+                                "Ledger rejected the versioned {} request, the current version is used: {}",
+                                downgrade.txn_type, downgrade.reason
+                            )
+                        }),
+                );
+
+                let did_document = DidDocument::new(
+                    &did_url.namespace,
+                    &get_nym_result.dest,
+                    get_nym_result.verkey.as_deref().unwrap_or_default(),
+                    endpoint,
+                    diddoc_content,
+                )
+                .with_deactivated(get_nym_result.is_deactivated())
+                .with_relationships(self.config.verification_relationships)
+                .with_method_type(self.config.verification_method_type)
+                .with_endpoint_base(self.config.endpoint_base.clone())
+                .with_endpoint_validation(self.config.endpoint_validation.clone())
+                .with_strict_endpoints(options.strict.unwrap_or(self.config.strict))
+                .with_service_conflict_policy(self.config.service_conflict_policy)
+                .with_best_effort(self.config.best_effort);
+                let did_document = warnings
+                    .into_iter()
+                    .fold(did_document, DidDocument::with_warning);
+                (Result::DidDocument(did_document), ObjectType::Nym)
+            }
+            constants::GET_CRED_DEF => (Result::Content(data), ObjectType::CredDef),
+            constants::GET_SCHEMA => (Result::Content(data), ObjectType::Schema),
+            constants::GET_REVOC_REG_DEF => (Result::Content(data), ObjectType::RevRegDef),
+            constants::GET_REVOC_REG_DELTA => (Result::Content(data), ObjectType::RevRegDelta),
+            constants::GET_REVOC_REG => (Result::Content(data), ObjectType::RevRegEntry),
+            _ => (Result::Content(data), ObjectType::Unknown),
+        };
+        if object_type != ObjectType::Nym {
+            trace.step(|| json!({ "step": "ledgerObject", "objectType": object_type }));
+        }
+        if cred_def.is_some() {
+            trace.step(|| json!({ "step": "attachCredDef" }));
+        }
+
+        let node_response: Value = serde_json::from_str(&ledger_data).unwrap();
+        let signed_txn = if self.config.include_signed_txn && request.txn_type == constants::GET_NYM
+        {
+            signed_txn(&node_response)
+        } else {
+            None
+        };
+
+        let status_list_requests = if options.revocation_status_list {
+            build_status_list_requests(&did_url, &builder)?
+        } else {
+            None
+        };
+        let revocation_status_list = match status_list_requests {
+            Some((rev_reg_def_request, delta_request, timestamp)) => {
+                let rev_reg_def_request = self.prepare(rev_reg_def_request)?;
+                let delta_request = self.prepare(delta_request)?;
+                trace.submitted(&rev_reg_def_request);
+                trace.submitted(&delta_request);
+                trace.step(|| json!({ "step": "revocationStatusList" }));
+                let (rev_reg_def, delta) = join(
+                    submit_within(&self.pool, &self.limiter, &rev_reg_def_request, &deadline),
+                    submit_within(&self.pool, &self.limiter, &delta_request, &deadline),
+                )
+                .await;
+                Some(revocation_status_list(
+                    &parse_ledger_data(&rev_reg_def?)?,
+                    &[parse_ledger_data(&delta?)?],
+                    timestamp,
+                )?)
+            }
+            None => None,
+        };
+
+        let (seq_no, txn_time) = ledger_position(&request.txn_type, &node_response["result"]);
+        let metadata = ContentMetadata {
+            node_response,
+            object_type,
+            cred_def,
+            earliest_entry,
+            signed_txn,
+            raw_reply: false,
+            trust_policy,
+            request_downgrades,
+            legacy_endpoint_skipped,
+            revocation_status_list,
+            seq_no,
+            txn_time,
+        };
+
+        let result_with_metadata = (result, metadata);
+
+        Ok(result_with_metadata)
+    }
+
+    // Applies the request hook and signs the request, if configured. Signing comes
+    // last so that the signature covers the changes of the hook.
+    fn prepare(&self, mut request: PreparedRequest) -> DidIndyResult<PreparedRequest> {
+        if let Some(hook) = &self.request_hook {
+            hook(&mut request);
+        }
+        if let Some(signing) = &self.read_request_signing {
+            sign_request(&mut request, signing)?;
+        }
+        Ok(request)
+    }
+
+    async fn handle_request(
+        &self,
+        request: &PreparedRequest,
+        deadline: &Deadline,
+    ) -> DidIndyResult<String> {
+        self.submit_downgrading(request, deadline, &mut Vec::new(), &Trace::default())
+            .await
+    }
+
+    // Submits the request, retrying once in its older form if the nodes reject
+    // its form. An older form dropping the requested version is only sent under
+    // best effort. Sent downgrades are added to the downgrades.
+    async fn submit_downgrading(
+        &self,
+        request: &PreparedRequest,
+        deadline: &Deadline,
+        downgrades: &mut Vec<RequestDowngrade>,
+        trace: &Trace,
+    ) -> DidIndyResult<String> {
+        trace.submitted(request);
+        let err = match submit_within(&self.pool, &self.limiter, request, deadline).await {
+            Err(err) => err,
+            reply => return reply,
+        };
+        let (older, downgrade) =
+            match rejected_form(&err).and_then(|reason| older_form(&request.req_json, &reason)) {
+                Some(older) => older,
+                None => return Err(err),
+            };
+        if downgrade.lossy && !self.config.best_effort {
+            return Err(DidIndyError::VersionedRequestRejected {
+                txn_type: downgrade.txn_type,
+                reason: downgrade.reason,
+            });
+        }
+        warn!(
+            "{} request rejected, retrying with {:?}: {}",
+            downgrade.txn_type, downgrade.changes, downgrade.reason
+        );
+        let older = self.prepare(PreparedRequest::from_request_json(older.to_string())?)?;
+        trace.downgraded(request, &older, &downgrade);
+        trace.submitted(&older);
+        let reply = submit_within(&self.pool, &self.limiter, &older, deadline).await?;
+        downgrades.push(downgrade);
+        Ok(reply)
+    }
+
+    /// Finds the first entry of the revocation registry of a GET_REVOC_REG request
+    /// that has no entry at the requested time. Bisects the time between the
+    /// requested time and the latest entry, as an entry found at a time is the
+    /// latest one before it. Returns the ledger reply and time of the entry.
+    async fn find_first_rev_reg_entry(
+        &self,
+        request: &PreparedRequest,
+        deadline: &Deadline,
+    ) -> DidIndyResult<Option<(String, i64)>> {
+        let operation = &request.req_json["operation"];
+        let id = operation["revocRegDefId"]
+            .as_str()
+            .ok_or(DidIndyError::InvalidDidUrl)?;
+        let rev_reg_id =
+            RevocationRegistryId::from_str(id).map_err(|_| DidIndyError::InvalidDidUrl)?;
+        let requested = operation["timestamp"]
+            .as_i64()
+            .ok_or(DidIndyError::InvalidDidUrl)?;
+
+        let now = Utc::now().timestamp();
+        let mut earliest = match self.rev_reg_entry_at(&rev_reg_id, now, deadline).await? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        // There is no entry before lower
+        let mut lower = requested + 1;
+        while lower < earliest.1 {
+            let middle = lower + (earliest.1 - lower) / 2;
+            match self.rev_reg_entry_at(&rev_reg_id, middle, deadline).await? {
+                Some(entry) => earliest = entry,
+                None => lower = middle + 1,
+            }
+        }
+        debug!(
+            "First entry of {} at {}, requested {}",
+            id, earliest.1, requested
+        );
+        Ok(Some(earliest))
+    }
+
+    // Latest revocation registry entry at the time
+    async fn rev_reg_entry(
+        &self,
+        rev_reg_id: &RevocationRegistryId,
+        timestamp: i64,
+    ) -> DidIndyResult<RevRegEntry> {
+        let deadline = Deadline::start(self.config.timeouts);
+        let (ledger_data, txn_time) = self
+            .rev_reg_entry_at(rev_reg_id, timestamp, &deadline)
+            .await?
+            .ok_or(DidIndyError::EmptyData)?;
+        let reply: Value = serde_json::from_str(&ledger_data)?;
+        Ok(RevRegEntry {
+            txn_time,
+            seq_no: reply["result"]["seqNo"].as_u64(),
+            value: reply["result"]["data"]["value"].clone(),
+        })
+    }
+
+    // Ledger reply and time of the latest revocation registry entry at the time
+    async fn rev_reg_entry_at(
+        &self,
+        rev_reg_id: &RevocationRegistryId,
+        timestamp: i64,
+        deadline: &Deadline,
+    ) -> DidIndyResult<Option<(String, i64)>> {
+        let builder = self.pool.request_builder();
+        let request =
+            self.prepare(builder.build_get_revoc_reg_request(None, rev_reg_id, timestamp)?)?;
+        let ledger_data = self.handle_request(&request, deadline).await?;
+
+        match parse_ledger_data(&ledger_data) {
+            Ok(_) => {
+                let reply: Value = serde_json::from_str(&ledger_data)?;
+                let txn_time = reply["result"]["txnTime"]
+                    .as_i64()
+                    .ok_or(DidIndyError::EmptyData)?;
+                Ok(Some((ledger_data, txn_time)))
+            }
+            Err(DidIndyError::EmptyData) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Legacy endpoint as of the version of the DID requested with versionId or
+    // versionTime, the current one if neither is given
+    async fn fetch_legacy_endpoint(
+        &self,
+        did: &DidValue,
+        seq_no: Option<i64>,
+        timestamp: Option<i64>,
+        deadline: &Deadline,
+        downgrades: &mut Vec<RequestDowngrade>,
+        trace: &Trace,
+    ) -> DidIndyResult<Endpoint> {
+        let builder = self.pool.request_builder();
+        let mut request = builder.build_get_attrib_request(
+            None,
+            did,
+            Some(String::from(LEGACY_INDY_SERVICE)),
+            None,
+            None,
+        )?;
+        // The request builder of indy-vdr 0.3 takes no version of the ATTRIB, it
+        // is added to the operation as later versions of the builder do
+        if let Some(seq_no) = seq_no {
+            request.req_json["operation"]["seqNo"] = json!(seq_no);
+        }
+        if let Some(timestamp) = timestamp {
+            request.req_json["operation"]["timestamp"] = json!(timestamp);
+        }
+        let request = self.prepare(request)?;
+        let ledger_data = self
+            .submit_downgrading(&request, deadline, downgrades, trace)
+            .await?;
+        let endpoint_data = parse_ledger_data(&ledger_data)?;
+        let endpoint_data: Endpoint = serde_json::from_str(endpoint_data.as_str().unwrap())?;
+        Ok(endpoint_data)
+    }
+}
+
+static DOMAIN_LEDGER_ID: i32 = 1;
+
+// Requests in flight at the same time for the entries of `get_rev_reg_entries`
+static MAX_CONCURRENT_ENTRY_REQUESTS: usize = 8;
+
+// Attributes of a NYM compared between versions
+static NYM_ATTRIBUTES: [&str; 3] = ["verkey", "role", "diddocContent"];
+
+// diddocContent is written as JSON encoded string, it is compared as written
+// and returned decoded
+fn nym_attribute_value(value: &Value) -> Value {
+    match value.as_str().map(serde_json::from_str::<Value>) {
+        Some(Ok(decoded)) if decoded.is_object() => decoded,
+        _ => value.clone(),
+    }
+}
+
+// Id of the revocation registry of a REV_REG_DEF, REV_REG_ENTRY or
+// REV_REG_DELTA DID URL
+fn rev_reg_id(did: &DidValue, rev_reg: &RevReg) -> DidIndyResult<RevocationRegistryId> {
+    RevocationRegistryId::from_str(&format!(
+        "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
+        did, did, rev_reg.schema_seq_no, rev_reg.claim_def_name, rev_reg.tag
+    ))
+    .map_err(|_| DidIndyError::InvalidDidUrl)
+}
+
+// diddocContent is written to the ledger as JSON encoded string
+fn parse_diddoc_content(diddoc_content: Option<Value>) -> DidIndyResult<Option<Value>> {
+    match diddoc_content {
+        Some(Value::String(content)) => Ok(Some(serde_json::from_str(&content)?)),
+        other => Ok(other),
+    }
+}
+
+fn needs_legacy_endpoint(diddoc_content: &Option<Value>, precedence: EndpointPrecedence) -> bool {
+    match precedence {
+        EndpointPrecedence::DiddocContent => diddoc_content.is_none(),
+        EndpointPrecedence::Merge => true,
+    }
+}
+
+/// Ledger request the DID URL is resolved or dereferenced with, built without
+/// querying the ledger. Resolving a NYM may also need the legacy endpoint ATTRIB.
+pub fn build_request(did: &DidUrl, builder: &RequestBuilder) -> DidIndyResult<PreparedRequest> {
+    let request = if did.path.is_some() {
+        match LedgerObject::from_str(did.path.as_ref().unwrap().as_str())? {
+            LedgerObject::Schema(schema) => builder.build_get_schema_request(
+                None,
+                &SchemaId::new(&did.id, &schema.name, &schema.version),
+            ),
+            LedgerObject::ClaimDef(claim_def) => builder.build_get_cred_def_request(
+                None,
+                &CredentialDefinitionId::from_str(
+                    format!(
+                        "{}:3:CL:{}:{}",
+                        &did.id, claim_def.schema_seq_no, claim_def.name
+                    )
+                    .as_str(),
+                )
+                .unwrap(),
+            ),
+            LedgerObject::RevRegDef(rev_reg_def) => builder.build_get_revoc_reg_def_request(
+                None,
+                &RevocationRegistryId::from_str(
+                    format!(
+                        "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
+                        &did.id,
+                        &did.id,
+                        rev_reg_def.schema_seq_no,
+                        rev_reg_def.claim_def_name,
+                        rev_reg_def.tag
+                    )
+                    .as_str(),
+                )
+                .unwrap(),
+            ),
+            LedgerObject::RevRegEntry(rev_reg_entry) => {
+                let timestamp = parse_or_now(did.query.get(&QueryParameter::VersionTime))?;
+
+                builder.build_get_revoc_reg_request(
+                    None,
+                    &RevocationRegistryId::from_str(
+                        format!(
+                            "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
+                            &did.id,
+                            &did.id,
+                            rev_reg_entry.schema_seq_no,
+                            rev_reg_entry.claim_def_name,
+                            rev_reg_entry.tag
+                        )
+                        .as_str(),
+                    )
+                    .unwrap(),
+                    timestamp,
+                )
+            }
+            LedgerObject::RevRegDelta(rev_reg_delta) => {
+                let from = match did.query.get(&QueryParameter::From) {
+                    Some(from) => Some(DateTime::parse_from_rfc3339(from)?.timestamp()),
+                    None => None,
+                };
+
+                let to = parse_or_now(did.query.get(&QueryParameter::To))?;
+
+                builder.build_get_revoc_reg_delta_request(
+                    None,
+                    &RevocationRegistryId::from_str(
+                        format!(
+                            "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
+                            &did.id,
+                            &did.id,
+                            rev_reg_delta.schema_seq_no,
+                            rev_reg_delta.claim_def_name,
+                            rev_reg_delta.tag
+                        )
+                        .as_str(),
+                    )
+                    .unwrap(),
+                    from,
+                    to,
+                )
+            }
+        }
+    } else {
+        let (seq_no, timestamp) = requested_version(did)?;
+
+        // The request builder of indy-vdr 0.3 takes no version of the NYM, it is
+        // added to the operation as later versions of the builder do
+        builder
+            .build_get_nym_request(Option::None, &did.id)
+            .map(|mut request| {
+                if let Some(seq_no) = seq_no {
+                    request.req_json["operation"]["seqNo"] = json!(seq_no);
+                }
+                if let Some(timestamp) = timestamp {
+                    request.req_json["operation"]["timestamp"] = json!(timestamp);
+                }
+                request
+            })
+    };
+    request.map_err(|e| DidIndyError::from(e))
+}
+
+// Sequence number and time of the version of the DID requested with versionId
+// and versionTime. Values that are no sequence number or time fail rather than
+// resolving the current version.
+fn requested_version(did: &DidUrl) -> DidIndyResult<(Option<i64>, Option<i64>)> {
+    let seq_no = match did.query.get(&QueryParameter::VersionId) {
+        Some(seq_no) => Some(
+            seq_no
+                .parse::<i64>()
+                .ok()
+                .filter(|seq_no| *seq_no >= 0)
+                .ok_or(DidIndyError::InvalidDidUrl)?,
+        ),
+        None => None,
+    };
+    let timestamp = match did.query.get(&QueryParameter::VersionTime) {
+        Some(datetime) => Some(DateTime::parse_from_rfc3339(datetime)?.timestamp()),
+        None => None,
+    };
+    Ok((seq_no, timestamp))
+}
+
+// The cred def of a revocation registry is identified by the DID URL of the
+// REV_REG_DEF, so both can be requested at the same time
+fn build_cred_def_request_for_rev_reg(
+    did: &DidUrl,
+    builder: &RequestBuilder,
+) -> DidIndyResult<Option<PreparedRequest>> {
+    let rev_reg_def = match did.path.as_ref().map(|path| LedgerObject::from_str(path)) {
+        Some(Ok(LedgerObject::RevRegDef(rev_reg_def))) => rev_reg_def,
+        _ => return Ok(None),
+    };
+    let request = builder.build_get_cred_def_request(
+        None,
+        &CredentialDefinitionId::from_str(
+            format!(
+                "{}:3:CL:{}:{}",
+                &did.id, rev_reg_def.schema_seq_no, rev_reg_def.claim_def_name
+            )
+            .as_str(),
+        )
+        .unwrap(),
+    )?;
+    Ok(Some(request))
+}
+
+// Requests of the definition of the revocation registry of a REV_REG_ENTRY or
+// REV_REG_DELTA DID URL and of its delta from its creation up to the requested
+// time, together with that time
+fn build_status_list_requests(
+    did: &DidUrl,
+    builder: &RequestBuilder,
+) -> DidIndyResult<Option<(PreparedRequest, PreparedRequest, i64)>> {
+    let (rev_reg, time) = match did.path.as_ref().map(|path| LedgerObject::from_str(path)) {
+        Some(Ok(LedgerObject::RevRegEntry(rev_reg))) => {
+            (rev_reg, did.query.get(&QueryParameter::VersionTime))
+        }
+        Some(Ok(LedgerObject::RevRegDelta(rev_reg))) => {
+            (rev_reg, did.query.get(&QueryParameter::To))
+        }
+        _ => return Ok(None),
+    };
+    let timestamp = parse_or_now(time)?;
+    let rev_reg_def_id = RevocationRegistryId::from_str(
+        format!(
+            "{}:4:{}:3:CL:{}:{}:CL_ACCUM:{}",
+            &did.id, &did.id, rev_reg.schema_seq_no, rev_reg.claim_def_name, rev_reg.tag
+        )
+        .as_str(),
+    )
+    .unwrap();
+    let rev_reg_def_request = builder.build_get_revoc_reg_def_request(None, &rev_reg_def_id)?;
+    let delta_request =
+        builder.build_get_revoc_reg_delta_request(None, &rev_reg_def_id, None, timestamp)?;
+    Ok(Some((rev_reg_def_request, delta_request, timestamp)))
+}
+
+fn parse_ledger_data(ledger_data: &str) -> DidIndyResult<Value> {
+    let v: Value = serde_json::from_str(&ledger_data)?;
+    let data: &Value = &v["result"]["data"];
+    if *data == Value::Null {
+        Err(DidIndyError::EmptyData)
+    } else {
+        Ok(data.to_owned())
+    }
+}
+
+// Serializes the result with sorted keys, as it is returned and cached
+fn serialize_result<R: Serialize>(result: &R) -> DidIndyResult<String> {
+    Ok(serde_json::to_string_pretty(&sort_keys(
+        serde_json::to_value(result)?,
+    ))?)
+}
+
+fn audit_record<R: SignedResult>(
+    audit: &AuditLog,
+    operation: &str,
+    did_url: &str,
+    result: &DidIndyResult<R>,
+    timestamp: String,
+    duration: Duration,
+    cache: CacheStatus,
+) -> AuditRecord {
+    let namespace = DidUrl::from_str(did_url)
+        .map(|did_url| did_url.namespace)
+        .unwrap_or_default();
+
+    let (object_type, seq_no) = match result.as_ref().map(SignedResult::method_metadata) {
+        Ok(Some(metadata)) => (
+            Some(metadata.object_type().as_str().to_string()),
+            metadata.node_response()["result"]["seqNo"].as_u64(),
+        ),
+        _ => (None, None),
+    };
+
+    AuditRecord {
+        timestamp,
+        operation: operation.to_string(),
+        did_url: audit.identifier(did_url.trim()),
+        namespace,
+        object_type,
+        result: match result {
+            Ok(_) => String::from("ok"),
+            Err(err) => err.error_code().to_string(),
+        },
+        seq_no,
+        duration_ms: duration.as_millis() as u64,
+        cache,
+    }
+}
+
+// Data and state proof of a ledger reply, None if the reply carries no state proof
+fn signed_txn(node_response: &Value) -> Option<SignedTransaction> {
+    match serde_json::from_value(node_response["result"].clone()) {
+        Ok(signed_txn) => Some(signed_txn),
+        Err(err) => {
+            warn!("Ledger reply without valid state proof, {}", err);
+            None
+        }
+    }
+}
+
+fn parse_or_now(datetime: Option<&String>) -> DidIndyResult<i64> {
+    match datetime {
+        Some(datetime) => {
+            let dt = DateTime::parse_from_rfc3339(datetime)?;
+            Ok(dt.timestamp())
+        }
+        None => Ok(Utc::now().timestamp()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use urlencoding::encode;
+
+    use super::*;
+    use crate::audit::AuditSink;
+    use crate::cache::{InMemoryCache, ResultCache};
+    use crate::config::{Timeouts, TrustPolicy, VersionConflict};
+    use crate::registry::ResolverRegistry;
+    use crate::signing::{verify_result, RequestSigner, SigningConfig};
+    use crate::tests::{endpoint_reply, ledger_reply, nym_reply, MockLedger};
+    use futures_util::future::LocalBoxFuture;
+    use rstest::*;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use indy_vdr::common::error::{VdrError, VdrErrorKind};
+    use indy_vdr::pool::ProtocolVersion;
+
+    #[fixture]
+    fn request_builder() -> RequestBuilder {
+        RequestBuilder::new(ProtocolVersion::Node1_4)
+    }
+
+    #[rstest]
+    fn build_get_nym_request_for_version_id(request_builder: RequestBuilder) {
+        let did_url =
+            DidUrl::from_str("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=42").unwrap();
+
+        let request = build_request(&did_url, &request_builder).unwrap();
+
+        assert_eq!(constants::GET_NYM, request.txn_type);
+        assert_eq!(42, request.req_json["operation"]["seqNo"]);
+        assert_eq!(Value::Null, request.req_json["operation"]["timestamp"]);
+    }
+
+    #[rstest]
+    fn build_get_nym_request_for_version_time(request_builder: RequestBuilder) {
+        let did_url = DidUrl::from_str(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14:33:42Z",
+        )
+        .unwrap();
+
+        let request = build_request(&did_url, &request_builder).unwrap();
+
+        assert_eq!(1636641222, request.req_json["operation"]["timestamp"]);
+        assert_eq!(Value::Null, request.req_json["operation"]["seqNo"]);
+    }
+
+    #[rstest]
+    #[case("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=abc")]
+    #[case("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=-1")]
+    #[case("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=yesterday")]
+    fn build_get_nym_request_fails_with_invalid_version(
+        request_builder: RequestBuilder,
+        #[case] did_url: &str,
+    ) {
+        let did_url = DidUrl::from_str(did_url).unwrap();
+
+        assert!(build_request(&did_url, &request_builder).is_err());
+    }
+
+    #[rstest]
+    fn build_get_nym_request_without_version(request_builder: RequestBuilder) {
+        let did_url = DidUrl::from_str("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp").unwrap();
+
+        let request = build_request(&did_url, &request_builder).unwrap();
+
+        assert_eq!(Value::Null, request.req_json["operation"]["seqNo"]);
+        assert_eq!(Value::Null, request.req_json["operation"]["timestamp"]);
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_request_from_version_time(request_builder: RequestBuilder) {
+        let datetime_as_str = "2020-12-20T19:17:47Z";
+        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime={}",datetime_as_str);
+        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder).unwrap();
+        let timestamp = (*(request.req_json).get("operation").unwrap())
+            .get("timestamp")
+            .unwrap()
+            .as_u64()
+            .unwrap() as i64;
+        assert_eq!(constants::GET_REVOC_REG, request.txn_type);
+
+        assert_eq!(
+            DateTime::parse_from_rfc3339(datetime_as_str)
+                .unwrap()
+                .timestamp(),
+            timestamp
+        );
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_without_version_time(request_builder: RequestBuilder) {
+        let now = chrono::Utc::now().timestamp();
+
+        let did_url_as_str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54";
+        let did_url = DidUrl::from_str(did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder).unwrap();
+        let timestamp = (*(request.req_json).get("operation").unwrap())
+            .get("timestamp")
+            .unwrap()
+            .as_u64()
+            .unwrap() as i64;
+
+        assert_eq!(constants::GET_REVOC_REG, request.txn_type);
+        assert!(timestamp >= now);
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_request_fails_with_unparsable_version_time(
+        request_builder: RequestBuilder,
+    ) {
+        let datetime_as_str = "20201220T19:17:47Z";
+        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime={}",datetime_as_str);
+        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder);
+
+        assert!(matches! {
+            request, Err(DidIndyError::DateTimeError(_))
+        })
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_delta_request_fails_with_unparsable_from(
         request_builder: RequestBuilder,
     ) {
-        let datetime_as_str = "20201220T19:17:47Z";
-        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime={}",datetime_as_str);
-        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
-        let request = build_request(&did_url, &request_builder);
+        let from_as_str = "20191220T19:17:47Z";
+        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from={}",from_as_str);
+        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder);
+
+        assert!(matches! {
+            request, Err(DidIndyError::DateTimeError(_))
+        })
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_delta_request_with_from_to(request_builder: RequestBuilder) {
+        let from_as_str = "2019-12-20T19:17:47Z";
+        let to_as_str = "2020-12-20T19:17:47Z";
+        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from={}&to={}",from_as_str, to_as_str);
+        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder).unwrap();
+        assert_eq!(request.txn_type, constants::GET_REVOC_REG_DELTA);
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_delta_request_with_from_only(request_builder: RequestBuilder) {
+        let now = chrono::Utc::now().timestamp();
+        let from_as_str = "2019-12-20T19:17:47Z";
+        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from={}",from_as_str);
+        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder).unwrap();
+
+        let to = (*(request.req_json).get("operation").unwrap())
+            .get("to")
+            .unwrap()
+            .as_u64()
+            .unwrap() as i64;
+        assert_eq!(request.txn_type, constants::GET_REVOC_REG_DELTA);
+        assert!(to >= now)
+    }
+
+    #[rstest]
+    fn build_get_revoc_reg_delta_request_without_parameter(request_builder: RequestBuilder) {
+        let now = chrono::Utc::now().timestamp();
+        let did_url_as_str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54";
+        let did_url = DidUrl::from_str(did_url_as_str).unwrap();
+        let request = build_request(&did_url, &request_builder).unwrap();
+
+        let to = (*(request.req_json).get("operation").unwrap())
+            .get("to")
+            .unwrap()
+            .as_u64()
+            .unwrap() as i64;
+
+        let from = (*(request.req_json).get("operation").unwrap()).get("from");
+        assert_eq!(request.txn_type, constants::GET_REVOC_REG_DELTA);
+        assert!(from.is_none());
+        assert!(to >= now);
+    }
+
+    #[test]
+    fn parse_diddoc_content_from_string() {
+        let content = serde_json::json!({"service": []});
+        assert_eq!(
+            Some(content.clone()),
+            parse_diddoc_content(Some(Value::String(content.to_string()))).unwrap()
+        );
+        assert_eq!(
+            Some(content.clone()),
+            parse_diddoc_content(Some(content)).unwrap()
+        );
+        assert_eq!(None, parse_diddoc_content(None).unwrap());
+    }
+
+    #[test]
+    fn legacy_endpoint_only_fetched_without_diddoc_content_by_default() {
+        let precedence = EndpointPrecedence::default();
+        assert!(needs_legacy_endpoint(&None, precedence));
+        assert!(!needs_legacy_endpoint(
+            &Some(serde_json::json!({"service": []})),
+            precedence
+        ));
+    }
+
+    #[test]
+    fn legacy_endpoint_always_fetched_when_merging() {
+        assert!(needs_legacy_endpoint(&None, EndpointPrecedence::Merge));
+        assert!(needs_legacy_endpoint(
+            &Some(serde_json::json!({"service": []})),
+            EndpointPrecedence::Merge
+        ));
+    }
+
+    #[test]
+    fn resolve_fails_when_request_exceeds_request_timeout() {
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(500))
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                None,
+            ));
+        let config = ResolverConfig {
+            timeouts: Timeouts {
+                request: Some(Duration::from_millis(50)),
+                total: None,
+            },
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        assert!(matches!(
+            resolver.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::RequestTimeout)
+        ));
+    }
+
+    #[test]
+    fn resolve_fails_when_round_trips_exceed_total_timeout() {
+        // Each round-trip is within the request timeout, NYM and ATTRIB together are not
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(150))
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                None,
+            ))
+            .reply(endpoint_reply("https://agent.com"));
+        let config = ResolverConfig {
+            timeouts: Timeouts {
+                request: Some(Duration::from_secs(1)),
+                total: Some(Duration::from_millis(250)),
+            },
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        assert!(matches!(
+            resolver.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::ResolutionTimeout)
+        ));
+    }
+
+    #[test]
+    fn resolve_within_timeouts() {
+        let ledger = MockLedger::new()
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                None,
+            ))
+            .reply(endpoint_reply("https://agent.com"));
+        let config = ResolverConfig {
+            timeouts: Timeouts {
+                request: Some(Duration::from_secs(1)),
+                total: Some(Duration::from_secs(2)),
+            },
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        assert!(resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+        assert_eq!(2, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn async_resolution_awaits_legacy_endpoint() {
+        let resolver = Resolver::new(ledger_with_legacy_endpoint());
+
+        let result =
+            block_on(resolver.resolve_async("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")).unwrap();
+
+        assert_eq!(
+            "https://agent.com",
+            result.did_document_value().unwrap()["service"][0]["serviceEndpoint"]
+        );
+        assert_eq!(2, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn async_dereferencing_matches_blocking_one() {
+        let blocking = Resolver::new(MockLedger::new().reply(schema_reply()));
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+
+        assert_eq!(
+            blocking.dereference(SCHEMA_URL).unwrap(),
+            block_on(resolver.dereference_async(SCHEMA_URL)).unwrap()
+        );
+    }
+
+    #[test]
+    fn strict_resolve_rejects_invalid_did_document() {
+        let diddoc_content = serde_json::json!({ "authentication": ["#keys-2"] });
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(diddoc_content),
+        );
+
+        let lenient = Resolver::new(MockLedger::new().reply(nym.clone()));
+        assert!(lenient
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+
+        let config = ResolverConfig {
+            strict: true,
+            ..ResolverConfig::default()
+        };
+        let strict = Resolver::with_config(MockLedger::new().reply(nym), config);
+        assert!(matches!(
+            strict.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::DidDocumentValidation(issues)) if issues.len() == 1
+        ));
+    }
+
+    #[test]
+    fn strict_mode_overridden_by_options() {
+        let diddoc_content = serde_json::json!({ "authentication": ["#keys-2"] });
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(diddoc_content),
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(nym.clone()).reply(nym));
+
+        assert!(matches!(
+            resolver.resolve_string_with_options(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                &ResolveOptions::new().strict(true)
+            ),
+            Err(DidIndyError::DidDocumentValidation(_))
+        ));
+        assert!(resolver
+            .resolve_string_with_options(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                &ResolveOptions::new().strict(false)
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn legacy_endpoint_with_disallowed_scheme_left_out() {
+        let nym = nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None);
+        let endpoint = endpoint_reply("javascript:alert(1)");
+        let resolver = Resolver::new(
+            MockLedger::new()
+                .reply(nym.clone())
+                .reply(endpoint.clone())
+                .reply(nym)
+                .reply(endpoint),
+        );
+
+        let json = resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        let result: ResolutionResult = serde_json::from_str(&json).unwrap();
+        assert!(serde_json::from_str::<Value>(&json).unwrap()["didDocument"]["service"].is_null());
+        assert_eq!(
+            vec!["Invalid service endpoint javascript:alert(1), service endpoint left out"],
+            result.warnings()
+        );
+
+        assert!(matches!(
+            resolver.resolve_string_with_options(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                &ResolveOptions::new().strict(true)
+            ),
+            Err(DidIndyError::InvalidServiceEndpoint(_))
+        ));
+    }
+
+    static CONFLICTING_VERSIONS_DID: &str =
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=12&versionTime=2021-11-11T14:33:42Z";
+
+    #[test]
+    fn conflicting_version_parameters_rejected_by_default() {
+        let resolver = Resolver::new(MockLedger::new());
+
+        let err = resolver
+            .resolve_string(CONFLICTING_VERSIONS_DID)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DidIndyError::ConflictingQueryParameters {
+                first: "versionId",
+                second: "versionTime"
+            }
+        ));
+        assert_eq!("invalidDidUrl", err.error_code());
+        assert!(matches!(
+            resolver.dereference_string(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionId=12&versionTime=2021-11-11T14:33:42Z"
+            ),
+            Err(DidIndyError::ConflictingQueryParameters { .. })
+        ));
+        assert!(resolver.validate_did_url(CONFLICTING_VERSIONS_DID).is_err());
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[rstest]
+    #[case(
+        VersionConflict::PreferVersionId,
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=12"
+    )]
+    #[case(
+        VersionConflict::PreferVersionTime,
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14%3A33%3A42Z"
+    )]
+    fn conflicting_version_parameters_settled_by_preference(
+        #[case] preference: VersionConflict,
+        #[case] settled: &str,
+    ) {
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(nym.clone()));
+        let options = ResolveOptions::new()
+            .echo_input(true)
+            .version_conflict(preference);
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string_with_options(CONFLICTING_VERSIONS_DID, &options)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(settled, result["didResolutionMetadata"]["did"]);
+
+        // The same preference in the config
+        let resolver = Resolver::with_config(
+            MockLedger::new().reply(nym),
+            ResolverConfig {
+                version_conflict: preference,
+                ..ResolverConfig::default()
+            },
+        );
+        assert!(resolver.resolve_string(CONFLICTING_VERSIONS_DID).is_ok());
+    }
+
+    #[rstest]
+    #[case(VersionConflict::Reject, None)]
+    #[case(
+        VersionConflict::PreferVersionId,
+        Some("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=12")
+    )]
+    #[case(
+        VersionConflict::PreferVersionTime,
+        Some("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14%3A33%3A42Z")
+    )]
+    fn seq_no_alias_settled_like_version_id(
+        #[case] preference: VersionConflict,
+        #[case] settled: Option<&str>,
+    ) {
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(nym));
+        let options = ResolveOptions::new()
+            .echo_input(true)
+            .version_conflict(preference);
+
+        let result = resolver.resolve_string_with_options(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?seqNo=12&versionTime=2021-11-11T14:33:42Z",
+            &options,
+        );
+        match settled {
+            Some(settled) => {
+                let result: Value = serde_json::from_str(&result.unwrap()).unwrap();
+                assert_eq!(settled, result["didResolutionMetadata"]["did"]);
+            }
+            None => assert!(matches!(
+                result,
+                Err(DidIndyError::ConflictingQueryParameters {
+                    first: "seqNo",
+                    second: "versionTime"
+                })
+            )),
+        }
+
+        // Naming different versions with both is ambiguous whatever the preference
+        assert!(matches!(
+            resolver.resolve_string_with_options(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=12&seqNo=13",
+                &options
+            ),
+            Err(DidIndyError::ConflictingQueryParameters {
+                first: "versionId",
+                second: "seqNo"
+            })
+        ));
+    }
+
+    #[test]
+    fn seq_no_alias_requests_version() {
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(nym.clone()).reply(nym));
+
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?seqNo=12")
+            .unwrap();
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=12&seqNo=12")
+            .unwrap();
+        let requests = resolver.pool.requests();
+        assert_eq!(12, requests[0]["operation"]["seqNo"]);
+        assert_eq!(requests[0]["operation"], requests[1]["operation"]);
+    }
+
+    #[test]
+    fn namespace_mismatch_is_rejected() {
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(nym)).with_namespace("idunion");
+
+        assert!(matches!(
+            resolver.resolve_string("did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::NamespaceNotSupported)
+        ));
+        assert!(matches!(
+            resolver.dereference_string(
+                "did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4"
+            ),
+            Err(DidIndyError::NamespaceNotSupported)
+        ));
+        assert!(resolver.pool.requests().is_empty());
+        assert!(resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+    }
+
+    fn nym_txn_reply(seq_no: u64, data: Value) -> String {
+        ledger_reply(
+            constants::GET_TXN,
+            serde_json::json!({
+                "txn": { "type": constants::NYM, "data": data },
+                "txnMetadata": { "seqNo": seq_no, "txnTime": 1636641222 }
+            }),
+            seq_no,
+            1636641222,
+        )
+    }
+
+    #[test]
+    fn nym_changes_between_versions() {
+        let history = MockLedger::new()
+            .reply(nym_txn_reply(
+                12,
+                serde_json::json!({
+                    "dest": "Dk1fRRTtNazyMuK2cr64wp",
+                    "verkey": "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    "role": "101"
+                }),
+            ))
+            .reply(nym_txn_reply(
+                40,
+                serde_json::json!({
+                    "dest": "Dk1fRRTtNazyMuK2cr64wp",
+                    "verkey": "~CoRER63DVYnWZtK8uAzNbx",
+                    "diddocContent": "{\"service\":[]}"
+                }),
+            ));
+        let resolver = Resolver::new(history);
+
+        let changes = resolver
+            .nym_changes("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", 12, 40)
+            .unwrap();
+
+        assert_eq!(12, changes.from_version);
+        assert_eq!(40, changes.to_version);
+        // The role is not set by the newer transaction
+        assert_eq!(
+            vec![
+                AttributeChange {
+                    attribute: String::from("verkey"),
+                    from: Value::from("~4Vr1bJ6V6jEPQ1PdmWyNkK"),
+                    to: Value::from("~CoRER63DVYnWZtK8uAzNbx"),
+                },
+                AttributeChange {
+                    attribute: String::from("diddocContent"),
+                    from: Value::Null,
+                    to: serde_json::json!({ "service": [] }),
+                },
+            ],
+            changes.changes
+        );
+        let requests = resolver.pool.requests();
+        assert_eq!(12, requests[0]["operation"]["data"]);
+        assert_eq!(40, requests[1]["operation"]["data"]);
+    }
+
+    #[test]
+    fn nym_changes_of_unchanged_versions() {
+        let data = serde_json::json!({
+            "dest": "Dk1fRRTtNazyMuK2cr64wp",
+            "verkey": "~4Vr1bJ6V6jEPQ1PdmWyNkK"
+        });
+        let resolver = Resolver::new(
+            MockLedger::new()
+                .reply(nym_txn_reply(12, data.clone()))
+                .reply(nym_txn_reply(12, data)),
+        );
+
+        let changes = resolver
+            .nym_changes("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", 12, 12)
+            .unwrap();
+        assert!(changes.changes.is_empty());
+    }
+
+    #[test]
+    fn nym_changes_of_other_transaction_fails() {
+        let resolver = Resolver::new(
+            MockLedger::new()
+                .reply(nym_txn_reply(
+                    12,
+                    serde_json::json!({ "dest": "V4SGRU86Z58d6TV7PBUe6f", "verkey": "~4Vr1bJ6V6jEPQ1PdmWyNkK" }),
+                ))
+                .reply(schema_reply()),
+        );
+
+        assert!(matches!(
+            resolver.nym_changes("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", 12, 74),
+            Err(DidIndyError::NotFound)
+        ));
+    }
+
+    #[rstest]
+    #[case("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", None)]
+    #[case(SCHEMA_URL, None)]
+    #[case("did:sov:Dk1fRRTtNazyMuK2cr64wp", Some("invalidDid"))]
+    #[case("did:indy:idunion:Dk1f", Some("invalidDid"))]
+    #[case(
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v5/SCHEMA/npdb/4.3.4",
+        Some("invalidDidUrl")
+    )]
+    #[case(
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/vcdm/v0/SCHEMA/npdb/4.3.4",
+        Some("invalidDidUrl")
+    )]
+    #[case(
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=yesterday",
+        Some("invalidDidUrl")
+    )]
+    #[case(
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?color=blue",
+        Some("invalidDidUrl")
+    )]
+    #[case("did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp", Some("methodNotSupported"))]
+    fn validate_did_url_error_codes(#[case] input: &str, #[case] error_code: Option<&str>) {
+        let resolver = Resolver::new(MockLedger::new()).with_namespace("idunion");
+        assert_eq!(
+            error_code,
+            resolver
+                .validate_did_url(input)
+                .err()
+                .map(|err| err.error_code())
+        );
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn explain_resolution() {
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(nym.clone()));
+
+        let trace = resolver
+            .explain("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        assert_eq!("resolve", trace["operation"]);
+        assert_eq!("idunion", trace["components"]["namespace"]);
+        assert_eq!("105", trace["request"]["txnType"]);
+        assert_eq!(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            trace["request"]["operation"]["dest"]
+        );
+        assert_eq!(serde_json::from_str::<Value>(&nym).unwrap(), trace["reply"]);
+        let steps: Vec<&Value> = trace["steps"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|step| &step["step"])
+            .collect();
+        assert_eq!(vec!["didDocument", "metadata"], steps);
+        assert_eq!(true, trace["steps"][0]["diddocContent"]);
+        assert_eq!(1, trace["requests"].as_array().unwrap().len());
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            trace["result"]["didDocument"]["id"]
+        );
+        // The ledger is asked once
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn explain_dereferencing() {
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+
+        let trace = resolver.explain(SCHEMA_URL).unwrap();
+
+        assert_eq!("dereference", trace["operation"]);
+        assert_eq!("SCHEMA", trace["components"]["ledgerObject"]["objectType"]);
+        assert_eq!("107", trace["request"]["txnType"]);
+        assert_eq!("ledgerObject", trace["steps"][0]["step"]);
+        assert_eq!("SCHEMA", trace["steps"][0]["objectType"]);
+        assert!(trace["result"]["contentStream"].is_object());
+    }
+
+    #[test]
+    fn explain_failed_resolution() {
+        let resolver = Resolver::new(MockLedger::new());
+
+        let trace = resolver
+            .explain("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert_eq!("notFound", trace["error"]["code"]);
+        assert!(trace.get("result").is_none());
+
+        assert!(matches!(
+            resolver.explain("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?color=blue"),
+            Err(DidIndyError::QueryParameterNotSupported)
+        ));
+    }
+
+    // Names of the steps of a trace, in order
+    fn step_names(trace: &Value) -> Vec<&str> {
+        trace["steps"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|step| step["step"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn explain_traces_downgraded_requests() {
+        let ledger = MockLedger::new()
+            .fail(rejection(
+                "client request invalid: Unknown protocol version 2",
+            ))
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                None,
+            ))
+            .fail(rejection("client request invalid: unknown field timestamp"))
+            .answer(constants::GET_ATTR, endpoint_reply("https://agent.com"));
+        let config = ResolverConfig {
+            best_effort: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        let trace = resolver
+            .explain("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14:33:42Z")
+            .unwrap();
+
+        // The NYM request in the form that was answered
+        assert_eq!("105", trace["request"]["txnType"]);
+        assert_eq!(1, trace["request"]["protocolVersion"]);
+        let requests = trace["requests"].as_array().unwrap();
+        assert_eq!(resolver.pool.requests().len(), requests.len());
+        assert_eq!(
+            vec!["105", "105", "104", "104"],
+            requests
+                .iter()
+                .map(|request| request["txnType"].as_str().unwrap())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(1636641222, requests[2]["operation"]["timestamp"]);
+        assert_eq!(Value::Null, requests[3]["operation"]["timestamp"]);
+        assert_eq!(
+            vec![
+                "requestDowngrade",
+                "didDocument",
+                "requestDowngrade",
+                "legacyEndpoint",
+                "endpointValidation",
+                "bestEffort",
+                "metadata"
+            ],
+            step_names(&trace)
+        );
+        assert_eq!("104", trace["steps"][2]["txnType"]);
+        assert_eq!(true, trace["steps"][3]["found"]);
+    }
+
+    #[test]
+    fn explain_deactivated_did_without_legacy_endpoint() {
+        let nym = json!({
+            "dest": "Dk1fRRTtNazyMuK2cr64wp",
+            "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+            "role": null,
+            "seqNo": 14,
+            "txnTime": 1636641240,
+            "verkey": null,
+        });
+        let resolver = Resolver::new(MockLedger::new().answer(
+            constants::GET_NYM,
+            ledger_reply(
+                constants::GET_NYM,
+                Value::String(nym.to_string()),
+                14,
+                1636641240,
+            ),
+        ));
+
+        let trace = resolver
+            .explain("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        assert_eq!(vec!["didDocument", "metadata"], step_names(&trace));
+        assert_eq!(true, trace["steps"][0]["deactivated"]);
+        assert_eq!(1, trace["requests"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn explain_with_options_of_the_call() {
+        let resolver = Resolver::new(ledger_with_legacy_endpoint());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        let trace = resolver.explain(did).unwrap();
+        assert_eq!(
+            vec![
+                "didDocument",
+                "legacyEndpoint",
+                "endpointValidation",
+                "metadata"
+            ],
+            step_names(&trace)
+        );
+        assert_eq!(false, trace["steps"][1]["skipped"]);
+        assert_eq!(2, trace["requests"].as_array().unwrap().len());
+
+        let options = ResolveOptions::new()
+            .skip_legacy_endpoint(true)
+            .strict(true)
+            .representation(Representation::JsonLd);
+        let trace = resolver.explain_with_options(did, &options).unwrap();
+        assert_eq!(
+            vec![
+                "didDocument",
+                "legacyEndpoint",
+                "jsonLd",
+                "validation",
+                "metadata"
+            ],
+            step_names(&trace)
+        );
+        assert_eq!(true, trace["steps"][1]["skipped"]);
+        assert_eq!(1, trace["requests"].as_array().unwrap().len());
+        assert_eq!(
+            "https://www.w3.org/ns/did/v1",
+            trace["result"]["didDocument"]["@context"]
+        );
+    }
+
+    #[test]
+    fn resolve_with_options_timeouts() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let options = ResolveOptions::new().timeouts(Timeouts {
+            request: Some(Duration::from_millis(50)),
+            total: None,
+        });
+
+        assert!(matches!(
+            resolver.dereference_string_with_options(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                &options
+            ),
+            Err(DidIndyError::RequestTimeout)
+        ));
+    }
+
+    #[test]
+    fn resolve_json_ld_representation() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let options = ResolveOptions::new().representation(Representation::JsonLd);
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string_with_options("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", &options)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            "https://www.w3.org/ns/did/v1",
+            result["didDocument"]["@context"]
+        );
+    }
+
+    #[test]
+    fn resolve_echoes_input_did() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        let result: ResolutionResult =
+            serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+        assert_eq!(None, result.did());
+
+        let options = ResolveOptions::new().echo_input(true);
+        let result: ResolutionResult = serde_json::from_str(
+            &resolver
+                .resolve_string_with_options(&format!("{}#verkey", did), &options)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Some(format!("{}#verkey", did).as_str()), result.did());
+    }
+
+    #[test]
+    fn best_effort_resolution_returns_document_with_warnings() {
+        let ledger = || {
+            MockLedger::new()
+                .reply(nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    None,
+                ))
+                .fail(DidIndyError::RequestTimeout)
+        };
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        assert!(matches!(
+            Resolver::new(ledger()).resolve_string(did),
+            Err(DidIndyError::RequestTimeout)
+        ));
+
+        let config = ResolverConfig {
+            best_effort: true,
+            ..ResolverConfig::default()
+        };
+        let result: ResolutionResult = serde_json::from_str(
+            &Resolver::with_config(ledger(), config)
+                .resolve_string(did)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(did, result.did_document().unwrap().unwrap().id);
+        assert_eq!(
+            vec!["Legacy endpoint not resolved: Ledger request timed out"],
+            result.warnings()
+        );
+    }
+
+    fn ledger_with_legacy_endpoint() -> MockLedger {
+        MockLedger::new()
+            .answer(
+                constants::GET_NYM,
+                nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+            )
+            .answer(constants::GET_ATTR, endpoint_reply("https://agent.com"))
+    }
+
+    #[test]
+    fn legacy_endpoint_fetched_as_of_requested_version() {
+        let resolver = Resolver::new(ledger_with_legacy_endpoint());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        resolver
+            .resolve_string(&format!("{}?versionTime=2021-11-11T14:33:42Z", did))
+            .unwrap();
+        resolver
+            .resolve_string(&format!("{}?versionId=12", did))
+            .unwrap();
+        resolver.resolve_string(did).unwrap();
+
+        let requests = resolver.pool.requests();
+        let attrib = |i: usize| &requests[i]["operation"];
+        assert_eq!(constants::GET_ATTR, attrib(1)["type"]);
+        assert_eq!(1636641222, attrib(1)["timestamp"]);
+        assert_eq!(Value::Null, attrib(1)["seqNo"]);
+        assert_eq!(12, attrib(3)["seqNo"]);
+        assert_eq!(Value::Null, attrib(3)["timestamp"]);
+        assert_eq!(Value::Null, attrib(5)["seqNo"]);
+        assert_eq!(Value::Null, attrib(5)["timestamp"]);
+    }
+
+    #[test]
+    fn nym_resolved_as_of_version_time_reports_prior_state() {
+        // The ledger answers with the NYM transaction in effect at the time
+        let ledger = MockLedger::new().reply(ledger_reply(
+            constants::GET_NYM,
+            Value::String(
+                json!({
+                    "dest": "Dk1fRRTtNazyMuK2cr64wp",
+                    "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+                    "role": null,
+                    "seqNo": 9,
+                    "txnTime": 1609459100,
+                    "verkey": "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    "diddocContent": "{}"
+                })
+                .to_string(),
+            ),
+            9,
+            1609459100,
+        ));
+        let resolver = Resolver::new(ledger);
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string(
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-01-01T00:00:00Z",
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            1609459200,
+            resolver.pool.requests()[0]["operation"]["timestamp"]
+        );
+        let metadata = &result["didDocumentMetadata"];
+        assert_eq!("9", metadata["versionId"]);
+        assert_eq!("2020-12-31T23:58:20Z", metadata["updated"]);
+    }
+
+    #[test]
+    fn nym_at_unparsable_version_time_not_requested() {
+        let resolver = Resolver::new(MockLedger::new());
+
+        assert!(matches!(
+            resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-01-01"),
+            Err(DidIndyError::DateTimeError(_))
+        ));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn didcomm_service_of_diddoc_content_resolved() {
+        let diddoc_content = json!({
+            "@context": [
+                "https://www.w3.org/ns/did/v1",
+                "https://didcomm.org/messaging/contexts/v2"
+            ],
+            "verificationMethod": [{
+                "id": "#key-agreement-1",
+                "type": "X25519KeyAgreementKey2019",
+                "controller": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                "publicKeyBase58": "JhNWeSVLMYccCk7iopQW4guaSJTojqpMEELgSLhKwRr"
+            }],
+            "keyAgreement": ["#key-agreement-1"],
+            "service": [{
+                "id": "#didcomm-1",
+                "type": "DIDCommMessaging",
+                "serviceEndpoint": {
+                    "uri": "https://agent.example.com/didcomm",
+                    "accept": ["didcomm/v2"],
+                    "routingKeys": []
+                }
+            }]
+        });
+        let resolver = Resolver::new(MockLedger::new().answer(
+            constants::GET_NYM,
+            nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(diddoc_content),
+            ),
+        ));
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+
+        let doc = &result["didDocument"];
+        assert_eq!(
+            json!([
+                "https://www.w3.org/ns/did/v1",
+                "https://didcomm.org/messaging/contexts/v2"
+            ]),
+            doc["@context"]
+        );
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#verkey",
+            doc["verificationMethod"][0]["id"]
+        );
+        assert_eq!("#key-agreement-1", doc["verificationMethod"][1]["id"]);
+        assert_eq!(json!(["#key-agreement-1"]), doc["keyAgreement"]);
+        assert_eq!("DIDCommMessaging", doc["service"][0]["type"]);
+        assert_eq!(
+            "https://agent.example.com/didcomm",
+            doc["service"][0]["serviceEndpoint"]["uri"]
+        );
+        // The legacy endpoint is not looked up for NYMs with diddocContent
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn ledger_replies_cached_within_ttl() {
+        let resolver = Resolver::with_cache(
+            MockLedger::new()
+                .answer(
+                    constants::GET_NYM,
+                    nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+                )
+                .answer(constants::GET_ATTR, endpoint_reply("https://agent.com"))
+                .answer(constants::GET_SCHEMA, schema_reply()),
+            CacheConfig::default(),
+        );
+
+        for _ in 0..2 {
+            resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap();
+            resolver.dereference_string(SCHEMA_URL).unwrap();
+        }
+
+        // NYM, legacy endpoint ATTRIB and schema, each requested once
+        assert_eq!(3, resolver.pool().pool().requests().len());
+    }
+
+    #[test]
+    fn ledger_replies_requested_again_after_ttl() {
+        let resolver = Resolver::with_cache(
+            MockLedger::new().answer(
+                constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    Some(json!({})),
+                ),
+            ),
+            CacheConfig {
+                ttl: Duration::from_millis(0),
+                ..CacheConfig::default()
+            },
+        );
+
+        for _ in 0..2 {
+            resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap();
+        }
+
+        assert_eq!(2, resolver.pool().pool().requests().len());
+    }
+
+    #[rstest]
+    #[case(Value::Null)]
+    #[case(json!(""))]
+    fn deactivated_nym_resolved_with_minimal_document(#[case] verkey: Value) {
+        let nym = json!({
+            "dest": "Dk1fRRTtNazyMuK2cr64wp",
+            "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+            "role": null,
+            "seqNo": 14,
+            "txnTime": 1636641240,
+            "verkey": verkey,
+        });
+        let resolver = Resolver::new(MockLedger::new().answer(
+            constants::GET_NYM,
+            ledger_reply(
+                constants::GET_NYM,
+                Value::String(nym.to_string()),
+                14,
+                1636641240,
+            ),
+        ));
+
+        let result = resolver
+            .resolve_typed("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        assert_eq!(
+            Some(&json!({ "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp" })),
+            result.did_document_value()
+        );
+        let metadata = match result.did_document_metadata() {
+            Some(Metadata::Spec(metadata)) => metadata,
+            _ => panic!("Expected DID document metadata"),
+        };
+        assert_eq!(Some(true), metadata.deactivated());
+        assert_eq!(Some("14"), metadata.version_id());
+        // No legacy endpoint is looked up for a deactivated DID
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    fn rejection(reason: &str) -> DidIndyError {
+        DidIndyError::VdrError(VdrError::from(VdrErrorKind::PoolRequestFailed(
+            String::from(reason),
+        )))
+    }
+
+    #[test]
+    fn versioned_endpoint_falls_back_to_current_one() {
+        let ledger = ledger_with_legacy_endpoint()
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                None,
+            ))
+            .fail(rejection("client request invalid: unknown field timestamp"));
+        let config = ResolverConfig {
+            best_effort: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        let json = resolver
+            .resolve_string(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14:33:42Z",
+            )
+            .unwrap();
+        let result_value = serde_json::from_str::<Value>(&json).unwrap();
+        assert_eq!(
+            "did-communication",
+            result_value["didDocument"]["service"][0]["type"]
+        );
+        let downgrade =
+            &result_value["didDocumentMetadata"]["methodMetadata"]["requestDowngrades"][0];
+        assert_eq!(constants::GET_ATTR, downgrade["txnType"]);
+        assert_eq!(true, downgrade["lossy"]);
+        let result: ResolutionResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(1, result.warnings().len());
+        assert!(result.warnings()[0].starts_with("Ledger rejected the versioned 104 request"));
+
+        let requests = resolver.pool.requests();
+        assert_eq!(3, requests.len());
+        assert_eq!(1636641222, requests[1]["operation"]["timestamp"]);
+        assert_eq!(Value::Null, requests[2]["operation"]["timestamp"]);
+    }
+
+    #[test]
+    fn versioned_request_rejection_fails_without_best_effort() {
+        let ledger = ledger_with_legacy_endpoint()
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                None,
+            ))
+            .fail(rejection("client request invalid: unknown field timestamp"));
+        let resolver = Resolver::new(ledger);
+
+        let err = resolver
+            .resolve_string(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionTime=2021-11-11T14:33:42Z",
+            )
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            DidIndyError::VersionedRequestRejected { txn_type, .. } if txn_type == constants::GET_ATTR
+        ));
+        // The versioned request is not sent again in the older form
+        assert_eq!(2, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn rejected_protocol_version_retried_in_older_form() {
+        let ledger = MockLedger::new()
+            .fail(rejection(
+                "client request invalid: Unknown protocol version 2",
+            ))
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(serde_json::json!({})),
+            ));
+        let resolver = Resolver::new(ledger);
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            result["didDocument"]["id"]
+        );
+        let downgrades = &result["didDocumentMetadata"]["methodMetadata"]["requestDowngrades"];
+        assert_eq!(1, downgrades.as_array().unwrap().len());
+        assert_eq!(
+            serde_json::json!(["protocolVersion 2 -> 1"]),
+            downgrades[0]["changes"]
+        );
+        assert_eq!(false, downgrades[0]["lossy"]);
+        let requests = resolver.pool.requests();
+        assert_eq!(2, requests.len());
+        assert_eq!(2, requests[0]["protocolVersion"]);
+        assert_eq!(1, requests[1]["protocolVersion"]);
+        assert_eq!(requests[0]["operation"], requests[1]["operation"]);
+    }
+
+    #[test]
+    fn other_rejections_not_retried() {
+        let ledger = MockLedger::new().fail(rejection("insufficient privileges"));
+        let resolver = Resolver::new(ledger);
+
+        assert!(matches!(
+            resolver.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::VdrError(_))
+        ));
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn resolve_transforms_keys() {
+        let ledger = MockLedger::new().reply(nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "B12NYF8RrR3h41TDCTJojY59usg3mbtbjnFs7Eud1Y6u",
+            Some(serde_json::json!({})),
+        ));
+        let resolver = Resolver::new(ledger);
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string(
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?transformKeys=publicKeyMultibase",
+                )
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            "z6MkpTHR8VNsBxYAAWHut2Geadd9jSwuBV8xRoAnwWsdvktH",
+            result["didDocument"]["verificationMethod"][0]["publicKeyMultibase"]
+        );
+    }
+
+    #[test]
+    fn resolve_fails_with_unsupported_transform_before_querying_ledger() {
+        let resolver = Resolver::new(MockLedger::new());
+
+        assert!(matches!(
+            resolver.resolve_string(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?transformKeys=publicKeyHex"
+            ),
+            Err(DidIndyError::UnsupportedTransform)
+        ));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn resolve_signs_result_when_configured() {
+        let nym = nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        );
+
+        let unsigned = Resolver::new(MockLedger::new().reply(nym.clone()))
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        let unsigned: Value = serde_json::from_str(&unsigned).unwrap();
+        assert_eq!(Value::Null, unsigned["didResolutionMetadata"]["proof"]);
+
+        let signing =
+            SigningConfig::new(&[7; 32], "did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f#resolver")
+                .unwrap();
+        let config = ResolverConfig {
+            signing: Some(signing.clone()),
+            ..ResolverConfig::default()
+        };
+        let signed = Resolver::with_config(MockLedger::new().reply(nym), config)
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert!(verify_result(&signed, &signing.public_key()).unwrap());
+    }
+
+    #[test]
+    fn resolve_serves_cached_result_within_ttl() {
+        // A second ledger request would fail as no reply is left
+        let ledger = MockLedger::new().reply(nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        ));
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        let first = resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        let second = resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn strict_and_lenient_results_cached_separately() {
+        let ledger = MockLedger::new().answer(
+            constants::GET_NYM,
+            nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(serde_json::json!({})),
+            ),
+        );
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        for options in [
+            ResolveOptions::new(),
+            ResolveOptions::new().strict(true),
+            ResolveOptions::new().strict(false),
+        ] {
+            resolver.resolve_string_with_options(did, &options).unwrap();
+            resolver.resolve_string_with_options(did, &options).unwrap();
+        }
+
+        assert_eq!(3, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn immutable_results_cached_past_ttl() {
+        let pinned = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp?versionId=12";
+        let ledger = MockLedger::new()
+            .reply(nym())
+            .reply(schema_reply())
+            .reply(nym())
+            .reply(nym());
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_millis(0))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        let first_pinned = resolver.resolve_string(pinned).unwrap();
+        let first_schema = resolver.dereference_string(SCHEMA_URL).unwrap();
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert_eq!(3, resolver.pool.requests().len());
+
+        assert_eq!(first_pinned, resolver.resolve_string(pinned).unwrap());
+        assert_eq!(
+            first_schema,
+            resolver.dereference_string(SCHEMA_URL).unwrap()
+        );
+        assert_eq!(3, resolver.pool.requests().len());
+        // The plain NYM expired right away
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert_eq!(4, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn resolvers_share_cache_backend() {
+        let cache = ResultCache::new(Arc::new(InMemoryCache::default()), Duration::from_secs(60));
+        let config = ResolverConfig {
+            cache: Some(cache),
+            ..ResolverConfig::default()
+        };
+        let first = Resolver::with_config(
+            MockLedger::new().reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(serde_json::json!({})),
+            )),
+            config.clone(),
+        );
+        let second = Resolver::with_config(MockLedger::new(), config);
+
+        first
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert!(second
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+        assert!(second.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn cache_modes_of_options() {
+        let sink = Arc::new(CollectingSink::default());
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            audit: Some(AuditLog::new(sink.clone())),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        resolver
+            .resolve_string_with_options(did, &ResolveOptions::new().cache(CacheMode::Bypass))
+            .unwrap();
+        resolver
+            .resolve_string_with_options(did, &ResolveOptions::new().cache(CacheMode::Refresh))
+            .unwrap();
+        resolver.resolve_string(did).unwrap();
+        // Representations are cached separately
+        resolver
+            .resolve_string_with_options(
+                did,
+                &ResolveOptions::new().representation(Representation::JsonLd),
+            )
+            .unwrap();
+
+        let cache: Vec<CacheStatus> = sink.0.lock().unwrap().iter().map(|r| r.cache).collect();
+        assert_eq!(
+            vec![
+                CacheStatus::Bypassed,
+                CacheStatus::Miss,
+                CacheStatus::Hit,
+                CacheStatus::Miss
+            ],
+            cache
+        );
+        assert_eq!(3, resolver.pool.requests().len());
+    }
+
+    fn stale_config() -> ResolverConfig {
+        ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_millis(0))),
+            serve_stale_on_error: Some(Duration::from_secs(3600)),
+            ..ResolverConfig::default()
+        }
+    }
+
+    fn nym() -> String {
+        nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        )
+    }
+
+    #[test]
+    fn stale_result_served_during_outage() {
+        let ledger = MockLedger::new()
+            .reply(nym())
+            .fail(DidIndyError::RequestTimeout);
+        let resolver = Resolver::with_config(ledger, stale_config());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        let fresh: Value = serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+        let stale: Value = serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+
+        assert_eq!(2, resolver.pool.requests().len());
+        assert_eq!(fresh["didDocument"], stale["didDocument"]);
+        let metadata = &stale["didResolutionMetadata"];
+        assert_eq!(true, metadata["fromCache"]);
+        assert_eq!(true, metadata["stale"]);
+        assert!(DateTime::parse_from_rfc3339(metadata["retrieved"].as_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn stale_result_not_served_for_ledger_answers_or_refresh() {
+        let ledger = MockLedger::new()
+            .reply(nym())
+            .fail(DidIndyError::NotFound)
+            .fail(DidIndyError::RequestTimeout);
+        let resolver = Resolver::with_config(ledger, stale_config());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        resolver.resolve_string(did).unwrap();
+        assert!(matches!(
+            resolver.resolve_string(did),
+            Err(DidIndyError::NotFound)
+        ));
+        assert!(matches!(
+            resolver
+                .resolve_string_with_options(did, &ResolveOptions::new().cache(CacheMode::Refresh)),
+            Err(DidIndyError::RequestTimeout)
+        ));
+    }
+
+    #[test]
+    fn unbounded_stale_ttl_served_during_outage() {
+        let ledger = MockLedger::new()
+            .reply(nym())
+            .fail(DidIndyError::RequestTimeout);
+        let config = ResolverConfig {
+            serve_stale_on_error: Some(Duration::MAX),
+            ..stale_config()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        resolver.resolve_string(did).unwrap();
+        let stale: Value = serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+
+        assert_eq!(true, stale["didResolutionMetadata"]["stale"]);
+    }
+
+    #[test]
+    fn outage_fails_without_cached_result() {
+        let ledger = MockLedger::new().fail(DidIndyError::RequestTimeout);
+        let resolver = Resolver::with_config(ledger, stale_config());
+        assert!(matches!(
+            resolver.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::RequestTimeout)
+        ));
+    }
+
+    #[test]
+    fn failed_resolution_is_not_cached() {
+        let ledger = MockLedger::new()
+            .fail(DidIndyError::NotFound)
+            .reply(nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(serde_json::json!({})),
+            ));
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        assert!(resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_err());
+        assert!(resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+    }
+
+    #[test]
+    fn supports_did_indy_without_querying_ledger() {
+        let resolver = Resolver::new(MockLedger::new());
+
+        assert!(resolver.supports("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"));
+        assert!(resolver
+            .supports("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4"));
+        assert!(!resolver.supports("did:sov:Dk1fRRTtNazyMuK2cr64wp"));
+        assert!(!resolver.supports("did:indy:idunion"));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn supports_only_namespace_of_bound_resolver() {
+        let resolver = Resolver::new(MockLedger::new()).with_namespace("idunion");
+
+        assert!(resolver.supports("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"));
+        assert!(!resolver.supports("did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp"));
+        assert!(!resolver.supports("did:indy:sovrin:staging:Dk1fRRTtNazyMuK2cr64wp"));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    // Resolves the DID on several threads at once and waits for all of them
+    fn resolve_concurrently(
+        resolver: Resolver<MockLedger>,
+        resolutions: usize,
+    ) -> (Arc<Resolver<MockLedger>>, Vec<DidIndyResult<String>>) {
+        let resolver = Arc::new(resolver);
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..resolutions {
+            let resolver = resolver.clone();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let result = resolver.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp");
+                sender.send(result).unwrap();
+            });
+        }
+        let results = (0..resolutions)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(10)).unwrap())
+            .collect();
+        (resolver, results)
+    }
+
+    #[test]
+    fn concurrent_ledger_requests_are_limited() {
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(50))
+            .answer(
+                constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    Some(serde_json::json!({})),
+                ),
+            );
+        let config = ResolverConfig {
+            max_concurrent_requests: Some(2),
+            ..ResolverConfig::default()
+        };
+
+        let (resolver, results) = resolve_concurrently(Resolver::with_config(ledger, config), 6);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert!(resolver.pool.max_in_flight() <= 2);
+        assert_eq!(RequestStats::default(), resolver.stats());
+    }
+
+    #[test]
+    fn resolutions_needing_two_requests_do_not_deadlock() {
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(20))
+            .answer(
+                constants::GET_NYM,
+                nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+            )
+            .answer(constants::GET_ATTR, endpoint_reply("https://agent.com"));
+        let config = ResolverConfig {
+            max_concurrent_requests: Some(1),
+            ..ResolverConfig::default()
+        };
+
+        let (resolver, results) = resolve_concurrently(Resolver::with_config(ledger, config), 4);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(8, resolver.pool.requests().len());
+        assert_eq!(1, resolver.pool.max_in_flight());
+    }
+
+    #[test]
+    fn waiting_for_limit_counts_towards_request_timeout() {
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(300))
+            .answer(
+                constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    Some(serde_json::json!({})),
+                ),
+            );
+        let config = ResolverConfig {
+            max_concurrent_requests: Some(1),
+            timeouts: Timeouts {
+                request: Some(Duration::from_millis(450)),
+                total: None,
+            },
+            ..ResolverConfig::default()
+        };
+
+        // The second resolution waits 300ms for the permit and times out
+        let (_, results) = resolve_concurrently(Resolver::with_config(ledger, config), 2);
+        assert_eq!(1, results.iter().filter(|result| result.is_ok()).count());
+        assert!(results
+            .iter()
+            .any(|result| matches!(result, Err(DidIndyError::RequestTimeout))));
+    }
+
+    fn nym_answering_ledger() -> MockLedger {
+        MockLedger::new()
+            .with_delay(Duration::from_millis(200))
+            .answer(
+                constants::GET_NYM,
+                nym_reply(
+                    "Dk1fRRTtNazyMuK2cr64wp",
+                    "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                    Some(serde_json::json!({})),
+                ),
+            )
+    }
+
+    #[test]
+    fn request_hook_mutates_every_request() {
+        let pool = MockLedger::new().answer(
+            constants::GET_NYM,
+            nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+        );
+        let resolver = Resolver::new(pool).with_request_hook(|request| {
+            request.req_json["endorser"] = Value::from("V4SGRU86Z58d6TV7PBUe6f");
+        });
+
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        // GET_NYM and the GET_ATTRIB of the legacy endpoint
+        let requests = resolver.pool.requests();
+        assert_eq!(2, requests.len());
+        assert_eq!(constants::GET_ATTR, requests[1]["operation"]["type"]);
+        assert!(requests
+            .iter()
+            .all(|request| request["endorser"] == "V4SGRU86Z58d6TV7PBUe6f"));
+    }
+
+    static SUBMITTER_DID: &str = "V4SGRU86Z58d6TV7PBUe6f";
+    static SUBMITTER_KEY: [u8; 32] = [9; 32];
+
+    #[test]
+    fn read_requests_signed_by_submitter() {
+        let pool = MockLedger::new().answer(
+            constants::GET_NYM,
+            nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+        );
+        let resolver = Resolver::new(pool).with_read_request_signing(
+            ReadRequestSigning::ed25519(SUBMITTER_DID, &SUBMITTER_KEY).unwrap(),
+        );
+
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        let public_key = ed25519_dalek::PublicKey::from(
+            &ed25519_dalek::SecretKey::from_bytes(&SUBMITTER_KEY).unwrap(),
+        );
+        let requests = resolver.pool.requests();
+        assert_eq!(2, requests.len());
+        for request in requests {
+            assert_eq!(SUBMITTER_DID, request["identifier"]);
+            let signature = bs58::decode(request["signature"].as_str().unwrap())
+                .into_vec()
+                .unwrap();
+            let input = PreparedRequest::from_request_json(request.to_string())
+                .unwrap()
+                .get_signature_input()
+                .unwrap();
+            assert!(public_key
+                .verify_strict(
+                    input.as_bytes(),
+                    &ed25519_dalek::Signature::try_from(signature.as_slice()).unwrap()
+                )
+                .is_ok());
+        }
+    }
+
+    struct FailingSigner;
+
+    impl RequestSigner for FailingSigner {
+        fn sign(&self, _message: &[u8]) -> std::result::Result<Vec<u8>, String> {
+            Err(String::from("HSM not reachable"))
+        }
+    }
+
+    #[test]
+    fn failing_request_signature_not_submitted() {
+        let resolver = Resolver::new(MockLedger::new()).with_read_request_signing(
+            ReadRequestSigning::new(SUBMITTER_DID, Arc::new(FailingSigner)),
+        );
+
+        let err = resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap_err();
+
+        assert!(
+            matches!(err, DidIndyError::RequestSigningFailed(reason) if reason == "HSM not reachable")
+        );
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn read_requests_unsigned_by_default() {
+        let resolver = Resolver::new(nym_answering_ledger());
+
+        resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        assert!(resolver
+            .pool
+            .requests()
+            .iter()
+            .all(|request| request.get("signature").is_none()));
+    }
+
+    #[test]
+    fn did_document_metadata_has_spec_properties() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        let metadata = &result["didDocumentMetadata"];
+
+        assert_eq!("2021-11-11T14:33:42Z", metadata["updated"]);
+        assert_eq!("12", metadata["versionId"]);
+        assert_eq!("NYM", metadata["methodMetadata"]["objectType"]);
+        assert_eq!(
+            12,
+            metadata["methodMetadata"]["nodeResponse"]["result"]["seqNo"]
+        );
+        assert!(metadata.get("objectType").is_none());
+        assert!(metadata.get("nodeResponse").is_none());
+        assert!(metadata.get("created").is_none());
+    }
+
+    #[test]
+    fn resolution_metadata_always_set() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        let result: Value = serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+        let metadata = &result["didResolutionMetadata"];
+        assert_eq!("application/did+json", metadata["contentType"]);
+        assert!(metadata["duration"].is_u64());
+        assert!(metadata.get("error").is_none());
+
+        let options = ResolveOptions::new().representation(Representation::JsonLd);
+        let result: Value =
+            serde_json::from_str(&resolver.resolve_string_with_options(did, &options).unwrap())
+                .unwrap();
+        assert_eq!(
+            "application/did+ld+json",
+            result["didResolutionMetadata"]["contentType"]
+        );
+
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(SCHEMA_URL).unwrap()).unwrap();
+        assert_eq!(
+            "application/json",
+            result["dereferencingMetadata"]["contentType"]
+        );
+        assert!(result["dereferencingMetadata"]["duration"].is_u64());
+    }
+
+    #[test]
+    fn failed_results_carry_error_code() {
+        let result = ResolutionResult::failed(&DidIndyError::NotFound);
+        assert_eq!(Some("notFound"), result.error());
+        assert_eq!(
+            json!({
+                "didResolutionMetadata": {"error": "notFound"},
+                "didDocument": null,
+                "didDocumentMetadata": null
+            }),
+            serde_json::to_value(&result).unwrap()
+        );
+
+        let result = DereferencingResult::failed(&DidIndyError::InvalidDidUrl);
+        assert_eq!(Some("invalidDid"), result.error());
+        assert_eq!(None, result.did_url());
+    }
+
+    #[rstest]
+    #[case("did:indy:idunion:invalid", MockLedger::new(), "invalidDid")]
+    #[case(
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+        MockLedger::new().reply(ledger_reply(constants::GET_NYM, Value::Null, 0, 0)),
+        "notFound"
+    )]
+    #[case(
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+        MockLedger::new().fail(rejection("unknown")),
+        "internalError"
+    )]
+    fn failed_resolution_as_result(
+        #[case] did: &str,
+        #[case] ledger: MockLedger,
+        #[case] error: &str,
+    ) {
+        let result = Resolver::new(ledger).resolve_to_result(did, &ResolveOptions::default());
+
+        let result: ResolutionResult = serde_json::from_str(&result).unwrap();
+        assert_eq!(Some(error), result.error());
+        assert_eq!(None, result.did_document);
+    }
+
+    #[test]
+    fn representations_of_media_types() {
+        assert_eq!(
+            Representation::JsonLd,
+            Representation::from_media_type("application/did+ld+json").unwrap()
+        );
+        assert_eq!(
+            "application/did+json",
+            Representation::from_media_type("application/did+json")
+                .unwrap()
+                .media_type()
+        );
+        let err = Representation::from_media_type("text/html").unwrap_err();
+        assert_eq!("representationNotSupported", err.error_code());
+    }
+
+    #[test]
+    fn metadata_without_txn_time() {
+        let mut reply: Value = serde_json::from_str(&nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        ))
+        .unwrap();
+        reply["result"].as_object_mut().unwrap().remove("txnTime");
+        let resolver =
+            Resolver::new(MockLedger::new().answer(constants::GET_NYM, reply.to_string()));
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        let metadata = result["didDocumentMetadata"].as_object().unwrap();
+
+        assert!(!metadata.contains_key("updated"));
+        assert!(!metadata.contains_key("created"));
+        assert_eq!("12", metadata["versionId"]);
+    }
+
+    #[test]
+    fn did_document_metadata_in_legacy_shape() {
+        let config = ResolverConfig {
+            legacy_metadata: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        let metadata = &result["didDocumentMetadata"];
+
+        assert_eq!("NYM", metadata["objectType"]);
+        assert_eq!(12, metadata["nodeResponse"]["result"]["seqNo"]);
+        assert!(metadata.get("methodMetadata").is_none());
+        assert!(metadata.get("updated").is_none());
+    }
+
+    #[test]
+    fn resolution_result_deserializes_both_metadata_shapes() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        let result: ResolutionResult =
+            serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+        assert!(matches!(
+            result.did_document_metadata,
+            Some(Metadata::Spec(_))
+        ));
+
+        let config = ResolverConfig {
+            legacy_metadata: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+        let result: ResolutionResult =
+            serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+        assert!(matches!(
+            result.did_document_metadata,
+            Some(Metadata::Legacy(_))
+        ));
+    }
+
+    #[test]
+    fn results_are_byte_identical_with_sorted_keys() {
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        let attrib = ledger_reply(
+            constants::GET_ATTR,
+            Value::String(
+                json!({"endpoint": {
+                    "endpoint": "https://agent.com",
+                    "profile": "https://agent.com/profile",
+                    "linked-domains": "https://agent.com",
+                    "agent": "https://agent.com/agent"
+                }})
+                .to_string(),
+            ),
+            13,
+            1636641230,
+        );
+        let resolve = || {
+            let ledger = MockLedger::new()
+                .answer(
+                    constants::GET_NYM,
+                    nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+                )
+                .answer(constants::GET_ATTR, attrib.clone());
+            let result = Resolver::new(ledger).resolve_string(did).unwrap();
+            // How long the resolution took differs between runs
+            regex::Regex::new(r#""duration": \d+"#)
+                .unwrap()
+                .replace(&result, r#""duration": 0"#)
+                .into_owned()
+        };
+
+        let first = resolve();
+        for _ in 0..10 {
+            assert_eq!(first, resolve());
+        }
+        let position = |key: &str| first.find(&format!("\"{}\"", key)).unwrap();
+        assert!(position("didDocument") < position("didDocumentMetadata"));
+        assert!(position("didDocumentMetadata") < position("didResolutionMetadata"));
+        assert!(position("id") < position("service"));
+        assert!(position("service") < position("verificationMethod"));
+    }
+
+    fn ledger_with_services() -> MockLedger {
+        let diddoc_content = json!({
+            "service": [{
+                "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#didcomm-1",
+                "type": "DIDCommMessaging",
+                "serviceEndpoint": {"uri": "https://agent.com/didcomm"}
+            }, {
+                "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#didcomm-2",
+                "type": ["DIDCommMessaging", "LinkedDomains"],
+                "serviceEndpoint": "https://agent.com"
+            }, {
+                "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#profile",
+                "type": "profile",
+                "serviceEndpoint": "https://agent.com/profile"
+            }]
+        });
+        MockLedger::new().answer(
+            constants::GET_NYM,
+            nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(diddoc_content),
+            ),
+        )
+    }
+
+    #[test]
+    fn resolve_services_filtered_by_type() {
+        let resolver = Resolver::new(ledger_with_services());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        let services = resolver
+            .resolve_services(did, Some("DIDCommMessaging"))
+            .unwrap();
+        let ids: Vec<&str> = services
+            .iter()
+            .map(|service| service["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#didcomm-1",
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#didcomm-2"
+            ],
+            ids
+        );
+        assert!(resolver
+            .resolve_services(did, Some("did-communication"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn resolve_services_unfiltered() {
+        let resolver = Resolver::new(ledger_with_services());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        assert_eq!(3, resolver.resolve_services(did, None).unwrap().len());
+        // A DID document without services
+        let resolver = Resolver::new(nym_answering_ledger());
+        assert!(resolver.resolve_services(did, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sort_keys_orders_nested_objects() {
+        let value = json!({"b": [{"d": 1, "c": 2}], "a": {"f": null, "e": true}});
+        assert_eq!(
+            r#"{"a":{"e":true,"f":null},"b":[{"c":2,"d":1}]}"#,
+            serde_json::to_string(&sort_keys(value)).unwrap()
+        );
+    }
+
+    #[test]
+    fn resolution_result_with_typed_document() {
+        use crate::did_document::VerificationRelationship;
+
+        let resolver = Resolver::new(nym_answering_ledger());
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        let result: ResolutionResult =
+            serde_json::from_str(&resolver.resolve_string(did).unwrap()).unwrap();
+
+        let document = result.did_document().unwrap().unwrap();
+        assert_eq!(did, document.id);
+        assert_eq!(
+            vec![VerificationRelationship::Reference(format!(
+                "{}#verkey",
+                did
+            ))],
+            document.authentication
+        );
+    }
+
+    fn nym_reply_with_state_proof() -> String {
+        let mut reply: Value = serde_json::from_str(&nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            None,
+        ))
+        .unwrap();
+        reply["result"]["state_proof"] = serde_json::json!({
+            "root_hash": "7Wdj3rrMCZ1R1M78H4xK5jxikmdUUGW2kbfJQ1HoEpK",
+            "proof_nodes": "+QHo+FGAgICg0he/hjc9t/tPFzmCrb2T+nHnN0cRwqPKqZEc3pw2iCaAoAsA80p3oFwfl4dDaKkNI8z8weRsSaS9Y8n3HoardRzxgICAgICAgICAgID4naAgwxDOAEoIq+wUHr5h9jjSAIPDjS7SEG1NvWJbToxVQbh6+Hi4dnsiaWRlbnRpZmllciI6IlY0U0dSVTg2WjU4ZDZUVjdQQlVlNmYiLCJyb2xlIjoiMCIsInNlcU5vIjoxMCwidHhuVGltZSI6bnVsbCwidmVya2V5Ijoifjd0cVJpNFNhNG1xTk1WejlRbnBrb2UifQ==",
+            "multi_signature": {
+                "signature": "RNsPhUuPwwtA7NEf4VySCg1Fb2NpwapXrY8d64TEsRHR9rB3JHKhKvk9wzsGxyJP6jcZHHsqZkcK5uNFj5xzWV7aCi5r2rG6x3ev3kVk5cFpyAUWtUtEDpJBFcN9EBUz9wUVFDbnT7f5mAxMASxRWPQVHJWqrGvRJyNiypGbVS6sKM",
+                "participants": ["Node1", "Node2", "Node3"],
+                "value": {
+                    "ledger_id": 1,
+                    "pool_state_root_hash": "7siDH8Qanh82UviK4zjBSfLXcoCvLaeGkrByi1ow9Tsm",
+                    "state_root_hash": "7Wdj3rrMCZ1R1M78H4xK5jxikmdUUGW2kbfJQ1HoEpK",
+                    "timestamp": 1636641222,
+                    "txn_root_hash": "Hvs4E8nBa6YPHrmPSMCEJrQEWLSTbTgoNtVRTJUNTHfQ"
+                }
+            }
+        });
+        reply.to_string()
+    }
+
+    #[test]
+    fn signed_txn_carries_multi_signature() {
+        let config = ResolverConfig {
+            include_signed_txn: true,
+            ..ResolverConfig::default()
+        };
+        let ledger = MockLedger::new().reply(nym_reply_with_state_proof());
+        let resolver = Resolver::with_config(ledger, config);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        let signed_txn = &result["didDocumentMetadata"]["methodMetadata"]["signedTxn"];
+
+        assert_eq!(12, signed_txn["seqNo"]);
+        assert_eq!(1636641222, signed_txn["txnTime"]);
+        assert!(signed_txn["data"]
+            .as_str()
+            .unwrap()
+            .contains("Dk1fRRTtNazyMuK2cr64wp"));
+        let multi_signature = &signed_txn["state_proof"]["multi_signature"];
+        assert!(multi_signature["signature"]
+            .as_str()
+            .unwrap()
+            .starts_with("RNsPhUuPwwtA7NEf4VySCg1Fb2Np"));
+        assert_eq!(
+            serde_json::json!(["Node1", "Node2", "Node3"]),
+            multi_signature["participants"]
+        );
+        assert_eq!(
+            "7Wdj3rrMCZ1R1M78H4xK5jxikmdUUGW2kbfJQ1HoEpK",
+            multi_signature["value"]["state_root_hash"]
+        );
+    }
+
+    #[test]
+    fn fully_verified_resolution_reported() {
+        let verkey = [5; 32];
+        let did = bs58::encode(&verkey[..16]).into_string();
+        let mut reply: Value = serde_json::from_str(&nym_reply_with_state_proof()).unwrap();
+        reply["result"]["data"] = Value::from(
+            serde_json::json!({
+                "dest": did,
+                "identifier": did,
+                "role": null,
+                "seqNo": 12,
+                "txnTime": 1636641222,
+                "verkey": bs58::encode(verkey).into_string(),
+                "diddocContent": serde_json::json!({}).to_string(),
+            })
+            .to_string(),
+        );
+        let config = ResolverConfig {
+            trust_policies: vec![(
+                String::from("idunion"),
+                TrustPolicy {
+                    required_nodes: vec![String::from("Node1"), String::from("Node2")],
+                    min_count: 2,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(MockLedger::new().reply(reply.to_string()), config);
+
+        let (result, report) = resolver
+            .resolve_with_report(&format!("did:indy:idunion:{}", did), &ResolveOptions::new())
+            .unwrap();
+
+        let result: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            format!("did:indy:idunion:{}", did),
+            result["didDocument"]["id"]
+        );
+        assert!(report.state_proof);
+        assert_eq!(vec!["Node1", "Node2", "Node3"], report.signers);
+        assert!(report.trust_policy.unwrap().satisfied);
+        assert!(report.verkey_self_certifying);
+        assert_eq!(Some(did), report.diddoc_content_signer);
+        assert_eq!(Some(true), report.diddoc_content_self_signed);
+    }
+
+    #[test]
+    fn unverified_resolution_reported() {
+        let resolver = Resolver::new(nym_answering_ledger());
+
+        let (_, report) = resolver
+            .resolve_with_report(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                &ResolveOptions::new(),
+            )
+            .unwrap();
+
+        assert!(!report.state_proof);
+        assert!(report.signers.is_empty());
+        assert_eq!(None, report.trust_policy);
+        // Abbreviated verkey
+        assert!(report.verkey_self_certifying);
+        assert_eq!(
+            Some(String::from("V4SGRU86Z58d6TV7PBUe6f")),
+            report.diddoc_content_signer
+        );
+        assert_eq!(Some(false), report.diddoc_content_self_signed);
+    }
+
+    #[rstest]
+    // Legacy DID, the first 16 bytes of the abbreviated verkey
+    #[case(
+        "Dk1fRRTtNazyMuK2cr64wp",
+        "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+        Some("did:indy:idunion:PvGvSvaH7WrF7tfM5qZtuT")
+    )]
+    #[case(
+        "Na6EczbX5yvuS59hRb3JJ",
+        "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8",
+        Some("did:indy:idunion:DR65sgCxdLQPg5DhhpqM5R")
+    )]
+    // Already self-certifying
+    #[case(
+        "DR65sgCxdLQPg5DhhpqM5R",
+        "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8",
+        None
+    )]
+    // Not derived from the verkey
+    #[case(
+        "Dk1fRRTtNazyMuK2cr64wp",
+        "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8",
+        None
+    )]
+    fn canonical_id_in_metadata(
+        #[case] id: &str,
+        #[case] verkey: &str,
+        #[case] canonical_id: Option<&str>,
+    ) {
+        let config = ResolverConfig {
+            canonical_id: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(
+            MockLedger::new().answer(
+                constants::GET_NYM,
+                nym_reply(id, verkey, Some(serde_json::json!({}))),
+            ),
+            config,
+        );
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string(&format!("did:indy:idunion:{}", id))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            canonical_id,
+            result["didDocumentMetadata"]["canonicalId"].as_str()
+        );
+
+        let result: Value = serde_json::from_str(
+            &Resolver::new(nym_answering_ledger())
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(result["didDocumentMetadata"].get("canonicalId").is_none());
+    }
+
+    // Resolver for idunion requiring signatures of the nodes, on a pool of four
+    // nodes whose reply is signed by Node1 to Node3
+    fn resolver_with_trust_policy(
+        required_nodes: &[&str],
+        min_count: usize,
+    ) -> Resolver<MockLedger> {
+        let policy = TrustPolicy {
+            required_nodes: required_nodes.iter().map(|node| node.to_string()).collect(),
+            min_count,
+        };
+        let config = ResolverConfig {
+            trust_policies: vec![(String::from("idunion"), policy)]
+                .into_iter()
+                .collect(),
+            ..ResolverConfig::default()
+        };
+        let ledger = MockLedger::new()
+            .reply(nym_reply_with_state_proof())
+            .with_nodes(&[
+                ("Node1", Some(0.1)),
+                ("Node2", Some(0.1)),
+                ("Node3", Some(0.1)),
+                ("Node4", None),
+            ]);
+        Resolver::with_config(ledger, config)
+    }
+
+    #[test]
+    fn trust_policy_satisfied() {
+        let resolver = resolver_with_trust_policy(&["Node2", "Node4"], 1);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "satisfied": true,
+                "minCount": 1,
+                "signedBy": ["Node2"],
+                "missing": ["Node4"]
+            }),
+            result["didDocumentMetadata"]["methodMetadata"]["trustPolicy"]
+        );
+    }
+
+    #[test]
+    fn trust_policy_not_satisfied() {
+        let resolver = resolver_with_trust_policy(&["Node2", "Node4"], 2);
+        let err = resolver
+            .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap_err();
+
+        assert!(matches!(
+            &err,
+            DidIndyError::PolicyNotSatisfied { missing } if missing == &vec![String::from("Node4")]
+        ));
+        assert_eq!(
+            "Trust policy not satisfied, missing signatures of Node4",
+            err.to_string()
+        );
+        // Rejected before the legacy endpoint is fetched
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn trust_policy_with_unknown_node() {
+        let resolver = resolver_with_trust_policy(&["Node1", "Steward9"], 1);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        let evaluation = &result["didDocumentMetadata"]["methodMetadata"]["trustPolicy"];
+        assert_eq!(serde_json::json!(["Steward9"]), evaluation["unknown"]);
+        assert_eq!(serde_json::json!(["Steward9"]), evaluation["missing"]);
+
+        let resolver = resolver_with_trust_policy(&["Node1", "Steward9"], 2);
+        assert!(matches!(
+            resolver.resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::PolicyNotSatisfied { .. })
+        ));
+        // Policies of other namespaces do not apply
+        let resolver = resolver_with_trust_policy(&["Steward9"], 1);
+        assert!(resolver
+            .resolve_string("did:indy:sovrin:Dk1fRRTtNazyMuK2cr64wp")
+            .is_ok());
+    }
+
+    #[test]
+    fn trust_policy_applied_to_raw_replies() {
+        let resolver = resolver_with_trust_policy(&["Node2", "Node4"], 2);
+        let options = ResolveOptions::new().raw_reply(true);
+        assert!(matches!(
+            resolver
+                .resolve_string_with_options("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", &options),
+            Err(DidIndyError::PolicyNotSatisfied { .. })
+        ));
+
+        let resolver = resolver_with_trust_policy(&["Node2", "Node4"], 1);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string_with_options(
+                    "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                    &options,
+                )
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::json!(["Node2"]),
+            result["contentMetadata"]["methodMetadata"]["trustPolicy"]["signedBy"]
+        );
+    }
+
+    #[test]
+    fn trust_policy_of_namespace_applied_to_alias() {
+        let mut registry = ResolverRegistry::new();
+        registry.register(
+            "idunion",
+            resolver_with_trust_policy(&["Node2", "Node4"], 2),
+        );
+        registry.register_alias("idu", "idunion").unwrap();
+
+        assert!(matches!(
+            registry
+                .get("idu")
+                .unwrap()
+                .resolve_string("did:indy:idu:Dk1fRRTtNazyMuK2cr64wp"),
+            Err(DidIndyError::PolicyNotSatisfied { .. })
+        ));
+    }
+
+    #[test]
+    fn signed_txn_omitted_by_default_or_without_state_proof() {
+        let ledger = MockLedger::new().reply(nym_reply_with_state_proof());
+        let result: Value = serde_json::from_str(
+            &Resolver::new(ledger)
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(result["didDocumentMetadata"]["methodMetadata"]
+            .get("signedTxn")
+            .is_none());
+
+        let config = ResolverConfig {
+            include_signed_txn: true,
+            ..ResolverConfig::default()
+        };
+        let ledger = MockLedger::new().reply(nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            None,
+        ));
+        let result: Value = serde_json::from_str(
+            &Resolver::with_config(ledger, config)
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(result["didDocumentMetadata"]["methodMetadata"]
+            .get("signedTxn")
+            .is_none());
+    }
+
+    #[derive(Default)]
+    struct CollectingSink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for CollectingSink {
+        fn record(&self, record: AuditRecord) {
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    #[test]
+    fn resolutions_are_audited() {
+        let sink = Arc::new(CollectingSink::default());
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            audit: Some(AuditLog::new(sink.clone())),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+        resolver.resolve_string(did).unwrap();
+        resolver.resolve_string(did).unwrap();
+        assert!(resolver
+            .dereference_string(
+                "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/PANTS/npdb/4.3.4"
+            )
+            .is_err());
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(3, records.len());
+        assert_eq!("resolve", records[0].operation);
+        assert_eq!(did, records[0].did_url);
+        assert_eq!("idunion", records[0].namespace);
+        assert_eq!(Some(String::from("NYM")), records[0].object_type);
+        assert_eq!(Some(12), records[0].seq_no);
+        assert_eq!("ok", records[0].result);
+        assert_eq!(CacheStatus::Miss, records[0].cache);
+        assert!(records[0].duration_ms >= 200);
+        assert_eq!(CacheStatus::Hit, records[1].cache);
+        assert_eq!(Some(12), records[1].seq_no);
+        assert_eq!("dereference", records[2].operation);
+        assert_eq!("invalidDidUrl", records[2].result);
+        assert_eq!(None, records[2].object_type);
+    }
+
+    #[test]
+    fn audit_hashes_identifiers() {
+        let sink = Arc::new(CollectingSink::default());
+        let audit = AuditLog {
+            sink: sink.clone(),
+            hash_identifiers: true,
+        };
+        let config = ResolverConfig {
+            audit: Some(audit.clone()),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+        let did = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+        resolver.resolve_string(did).unwrap();
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(audit.identifier(did), records[0].did_url);
+        assert_ne!(did, records[0].did_url);
+        assert_eq!(CacheStatus::Disabled, records[0].cache);
+    }
+
+    #[test]
+    fn resolve_batch_resolves_concurrently() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let dids = [
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            "did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f",
+            "did:indy:idunion:BDrEcHc8Tb4Lb2VyQZWEDE",
+        ];
+
+        let started = std::time::Instant::now();
+        let results = resolver.resolve_batch(&dids);
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(3, results.len());
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(3, resolver.pool.max_in_flight());
+    }
+
+    #[test]
+    fn skip_legacy_endpoint_sends_one_request_per_did() {
+        let ledger = || {
+            MockLedger::new()
+                .answer(
+                    constants::GET_NYM,
+                    nym_reply("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK", None),
+                )
+                .answer(constants::GET_ATTR, endpoint_reply("https://agent.com"))
+        };
+        let dids = [
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            "did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f",
+            "did:indy:idunion:BDrEcHc8Tb4Lb2VyQZWEDE",
+        ];
+
+        let resolver = Resolver::new(ledger());
+        let results = resolver
+            .resolve_batch_with_options(&dids, &ResolveOptions::new().skip_legacy_endpoint(true));
+
+        assert_eq!(3, resolver.pool.requests().len());
+        for result in results {
+            let result: Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert!(result["didDocument"]["service"].is_null());
+            assert_eq!(
+                true,
+                result["didDocumentMetadata"]["methodMetadata"]["legacyEndpointSkipped"]
+            );
+        }
+
+        let config = ResolverConfig {
+            skip_legacy_endpoint: true,
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger(), config);
+        assert_eq!(3, resolver.warm_cache(&dids).resolved.len());
+        assert_eq!(3, resolver.pool.requests().len());
+
+        // The option overrides the config
+        resolver
+            .resolve_string_with_options(
+                dids[0],
+                &ResolveOptions::new().skip_legacy_endpoint(false),
+            )
+            .unwrap();
+        assert_eq!(5, resolver.pool.requests().len());
+    }
+
+    #[test]
+    fn warm_cache_populates_cache_and_reports_failures() {
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+
+        let summary = resolver.warm_cache(&[
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            "did:indy:idunion:invalid",
+            "did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f",
+        ]);
+        assert_eq!(
+            vec![
+                String::from("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"),
+                String::from("did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f")
+            ],
+            summary.resolved
+        );
+        assert_eq!(1, summary.failed.len());
+        assert_eq!("did:indy:idunion:invalid", summary.failed[0].0);
+        assert!(matches!(summary.failed[0].1, DidIndyError::InvalidDidUrl));
+
+        assert!(resolver
+            .resolve_string("did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f")
+            .is_ok());
+        assert_eq!(2, resolver.pool.requests().len());
+    }
+
+    /// Ledger holding the entries of a revocation registry at the given times.
+    /// Answers GET_REVOC_REG with the latest entry at the requested time.
+    struct RevRegLedger {
+        entries: Vec<i64>,
+        requests: Mutex<usize>,
+    }
+
+    impl RevRegLedger {
+        fn new(entries: Vec<i64>) -> Self {
+            RevRegLedger {
+                entries,
+                requests: Mutex::new(0),
+            }
+        }
+    }
+
+    impl LedgerRequester for RevRegLedger {
+        fn request_builder(&self) -> RequestBuilder {
+            RequestBuilder::new(ProtocolVersion::Node1_4)
+        }
+
+        fn submit<'a>(
+            &'a self,
+            request: &'a PreparedRequest,
+        ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+            *self.requests.lock().unwrap() += 1;
+            let timestamp = request.req_json["operation"]["timestamp"].as_i64().unwrap();
+            let reply = match self.entries.iter().filter(|t| **t <= timestamp).max() {
+                Some(txn_time) => ledger_reply(
+                    constants::GET_REVOC_REG,
+                    serde_json::json!({"value": {"accum": format!("accum at {}", txn_time)}}),
+                    *txn_time as u64 / 100,
+                    *txn_time as u64,
+                ),
+                None => serde_json::json!({
+                    "op": "REPLY",
+                    "result": {"type": constants::GET_REVOC_REG, "seqNo": null, "txnTime": null, "data": null}
+                })
+                .to_string(),
+            };
+            Box::pin(async move { Ok(reply) })
+        }
+    }
+
+    static REV_REG_ENTRY_URL: &str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=1970-01-01T00:01:40Z";
+
+    fn with_fallback(
+        ledger: RevRegLedger,
+        fallback: RevRegEntryFallback,
+    ) -> Resolver<RevRegLedger> {
+        let config = ResolverConfig {
+            rev_reg_entry_fallback: fallback,
+            ..ResolverConfig::default()
+        };
+        Resolver::with_config(ledger, config)
+    }
+
+    #[test]
+    fn dereferencing_echoes_did_url_and_fragment() {
+        let resolver = Resolver::new(RevRegLedger::new(vec![1000, 5000]));
+        let json = resolver
+            .dereference_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=1970-01-01T01:00:00Z#accum")
+            .unwrap();
+
+        let result: DereferencingResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            Some("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=1970-01-01T01%3A00%3A00Z#accum"),
+            result.did_url()
+        );
+        assert_eq!(Some("accum"), result.fragment());
+        // The fragment does not change the content stream
+        let result: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!("accum at 1000", result["contentStream"]["value"]["accum"]);
+    }
+
+    #[test]
+    fn rev_reg_entry_before_first_entry_is_empty_by_default() {
+        let resolver = Resolver::new(RevRegLedger::new(vec![1000, 5000]));
+        assert!(matches!(
+            resolver.dereference_string(REV_REG_ENTRY_URL),
+            Err(DidIndyError::EmptyData)
+        ));
+        assert_eq!(1, *resolver.pool.requests.lock().unwrap());
+    }
+
+    #[test]
+    fn rev_reg_entry_before_first_entry_reports_first_entry() {
+        let resolver = with_fallback(
+            RevRegLedger::new(vec![1000, 5000]),
+            RevRegEntryFallback::NotFoundBefore,
+        );
+        assert!(matches!(
+            resolver.dereference_string(REV_REG_ENTRY_URL),
+            Err(DidIndyError::NotFoundBefore { first_entry: 1000 })
+        ));
+    }
+
+    #[test]
+    fn rev_reg_entry_before_first_entry_returns_earliest_entry() {
+        let resolver = with_fallback(
+            RevRegLedger::new(vec![1000, 5000]),
+            RevRegEntryFallback::EarliestEntry,
+        );
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(REV_REG_ENTRY_URL).unwrap()).unwrap();
+
+        assert_eq!("accum at 1000", result["contentStream"]["value"]["accum"]);
+        assert_eq!(
+            true,
+            result["contentMetadata"]["methodMetadata"]["earliestEntry"]
+        );
+    }
+
+    #[test]
+    fn rev_reg_entry_without_any_entry_stays_empty() {
+        let resolver = with_fallback(
+            RevRegLedger::new(vec![]),
+            RevRegEntryFallback::EarliestEntry,
+        );
+        assert!(matches!(
+            resolver.dereference_string(REV_REG_ENTRY_URL),
+            Err(DidIndyError::EmptyData)
+        ));
+        assert_eq!(2, *resolver.pool.requests.lock().unwrap());
+    }
+
+    #[test]
+    fn rev_reg_entry_found_is_not_flagged() {
+        let resolver = with_fallback(
+            RevRegLedger::new(vec![50]),
+            RevRegEntryFallback::EarliestEntry,
+        );
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(REV_REG_ENTRY_URL).unwrap()).unwrap();
+
+        assert_eq!("accum at 50", result["contentStream"]["value"]["accum"]);
+        assert!(result["contentMetadata"]["methodMetadata"]
+            .get("earliestEntry")
+            .is_none());
+        assert_eq!(1, *resolver.pool.requests.lock().unwrap());
+    }
+
+    static REV_REG_ENTRIES_URL: &str =
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54";
+
+    #[test]
+    fn rev_reg_entries_at_times() {
+        let resolver = Resolver::new(RevRegLedger::new(vec![1000, 5000]));
+
+        let entries = resolver
+            .get_rev_reg_entries(REV_REG_ENTRIES_URL, &[1200, 500, 6000, 1200])
+            .unwrap();
+
+        assert_eq!(
+            vec![500, 1200, 6000],
+            entries.keys().copied().collect::<Vec<i64>>()
+        );
+        assert!(matches!(entries[&500], Err(DidIndyError::EmptyData)));
+        let entry = entries[&1200].as_ref().unwrap();
+        assert_eq!(1000, entry.txn_time);
+        assert_eq!(Some(10), entry.seq_no);
+        assert_eq!("accum at 1000", entry.value["accum"]);
+        assert_eq!(
+            "accum at 5000",
+            entries[&6000].as_ref().unwrap().value["accum"]
+        );
+        // Duplicate times are requested once
+        assert_eq!(3, *resolver.pool.requests.lock().unwrap());
+    }
+
+    #[test]
+    fn rev_reg_entries_fail_per_time() {
+        let ledger = MockLedger::new().fail(DidIndyError::RequestTimeout).answer(
+            constants::GET_REVOC_REG,
+            ledger_reply(
+                constants::GET_REVOC_REG,
+                serde_json::json!({"value": {"accum": "accum"}}),
+                7,
+                100,
+            ),
+        );
+        let resolver = Resolver::new(ledger);
 
-        assert!(matches! {
-            request, Err(DidIndyError::DateTimeError(_))
-        })
+        let entries = resolver
+            .get_rev_reg_entries(REV_REG_ENTRIES_URL, &[100, 200])
+            .unwrap();
+
+        assert_eq!(2, entries.len());
+        let (failed, found): (Vec<_>, Vec<_>) = entries.values().partition(|entry| entry.is_err());
+        assert!(matches!(failed[..], [Err(DidIndyError::RequestTimeout)]));
+        assert_eq!(100, found[0].as_ref().unwrap().txn_time);
     }
 
-    #[rstest]
-    fn build_get_revoc_reg_delta_request_with_from_to(request_builder: RequestBuilder) {
-        let from_as_str = "2019-12-20T19:17:47Z";
-        let to_as_str = "2020-12-20T19:17:47Z";
-        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from={}&to={}",from_as_str, to_as_str);
-        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
-        let request = build_request(&did_url, &request_builder).unwrap();
-        assert_eq!(request.txn_type, constants::GET_REVOC_REG_DELTA);
+    #[test]
+    fn rev_reg_entries_bound_concurrency() {
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(20))
+            .answer(
+                constants::GET_REVOC_REG,
+                ledger_reply(
+                    constants::GET_REVOC_REG,
+                    serde_json::json!({"value": {"accum": "accum"}}),
+                    7,
+                    100,
+                ),
+            );
+        let resolver = Resolver::new(ledger);
+        let timestamps: Vec<i64> = (100..120).collect();
+
+        let entries = resolver
+            .get_rev_reg_entries(REV_REG_ENTRIES_URL, &timestamps)
+            .unwrap();
+
+        assert_eq!(20, entries.len());
+        assert!(entries.values().all(|entry| entry.is_ok()));
+        assert_eq!(MAX_CONCURRENT_ENTRY_REQUESTS, resolver.pool.max_in_flight());
     }
 
-    #[rstest]
-    fn build_get_revoc_reg_delta_request_with_from_only(request_builder: RequestBuilder) {
-        let now = chrono::Utc::now().timestamp();
-        let from_as_str = "2019-12-20T19:17:47Z";
-        let did_url_as_str = format!("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54?from={}",from_as_str);
-        let did_url = DidUrl::from_str(&did_url_as_str).unwrap();
-        let request = build_request(&did_url, &request_builder).unwrap();
+    #[test]
+    fn rev_reg_entries_reject_invalid_input() {
+        let resolver = Resolver::new(RevRegLedger::new(vec![1000]));
 
-        let to = (*(request.req_json).get("operation").unwrap())
-            .get("to")
-            .unwrap()
-            .as_u64()
-            .unwrap() as i64;
-        assert_eq!(request.txn_type, constants::GET_REVOC_REG_DELTA);
-        assert!(to >= now)
+        assert!(matches!(
+            resolver.get_rev_reg_entries(REV_REG_ENTRIES_URL, &[1000, -1]),
+            Err(DidIndyError::InvalidTimestamp(-1))
+        ));
+        assert!(matches!(
+            resolver.get_rev_reg_entries(REV_REG_ENTRY_URL, &[1000]),
+            Err(DidIndyError::InvalidDidUrl)
+        ));
+        assert!(matches!(
+            resolver.get_rev_reg_entries(SCHEMA_URL, &[1000]),
+            Err(DidIndyError::InvalidDidUrl)
+        ));
+        assert_eq!(0, *resolver.pool.requests.lock().unwrap());
+    }
+
+    static REV_REG_DEF_URL: &str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DEF/104/revocable/a4e25e54-e028-462b-a4d6-b1d1712d51a1";
+
+    fn rev_reg_def_reply() -> String {
+        ledger_reply(
+            "115",
+            serde_json::json!({
+                "id": "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:a4e25e54-e028-462b-a4d6-b1d1712d51a1",
+                "credDefId": "Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable",
+                "revocDefType": "CL_ACCUM",
+                "tag": "a4e25e54-e028-462b-a4d6-b1d1712d51a1",
+                "value": {"issuanceType": "ISSUANCE_BY_DEFAULT", "maxCredNum": 100}
+            }),
+            120,
+            1636641300,
+        )
+    }
+
+    fn cred_def_reply() -> String {
+        ledger_reply(
+            "108",
+            serde_json::json!({"primary": {"n": "1"}, "revocation": {"g": "1"}}),
+            110,
+            1636641250,
+        )
+    }
+
+    #[test]
+    fn dereference_rev_reg_def_with_cred_def() {
+        // Both requests are in flight at the same time, one after the other would
+        // exceed the total timeout
+        let ledger = MockLedger::new()
+            .with_delay(Duration::from_millis(200))
+            .reply(rev_reg_def_reply())
+            .reply(cred_def_reply());
+        let config = ResolverConfig {
+            attach_cred_def: true,
+            timeouts: Timeouts {
+                request: None,
+                total: Some(Duration::from_millis(350)),
+            },
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(REV_REG_DEF_URL).unwrap()).unwrap();
+        assert_eq!(
+            "Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable",
+            result["contentStream"]["credDefId"]
+        );
+        assert_eq!(
+            serde_json::json!({"primary": {"n": "1"}, "revocation": {"g": "1"}}),
+            result["contentMetadata"]["methodMetadata"]["credDef"]
+        );
+
+        let requests = resolver.pool.requests();
+        assert_eq!(
+            constants::GET_REVOC_REG_DEF,
+            requests[0]["operation"]["type"]
+        );
+        assert_eq!(constants::GET_CRED_DEF, requests[1]["operation"]["type"]);
+    }
+
+    #[test]
+    fn dereference_rev_reg_def_fails_without_cred_def() {
+        let ledger = MockLedger::new()
+            .reply(rev_reg_def_reply())
+            .fail(DidIndyError::NotFound);
+        let config = ResolverConfig {
+            attach_cred_def: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(ledger, config);
+
+        assert!(matches!(
+            resolver.dereference_string(REV_REG_DEF_URL),
+            Err(DidIndyError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn dereference_rev_reg_def_without_cred_def_by_default() {
+        let resolver = Resolver::new(MockLedger::new().reply(rev_reg_def_reply()));
+
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(REV_REG_DEF_URL).unwrap()).unwrap();
+        assert!(result["contentMetadata"]["methodMetadata"]
+            .get("credDef")
+            .is_none());
+        assert_eq!(1, resolver.pool.requests().len());
+    }
+
+    static SCHEMA_URL: &str =
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/SCHEMA/npdb/4.3.4";
+
+    fn schema_reply() -> String {
+        ledger_reply(
+            constants::GET_SCHEMA,
+            serde_json::json!({
+                "attr_names": ["name", "birthdate"],
+                "name": "npdb",
+                "version": "4.3.4"
+            }),
+            74,
+            1636641222,
+        )
+    }
+
+    #[test]
+    fn resolve_any_legacy_schema_id() {
+        let resolver =
+            Resolver::new(MockLedger::new().reply(schema_reply())).with_namespace("idunion");
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_any("Dk1fRRTtNazyMuK2cr64wp:2:npdb:4.3.4", None)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("npdb", result["contentStream"]["name"]);
+        assert_eq!(
+            serde_json::json!({ "inputType": "schemaId", "didUrl": SCHEMA_URL }),
+            result["dereferencingMetadata"]["interpretation"]
+        );
+    }
+
+    #[test]
+    fn resolve_any_did_sov() {
+        let resolver = Resolver::new(MockLedger::new().reply(nym_reply(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+            Some(serde_json::json!({})),
+        )));
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_any("did:sov:Dk1fRRTtNazyMuK2cr64wp", Some("idunion"))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            result["didDocument"]["id"]
+        );
+        assert_eq!(
+            "didSov",
+            result["didResolutionMetadata"]["interpretation"]["inputType"]
+        );
+    }
+
+    #[test]
+    fn resolve_any_ambiguous_input_not_requested() {
+        let resolver = Resolver::new(MockLedger::new());
+
+        let err = resolver
+            .resolve_any("Dk1fRRTtNazyMuK2cr64wp:2:npdb:4.3:4.3", Some("idunion"))
+            .unwrap_err();
+        assert!(matches!(err, DidIndyError::AmbiguousIdentifier { .. }));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    #[test]
+    fn dereference_raw_reply() {
+        let reply = schema_reply();
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(
+            MockLedger::new().reply(reply.clone()).reply(reply.clone()),
+            config,
+        );
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string_with_options(SCHEMA_URL, &ResolveOptions::new().raw_reply(true))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&reply).unwrap(),
+            result["contentStream"]
+        );
+        let metadata = &result["contentMetadata"];
+        assert_eq!("application/json", metadata["contentType"]);
+        assert_eq!("RAW", metadata["methodMetadata"]["objectType"]);
+        assert_eq!(true, metadata["methodMetadata"]["rawReply"]);
+
+        // The raw reply is not served for a normal dereferencing
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(SCHEMA_URL).unwrap()).unwrap();
+        assert_eq!(
+            "SCHEMA",
+            result["contentMetadata"]["methodMetadata"]["objectType"]
+        );
+        assert!(result["contentMetadata"]["methodMetadata"]
+            .get("rawReply")
+            .is_none());
+    }
+
+    #[test]
+    fn schema_content_metadata() {
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(SCHEMA_URL).unwrap()).unwrap();
+        let metadata = &result["contentMetadata"];
+
+        assert_eq!("application/json", metadata["contentType"]);
+        assert!(DateTime::parse_from_rfc3339(metadata["retrieved"].as_str().unwrap()).is_ok());
+        assert_eq!(74, metadata["seqNo"]);
+        assert_eq!("2021-11-11T14:33:42Z", metadata["txnTime"]);
+        assert_eq!("SCHEMA", metadata["methodMetadata"]["objectType"]);
+        assert_eq!(
+            74,
+            metadata["methodMetadata"]["nodeResponse"]["result"]["seqNo"]
+        );
+        assert!(metadata.get("nodeResponse").is_none());
+    }
+
+    #[test]
+    fn schema_dereferenced_as_json_ld() {
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string_with_options(
+                    SCHEMA_URL,
+                    &ResolveOptions::new().representation(Representation::JsonLd),
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "@context": [DEFAULT_CONTENT_CONTEXT],
+                "id": SCHEMA_URL,
+                "type": "AnonCredsSchema",
+                "content": {
+                    "attr_names": ["name", "birthdate"],
+                    "name": "npdb",
+                    "version": "4.3.4"
+                }
+            }),
+            result["contentStream"]
+        );
+        assert_eq!(
+            "application/ld+json",
+            result["contentMetadata"]["contentType"]
+        );
+    }
+
+    #[test]
+    fn cred_def_dereferenced_as_json_ld_with_configured_context() {
+        let config = ResolverConfig {
+            content_context: Some(String::from("https://example.com/anoncreds/v1")),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(MockLedger::new().reply(cred_def_reply()), config);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string_with_options(
+                    &format!("{}#primary", CRED_DEF_URL),
+                    &ResolveOptions::new().representation(Representation::JsonLd),
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        let content = &result["contentStream"];
+        assert_eq!(
+            serde_json::json!(["https://example.com/anoncreds/v1"]),
+            content["@context"]
+        );
+        assert_eq!(CRED_DEF_URL, content["id"]);
+        assert_eq!("AnonCredsCredentialDefinition", content["type"]);
+        assert_eq!(
+            serde_json::json!({"primary": {"n": "1"}, "revocation": {"g": "1"}}),
+            content["content"]
+        );
+    }
+
+    #[test]
+    fn content_plain_json_by_default() {
+        let resolver = Resolver::new(MockLedger::new().reply(cred_def_reply()));
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(CRED_DEF_URL).unwrap()).unwrap();
+
+        assert_eq!(
+            serde_json::json!({"primary": {"n": "1"}, "revocation": {"g": "1"}}),
+            result["contentStream"]
+        );
+        assert_eq!("application/json", result["contentMetadata"]["contentType"]);
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct Schema {
+        name: String,
+        version: String,
+        attr_names: Vec<String>,
+    }
+
+    #[test]
+    fn resolution_result_typed() {
+        let resolver = Resolver::new(nym_answering_ledger());
+
+        let result = resolver
+            .resolve_typed("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+
+        assert_eq!(
+            "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+            result.did_document_value().unwrap()["id"]
+        );
+        assert_eq!(
+            Some("application/did+json"),
+            result.resolution_metadata().unwrap().content_type()
+        );
+        match result.did_document_metadata() {
+            Some(Metadata::Spec(metadata)) => {
+                assert_eq!(Some("12"), metadata.version_id());
+                assert_eq!(ObjectType::Nym, metadata.method_metadata().object_type());
+            }
+            metadata => panic!("Unexpected metadata {:?}", metadata),
+        }
+    }
+
+    #[test]
+    fn typed_resolution_with_options_serialized_by_caller() {
+        let resolver = Resolver::new(nym_answering_ledger());
+        let options = ResolveOptions::new().representation(Representation::JsonLd);
+
+        let result = resolver
+            .resolve_typed_with_options("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", &options)
+            .unwrap();
+
+        assert_eq!(
+            Some("application/did+ld+json"),
+            result.resolution_metadata().unwrap().content_type()
+        );
+        assert_eq!(
+            "https://www.w3.org/ns/did/v1",
+            result.did_document_value().unwrap()["@context"]
+        );
+        let compact = serde_json::to_string(&result).unwrap();
+        assert!(!compact.contains('\n'));
+    }
+
+    #[test]
+    fn dereferencing_result_typed() {
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+
+        let result = resolver.dereference_typed(SCHEMA_URL).unwrap();
+
+        assert_eq!("npdb", result.content_stream().unwrap()["name"]);
+        let metadata = result.content_metadata().unwrap();
+        assert_eq!(Some(74), metadata.method_metadata().seq_no());
+        assert_eq!(Some(1636641222), metadata.method_metadata().txn_time());
+        match metadata {
+            Metadata::Spec(metadata) => assert_eq!(Some(74), metadata.seq_no()),
+            metadata => panic!("Unexpected metadata {:?}", metadata),
+        }
+    }
+
+    #[test]
+    fn typed_results_cached_and_signed() {
+        let signing =
+            SigningConfig::new(&[7; 32], "did:indy:idunion:V4SGRU86Z58d6TV7PBUe6f#resolver")
+                .unwrap();
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            signing: Some(signing.clone()),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+
+        let result = resolver
+            .resolve_typed("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        let cached = resolver
+            .resolve_typed("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::to_value(&cached).unwrap()
+        );
+        assert_eq!(1, resolver.pool.requests().len());
+        assert!(result.resolution_metadata().unwrap().proof().is_some());
+        assert!(verify_result(
+            &serde_json::to_string(&result).unwrap(),
+            &signing.public_key()
+        )
+        .unwrap());
+
+        let resolver = Resolver::with_config(
+            MockLedger::new().reply(schema_reply()),
+            ResolverConfig {
+                signing: Some(signing.clone()),
+                ..ResolverConfig::default()
+            },
+        );
+        let result = resolver.dereference_typed(SCHEMA_URL).unwrap();
+        assert!(verify_result(
+            &serde_json::to_string(&result).unwrap(),
+            &signing.public_key()
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn schema_dereferenced_into_type_of_caller() {
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+
+        let (schema, metadata) = resolver.dereference_content::<Schema>(SCHEMA_URL).unwrap();
+
+        assert_eq!(
+            Schema {
+                name: String::from("npdb"),
+                version: String::from("4.3.4"),
+                attr_names: vec![String::from("name"), String::from("birthdate")],
+            },
+            schema
+        );
+        assert_eq!("SCHEMA", metadata.object_type());
+        assert_eq!(74, metadata.node_response()["result"]["seqNo"]);
+    }
+
+    #[test]
+    fn object_types_serialized_as_ledger_object_names() {
+        for (object_type, name) in [
+            (ObjectType::Nym, "NYM"),
+            (ObjectType::Schema, "SCHEMA"),
+            (ObjectType::CredDef, "CRED_DEF"),
+            (ObjectType::RevRegDef, "REVOC_REG_DEF"),
+            (ObjectType::RevRegDelta, "REVOC_REG_DELTA"),
+            (ObjectType::RevRegEntry, "REVOC_REG_ENTRY"),
+            (ObjectType::Raw, "RAW"),
+            (ObjectType::Unknown, "UNKNOWN"),
+        ] {
+            assert_eq!(json!(name), json!(object_type));
+            assert_eq!(name, object_type);
+            assert_eq!(
+                object_type,
+                serde_json::from_value::<ObjectType>(json!(name)).unwrap()
+            );
+        }
+        assert_eq!(
+            ObjectType::Unknown,
+            serde_json::from_value::<ObjectType>(json!("UNKOWN")).unwrap()
+        );
+    }
+
+    #[test]
+    fn content_dereferenced_with_ledger_position() {
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+
+        let (_, metadata) = resolver.dereference_content::<Schema>(SCHEMA_URL).unwrap();
+
+        assert_eq!(Some(74), metadata.seq_no());
+        assert_eq!(Some(1636641222), metadata.txn_time());
+    }
+
+    #[test]
+    fn cached_content_keeps_ledger_position() {
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(MockLedger::new().reply(schema_reply()), config);
+
+        resolver.dereference_content::<Schema>(SCHEMA_URL).unwrap();
+        let (_, metadata) = resolver.dereference_content::<Schema>(SCHEMA_URL).unwrap();
+
+        assert_eq!(1, resolver.pool.requests().len());
+        assert_eq!(Some(74), metadata.seq_no());
+        assert_eq!(Some(1636641222), metadata.txn_time());
+    }
+
+    #[test]
+    fn content_of_other_type_not_parsed() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct CredDef {
+            tag: String,
+        }
+        let resolver = Resolver::new(MockLedger::new().reply(schema_reply()));
+
+        match resolver.dereference_content::<CredDef>(SCHEMA_URL) {
+            Err(DidIndyError::ParseContent { message, content }) => {
+                assert!(message.contains("tag"), "{}", message);
+                assert_eq!("npdb", content["name"]);
+            }
+            other => panic!("Expected ParseContent, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rev_reg_delta_content_metadata() {
+        let reply = ledger_reply(
+            constants::GET_REVOC_REG_DELTA,
+            serde_json::json!({
+                "revocDefType": "CL_ACCUM",
+                "revocRegDefId": "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:a4e25e54",
+                "value": {"accum_to": {"value": {"accum": "21 11"}, "txnTime": 1636641230}}
+            }),
+            105,
+            1636641230,
+        );
+        let resolver = Resolver::new(MockLedger::new().reply(reply));
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54")
+                .unwrap(),
+        )
+        .unwrap();
+        let metadata = &result["contentMetadata"];
+
+        assert_eq!("application/json", metadata["contentType"]);
+        assert_eq!(105, metadata["seqNo"]);
+        assert_eq!("2021-11-11T14:33:50Z", metadata["txnTime"]);
+        assert_eq!("REVOC_REG_DELTA", metadata["methodMetadata"]["objectType"]);
     }
 
     #[rstest]
-    fn build_get_revoc_reg_delta_request_without_parameter(request_builder: RequestBuilder) {
-        let now = chrono::Utc::now().timestamp();
-        let did_url_as_str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54";
-        let did_url = DidUrl::from_str(did_url_as_str).unwrap();
-        let request = build_request(&did_url, &request_builder).unwrap();
+    #[case::schema(
+        constants::GET_SCHEMA,
+        serde_json::json!({"seqNo": 74, "txnTime": 1636641222, "data": {"name": "npdb"}}),
+        (Some(74), Some(1636641222))
+    )]
+    #[case::cred_def(
+        constants::GET_CRED_DEF,
+        serde_json::json!({"seqNo": 110, "txnTime": 1636641250, "data": {"primary": {}}}),
+        (Some(110), Some(1636641250))
+    )]
+    #[case::rev_reg_delta(
+        constants::GET_REVOC_REG_DELTA,
+        serde_json::json!({
+            "seqNo": null,
+            "txnTime": null,
+            "data": {"value": {"accum_to": {"seqNo": 105, "txnTime": 1636641230, "value": {}}}}
+        }),
+        (Some(105), Some(1636641230))
+    )]
+    #[case::txn(
+        constants::GET_TXN,
+        serde_json::json!({"data": {"txnMetadata": {"seqNo": 12, "txnTime": 1636641222}}}),
+        (Some(12), Some(1636641222))
+    )]
+    #[case::not_found(
+        constants::GET_SCHEMA,
+        serde_json::json!({"seqNo": null, "data": null}),
+        (None, None)
+    )]
+    fn ledger_position_of_reply_layouts(
+        #[case] txn_type: &str,
+        #[case] result: Value,
+        #[case] expected: (Option<u64>, Option<i64>),
+    ) {
+        assert_eq!(expected, ledger_position(txn_type, &result));
+    }
 
-        let to = (*(request.req_json).get("operation").unwrap())
-            .get("to")
+    #[test]
+    fn cred_def_content_metadata() {
+        let resolver = Resolver::new(MockLedger::new().reply(cred_def_reply()));
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(CRED_DEF_URL).unwrap()).unwrap();
+        let metadata = &result["contentMetadata"];
+
+        assert_eq!(110, metadata["seqNo"]);
+        assert_eq!("2021-11-11T14:34:10Z", metadata["txnTime"]);
+    }
+
+    #[test]
+    fn rev_reg_delta_position_from_accum_to() {
+        let reply = serde_json::json!({
+            "op": "REPLY",
+            "result": {
+                "type": constants::GET_REVOC_REG_DELTA,
+                "reqId": 1,
+                "seqNo": null,
+                "txnTime": null,
+                "data": {
+                    "revocDefType": "CL_ACCUM",
+                    "revocRegDefId": "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:a4e25e54",
+                    "value": {"accum_to": {"seqNo": 105, "txnTime": 1636641230, "value": {"accum": "21 11"}}}
+                },
+                "state_proof": {}
+            }
+        });
+        let resolver = Resolver::new(MockLedger::new().reply(reply.to_string()));
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_DELTA/104/revocable/a4e25e54")
+                .unwrap(),
+        )
+        .unwrap();
+        let metadata = &result["contentMetadata"];
+
+        assert_eq!(105, metadata["seqNo"]);
+        assert_eq!("2021-11-11T14:33:50Z", metadata["txnTime"]);
+    }
+
+    #[test]
+    fn rev_reg_entry_with_revocation_status_list() {
+        let rev_reg_def_id =
+            "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:a4e25e54";
+        let ledger = MockLedger::new()
+            .answer(
+                constants::GET_REVOC_REG,
+                ledger_reply(
+                    constants::GET_REVOC_REG,
+                    serde_json::json!({"revocRegDefId": rev_reg_def_id, "value": {"accum": "21 13"}}),
+                    107,
+                    1636641250,
+                ),
+            )
+            .answer(
+                constants::GET_REVOC_REG_DEF,
+                ledger_reply(
+                    constants::GET_REVOC_REG_DEF,
+                    serde_json::json!({
+                        "id": rev_reg_def_id,
+                        "revocDefType": "CL_ACCUM",
+                        "value": {"issuanceType": "ISSUANCE_BY_DEFAULT", "maxCredNum": 4}
+                    }),
+                    106,
+                    1636641240,
+                ),
+            )
+            .answer(
+                constants::GET_REVOC_REG_DELTA,
+                ledger_reply(
+                    constants::GET_REVOC_REG_DELTA,
+                    serde_json::json!({
+                        "revocDefType": "CL_ACCUM",
+                        "revocRegDefId": rev_reg_def_id,
+                        "value": {
+                            "accum_to": {"txnTime": 1636641250, "value": {"accum": "21 13"}},
+                            "issued": [],
+                            "revoked": [3]
+                        }
+                    }),
+                    107,
+                    1636641250,
+                ),
+            );
+        let resolver = Resolver::new(ledger);
+        let did_url = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/REV_REG_ENTRY/104/revocable/a4e25e54?versionTime=2021-11-11T14:35:00Z";
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .dereference_string_with_options(
+                    did_url,
+                    &ResolveOptions::new().revocation_status_list(true),
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "issuerId": "Dk1fRRTtNazyMuK2cr64wp",
+                "revRegDefId": rev_reg_def_id,
+                "revocationList": [0, 0, 1, 0],
+                "currentAccumulator": "21 13",
+                "timestamp": 1636641250
+            }),
+            result["contentMetadata"]["methodMetadata"]["revocationStatusList"]
+        );
+        // The delta covers the registry from its creation up to the versionTime
+        let delta_request = resolver
+            .pool
+            .requests()
+            .into_iter()
+            .find(|request| request["operation"]["type"] == constants::GET_REVOC_REG_DELTA)
+            .unwrap();
+        assert!(delta_request["operation"]["from"].is_null());
+        assert_eq!(1636641300, delta_request["operation"]["to"]);
+
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(did_url).unwrap()).unwrap();
+        assert!(result["contentMetadata"]["methodMetadata"]
+            .get("revocationStatusList")
+            .is_none());
+    }
+
+    #[test]
+    fn absent_position_omitted() {
+        let reply = serde_json::json!({
+            "op": "REPLY",
+            "result": {
+                "type": constants::GET_SCHEMA,
+                "reqId": 1,
+                "data": {"attr_names": ["name"], "name": "npdb", "version": "4.3.4"},
+                "state_proof": {}
+            }
+        });
+        let resolver = Resolver::new(MockLedger::new().reply(reply.to_string()));
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(SCHEMA_URL).unwrap()).unwrap();
+        let metadata = &result["contentMetadata"];
+
+        assert!(metadata.get("seqNo").is_none());
+        assert!(metadata.get("txnTime").is_none());
+    }
+
+    #[test]
+    fn cred_def_by_schema_uses_schema_seq_no() {
+        let ledger = MockLedger::new()
+            .reply(schema_reply())
+            .reply(cred_def_reply());
+        let resolver = Resolver::new(ledger);
+
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_cred_def_by_schema(SCHEMA_URL, "npdb tag")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            "CRED_DEF",
+            result["contentMetadata"]["methodMetadata"]["objectType"]
+        );
+
+        let requests = resolver.pool.requests();
+        assert_eq!(2, requests.len());
+        assert_eq!(constants::GET_SCHEMA, requests[0]["operation"]["type"]);
+        assert_eq!(constants::GET_CRED_DEF, requests[1]["operation"]["type"]);
+        assert_eq!(74, requests[1]["operation"]["ref"]);
+        assert_eq!("npdb tag", requests[1]["operation"]["tag"]);
+        assert_eq!("Dk1fRRTtNazyMuK2cr64wp", requests[1]["operation"]["origin"]);
+    }
+
+    #[test]
+    fn cred_def_by_schema_requires_schema_url() {
+        let resolver = Resolver::new(MockLedger::new());
+        assert!(matches!(
+            resolver.resolve_cred_def_by_schema("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp", "tag"),
+            Err(DidIndyError::InvalidDidUrl)
+        ));
+        assert!(matches!(
+            resolver.resolve_cred_def_by_schema(REV_REG_DEF_URL, "tag"),
+            Err(DidIndyError::InvalidDidUrl)
+        ));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    static CRED_DEF_URL: &str =
+        "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp/anoncreds/v0/CLAIM_DEF/104/revocable";
+
+    fn tagged_rev_reg_def_reply(tag: &str) -> String {
+        ledger_reply(
+            constants::GET_REVOC_REG_DEF,
+            serde_json::json!({
+                "id": format!("Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:{}", tag),
+                "revocDefType": "CL_ACCUM",
+                "tag": tag,
+                "credDefId": "Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable",
+                "value": { "issuanceType": "ISSUANCE_BY_DEFAULT", "maxCredNum": 100 }
+            }),
+            106,
+            1636641240,
+        )
+    }
+
+    #[test]
+    fn rev_reg_defs_for_cred_def() {
+        let ledger = MockLedger::new()
+            .with_rev_reg_defs(&[
+                "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:first",
+                "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:second",
+            ])
+            .reply(tagged_rev_reg_def_reply("first"))
+            .reply(tagged_rev_reg_def_reply("second"));
+        let resolver = Resolver::new(ledger);
+
+        let rev_reg_defs = resolver
+            .resolve_rev_reg_defs_for_cred_def(CRED_DEF_URL)
+            .unwrap();
+
+        assert_eq!(2, rev_reg_defs.len());
+        assert_eq!("first", rev_reg_defs[0]["tag"]);
+        assert_eq!("second", rev_reg_defs[1]["tag"]);
+        let requests = resolver.pool.requests();
+        assert!(requests
+            .iter()
+            .all(|request| request["operation"]["type"] == constants::GET_REVOC_REG_DEF));
+        assert!(requests[1]["operation"]["id"]
+            .as_str()
             .unwrap()
-            .as_u64()
-            .unwrap() as i64;
+            .ends_with(":CL_ACCUM:second"));
+    }
 
-        let from = (*(request.req_json).get("operation").unwrap()).get("from");
-        assert_eq!(request.txn_type, constants::GET_REVOC_REG_DELTA);
-        assert!(from.is_none());
-        assert!(to >= now);
+    #[test]
+    fn rev_reg_defs_need_listing_support() {
+        let resolver = Resolver::new(MockLedger::new());
+        assert!(matches!(
+            resolver.resolve_rev_reg_defs_for_cred_def(CRED_DEF_URL),
+            Err(DidIndyError::RevRegListingNotSupported)
+        ));
+        assert!(matches!(
+            resolver.resolve_rev_reg_defs_for_cred_def(REV_REG_DEF_URL),
+            Err(DidIndyError::InvalidDidUrl)
+        ));
+        assert!(resolver.pool.requests().is_empty());
+    }
+
+    fn schema_metadata_with_time_format(time_format: TimeFormat) -> Value {
+        let config = ResolverConfig {
+            metadata_time_format: time_format,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(MockLedger::new().reply(schema_reply()), config);
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(SCHEMA_URL).unwrap()).unwrap();
+        result["contentMetadata"].clone()
+    }
+
+    #[test]
+    fn metadata_times_as_rfc3339() {
+        let metadata = schema_metadata_with_time_format(TimeFormat::Rfc3339);
+        assert_eq!("2021-11-11T14:33:42Z", metadata["txnTime"]);
+        assert!(metadata["retrieved"].is_string());
+    }
+
+    #[test]
+    fn metadata_times_as_epoch_seconds() {
+        let metadata = schema_metadata_with_time_format(TimeFormat::EpochSeconds);
+        assert_eq!(1636641222, metadata["txnTime"]);
+        assert!(metadata["retrieved"].as_i64().unwrap() > 1636641222);
+    }
+
+    #[test]
+    fn metadata_times_in_both_formats() {
+        let metadata = schema_metadata_with_time_format(TimeFormat::Both);
+        assert_eq!(
+            serde_json::json!({"rfc3339": "2021-11-11T14:33:42Z", "epochSeconds": 1636641222}),
+            metadata["txnTime"]
+        );
+        assert!(metadata["retrieved"]["rfc3339"].is_string());
+        assert!(metadata["retrieved"]["epochSeconds"].is_i64());
+    }
+
+    #[test]
+    fn did_document_metadata_times_as_epoch_seconds() {
+        let config = ResolverConfig {
+            metadata_time_format: TimeFormat::EpochSeconds,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(nym_answering_ledger(), config);
+        let result: Value = serde_json::from_str(
+            &resolver
+                .resolve_string("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(1636641222, result["didDocumentMetadata"]["updated"]);
+    }
+
+    #[test]
+    fn content_metadata_in_legacy_shape() {
+        let config = ResolverConfig {
+            legacy_metadata: true,
+            ..ResolverConfig::default()
+        };
+        let resolver = Resolver::with_config(MockLedger::new().reply(schema_reply()), config);
+        let result: Value =
+            serde_json::from_str(&resolver.dereference_string(SCHEMA_URL).unwrap()).unwrap();
+        let metadata = &result["contentMetadata"];
+
+        assert_eq!("SCHEMA", metadata["objectType"]);
+        assert_eq!(74, metadata["nodeResponse"]["result"]["seqNo"]);
+        assert!(metadata.get("methodMetadata").is_none());
+        assert!(metadata.get("contentType").is_none());
     }
 
     #[rstest]
@@ -30,10 +30,30 @@ pub struct GetNymResultV1 {
     pub identifier: Option<DidValue>,
     pub dest: DidValue,
     pub role: Option<String>,
-    pub verkey: String,
+    /// Null or empty for a deactivated DID
+    #[serde(default)]
+    pub verkey: Option<String>,
     pub diddoc_content: Option<Value>,
 }
 
+impl GetNymResultV1 {
+    /// Whether the verkey of the NYM was removed, deactivating the DID
+    pub fn is_deactivated(&self) -> bool {
+        self.verkey.as_deref().map_or(true, str::is_empty)
+    }
+}
+
+/// Revocation registry entry in effect at a point in time
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RevRegEntry {
+    /// Time the entry was written
+    pub txn_time: i64,
+    pub seq_no: Option<u64>,
+    /// Value of the entry, holding the accumulator
+    pub value: Value,
+}
+
 #[derive(Deserialize, Eq, PartialEq, Debug)]
 pub struct GetSchemaResult {
     pub attr_names: Vec<String>,
@@ -55,5 +75,61 @@ pub struct GetClaimDefResult {
 
 #[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Debug)]
 pub struct Endpoint {
-    pub endpoint: HashMap<String, String>,
+    pub endpoint: HashMap<String, EndpointValue>,
+}
+
+/// Value of a service of the legacy endpoint ATTRIB, usually a single URL
+#[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[serde(untagged)]
+pub enum EndpointValue {
+    Url(String),
+    /// List of URLs, e.g. the origins of `linked_domains`
+    Urls(Vec<String>),
+}
+
+impl EndpointValue {
+    pub fn urls(&self) -> Vec<&str> {
+        match self {
+            EndpointValue::Url(url) => vec![url.as_str()],
+            EndpointValue::Urls(urls) => urls.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl From<&str> for EndpointValue {
+    fn from(url: &str) -> Self {
+        EndpointValue::Url(url.to_string())
+    }
+}
+
+impl From<String> for EndpointValue {
+    fn from(url: String) -> Self {
+        EndpointValue::Url(url)
+    }
+}
+
+/// Ledger data together with the state proof of the nodes. Verifiers check the
+/// BLS multi-signature over the state root and the proof nodes leading to the data.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct SignedTransaction {
+    #[serde(rename = "seqNo")]
+    pub seq_no: Option<u64>,
+    #[serde(rename = "txnTime")]
+    pub txn_time: Option<i64>,
+    pub data: Value,
+    pub state_proof: StateProof,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct StateProof {
+    pub root_hash: String,
+    pub proof_nodes: String,
+    pub multi_signature: MultiSignature,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct MultiSignature {
+    pub signature: String,
+    pub participants: Vec<String>,
+    pub value: Value,
 }
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::sync::Arc;
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use indy_vdr::pool::PreparedRequest;
+use serde_json::{json, Value};
+
+use super::error::{DidIndyError, DidIndyResult};
+
+pub const PROOF_TYPE: &str = "Ed25519Signature";
+pub const CANONICALIZATION: &str = "JCS";
+
+// Metadata properties carrying the proof of resolution and dereferencing results
+static METADATA_PROPERTIES: [&str; 2] = ["didResolutionMetadata", "dereferencingMetadata"];
+// Property of the proof recording the metadata it was added to, if that was
+// absent, null or an empty object
+const EMPTY_METADATA: &str = "emptyMetadata";
+const EMPTY_METADATA_ABSENT: &str = "absent";
+const EMPTY_METADATA_NULL: &str = "null";
+const EMPTY_METADATA_OBJECT: &str = "object";
+
+/// Ed25519 key used to sign resolution and dereferencing results, together with
+/// the DID URL under which verifiers find the public key.
+#[derive(Clone)]
+pub struct SigningConfig {
+    keypair: Arc<Keypair>,
+    verification_method: String,
+}
+
+impl SigningConfig {
+    pub fn new(secret_key: &[u8], verification_method: &str) -> DidIndyResult<Self> {
+        let secret =
+            SecretKey::from_bytes(secret_key).map_err(|_| DidIndyError::InvalidSigningKey)?;
+        let public = PublicKey::from(&secret);
+        Ok(SigningConfig {
+            keypair: Arc::new(Keypair { secret, public }),
+            verification_method: verification_method.to_string(),
+        })
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.keypair.public.to_bytes()
+    }
+}
+
+// The secret key must not end up in logs
+impl fmt::Debug for SigningConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningConfig")
+            .field("verification_method", &self.verification_method)
+            .finish()
+    }
+}
+
+/// Signs ledger requests, e.g. with a key held in an HSM. Returns the signature
+/// over the message or the reason signing failed.
+pub trait RequestSigner: Send + Sync {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// Signs ledger requests with an Ed25519 key held in memory
+pub struct Ed25519RequestSigner {
+    keypair: Keypair,
+}
+
+impl Ed25519RequestSigner {
+    pub fn new(secret_key: &[u8]) -> DidIndyResult<Self> {
+        let secret =
+            SecretKey::from_bytes(secret_key).map_err(|_| DidIndyError::InvalidSigningKey)?;
+        let public = PublicKey::from(&secret);
+        Ok(Ed25519RequestSigner {
+            keypair: Keypair { secret, public },
+        })
+    }
+
+    /// Verkey of the key, base58 encoded as on the ledger
+    pub fn verkey(&self) -> String {
+        bs58::encode(self.keypair.public.to_bytes()).into_string()
+    }
+}
+
+impl RequestSigner for Ed25519RequestSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(self.keypair.sign(message).to_bytes().to_vec())
+    }
+}
+
+/// Submitter DID and signer of ledger read requests, for networks whose read
+/// permissions only allow authenticated requests. The submitter DID is the
+/// unqualified DID whose NYM on the ledger carries the verkey of the signer.
+#[derive(Clone)]
+pub struct ReadRequestSigning {
+    submitter_did: String,
+    signer: Arc<dyn RequestSigner>,
+}
+
+impl ReadRequestSigning {
+    pub fn new(submitter_did: &str, signer: Arc<dyn RequestSigner>) -> Self {
+        ReadRequestSigning {
+            submitter_did: submitter_did.to_string(),
+            signer,
+        }
+    }
+
+    pub fn ed25519(submitter_did: &str, secret_key: &[u8]) -> DidIndyResult<Self> {
+        Ok(ReadRequestSigning::new(
+            submitter_did,
+            Arc::new(Ed25519RequestSigner::new(secret_key)?),
+        ))
+    }
+
+    pub fn submitter_did(&self) -> &str {
+        &self.submitter_did
+    }
+}
+
+impl fmt::Debug for ReadRequestSigning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadRequestSigning")
+            .field("submitter_did", &self.submitter_did)
+            .finish()
+    }
+}
+
+/// Sets the submitter DID as identifier of the request and signs it
+pub(crate) fn sign_request(
+    request: &mut PreparedRequest,
+    signing: &ReadRequestSigning,
+) -> DidIndyResult<()> {
+    request.req_json["identifier"] = Value::from(signing.submitter_did.as_str());
+    let input = request
+        .get_signature_input()
+        .map_err(|err| DidIndyError::RequestSigningFailed(err.to_string()))?;
+    let signature = signing
+        .signer
+        .sign(input.as_bytes())
+        .map_err(DidIndyError::RequestSigningFailed)?;
+    request
+        .set_signature(&signature)
+        .map_err(|err| DidIndyError::RequestSigningFailed(err.to_string()))
+}
+
+/// Attaches a detached signature over the JCS canonical form of a serialized
+/// resolution or dereferencing result. The signature covers the result as it is,
+/// the proof is added to the result metadata afterwards.
+pub(crate) fn sign_result(
+    result: &mut Value,
+    metadata_property: &str,
+    config: &SigningConfig,
+) -> DidIndyResult<()> {
+    let canonical = serde_jcs::to_vec(&*result)?;
+    let signature = config.keypair.sign(&canonical);
+
+    let mut proof = json!({
+        "type": PROOF_TYPE,
+        "canonicalization": CANONICALIZATION,
+        "verificationMethod": config.verification_method,
+        "proofValue": format!("z{}", bs58::encode(signature.to_bytes()).into_string()),
+    });
+    // Metadata holding nothing but the proof is restored to its signed form
+    // from this when the proof is taken
+    let empty_metadata = match result.get(metadata_property) {
+        None => Some(EMPTY_METADATA_ABSENT),
+        Some(Value::Object(metadata)) if metadata.is_empty() => Some(EMPTY_METADATA_OBJECT),
+        Some(Value::Object(_)) => None,
+        Some(_) => Some(EMPTY_METADATA_NULL),
+    };
+    if let Some(empty_metadata) = empty_metadata {
+        proof[EMPTY_METADATA] = Value::from(empty_metadata);
+    }
+    if !result[metadata_property].is_object() {
+        result[metadata_property] = json!({});
+    }
+    result[metadata_property]["proof"] = proof;
+    Ok(())
+}
+
+/// Verifies the proof of a signed resolution or dereferencing result against the
+/// public key of the resolver. Returns false if the result carries no proof or
+/// was modified after signing.
+pub fn verify_result(result: &str, public_key: &[u8]) -> DidIndyResult<bool> {
+    let public_key =
+        PublicKey::from_bytes(public_key).map_err(|_| DidIndyError::InvalidSigningKey)?;
+    let mut result: Value = serde_json::from_str(result)?;
+
+    let proof = match take_proof(&mut result) {
+        Some(proof) => proof,
+        None => return Ok(false),
+    };
+    let signature = match proof["proofValue"]
+        .as_str()
+        .and_then(|value| value.strip_prefix('z'))
+        .and_then(|value| bs58::decode(value).into_vec().ok())
+        .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok())
+    {
+        Some(signature) => signature,
+        None => return Ok(false),
+    };
+
+    let canonical = serde_jcs::to_vec(&result)?;
+    Ok(public_key.verify(&canonical, &signature).is_ok())
+}
+
+// Removes the proof, restoring the metadata as it was at signing time: absent,
+// null or an empty object if the proof was all it held
+pub(crate) fn take_proof(result: &mut Value) -> Option<Value> {
+    for property in METADATA_PROPERTIES {
+        let (proof, emptied) = match result.get_mut(property) {
+            Some(Value::Object(metadata)) => match metadata.remove("proof") {
+                Some(proof) => (proof, metadata.is_empty()),
+                None => continue,
+            },
+            _ => continue,
+        };
+        if emptied {
+            match proof[EMPTY_METADATA].as_str() {
+                Some(EMPTY_METADATA_ABSENT) => {
+                    if let Some(result) = result.as_object_mut() {
+                        result.remove(property);
+                    }
+                }
+                Some(EMPTY_METADATA_OBJECT) => {}
+                _ => result[property] = Value::Null,
+            }
+        }
+        return Some(proof);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SECRET_KEY: [u8; 32] = [7; 32];
+    static VERIFICATION_METHOD: &str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#resolver";
+
+    fn config() -> SigningConfig {
+        SigningConfig::new(&SECRET_KEY, VERIFICATION_METHOD).unwrap()
+    }
+
+    fn signed_fixture() -> String {
+        let mut result = json!({
+            "didResolutionMetadata": null,
+            "didDocument": {
+                "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                "verificationMethod": [{
+                    "id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp#verkey",
+                    "type": "Ed25519VerificationKey2018",
+                    "controller": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp",
+                    "publicKeyBase58": "B12NYF8RrR3h41TDCTJojY59usg3mbtbjnFs7Eud1Y6u"
+                }]
+            },
+            "didDocumentMetadata": {"objectType": "NYM"}
+        });
+        sign_result(&mut result, "didResolutionMetadata", &config()).unwrap();
+        serde_json::to_string_pretty(&result).unwrap()
+    }
+
+    fn signed_with_metadata(metadata: Option<Value>) -> String {
+        let mut result = json!({
+            "didDocument": {"id": "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp"},
+            "didDocumentMetadata": {"objectType": "NYM"}
+        });
+        if let Some(metadata) = metadata {
+            result["didResolutionMetadata"] = metadata;
+        }
+        sign_result(&mut result, "didResolutionMetadata", &config()).unwrap();
+        result.to_string()
+    }
+
+    #[test]
+    fn signed_result_carries_proof() {
+        let result: Value = serde_json::from_str(&signed_fixture()).unwrap();
+        let proof = &result["didResolutionMetadata"]["proof"];
+
+        assert_eq!(PROOF_TYPE, proof["type"]);
+        assert_eq!(VERIFICATION_METHOD, proof["verificationMethod"]);
+        assert!(proof["proofValue"].as_str().unwrap().starts_with('z'));
+    }
+
+    #[test]
+    fn verify_signed_result() {
+        assert!(verify_result(&signed_fixture(), &config().public_key()).unwrap());
+    }
+
+    #[test]
+    fn proof_taken_from_metadata_as_signed() {
+        for metadata in [
+            None,
+            Some(Value::Null),
+            Some(json!({})),
+            Some(json!({"contentType": "application/did+json"})),
+        ] {
+            let signed = signed_with_metadata(metadata.clone());
+            assert!(
+                verify_result(&signed, &config().public_key()).unwrap(),
+                "{:?}",
+                metadata
+            );
+
+            let mut result: Value = serde_json::from_str(&signed).unwrap();
+            assert!(take_proof(&mut result).is_some());
+            assert_eq!(
+                metadata.as_ref(),
+                result.get("didResolutionMetadata"),
+                "{:?}",
+                metadata
+            );
+        }
+    }
+
+    #[test]
+    fn verify_ignores_formatting() {
+        let result: Value = serde_json::from_str(&signed_fixture()).unwrap();
+        assert!(verify_result(&result.to_string(), &config().public_key()).unwrap());
+    }
+
+    #[test]
+    fn verify_detects_tampering() {
+        let mut result: Value = serde_json::from_str(&signed_fixture()).unwrap();
+        result["didDocument"]["verificationMethod"][0]["publicKeyBase58"] =
+            json!("67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk");
+
+        assert!(!verify_result(&result.to_string(), &config().public_key()).unwrap());
+    }
+
+    #[test]
+    fn verify_fails_with_other_key() {
+        let other = SigningConfig::new(&[8; 32], VERIFICATION_METHOD).unwrap();
+        assert!(!verify_result(&signed_fixture(), &other.public_key()).unwrap());
+    }
+
+    #[test]
+    fn verify_fails_without_proof() {
+        let result = json!({"didResolutionMetadata": null, "didDocument": {}});
+        assert!(!verify_result(&result.to_string(), &config().public_key()).unwrap());
+    }
+
+    #[test]
+    fn debug_omits_secret_key() {
+        assert_eq!(
+            format!(
+                "SigningConfig {{ verification_method: {:?} }}",
+                VERIFICATION_METHOD
+            ),
+            format!("{:?}", config())
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use futures_util::future::LocalBoxFuture;
+use indy_vdr::ledger::identifiers::{CredentialDefinitionId, RevocationRegistryId};
+use indy_vdr::ledger::RequestBuilder;
+use indy_vdr::pool::PreparedRequest;
+use log::{debug, warn};
+
+use super::error::DidIndyResult;
+use super::ledger::LedgerRequester;
+
+/// Pool serving reads from a warm standby while the primary is refreshed.
+///
+/// Outside of a refresh, all requests go to the primary. While `refresh` runs,
+/// reads go to the standby; if the standby cannot be reached they fall back to
+/// the primary being refreshed, so a read never fails only because a refresh is
+/// in progress. When the refresh succeeds, the refreshed pool becomes the
+/// primary; when it fails, the previous primary is kept.
+pub struct StandbyPool<T> {
+    primary: RwLock<Arc<T>>,
+    standby: Option<Arc<T>>,
+    refreshing: AtomicBool,
+    // Serializes refreshes
+    refresh_lock: Mutex<()>,
+}
+
+// Clears the refreshing flag when the refresh ends, also if it panics
+struct Refreshing<'a>(&'a AtomicBool);
+
+impl Drop for Refreshing<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<T: LedgerRequester> StandbyPool<T> {
+    /// Pool without standby, reads go to the primary during a refresh
+    pub fn new(primary: T) -> Self {
+        StandbyPool {
+            primary: RwLock::new(Arc::new(primary)),
+            standby: None,
+            refreshing: AtomicBool::new(false),
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    /// Serves reads from the standby while the primary is refreshed
+    pub fn with_standby(mut self, standby: T) -> Self {
+        self.standby = Some(Arc::new(standby));
+        self
+    }
+
+    /// The current primary
+    pub fn primary(&self) -> Arc<T> {
+        self.primary.read().unwrap().clone()
+    }
+
+    pub fn is_refreshing(&self) -> bool {
+        self.refreshing.load(Ordering::SeqCst)
+    }
+
+    /// Refreshes the primary, e.g. with `networks::refresh_pool`. Reads are
+    /// served by the standby until the refresh returns. The refreshed pool
+    /// replaces the primary; if the refresh fails, the primary is kept and the
+    /// error returned.
+    pub fn refresh<F>(&self, refresh: F) -> DidIndyResult<()>
+    where
+        F: FnOnce(&T) -> DidIndyResult<T>,
+    {
+        let _lock = self.refresh_lock.lock().unwrap();
+        let current = self.primary();
+        self.refreshing.store(true, Ordering::SeqCst);
+        let _refreshing = Refreshing(&self.refreshing);
+        debug!("Refreshing primary pool");
+
+        let refreshed = refresh(&current)?;
+        *self.primary.write().unwrap() = Arc::new(refreshed);
+        debug!("Switched back to refreshed primary pool");
+        Ok(())
+    }
+
+    // Pool to serve a read and the pool to fall back to if it is unreachable
+    fn pools(&self) -> (Arc<T>, Option<Arc<T>>) {
+        match &self.standby {
+            Some(standby) if self.is_refreshing() => (standby.clone(), Some(self.primary())),
+            _ => (self.primary(), None),
+        }
+    }
+}
+
+impl<T: LedgerRequester> LedgerRequester for StandbyPool<T> {
+    fn request_builder(&self) -> RequestBuilder {
+        self.primary().request_builder()
+    }
+
+    fn submit<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<String>> {
+        Box::pin(async move { Ok(self.submit_timed(request).await?.0) })
+    }
+
+    fn node_aliases(&self) -> Vec<String> {
+        self.pools().0.node_aliases()
+    }
+
+    fn pool_transactions(&self) -> Vec<String> {
+        self.pools().0.pool_transactions()
+    }
+
+    fn submit_timed<'a>(
+        &'a self,
+        request: &'a PreparedRequest,
+    ) -> LocalBoxFuture<'a, DidIndyResult<(String, HashMap<String, f32>)>> {
+        let (pool, fallback) = self.pools();
+        Box::pin(async move {
+            match pool.submit_timed(request).await {
+                Err(err) if err.is_transport() && fallback.is_some() => {
+                    warn!("Standby pool unreachable during refresh, {}", err);
+                    fallback.unwrap().submit_timed(request).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn rev_reg_def_ids<'a>(
+        &'a self,
+        cred_def_id: &'a CredentialDefinitionId,
+    ) -> LocalBoxFuture<'a, DidIndyResult<Vec<RevocationRegistryId>>> {
+        let pool = self.pools().0;
+        Box::pin(async move { pool.rev_reg_def_ids(cred_def_id).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DidIndyError;
+    use crate::resolver::Resolver;
+    use crate::tests::{nym_reply, MockLedger};
+    use indy_vdr::ledger::constants;
+    use serde_json::json;
+
+    static DID: &str = "did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp";
+
+    fn ledger(verkey: &str) -> MockLedger {
+        MockLedger::new().answer(
+            constants::GET_NYM,
+            nym_reply("Dk1fRRTtNazyMuK2cr64wp", verkey, Some(json!({}))),
+        )
+    }
+
+    fn verkey_of(resolver: &Resolver<StandbyPool<MockLedger>>) -> String {
+        let result: serde_json::Value =
+            serde_json::from_str(&resolver.resolve_string(DID).unwrap()).unwrap();
+        result["didDocument"]["verificationMethod"][0]["publicKeyBase58"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn standby_serves_reads_during_refresh() {
+        let pool = StandbyPool::new(ledger("5UBVMdSADMjGzuJMQwJ6yyzYV1krTcKRp6EqRAz8tiDP"))
+            .with_standby(ledger("4Vr1bJ6V6jEPQ1PdmWyNkKeJ2RSFHNKx2FCKwz4tJkjB"));
+        let resolver = Resolver::new(pool);
+        assert_eq!(
+            "5UBVMdSADMjGzuJMQwJ6yyzYV1krTcKRp6EqRAz8tiDP",
+            verkey_of(&resolver)
+        );
+
+        resolver
+            .pool()
+            .refresh(|_| {
+                assert!(resolver.pool().is_refreshing());
+                assert_eq!(
+                    "4Vr1bJ6V6jEPQ1PdmWyNkKeJ2RSFHNKx2FCKwz4tJkjB",
+                    verkey_of(&resolver)
+                );
+                Ok(ledger("H3C2AVvLMv6gmMNam3uVAjZpfkcJCwDwnZn6z3wXmqPV"))
+            })
+            .unwrap();
+
+        assert!(!resolver.pool().is_refreshing());
+        assert_eq!(
+            "H3C2AVvLMv6gmMNam3uVAjZpfkcJCwDwnZn6z3wXmqPV",
+            verkey_of(&resolver)
+        );
+    }
+
+    #[test]
+    fn unreachable_standby_falls_back_to_primary() {
+        let pool = StandbyPool::new(ledger("5UBVMdSADMjGzuJMQwJ6yyzYV1krTcKRp6EqRAz8tiDP"))
+            .with_standby(MockLedger::new().fail(DidIndyError::RequestTimeout));
+        let resolver = Resolver::new(pool);
+
+        let err = resolver
+            .pool()
+            .refresh(|_| {
+                assert_eq!(
+                    "5UBVMdSADMjGzuJMQwJ6yyzYV1krTcKRp6EqRAz8tiDP",
+                    verkey_of(&resolver)
+                );
+                Err(DidIndyError::LedgerUnreachable(String::from(
+                    "catchup failed",
+                )))
+            })
+            .unwrap_err();
+
+        // The failed refresh keeps the primary
+        assert!(matches!(err, DidIndyError::LedgerUnreachable(_)));
+        assert!(!resolver.pool().is_refreshing());
+        assert_eq!(1, resolver.pool().primary().requests().len());
+        assert_eq!(
+            "5UBVMdSADMjGzuJMQwJ6yyzYV1krTcKRp6EqRAz8tiDP",
+            verkey_of(&resolver)
+        );
+    }
+}
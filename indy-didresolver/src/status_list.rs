@@ -0,0 +1,287 @@
+use serde::de::{Error as DeError, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::error::{DidIndyError, DidIndyResult};
+
+static ISSUANCE_ON_DEMAND: &str = "ISSUANCE_ON_DEMAND";
+
+/// Largest `maxCredNum` a status list is built for. The list holds a byte per
+/// credential, so a ledger object claiming more is rejected as invalid rather
+/// than allocated.
+pub const MAX_CRED_NUM: u64 = 1 << 24;
+
+/// Revocation status list of AnonCreds 1.0, the state of every credential of a
+/// revocation registry at a point in time
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationStatusList {
+    pub issuer_id: String,
+    pub rev_reg_def_id: String,
+    /// Status of the credential with revocation index `i` at position `i - 1`,
+    /// 1 if it is revoked or, for `ISSUANCE_ON_DEMAND`, not issued
+    pub revocation_list: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_accumulator: Option<String>,
+    /// Time of the latest registry entry the list reflects
+    pub timestamp: i64,
+}
+
+// Ledger data of a revocation registry definition, as far as it is needed
+#[derive(Deserialize)]
+struct RevRegDef {
+    id: String,
+    value: RevRegDefValue,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RevRegDefValue {
+    issuance_type: String,
+    #[serde(deserialize_with = "bounded_max_cred_num")]
+    max_cred_num: u64,
+}
+
+fn bounded_max_cred_num<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let max_cred_num = u64::deserialize(deserializer)?;
+    if max_cred_num > MAX_CRED_NUM {
+        return Err(D::Error::invalid_value(
+            Unexpected::Unsigned(max_cred_num),
+            &"a maxCredNum of at most 16777216",
+        ));
+    }
+    Ok(max_cred_num)
+}
+
+// Ledger data of a revocation registry delta. The indices are the ones issued
+// and revoked between the accum_from and the accum_to entry.
+#[derive(Deserialize)]
+struct RevRegDelta {
+    value: RevRegDeltaValue,
+}
+
+#[derive(Deserialize)]
+struct RevRegDeltaValue {
+    accum_to: AccumEntry,
+    #[serde(default)]
+    issued: Vec<u64>,
+    #[serde(default)]
+    revoked: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccumEntry {
+    txn_time: i64,
+    value: Value,
+}
+
+/// Builds the status list of the revocation registry at the timestamp from the
+/// ledger data of its definition and of deltas covering its history, e.g. a
+/// single delta since its creation. The deltas are applied in the order of their
+/// `accum_to` entries, deltas ending after the timestamp are left out. Indices
+/// both issued and revoked within a delta end up revoked. Indices no delta
+/// names keep the state of the issuance type: issued for
+/// `ISSUANCE_BY_DEFAULT`, not issued for `ISSUANCE_ON_DEMAND`.
+pub fn revocation_status_list(
+    rev_reg_def: &Value,
+    deltas: &[Value],
+    timestamp: i64,
+) -> DidIndyResult<RevocationStatusList> {
+    let rev_reg_def: RevRegDef = serde_json::from_value(rev_reg_def.clone())?;
+    let mut deltas = deltas
+        .iter()
+        .map(|delta| serde_json::from_value::<RevRegDelta>(delta.clone()))
+        .collect::<Result<Vec<RevRegDelta>, serde_json::Error>>()?;
+    deltas.sort_by_key(|delta| delta.value.accum_to.txn_time);
+    let first_entry = deltas
+        .first()
+        .map(|delta| delta.value.accum_to.txn_time)
+        .ok_or(DidIndyError::EmptyData)?;
+
+    let max_cred_num = rev_reg_def.value.max_cred_num;
+    let default_state = u8::from(rev_reg_def.value.issuance_type == ISSUANCE_ON_DEMAND);
+    let mut revocation_list = vec![default_state; max_cred_num as usize];
+    let mut latest = None;
+    for delta in deltas
+        .iter()
+        .take_while(|delta| delta.value.accum_to.txn_time <= timestamp)
+    {
+        for (indices, state) in [(&delta.value.issued, 0), (&delta.value.revoked, 1)] {
+            for index in indices {
+                if *index == 0 || *index > max_cred_num {
+                    return Err(DidIndyError::RevocationIndexOutOfRange {
+                        index: *index,
+                        max_cred_num,
+                    });
+                }
+                revocation_list[*index as usize - 1] = state;
+            }
+        }
+        latest = Some(&delta.value.accum_to);
+    }
+    let latest = latest.ok_or(DidIndyError::NotFoundBefore { first_entry })?;
+
+    Ok(RevocationStatusList {
+        issuer_id: rev_reg_def
+            .id
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        rev_reg_def_id: rev_reg_def.id.clone(),
+        revocation_list,
+        current_accumulator: latest.value["accum"].as_str().map(String::from),
+        timestamp: latest.txn_time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    static REV_REG_DEF_ID: &str =
+        "Dk1fRRTtNazyMuK2cr64wp:4:Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable:CL_ACCUM:a4e25e54";
+
+    fn rev_reg_def(issuance_type: &str, max_cred_num: u64) -> Value {
+        json!({
+            "id": REV_REG_DEF_ID,
+            "revocDefType": "CL_ACCUM",
+            "tag": "a4e25e54",
+            "credDefId": "Dk1fRRTtNazyMuK2cr64wp:3:CL:104:revocable",
+            "value": {
+                "issuanceType": issuance_type,
+                "maxCredNum": max_cred_num,
+                "tailsHash": "91zvq2cFmBZmHCcLqFyzv7bfehHH5rMhdAG5wTjqy2PE",
+                "tailsLocation": "https://tails.example.com/91zvq2cFmBZmHCcLqFyzv7bfehHH5rMhdAG5wTjqy2PE"
+            }
+        })
+    }
+
+    fn delta(txn_time: i64, accum: &str, issued: &[u64], revoked: &[u64]) -> Value {
+        json!({
+            "revocDefType": "CL_ACCUM",
+            "revocRegDefId": REV_REG_DEF_ID,
+            "value": {
+                "accum_to": {
+                    "revocDefType": "CL_ACCUM",
+                    "revocRegDefId": REV_REG_DEF_ID,
+                    "txnTime": txn_time,
+                    "value": {"accum": accum}
+                },
+                "issued": issued,
+                "revoked": revoked
+            }
+        })
+    }
+
+    #[test]
+    fn issued_by_default_with_revocations() {
+        let list = revocation_status_list(
+            &rev_reg_def("ISSUANCE_BY_DEFAULT", 8),
+            &[delta(1636641230, "21 11", &[], &[2, 5])],
+            1636641300,
+        )
+        .unwrap();
+
+        assert_eq!(
+            RevocationStatusList {
+                issuer_id: String::from("Dk1fRRTtNazyMuK2cr64wp"),
+                rev_reg_def_id: String::from(REV_REG_DEF_ID),
+                revocation_list: vec![0, 1, 0, 0, 1, 0, 0, 0],
+                current_accumulator: Some(String::from("21 11")),
+                timestamp: 1636641230,
+            },
+            list
+        );
+    }
+
+    #[test]
+    fn issued_on_demand_beyond_observed_indices() {
+        // Indices 4 to 10 were never issued and count as revoked
+        let list = revocation_status_list(
+            &rev_reg_def("ISSUANCE_ON_DEMAND", 10),
+            &[delta(1636641230, "21 11", &[1, 2, 3], &[])],
+            1636641230,
+        )
+        .unwrap();
+
+        assert_eq!(vec![0, 0, 0, 1, 1, 1, 1, 1, 1, 1], list.revocation_list);
+    }
+
+    #[test]
+    fn issued_and_later_revoked() {
+        let deltas = [
+            delta(1636641250, "21 13", &[], &[3]),
+            delta(1636641230, "21 11", &[1, 2, 3], &[]),
+            // Issued and revoked between the entries of a single delta
+            delta(1636641270, "21 15", &[4, 5], &[4]),
+        ];
+
+        let list =
+            revocation_status_list(&rev_reg_def("ISSUANCE_ON_DEMAND", 5), &deltas, 1636641260)
+                .unwrap();
+        assert_eq!(vec![0, 0, 1, 1, 1], list.revocation_list);
+        assert_eq!(1636641250, list.timestamp);
+        assert_eq!(Some(String::from("21 13")), list.current_accumulator);
+
+        let list =
+            revocation_status_list(&rev_reg_def("ISSUANCE_ON_DEMAND", 5), &deltas, 1636641300)
+                .unwrap();
+        assert_eq!(vec![0, 0, 1, 1, 0], list.revocation_list);
+        assert_eq!(1636641270, list.timestamp);
+    }
+
+    #[test]
+    fn invalid_deltas_rejected() {
+        let def = rev_reg_def("ISSUANCE_BY_DEFAULT", 4);
+
+        assert!(matches!(
+            revocation_status_list(&def, &[delta(1636641230, "21 11", &[], &[5])], 1636641300),
+            Err(DidIndyError::RevocationIndexOutOfRange {
+                index: 5,
+                max_cred_num: 4
+            })
+        ));
+        assert!(matches!(
+            revocation_status_list(&def, &[delta(1636641230, "21 11", &[], &[1])], 1636641200),
+            Err(DidIndyError::NotFoundBefore {
+                first_entry: 1636641230
+            })
+        ));
+        assert!(matches!(
+            revocation_status_list(&def, &[], 1636641200),
+            Err(DidIndyError::EmptyData)
+        ));
+    }
+
+    #[test]
+    fn oversized_max_cred_num_rejected() {
+        let deltas = [delta(1636641230, "21 11", &[], &[1])];
+
+        assert!(matches!(
+            revocation_status_list(
+                &rev_reg_def("ISSUANCE_BY_DEFAULT", u64::MAX),
+                &deltas,
+                1636641300
+            ),
+            Err(DidIndyError::ParsingError(_))
+        ));
+        assert!(matches!(
+            revocation_status_list(
+                &rev_reg_def("ISSUANCE_BY_DEFAULT", MAX_CRED_NUM + 1),
+                &deltas,
+                1636641300
+            ),
+            Err(DidIndyError::ParsingError(_))
+        ));
+        let list = revocation_status_list(
+            &rev_reg_def("ISSUANCE_BY_DEFAULT", MAX_CRED_NUM),
+            &deltas,
+            1636641300,
+        )
+        .unwrap();
+        assert_eq!(MAX_CRED_NUM as usize, list.revocation_list.len());
+    }
+}
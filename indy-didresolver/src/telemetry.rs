@@ -0,0 +1,170 @@
+use std::future::Future;
+
+use super::audit::CacheStatus;
+use super::resolver::ObjectType;
+
+/// Tracing span of a resolution or dereferencing. With the `otel` feature the
+/// span is emitted with `tracing` for export with OpenTelemetry, without it all
+/// operations do nothing.
+pub(crate) struct ResolutionSpan {
+    #[cfg(feature = "otel")]
+    span: tracing::Span,
+}
+
+impl ResolutionSpan {
+    #[cfg(feature = "otel")]
+    pub(crate) fn new(operation: &str, did_url: &str) -> Self {
+        use tracing::field::Empty;
+
+        // Span names have to be static
+        let span = if operation == "resolve" {
+            tracing::info_span!(
+                "resolve",
+                namespace = Empty,
+                object_type = Empty,
+                txn_type = Empty,
+                cache_hit = Empty
+            )
+        } else {
+            tracing::info_span!(
+                "dereference",
+                namespace = Empty,
+                object_type = Empty,
+                txn_type = Empty,
+                cache_hit = Empty
+            )
+        };
+        if let Ok(did_url) = super::did::DidUrl::from_str(did_url) {
+            span.record("namespace", &did_url.namespace.as_str());
+        }
+        ResolutionSpan { span }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    pub(crate) fn new(_operation: &str, _did_url: &str) -> Self {
+        ResolutionSpan {}
+    }
+
+    /// Runs the future within the span
+    #[cfg(feature = "otel")]
+    pub(crate) async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        use tracing::Instrument;
+        future.instrument(self.span.clone()).await
+    }
+
+    #[cfg(not(feature = "otel"))]
+    pub(crate) async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        future.await
+    }
+
+    /// Records the object type of a successful result and whether it came from
+    /// the cache
+    #[cfg(feature = "otel")]
+    pub(crate) fn record_result(&self, object_type: Option<ObjectType>, cache: CacheStatus) {
+        self.span.record("cache_hit", &(cache == CacheStatus::Hit));
+        if let Some(object_type) = object_type {
+            self.span.record("object_type", &object_type.as_str());
+        }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    pub(crate) fn record_result(&self, _object_type: Option<ObjectType>, _cache: CacheStatus) {}
+}
+
+/// Records the transaction type of the ledger request on the current resolution
+/// span
+pub(crate) fn record_txn_type(txn_type: &str) {
+    #[cfg(feature = "otel")]
+    tracing::Span::current().record("txn_type", &txn_type);
+    #[cfg(not(feature = "otel"))]
+    let _ = txn_type;
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::future::BoxFuture;
+    use opentelemetry::sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+    use opentelemetry::sdk::trace::TracerProvider;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::Key;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::cache::ResultCache;
+    use crate::config::ResolverConfig;
+    use crate::resolver::Resolver;
+    use crate::tests::{nym_reply, MockLedger};
+
+    #[derive(Clone, Debug, Default)]
+    struct InMemoryExporter(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanExporter for InMemoryExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    fn attribute(span: &SpanData, key: &'static str) -> Option<String> {
+        span.attributes
+            .get(&Key::new(key))
+            .map(|value| value.to_string())
+    }
+
+    #[test]
+    fn resolution_spans_are_exported() {
+        let exporter = InMemoryExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let config = ResolverConfig {
+            cache: Some(ResultCache::in_memory(std::time::Duration::from_secs(60))),
+            ..ResolverConfig::default()
+        };
+        let ledger = MockLedger::new().answer(
+            "105",
+            nym_reply(
+                "Dk1fRRTtNazyMuK2cr64wp",
+                "~4Vr1bJ6V6jEPQ1PdmWyNkK",
+                Some(serde_json::json!({})),
+            ),
+        );
+        let resolver = Resolver::with_config(ledger, config);
+        tracing::subscriber::with_default(subscriber, || {
+            resolver
+                .resolve("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap();
+            resolver
+                .resolve("did:indy:idunion:Dk1fRRTtNazyMuK2cr64wp")
+                .unwrap();
+        });
+        provider.force_flush();
+
+        let spans = exporter.0.lock().unwrap();
+        assert_eq!(2, spans.len());
+        assert!(spans.iter().all(|span| span.name == "resolve"));
+        assert_eq!(
+            Some(String::from("idunion")),
+            attribute(&spans[0], "namespace")
+        );
+        assert_eq!(
+            Some(String::from("NYM")),
+            attribute(&spans[0], "object_type")
+        );
+        assert_eq!(Some(String::from("105")), attribute(&spans[0], "txn_type"));
+        assert_eq!(
+            Some(String::from("false")),
+            attribute(&spans[0], "cache_hit")
+        );
+        assert_eq!(
+            Some(String::from("true")),
+            attribute(&spans[1], "cache_hit")
+        );
+        // Served from the cache without a ledger request
+        assert_eq!(None, attribute(&spans[1], "txn_type"));
+    }
+}
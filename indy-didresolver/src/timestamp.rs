@@ -0,0 +1,138 @@
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+/// Representation of times in the result metadata
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// RFC3339 string, e.g. `2021-11-11T14:33:42Z`
+    Rfc3339,
+    /// Seconds since the epoch
+    EpochSeconds,
+    /// Object holding both, e.g. `{"rfc3339": "2021-11-11T14:33:42Z", "epochSeconds": 1636641222}`
+    Both,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        TimeFormat::Rfc3339
+    }
+}
+
+/// Time in the result metadata, serialized in the configured format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    seconds: i64,
+    format: TimeFormat,
+}
+
+impl Timestamp {
+    pub fn new(seconds: i64, format: TimeFormat) -> Self {
+        Timestamp { seconds, format }
+    }
+
+    pub fn now(format: TimeFormat) -> Self {
+        Timestamp::new(Utc::now().timestamp(), format)
+    }
+
+    /// Seconds since the epoch
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    fn rfc3339(&self) -> String {
+        Utc.timestamp_opt(self.seconds, 0)
+            .single()
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_default()
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.format {
+            TimeFormat::Rfc3339 => serializer.serialize_str(&self.rfc3339()),
+            TimeFormat::EpochSeconds => serializer.serialize_i64(self.seconds),
+            TimeFormat::Both => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("rfc3339", &self.rfc3339())?;
+                map.serialize_entry("epochSeconds", &self.seconds)?;
+                map.end()
+            }
+        }
+    }
+}
+
+// Any of the serialized formats, the format is kept as it was
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Serialized {
+    Rfc3339(String),
+    EpochSeconds(i64),
+    #[serde(rename_all = "camelCase")]
+    Both {
+        epoch_seconds: i64,
+    },
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Serialized::deserialize(deserializer)? {
+            Serialized::Rfc3339(time) => DateTime::parse_from_rfc3339(&time)
+                .map(|time| Timestamp::new(time.timestamp(), TimeFormat::Rfc3339))
+                .map_err(de::Error::custom),
+            Serialized::EpochSeconds(seconds) => {
+                Ok(Timestamp::new(seconds, TimeFormat::EpochSeconds))
+            }
+            Serialized::Both { epoch_seconds } => {
+                Ok(Timestamp::new(epoch_seconds, TimeFormat::Both))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    static SECONDS: i64 = 1636641222;
+
+    #[test]
+    fn serialize_rfc3339() {
+        let time = Timestamp::new(SECONDS, TimeFormat::Rfc3339);
+        assert_eq!(
+            json!("2021-11-11T14:33:42Z"),
+            serde_json::to_value(time).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_epoch_seconds() {
+        let time = Timestamp::new(SECONDS, TimeFormat::EpochSeconds);
+        assert_eq!(json!(1636641222), serde_json::to_value(time).unwrap());
+    }
+
+    #[test]
+    fn serialize_both() {
+        let time = Timestamp::new(SECONDS, TimeFormat::Both);
+        assert_eq!(
+            json!({"rfc3339": "2021-11-11T14:33:42Z", "epochSeconds": 1636641222}),
+            serde_json::to_value(time).unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_keeps_format() {
+        for format in [
+            TimeFormat::Rfc3339,
+            TimeFormat::EpochSeconds,
+            TimeFormat::Both,
+        ] {
+            let time = Timestamp::new(SECONDS, format);
+            let serialized = serde_json::to_string(&time).unwrap();
+            assert_eq!(time, serde_json::from_str(&serialized).unwrap());
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::config::TrustPolicy;
+
+/// Outcome of checking a ledger reply against the `TrustPolicy` of its namespace,
+/// added as `trustPolicy` to the method metadata
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyEvaluation {
+    pub satisfied: bool,
+    pub min_count: usize,
+    /// Required nodes among the signers of the reply
+    pub signed_by: Vec<String>,
+    /// Required nodes that did not sign the reply
+    pub missing: Vec<String>,
+    /// Required nodes that are not part of the pool
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<String>,
+}
+
+/// Checks which required nodes signed the reply, as listed in the participants
+/// of the multi-signature of its state proof. A reply without state proof is
+/// signed by none. Required nodes are reported as unknown if the pool knows its
+/// nodes and they are not among them.
+pub(crate) fn evaluate(
+    policy: &TrustPolicy,
+    reply: &Value,
+    pool_nodes: &[String],
+) -> PolicyEvaluation {
+    let participants: Vec<&str> = reply["result"]["state_proof"]["multi_signature"]["participants"]
+        .as_array()
+        .map(|participants| participants.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let (signed_by, missing): (Vec<String>, Vec<String>) = policy
+        .required_nodes
+        .iter()
+        .cloned()
+        .partition(|node| participants.contains(&node.as_str()));
+    let unknown: Vec<String> = if pool_nodes.is_empty() {
+        Vec::new()
+    } else {
+        policy
+            .required_nodes
+            .iter()
+            .filter(|node| !pool_nodes.contains(node))
+            .cloned()
+            .collect()
+    };
+    if !unknown.is_empty() {
+        warn!("Trust policy requires nodes not in the pool: {:?}", unknown);
+    }
+
+    PolicyEvaluation {
+        satisfied: signed_by.len() >= policy.min_count,
+        min_count: policy.min_count,
+        signed_by,
+        missing,
+        unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(nodes: &[&str], min_count: usize) -> TrustPolicy {
+        TrustPolicy {
+            required_nodes: nodes.iter().map(|node| node.to_string()).collect(),
+            min_count,
+        }
+    }
+
+    #[test]
+    fn reply_without_state_proof_is_signed_by_none() {
+        let evaluation = evaluate(&policy(&["Node1"], 1), &json!({"result": {}}), &[]);
+        assert!(!evaluation.satisfied);
+        assert_eq!(vec![String::from("Node1")], evaluation.missing);
+        assert!(evaluation.unknown.is_empty());
+    }
+}
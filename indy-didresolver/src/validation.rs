@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+use url::Url;
+
+pub const VERIFICATION_RELATIONSHIPS: [&str; 5] = [
+    "authentication",
+    "assertionMethod",
+    "keyAgreement",
+    "capabilityInvocation",
+    "capabilityDelegation",
+];
+
+pub const KEY_MATERIAL_PROPERTIES: [&str; 6] = [
+    "publicKeyJwk",
+    "publicKeyMultibase",
+    "publicKeyBase58",
+    "publicKeyHex",
+    "publicKeyPem",
+    "blockchainAccountId",
+];
+
+static DID_PATTERN: &str = r"^did:[a-z0-9]+:(?:[A-Za-z0-9._-]|%[0-9A-Fa-f]{2}|:)*[A-Za-z0-9._-]$";
+
+/// Violation of a DID Core structural rule
+#[derive(Clone, Debug, Error, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "issue")]
+pub enum ValidationIssue {
+    #[error("Document id is missing or not a DID")]
+    InvalidId,
+    #[error("Document id {found} does not match resolved DID {expected}")]
+    IdMismatch { expected: String, found: String },
+    #[error("Verification method {id} is missing property {property}")]
+    MissingProperty { id: String, property: String },
+    #[error("Verification method {id} has {count} key material properties instead of one")]
+    KeyMaterial { id: String, count: usize },
+    #[error("{relationship} references unknown verification method {reference}")]
+    DanglingReference {
+        relationship: String,
+        reference: String,
+    },
+    #[error("Service id {id} is not a URI")]
+    InvalidServiceId { id: String },
+    #[error("Service id {id} is not unique")]
+    DuplicateServiceId { id: String },
+}
+
+/// Checks a DID document against the structural rules of DID Core and returns
+/// all violations found.
+pub fn validate_did_document(doc: &Value, did: &str) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    match doc.get("id").and_then(|id| id.as_str()) {
+        Some(id) if is_did(id) => {
+            if id != did {
+                issues.push(ValidationIssue::IdMismatch {
+                    expected: did.to_string(),
+                    found: id.to_string(),
+                })
+            }
+        }
+        _ => issues.push(ValidationIssue::InvalidId),
+    }
+
+    let mut method_ids = HashSet::new();
+    for method in as_vec(doc.get("verificationMethod")) {
+        validate_verification_method(method, did, &mut method_ids, &mut issues);
+    }
+
+    // Embedded verification methods can be referenced from other relationships
+    for relationship in VERIFICATION_RELATIONSHIPS {
+        for entry in as_vec(doc.get(relationship)) {
+            if entry.is_object() {
+                validate_verification_method(entry, did, &mut method_ids, &mut issues);
+            }
+        }
+    }
+
+    for relationship in VERIFICATION_RELATIONSHIPS {
+        for entry in as_vec(doc.get(relationship)) {
+            if let Some(reference) = entry.as_str() {
+                if !method_ids.contains(&absolute_id(reference, did)) {
+                    issues.push(ValidationIssue::DanglingReference {
+                        relationship: relationship.to_string(),
+                        reference: reference.to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    let mut service_ids = HashSet::new();
+    for service in as_vec(doc.get("service")) {
+        let id = service.get("id").and_then(|id| id.as_str()).unwrap_or("");
+        let absolute = absolute_id(id, did);
+        if Url::parse(&absolute).is_err() {
+            issues.push(ValidationIssue::InvalidServiceId { id: id.to_string() });
+        } else if !service_ids.insert(absolute) {
+            issues.push(ValidationIssue::DuplicateServiceId { id: id.to_string() });
+        }
+    }
+
+    issues
+}
+
+fn validate_verification_method(
+    method: &Value,
+    did: &str,
+    method_ids: &mut HashSet<String>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let id = method
+        .get("id")
+        .and_then(|id| id.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    for property in ["id", "type", "controller"] {
+        if method.get(property).and_then(|p| p.as_str()).is_none() {
+            issues.push(ValidationIssue::MissingProperty {
+                id: id.clone(),
+                property: property.to_string(),
+            })
+        }
+    }
+
+    let count = KEY_MATERIAL_PROPERTIES
+        .iter()
+        .filter(|property| method.get(**property).is_some())
+        .count();
+    if count != 1 {
+        issues.push(ValidationIssue::KeyMaterial {
+            id: id.clone(),
+            count,
+        })
+    }
+
+    if !id.is_empty() {
+        method_ids.insert(absolute_id(&id, did));
+    }
+}
+
+fn is_did(id: &str) -> bool {
+    Regex::new(DID_PATTERN).unwrap().is_match(id)
+}
+
+// Relative DID URLs are resolved against the DID of the document
+fn absolute_id(id: &str, did: &str) -> String {
+    if id.starts_with('#') {
+        format!("{}{}", did, id)
+    } else {
+        id.to_string()
+    }
+}
+
+fn as_vec(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(Value::Null) | None => vec![],
+        Some(value) => vec![value],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did_document::DidDocument;
+    use serde_json::json;
+
+    static DID: &str = "did:indy:idunion:QowxFtwciWceMFr7WbwnM";
+
+    fn validate_with_content(diddoc_content: Value) -> Vec<ValidationIssue> {
+        DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            Some(diddoc_content),
+        )
+        .validate()
+        .unwrap()
+    }
+
+    #[test]
+    fn base_document_is_valid() {
+        let doc = DidDocument::new(
+            "idunion",
+            "QowxFtwciWceMFr7WbwnM",
+            "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBk",
+            None,
+            None,
+        );
+        assert!(doc.validate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn document_with_valid_content_is_valid() {
+        let issues = validate_with_content(json!({
+            "verificationMethod": [{
+                "id": "#keys-2",
+                "type": "Ed25519VerificationKey2018",
+                "controller": DID,
+                "publicKeyBase58": "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBc",
+            }],
+            "authentication": ["#keys-2"],
+            "service": [{
+                "id": "#did-communication",
+                "type": "did-communication",
+                "serviceEndpoint": "https://example.com",
+            }]
+        }));
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn id_must_be_a_did() {
+        assert_eq!(
+            vec![ValidationIssue::InvalidId],
+            validate_did_document(&json!({"id": "QowxFtwciWceMFr7WbwnM"}), DID)
+        );
+        assert_eq!(
+            vec![ValidationIssue::InvalidId],
+            validate_did_document(&json!({}), DID)
+        );
+    }
+
+    #[test]
+    fn id_must_match_resolved_did() {
+        assert_eq!(
+            vec![ValidationIssue::IdMismatch {
+                expected: DID.to_string(),
+                found: "did:indy:sovrin:QowxFtwciWceMFr7WbwnM".to_string()
+            }],
+            validate_did_document(&json!({"id": "did:indy:sovrin:QowxFtwciWceMFr7WbwnM"}), DID)
+        );
+    }
+
+    #[test]
+    fn verification_method_without_controller() {
+        let issues = validate_with_content(json!({
+            "verificationMethod": [{
+                "id": "#keys-2",
+                "type": "Ed25519VerificationKey2018",
+                "publicKeyBase58": "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBc",
+            }]
+        }));
+        assert_eq!(
+            vec![ValidationIssue::MissingProperty {
+                id: "#keys-2".to_string(),
+                property: "controller".to_string()
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn verification_method_with_two_key_materials() {
+        let issues = validate_with_content(json!({
+            "verificationMethod": [{
+                "id": "#keys-2",
+                "type": "Ed25519VerificationKey2018",
+                "controller": DID,
+                "publicKeyBase58": "67yDXtw6MK2D7V2kFSL7uMH6qTtrEbNtkdiTkbk9YJBc",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+            }]
+        }));
+        assert_eq!(
+            vec![ValidationIssue::KeyMaterial {
+                id: "#keys-2".to_string(),
+                count: 2
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn relationship_referencing_unknown_method() {
+        let issues = validate_with_content(json!({
+            "assertionMethod": ["#keys-3"]
+        }));
+        assert_eq!(
+            vec![ValidationIssue::DanglingReference {
+                relationship: "assertionMethod".to_string(),
+                reference: "#keys-3".to_string()
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn relationship_referencing_embedded_method() {
+        let issues = validate_with_content(json!({
+            "keyAgreement": [{
+                "id": "#keys-3",
+                "type": "X25519KeyAgreementKey2019",
+                "controller": DID,
+                "publicKeyBase58": "JhNWeSVLMYccCk7iopQW4guaSJTojqpMEELgSLhKwRr",
+            }],
+            "capabilityInvocation": ["#keys-3"]
+        }));
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn service_ids_must_be_unique() {
+        let issues = validate_with_content(json!({
+            "service": [{
+                "id": "#did-communication",
+                "type": "did-communication",
+                "serviceEndpoint": "https://example.com",
+            }, {
+                "id": "did:indy:idunion:QowxFtwciWceMFr7WbwnM#did-communication",
+                "type": "did-communication",
+                "serviceEndpoint": "https://example.org",
+            }]
+        }));
+        assert_eq!(
+            vec![ValidationIssue::DuplicateServiceId {
+                id: "did:indy:idunion:QowxFtwciWceMFr7WbwnM#did-communication".to_string()
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn service_ids_must_be_uris() {
+        let issues = validate_with_content(json!({
+            "service": [{
+                "id": "not a uri",
+                "type": "LinkedDomains",
+                "serviceEndpoint": "https://example.com",
+            }]
+        }));
+        assert_eq!(
+            vec![ValidationIssue::InvalidServiceId {
+                id: "not a uri".to_string()
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn all_issues_are_reported() {
+        let issues = validate_with_content(json!({
+            "verificationMethod": [{
+                "id": "#keys-2",
+                "controller": DID,
+            }],
+            "authentication": ["#keys-4"],
+        }));
+        assert_eq!(3, issues.len());
+    }
+}
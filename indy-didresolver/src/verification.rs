@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::error::DidIndyResult;
+use super::responses::GetNymResultV1;
+use super::trust::PolicyEvaluation;
+
+/// What was verified of a resolved NYM, for callers that need to know how far
+/// they can trust a DID document. Returned by `Resolver::resolve_with_report`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    /// The reply carries a state proof multi-signed by the nodes. indy-vdr only
+    /// accepts a single reply after verifying its state proof, replies without
+    /// one are only accepted if enough nodes sent the same reply.
+    pub state_proof: bool,
+    /// Nodes that signed the state proof
+    pub signers: Vec<String>,
+    /// Outcome of the trust policy of the namespace, if one is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust_policy: Option<PolicyEvaluation>,
+    /// The DID is derived from the verkey, so the verkey is the one the DID was
+    /// created with
+    pub verkey_self_certifying: bool,
+    /// Submitter of the NYM carrying `diddocContent`, whose signature the ledger
+    /// checked when the NYM was written. Not set without `diddocContent`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diddoc_content_signer: Option<String>,
+    /// `diddocContent` was signed with the key of the DID itself rather than by
+    /// an endorser or trustee writing on its behalf
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diddoc_content_self_signed: Option<bool>,
+}
+
+impl VerificationReport {
+    /// Report of a GET_NYM reply, with the trust policy evaluated for it
+    pub(crate) fn of_nym_reply(
+        reply: &Value,
+        trust_policy: Option<PolicyEvaluation>,
+    ) -> DidIndyResult<Self> {
+        let multi_signature = &reply["result"]["state_proof"]["multi_signature"];
+        let signers: Vec<String> = multi_signature["participants"]
+            .as_array()
+            .map(|participants| {
+                participants
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let nym: GetNymResultV1 =
+            serde_json::from_str(reply["result"]["data"].as_str().unwrap_or("null"))?;
+        let dest = nym.dest.to_string();
+        let signer = nym
+            .diddoc_content
+            .as_ref()
+            .and(nym.identifier.as_ref())
+            .map(ToString::to_string);
+
+        Ok(VerificationReport {
+            state_proof: multi_signature["signature"].is_string() && !signers.is_empty(),
+            signers,
+            trust_policy,
+            verkey_self_certifying: is_self_certifying(
+                &dest,
+                nym.verkey.as_deref().unwrap_or_default(),
+            ),
+            diddoc_content_self_signed: signer.as_ref().map(|signer| *signer == dest),
+            diddoc_content_signer: signer,
+        })
+    }
+}
+
+/// Whether the DID is derived from the verkey: the first 16 bytes of the verkey,
+/// or of its SHA-256 hash for DIDs of NYM version 2. An abbreviated verkey
+/// (`~...`) holds the remainder of a verkey starting with the DID.
+pub fn is_self_certifying(did: &str, verkey: &str) -> bool {
+    if verkey.starts_with('~') {
+        return true;
+    }
+    let verkey = match bs58::decode(verkey).into_vec() {
+        Ok(verkey) if verkey.len() == 32 => verkey,
+        _ => return false,
+    };
+    let did = bs58::decode(did).into_vec().unwrap_or_default();
+    did == verkey[..16] || did == Sha256::digest(&verkey)[..16]
+}
+
+/// The self-certifying form of a DID derived from its verkey the legacy way, as
+/// the first 16 bytes of the verkey: the first 16 bytes of the SHA-256 hash of
+/// the verkey, as for DIDs of NYM version 2. None if the DID is not derived from
+/// the verkey that way, i.e. already self-certifying or unrelated to the verkey.
+pub fn self_certifying_form(did: &str, verkey: &str) -> Option<String> {
+    let did_bytes = bs58::decode(did).into_vec().ok()?;
+    let verkey = match verkey.strip_prefix('~') {
+        Some(rest) => [did_bytes.clone(), bs58::decode(rest).into_vec().ok()?].concat(),
+        None => bs58::decode(verkey).into_vec().ok()?,
+    };
+    if verkey.len() != 32 || did_bytes != verkey[..16] {
+        return None;
+    }
+    Some(bs58::encode(&Sha256::digest(&verkey)[..16]).into_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static VERKEY: [u8; 32] = [3; 32];
+
+    #[test]
+    fn did_from_verkey_is_self_certifying() {
+        let verkey = bs58::encode(VERKEY).into_string();
+        let did = bs58::encode(&VERKEY[..16]).into_string();
+        let hashed_did = bs58::encode(&Sha256::digest(VERKEY)[..16]).into_string();
+
+        assert!(is_self_certifying(&did, &verkey));
+        assert!(is_self_certifying(&hashed_did, &verkey));
+        assert!(is_self_certifying(
+            "Dk1fRRTtNazyMuK2cr64wp",
+            "~4Vr1bJ6V6jEPQ1PdmWyNkK"
+        ));
+        assert!(!is_self_certifying("Dk1fRRTtNazyMuK2cr64wp", &verkey));
+        assert!(!is_self_certifying(&did, "not a verkey"));
+    }
+
+    #[test]
+    fn self_certifying_form_of_legacy_did() {
+        let verkey = bs58::encode(VERKEY).into_string();
+        let did = bs58::encode(&VERKEY[..16]).into_string();
+        let hashed_did = bs58::encode(&Sha256::digest(VERKEY)[..16]).into_string();
+
+        assert_eq!(
+            Some(hashed_did.clone()),
+            self_certifying_form(&did, &verkey)
+        );
+        assert_eq!(
+            Some(String::from("PvGvSvaH7WrF7tfM5qZtuT")),
+            self_certifying_form("Dk1fRRTtNazyMuK2cr64wp", "~4Vr1bJ6V6jEPQ1PdmWyNkK")
+        );
+        assert_eq!(None, self_certifying_form(&hashed_did, &verkey));
+        assert_eq!(
+            None,
+            self_certifying_form("Dk1fRRTtNazyMuK2cr64wp", &verkey)
+        );
+    }
+}
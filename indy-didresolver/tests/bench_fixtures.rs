@@ -0,0 +1,48 @@
+// Keeps the fixtures of the benchmarks from going stale as the parser and the
+// resolver change, `cargo test` does not build the benchmarks themselves
+
+#[path = "../benches/fixtures/mod.rs"]
+mod fixtures;
+
+use fixtures::*;
+use indy_didresolver::did::DidUrl;
+use indy_didresolver::resolver::{build_request, Resolver};
+use serde_json::Value;
+
+#[test]
+fn benchmark_did_urls_parse() {
+    for did_url in DID_URLS {
+        assert!(DidUrl::from_str(did_url).is_ok(), "{}", did_url);
+    }
+}
+
+#[test]
+fn benchmark_requests_build() {
+    let builder = request_builder();
+    for (object, did_url) in LEDGER_OBJECT_URLS {
+        let did_url = DidUrl::from_str(did_url).unwrap();
+        assert!(build_request(&did_url, &builder).is_ok(), "{}", object);
+    }
+}
+
+#[test]
+fn benchmark_nyms_resolve() {
+    for diddoc_content in [None, Some(diddoc_content())] {
+        let with_diddoc_content = diddoc_content.is_some();
+        let resolver = Resolver::new(FixedLedger::new(diddoc_content));
+
+        let result: Value = serde_json::from_str(&resolver.resolve_string(DID).unwrap()).unwrap();
+
+        assert_eq!(DID, result["didDocument"]["id"]);
+        assert_eq!(
+            1,
+            result["didDocument"]["service"].as_array().unwrap().len()
+        );
+        let service_type = &result["didDocument"]["service"][0]["type"];
+        if with_diddoc_content {
+            assert_eq!("DIDCommMessaging", *service_type);
+        } else {
+            assert_eq!("did-communication", *service_type);
+        }
+    }
+}